@@ -1,15 +1,17 @@
 // usage:  cargo run --release --example advanced-vars
 
+use fasteval3::Num;
+
 fn main() -> Result<(), fasteval3::Error> {
-    let mut cb = |name: &str, args: Vec<f32>| -> Option<f32> {
-        let mydata: [f32; 3] = [11.1, 22.2, 33.3];
+    let mut cb = |name: &str, args: Vec<Num>| -> Option<Num> {
+        let mydata: [Num; 3] = [11.1, 22.2, 33.3];
         match name {
             // Custom constants/variables:
             "x" => Some(3.0),
             "y" => Some(4.0),
 
             // Custom function:
-            "sum" => Some(args.into_iter().fold(0.0, |s, f| s + f)),
+            "total" => Some(args.into_iter().fold(0.0, |s, f| s + f)),
 
             // Custom array-like objects:
             // The `args.get...` code is the same as:
@@ -22,12 +24,12 @@ fn main() -> Result<(), fasteval3::Error> {
         }
     };
 
-    let val = fasteval3::ez_eval("sum(x^2, y^2)^0.5 + data[2]", &mut cb)?;
-    //                           |   |                   |
-    //                           |   |                   square-brackets act like parenthesis
-    //                           |   variables are like custom functions with zero args
+    let val = fasteval3::ez_eval("total(x^2, y^2)^0.5 + data[2]", &mut cb)?;
+    //                           |     |                   |
+    //                           |     |                   square-brackets act like parenthesis
+    //                           |     variables are like custom functions with zero args
     //                           custom function
-    assert!((val - 38.3).abs() < f32::EPSILON);
+    assert!((val - 38.3).abs() < Num::EPSILON);
 
     // Let's explore some of the hidden complexities of variables:
     //
@@ -35,7 +37,7 @@ fn main() -> Result<(), fasteval3::Error> {
     //       Therefore, variables can receive arguments too,
     //       which will probably be ignored.
     //       Therefore, these two expressions evaluate to the same thing:
-    //           eval("x + y")  ==  eval("x(1,2,3) + y(x, y, sum(x,y))")
+    //           eval("x + y")  ==  eval("x(1,2,3) + y(x, y, total(x,y))")
     //                                      ^^^^^      ^^^^^^^^^^^^^^
     //                                      All this stuff is ignored.
     //
@@ -50,9 +52,9 @@ fn main() -> Result<(), fasteval3::Error> {
     //           x         -- Uses the custom 'x' variable.
     //           x()       -- Uses the custom 'x' variable because there is no 'x' builtin.
     //           x(1,2,3)  -- Uses the custom 'x' variable.  The args are ignored.
-    //           sum       -- Uses the custom 'sum' function with no arguments.
-    //           sum()     -- Uses the custom 'sum' function with no arguments.
-    //           sum(1,2)  -- Uses the custom 'sum' function with two arguments.
+    //           total     -- Uses the custom 'total' function with no arguments.
+    //           total()   -- Uses the custom 'total' function with no arguments.
+    //           total(1,2) -- Uses the custom 'total' function with two arguments.
 
     Ok(())
 }
@@ -2,6 +2,7 @@
 
 use fasteval3::Compiler;
 use fasteval3::Evaler; // use this trait so we can call eval().
+use fasteval3::Num;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::rc::Rc;
@@ -10,10 +11,10 @@ use std::time::Instant;
 fn main() -> Result<(), fasteval3::Error> {
     let parser = fasteval3::Parser::new();
     let mut slab = fasteval3::Slab::new();
-    let map = Rc::new(RefCell::new(BTreeMap::<&str, f32>::new()));
+    let map = Rc::new(RefCell::new(BTreeMap::<&str, Num>::new()));
 
-    let mut cb = |name: &str, args: Vec<f32>| -> Option<f32> {
-        let mydata: [f32; 3] = [11.1, 22.2, 33.3];
+    let mut cb = |name: &str, args: Vec<Num>| -> Option<Num> {
+        let mydata: [Num; 3] = [11.1, 22.2, 33.3];
 
         match name {
             n if map.borrow().contains_key(n) => Some(*map.borrow().get(n).unwrap()),
@@ -23,7 +24,7 @@ fn main() -> Result<(), fasteval3::Error> {
             "Y" | "y" => Some(4.0),
 
             // Custom function:
-            "sum" => Some(args.into_iter().fold(0.0, |s, f| s + f)),
+            "total" => Some(args.into_iter().fold(0.0, |s, f| s + f)),
 
             // Custom array-like objects:
             // The `args.get...` code is the same as:
@@ -40,7 +41,7 @@ fn main() -> Result<(), fasteval3::Error> {
     //let expr_str = "(1/(a+1)+2/(a+2)+3/(a+3))";
     //let expr_str = "a * a * ((((87))) - 73) + (97 + (((15 / 55 * ((31)) + 35))) + (15 - (9)) - (39 / 26) / 20 / 91 + 27 / (33 * 26 + 28 - (7) / 10 + 66 * 6) + 60 / 35 - ((29) - (69) / 44 / (92)) / (89) + 2 + 87 / 47 * ((2)) * 83 / 98 * 42 / (((67)) * ((97))) / (34 / 89 + 77) - 29 + 70 * (20)) + ((((((92))) + 23 * (98) / (95) + (((99) * (41))) + (5 + 41) + 10) - (36) / (6 + 80 * 52 + (90))))";
     let expr_str =
-        "(sum(1+2+3+a) + x + 1+2*3/4^5%6 + log(100K) + log(e(),100) + [3*(3-3)/3] + (2<3) + data[a % 3]) * cos(60 * (pi() / 180))";
+        "(total(1+2+3+a) + x + 1+2*3/4^5%6 + log(100K) + log(e(),100) + [3*(3-3)/3] + (2<3) + data[a % 3]) * cos(60 * (pi() / 180))";
 
     let compiled = parser
         .parse(expr_str, &mut slab.ps)?
@@ -52,7 +53,7 @@ fn main() -> Result<(), fasteval3::Error> {
     const N: i32 = 100_000;
 
     for deg in 0..=N {
-        map.borrow_mut().insert("a", f64::from(deg) as f32);
+        map.borrow_mut().insert("a", deg as Num);
         // When working with compiled constant expressions, you can use the
         // eval_compiled*!() macros to save a function call:
         let val = fasteval3::eval_compiled!(compiled, &slab, &mut cb);
@@ -2,6 +2,8 @@
 
 // In case you didn't know, Rust allows `main()` to return a `Result`.
 // This lets us use the `?` operator inside of `main()`.  Very convenient!
+use fasteval3::Num;
+
 fn main() -> Result<(), fasteval3::Error> {
     // This example doesn't use any variables, so just use an EmptyNamespace:
     let mut ns = fasteval3::EmptyNamespace;
@@ -20,7 +22,7 @@ fn main() -> Result<(), fasteval3::Error> {
     //    |            many built-in functions: print, int, ceil, floor, abs, sign, log, round, min, max, sin, asin, ...
     //    standard binary operators
 
-    assert!((val - 1.23).abs() < f32::EPSILON);
+    assert!((val - 1.23).abs() < Num::EPSILON);
 
     Ok(())
 }
@@ -1,12 +1,13 @@
 // usage:  cargo run --release --example ns_btreemap_str
 
+use fasteval3::Num;
 use std::collections::BTreeMap;
 fn main() -> Result<(), fasteval3::Error> {
-    let mut map: BTreeMap<&'static str, f32> = BTreeMap::new();
+    let mut map: BTreeMap<&'static str, Num> = BTreeMap::new();
     map.insert("x", 2.0);
 
     let val = fasteval3::ez_eval("x * (x + 1)", &mut map)?;
-    assert!((val - 6.0).abs() < f32::EPSILON);
+    assert!((val - 6.0).abs() < Num::EPSILON);
 
     Ok(())
 }
@@ -1,10 +1,12 @@
 // usage:  cargo run --release --example ns_emptynamespace
 
+use fasteval3::Num;
+
 fn main() -> Result<(), fasteval3::Error> {
     let mut ns = fasteval3::EmptyNamespace;
 
     let val = fasteval3::ez_eval("sin(pi()/2)", &mut ns)?;
-    assert!((val - 1.0).abs() < f32::EPSILON);
+    assert!((val - 1.0).abs() < Num::EPSILON);
 
     Ok(())
 }
@@ -41,7 +41,7 @@
 //! 1.23
 
 use fasteval3::Evaler; // Import this trait for '.eval()' functionality.
-use fasteval3::{Parser, Slab};
+use fasteval3::{Num, Parser, Slab};
 
 use std::collections::BTreeMap;
 use std::io::{self, BufRead, Write};
@@ -87,7 +87,7 @@ fn repl() {
             eprintln!("Entered scope[{}]", ns_stack.len() - 1);
             continue;
         } else if pieces[0] == "pop" {
-            let mut return_value = std::f32::NAN;
+            let mut return_value = Num::NAN;
             let mut has_return_value = false;
             if let Some(v) = ns_stack.last().unwrap().get(&ans_key) {
                 return_value = *v;
@@ -1,8 +1,9 @@
 // usage:  cargo run --release --example simple-vars
 
+use fasteval3::Num;
 use std::collections::BTreeMap;
 fn main() -> Result<(), fasteval3::Error> {
-    let mut map: BTreeMap<String, f32> = BTreeMap::new();
+    let mut map: BTreeMap<String, Num> = BTreeMap::new();
     map.insert(String::from("x"), 1.0);
     map.insert(String::from("y"), 2.0);
     map.insert(String::from("z"), 3.0);
@@ -11,7 +12,7 @@ fn main() -> Result<(), fasteval3::Error> {
     //                                 |
     //                                 prints "y: 2" to stderr and then evaluates to 2.0
 
-    assert!((val - 6.0).abs() < f32::EPSILON);
+    assert!((val - 6.0).abs() < Num::EPSILON);
 
     Ok(())
 }
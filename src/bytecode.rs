@@ -0,0 +1,691 @@
+//! A compact binary encoding of compiled `Instruction`s.
+//!
+//! [`Instruction::to_bytecode()`](../compiler/enum.Instruction.html#method.to_bytecode)
+//! encodes a compiled expression (along with the instructions it refers to in
+//! the `CompileSlab`), and
+//! [`Instruction::from_bytecode()`](../compiler/enum.Instruction.html#method.from_bytecode)
+//! loads it back into a fresh `Slab`, without needing to parse or compile
+//! again.  This is useful for shipping pre-compiled formulas.
+//!
+//! # Format
+//!
+//! All integers (counts and indexes) are unsigned LEB128 varints.  Constants
+//! are little-endian [`Num`](../type.Num.html)s.  With the `f64` feature they
+//! take 8 bytes instead of 4, and the high bit of the version byte is set, so
+//! bytecode can only be loaded by a build with the same float width.
+//!
+//! ```text
+//! version     : u8                     -- Currently BYTECODE_VERSION (1).
+//! names       : count, (len, utf8)*    -- Interned variable/function names.
+//! constants   : count, Num*            -- Constants pool.
+//! instrs      : count, instr*          -- The CompileSlab instructions, in order.
+//! root        : instr                  -- The compiled expression itself.
+//! ```
+//!
+//! Each `instr` is an opcode byte followed by its operands.  An
+//! `InstructionI` operand is an index into `instrs`, and must refer to an
+//! earlier instruction, so loaded bytecode can never contain cycles.  An `IC`
+//! operand is a `0` tag followed by an `InstructionI`, or a `1` tag followed by
+//! an index into `constants`.
+//!
+//! The version byte is bumped whenever the meaning of existing bytecode
+//! changes.  New opcodes are only ever appended, so bytecode produced by an
+//! older version of `fasteval3` can still be loaded.
+//!
+//! # Limitations
+//!
+//! * Unsafe Variables are encoded as normal variables, because their pointers
+//!   are not portable.
+//! * `print()` instructions refer to the `ParseSlab`, so they can be encoded,
+//!   but `from_bytecode()` rejects them.
+
+use crate::compiler::{Instruction, InstructionI, IC};
+use crate::error::Error;
+use crate::slab::Slab;
+use crate::Num;
+
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+/// The version byte at the beginning of all bytecode.
+pub const BYTECODE_VERSION: u8 = 1;
+
+/// Set in the version byte of bytecode with `f64` constants.
+const F64_FLAG: u8 = 0x80;
+#[cfg(not(feature = "f64"))]
+const VERSION_BYTE: u8 = BYTECODE_VERSION;
+#[cfg(feature = "f64")]
+const VERSION_BYTE: u8 = BYTECODE_VERSION | F64_FLAG;
+
+const OP_CONST: u8 = 0;
+const OP_NEG: u8 = 1;
+const OP_NOT: u8 = 2;
+const OP_INV: u8 = 3;
+const OP_ADD: u8 = 4;
+const OP_MUL: u8 = 5;
+const OP_MOD: u8 = 6;
+const OP_EXP: u8 = 7;
+const OP_LT: u8 = 8;
+const OP_LTE: u8 = 9;
+const OP_EQ: u8 = 10;
+const OP_NE: u8 = 11;
+const OP_GTE: u8 = 12;
+const OP_GT: u8 = 13;
+const OP_OR: u8 = 14;
+const OP_AND: u8 = 15;
+const OP_VAR: u8 = 16;
+const OP_FUNC: u8 = 17;
+const OP_INT: u8 = 18;
+const OP_CEIL: u8 = 19;
+const OP_FLOOR: u8 = 20;
+const OP_ABS: u8 = 21;
+const OP_SIGN: u8 = 22;
+const OP_LOG: u8 = 23;
+const OP_ROUND: u8 = 24;
+const OP_MIN: u8 = 25;
+const OP_MAX: u8 = 26;
+const OP_BLEND: u8 = 27;
+const OP_NORM: u8 = 28;
+const OP_SUMSQ: u8 = 29;
+const OP_BUCKET: u8 = 30;
+const OP_SIN: u8 = 31;
+const OP_COS: u8 = 32;
+const OP_TAN: u8 = 33;
+const OP_ASIN: u8 = 34;
+const OP_ACOS: u8 = 35;
+const OP_ATAN: u8 = 36;
+const OP_SINH: u8 = 37;
+const OP_COSH: u8 = 38;
+const OP_TANH: u8 = 39;
+const OP_ASINH: u8 = 40;
+const OP_ACOSH: u8 = 41;
+const OP_ATANH: u8 = 42;
+const OP_ROUND_HALF_UP: u8 = 43;
+const OP_POWMOD: u8 = 44;
+const OP_ASIND: u8 = 45;
+const OP_ACOSD: u8 = 46;
+const OP_ATAND: u8 = 47;
+const OP_ATAN2D: u8 = 48;
+const OP_SEQ: u8 = 49;
+const OP_ROUNDZ: u8 = 50;
+const OP_AVG: u8 = 51;
+const OP_PROBROUND: u8 = 52;
+const OP_POLY: u8 = 53;
+const OP_MIN_FIRST: u8 = 54;
+const OP_MAX_FIRST: u8 = 55;
+const OP_XOR: u8 = 56;
+const OP_SMOOTHSTEP: u8 = 57;
+const OP_MIN_N: u8 = 58;
+const OP_MAX_N: u8 = 59;
+const OP_SELECT: u8 = 60;
+const OP_SQRT: u8 = 61;
+const OP_ATAN2: u8 = 62;
+const OP_NAN_MIN: u8 = 63;
+const OP_NAN_MAX: u8 = 64;
+const OP_CLAMP: u8 = 65;
+const OP_CBRT: u8 = 66;
+const OP_SUM: u8 = 67;
+const OP_PRINT: u8 = 0xFF;
+
+const TAG_I: u8 = 0;
+const TAG_C: u8 = 1;
+
+impl Instruction {
+    /// Encodes this compiled `Instruction` (and everything it refers to in
+    /// `Slab.cs`) into a compact binary bytecode.
+    ///
+    /// [See the `bytecode` module documentation for the format.](../bytecode/index.html)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fasteval3::{Compiler, Evaler, Instruction};
+    /// fn main() -> Result<(), fasteval3::Error> {
+    ///     let mut slab = fasteval3::Slab::new();
+    ///     let mut ns = fasteval3::StrTof32Namespace::new();
+    ///     ns.insert("x", 2.0);
+    ///     let expr_i = fasteval3::Parser::new().parse("sin(x) * 3 + x", &mut slab.ps)?;
+    ///     let compiled = slab.ps.get_expr(expr_i).compile(&slab.ps, &mut slab.cs, &mut ns);
+    ///     let bytes = compiled.to_bytecode(&slab);
+    ///
+    ///     let (loaded_slab, loaded) = Instruction::from_bytecode(&bytes)?;
+    ///     assert_eq!(loaded.eval(&loaded_slab, &mut ns)?, compiled.eval(&slab, &mut ns)?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn to_bytecode(&self, slab: &Slab) -> Vec<u8> {
+        let mut enc = Encoder::default();
+        enc.varint(slab.cs.instrs.len());
+        for instr in &slab.cs.instrs {
+            enc.instr(instr);
+        }
+        enc.instr(self);
+
+        let mut out = Vec::with_capacity(enc.body.len() + 64);
+        out.push(VERSION_BYTE);
+        write_varint(&mut out, enc.names.len());
+        for name in &enc.names {
+            write_varint(&mut out, name.len());
+            out.extend_from_slice(name.as_bytes());
+        }
+        write_varint(&mut out, enc.consts.len());
+        for c in &enc.consts {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+        out.extend_from_slice(&enc.body);
+        out
+    }
+
+    /// Decodes bytecode produced by [`to_bytecode()`](#method.to_bytecode)
+    /// into a new `Slab` and the root `Instruction`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidBytecode` if the bytecode is malformed, was
+    /// produced by a newer version, or contains `print()`.
+    pub fn from_bytecode(bytes: &[u8]) -> Result<(Slab, Self), Error> {
+        let mut dec = Decoder {
+            bs: bytes,
+            names: Vec::new(),
+            consts: Vec::new(),
+            limit: 0,
+        };
+        let version = dec.byte()?;
+        if version ^ VERSION_BYTE == F64_FLAG {
+            return Err(Error::InvalidBytecode(String::from(
+                "constants have a different float width (see the `f64` feature)",
+            )));
+        }
+        if version != VERSION_BYTE {
+            return Err(Error::InvalidBytecode(format!(
+                "unsupported version {version}"
+            )));
+        }
+
+        let count = dec.count()?;
+        for _ in 0..count {
+            let len = dec.varint()?;
+            let bs = dec.take(len)?;
+            let name = std::str::from_utf8(bs)
+                .map_err(|_| Error::InvalidBytecode(String::from("name is not utf8")))?;
+            dec.names.push(name.to_owned());
+        }
+        let count = dec.count()?;
+        for _ in 0..count {
+            let mut buf = [0u8; size_of::<Num>()];
+            buf.copy_from_slice(dec.take(size_of::<Num>())?);
+            dec.consts.push(Num::from_le_bytes(buf));
+        }
+
+        let mut slab = Slab::new();
+        let count = dec.count()?;
+        for i in 0..count {
+            dec.limit = i;
+            let instr = dec.instr()?;
+            slab.cs.instrs.push(instr);
+        }
+        dec.limit = count;
+        let root = dec.instr()?;
+
+        if !dec.bs.is_empty() {
+            return Err(Error::InvalidBytecode(String::from("trailing bytes")));
+        }
+        Ok((slab, root))
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+#[derive(Default)]
+struct Encoder {
+    body: Vec<u8>,
+    names: Vec<String>,
+    name_idx: BTreeMap<String, usize>,
+    consts: Vec<Num>,
+    const_idx: BTreeMap<[u8; size_of::<Num>()], usize>,
+}
+
+impl Encoder {
+    fn varint(&mut self, n: usize) {
+        write_varint(&mut self.body, n);
+    }
+
+    fn op(&mut self, op: u8) {
+        self.body.push(op);
+    }
+
+    fn i(&mut self, ii: InstructionI) {
+        self.varint(ii.0);
+    }
+
+    fn konst(&mut self, c: Num) {
+        // Index by bits so that -0.0 and NaN payloads survive the round-trip:
+        let next = self.consts.len();
+        let idx = *self.const_idx.entry(c.to_le_bytes()).or_insert(next);
+        if idx == next {
+            self.consts.push(c);
+        }
+        self.varint(idx);
+    }
+
+    fn name(&mut self, name: &str) {
+        let next = self.names.len();
+        let idx = *self.name_idx.entry(name.to_owned()).or_insert(next);
+        if idx == next {
+            self.names.push(name.to_owned());
+        }
+        self.varint(idx);
+    }
+
+    fn ic(&mut self, ic: &IC) {
+        match ic {
+            IC::I(ii) => {
+                self.body.push(TAG_I);
+                self.i(*ii);
+            }
+            IC::C(c) => {
+                self.body.push(TAG_C);
+                self.konst(*c);
+            }
+        }
+    }
+
+    fn ics(&mut self, ics: &[IC]) {
+        self.varint(ics.len());
+        for ic in ics {
+            self.ic(ic);
+        }
+    }
+
+    fn unary(&mut self, op: u8, ii: InstructionI) {
+        self.op(op);
+        self.i(ii);
+    }
+
+    fn binary_i(&mut self, op: u8, li: InstructionI, ric: &IC) {
+        self.op(op);
+        self.i(li);
+        self.ic(ric);
+    }
+
+    fn binary_ic(&mut self, op: u8, lic: &IC, ric: &IC) {
+        self.op(op);
+        self.ic(lic);
+        self.ic(ric);
+    }
+
+    fn instr(&mut self, instr: &Instruction) {
+        match instr {
+            Instruction::IConst(c) => {
+                self.op(OP_CONST);
+                self.konst(*c);
+            }
+            Instruction::INeg(ii) => self.unary(OP_NEG, *ii),
+            Instruction::INot(ii) => self.unary(OP_NOT, *ii),
+            Instruction::IInv(ii) => self.unary(OP_INV, *ii),
+            Instruction::IAdd(li, ric) => self.binary_i(OP_ADD, *li, ric),
+            Instruction::IMul(li, ric) => self.binary_i(OP_MUL, *li, ric),
+            Instruction::IMod { dividend, divisor } => self.binary_ic(OP_MOD, dividend, divisor),
+            Instruction::IExp { base, power } => self.binary_ic(OP_EXP, base, power),
+            Instruction::ILT(lic, ric) => self.binary_ic(OP_LT, lic, ric),
+            Instruction::ILTE(lic, ric) => self.binary_ic(OP_LTE, lic, ric),
+            Instruction::IEQ(lic, ric) => self.binary_ic(OP_EQ, lic, ric),
+            Instruction::INE(lic, ric) => self.binary_ic(OP_NE, lic, ric),
+            Instruction::IGTE(lic, ric) => self.binary_ic(OP_GTE, lic, ric),
+            Instruction::IGT(lic, ric) => self.binary_ic(OP_GT, lic, ric),
+            Instruction::IOR(li, ric) => self.binary_i(OP_OR, *li, ric),
+            Instruction::IXOR(li, ric) => self.binary_i(OP_XOR, *li, ric),
+            Instruction::IAND(li, ric) => self.binary_i(OP_AND, *li, ric),
+            Instruction::IVar(name) => {
+                self.op(OP_VAR);
+                self.name(name);
+            }
+            #[cfg(feature = "unsafe-vars")]
+            Instruction::IUnsafeVar { name, .. } => {
+                self.op(OP_VAR);
+                self.name(name);
+            }
+            Instruction::IFunc { name, args } => {
+                self.op(OP_FUNC);
+                self.name(name);
+                self.ics(args);
+            }
+            Instruction::IFuncInt(ii) => self.unary(OP_INT, *ii),
+            Instruction::IFuncCeil(ii) => self.unary(OP_CEIL, *ii),
+            Instruction::IFuncFloor(ii) => self.unary(OP_FLOOR, *ii),
+            Instruction::IFuncAbs(ii) => self.unary(OP_ABS, *ii),
+            Instruction::IFuncSign(ii) => self.unary(OP_SIGN, *ii),
+            Instruction::IFuncSqrt(ii) => self.unary(OP_SQRT, *ii),
+            Instruction::IFuncCbrt(ii) => self.unary(OP_CBRT, *ii),
+            Instruction::IFuncProbRound(ii) => self.unary(OP_PROBROUND, *ii),
+            Instruction::IFuncLog { base, of } => self.binary_ic(OP_LOG, base, of),
+            Instruction::IFuncRound { modulus, of } => self.binary_ic(OP_ROUND, modulus, of),
+            Instruction::IFuncRoundHalfUp { modulus, of } => {
+                self.binary_ic(OP_ROUND_HALF_UP, modulus, of);
+            }
+            Instruction::IFuncRoundZ { modulus, of } => self.binary_ic(OP_ROUNDZ, modulus, of),
+            Instruction::IFuncMin(li, ric) => self.binary_i(OP_MIN, *li, ric),
+            Instruction::IFuncMax(li, ric) => self.binary_i(OP_MAX, *li, ric),
+            Instruction::IFuncMinFirst(li, ric) => self.binary_i(OP_MIN_FIRST, *li, ric),
+            Instruction::IFuncMaxFirst(li, ric) => self.binary_i(OP_MAX_FIRST, *li, ric),
+            Instruction::IFuncNanMin(li, ric) => self.binary_i(OP_NAN_MIN, *li, ric),
+            Instruction::IFuncNanMax(li, ric) => self.binary_i(OP_NAN_MAX, *li, ric),
+            Instruction::IFuncBlend(ics) => {
+                self.op(OP_BLEND);
+                self.ics(ics);
+            }
+            Instruction::IFuncAvg(ics) => {
+                self.op(OP_AVG);
+                self.ics(ics);
+            }
+            Instruction::IFuncSum(ics) => {
+                self.op(OP_SUM);
+                self.ics(ics);
+            }
+            Instruction::IFuncNorm(ics) => {
+                self.op(OP_NORM);
+                self.ics(ics);
+            }
+            Instruction::IFuncSumSq(ics) => {
+                self.op(OP_SUMSQ);
+                self.ics(ics);
+            }
+            Instruction::IFuncPoly(ics) => {
+                self.op(OP_POLY);
+                self.ics(ics);
+            }
+            Instruction::IFuncSeq(ics) => {
+                self.op(OP_SEQ);
+                self.ics(ics);
+            }
+            Instruction::IFuncMinN(ics) => {
+                self.op(OP_MIN_N);
+                self.ics(ics);
+            }
+            Instruction::IFuncMaxN(ics) => {
+                self.op(OP_MAX_N);
+                self.ics(ics);
+            }
+            Instruction::IFuncBucket { x, lo, hi, n } => {
+                self.op(OP_BUCKET);
+                for ic in [x, lo, hi, n] {
+                    self.ic(ic);
+                }
+            }
+            Instruction::IFuncPowMod { base, exp, modulus } => {
+                self.op(OP_POWMOD);
+                for ic in [base, exp, modulus] {
+                    self.ic(ic);
+                }
+            }
+            Instruction::IFuncSmoothstep { edge0, edge1, x } => {
+                self.op(OP_SMOOTHSTEP);
+                for ic in [edge0, edge1, x] {
+                    self.ic(ic);
+                }
+            }
+            Instruction::IFuncClamp { val, lo, hi } => {
+                self.op(OP_CLAMP);
+                for ic in [val, lo, hi] {
+                    self.ic(ic);
+                }
+            }
+            Instruction::IFuncSelect { index, options } => {
+                self.op(OP_SELECT);
+                self.ic(index);
+                self.ics(options);
+            }
+            Instruction::IFuncSin(ii) => self.unary(OP_SIN, *ii),
+            Instruction::IFuncCos(ii) => self.unary(OP_COS, *ii),
+            Instruction::IFuncTan(ii) => self.unary(OP_TAN, *ii),
+            Instruction::IFuncASin(ii) => self.unary(OP_ASIN, *ii),
+            Instruction::IFuncACos(ii) => self.unary(OP_ACOS, *ii),
+            Instruction::IFuncATan(ii) => self.unary(OP_ATAN, *ii),
+            Instruction::IFuncASinD(ii) => self.unary(OP_ASIND, *ii),
+            Instruction::IFuncACosD(ii) => self.unary(OP_ACOSD, *ii),
+            Instruction::IFuncATanD(ii) => self.unary(OP_ATAND, *ii),
+            Instruction::IFuncATan2 { y, x } => self.binary_ic(OP_ATAN2, y, x),
+            Instruction::IFuncATan2D { y, x } => self.binary_ic(OP_ATAN2D, y, x),
+            Instruction::IFuncSinH(ii) => self.unary(OP_SINH, *ii),
+            Instruction::IFuncCosH(ii) => self.unary(OP_COSH, *ii),
+            Instruction::IFuncTanH(ii) => self.unary(OP_TANH, *ii),
+            Instruction::IFuncASinH(ii) => self.unary(OP_ASINH, *ii),
+            Instruction::IFuncACosH(ii) => self.unary(OP_ACOSH, *ii),
+            Instruction::IFuncATanH(ii) => self.unary(OP_ATANH, *ii),
+            #[cfg(feature = "print-builtin")]
+            Instruction::IPrintFunc(_) => self.op(OP_PRINT),
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bs: &'a [u8],
+    names: Vec<String>,
+    consts: Vec<Num>,
+    /// `InstructionI` operands must be less than this.
+    limit: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let (&b, rest) = self
+            .bs
+            .split_first()
+            .ok_or_else(|| Error::InvalidBytecode(String::from("unexpected end")))?;
+        self.bs = rest;
+        Ok(b)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.bs.len() {
+            return Err(Error::InvalidBytecode(String::from("unexpected end")));
+        }
+        let (out, rest) = self.bs.split_at(n);
+        self.bs = rest;
+        Ok(out)
+    }
+
+    fn varint(&mut self) -> Result<usize, Error> {
+        let mut n: usize = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            let bits = usize::from(b & 0x7F);
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(Error::InvalidBytecode(String::from("varint overflow")));
+            }
+            n |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads a count of items, each of which takes at least one byte.
+    ///
+    /// This prevents malicious inputs from causing huge allocations.
+    fn count(&mut self) -> Result<usize, Error> {
+        let n = self.varint()?;
+        if n > self.bs.len() {
+            return Err(Error::InvalidBytecode(String::from("count too large")));
+        }
+        Ok(n)
+    }
+
+    fn i(&mut self) -> Result<InstructionI, Error> {
+        let i = self.varint()?;
+        if i >= self.limit {
+            return Err(Error::InvalidBytecode(format!(
+                "instruction index {i} out of range"
+            )));
+        }
+        Ok(InstructionI(i))
+    }
+
+    fn konst(&mut self) -> Result<Num, Error> {
+        let i = self.varint()?;
+        self.consts
+            .get(i)
+            .copied()
+            .ok_or_else(|| Error::InvalidBytecode(format!("constant index {i} out of range")))
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let i = self.varint()?;
+        self.names
+            .get(i)
+            .cloned()
+            .ok_or_else(|| Error::InvalidBytecode(format!("name index {i} out of range")))
+    }
+
+    fn ic(&mut self) -> Result<IC, Error> {
+        match self.byte()? {
+            TAG_I => Ok(IC::I(self.i()?)),
+            TAG_C => Ok(IC::C(self.konst()?)),
+            tag => Err(Error::InvalidBytecode(format!("unknown operand tag {tag}"))),
+        }
+    }
+
+    fn ics(&mut self) -> Result<Vec<IC>, Error> {
+        let n = self.count()?;
+        let mut ics = Vec::with_capacity(n);
+        for _ in 0..n {
+            ics.push(self.ic()?);
+        }
+        Ok(ics)
+    }
+
+    fn instr(&mut self) -> Result<Instruction, Error> {
+        let op = self.byte()?;
+        Ok(match op {
+            OP_CONST => Instruction::IConst(self.konst()?),
+            OP_NEG => Instruction::INeg(self.i()?),
+            OP_NOT => Instruction::INot(self.i()?),
+            OP_INV => Instruction::IInv(self.i()?),
+            OP_ADD => Instruction::IAdd(self.i()?, self.ic()?),
+            OP_MUL => Instruction::IMul(self.i()?, self.ic()?),
+            OP_MOD => Instruction::IMod {
+                dividend: self.ic()?,
+                divisor: self.ic()?,
+            },
+            OP_EXP => Instruction::IExp {
+                base: self.ic()?,
+                power: self.ic()?,
+            },
+            OP_LT => Instruction::ILT(self.ic()?, self.ic()?),
+            OP_LTE => Instruction::ILTE(self.ic()?, self.ic()?),
+            OP_EQ => Instruction::IEQ(self.ic()?, self.ic()?),
+            OP_NE => Instruction::INE(self.ic()?, self.ic()?),
+            OP_GTE => Instruction::IGTE(self.ic()?, self.ic()?),
+            OP_GT => Instruction::IGT(self.ic()?, self.ic()?),
+            OP_OR => Instruction::IOR(self.i()?, self.ic()?),
+            OP_XOR => Instruction::IXOR(self.i()?, self.ic()?),
+            OP_AND => Instruction::IAND(self.i()?, self.ic()?),
+            OP_VAR => Instruction::IVar(self.name()?),
+            OP_FUNC => Instruction::IFunc {
+                name: self.name()?,
+                args: self.ics()?,
+            },
+            OP_INT => Instruction::IFuncInt(self.i()?),
+            OP_CEIL => Instruction::IFuncCeil(self.i()?),
+            OP_FLOOR => Instruction::IFuncFloor(self.i()?),
+            OP_ABS => Instruction::IFuncAbs(self.i()?),
+            OP_SIGN => Instruction::IFuncSign(self.i()?),
+            OP_SQRT => Instruction::IFuncSqrt(self.i()?),
+            OP_CBRT => Instruction::IFuncCbrt(self.i()?),
+            OP_PROBROUND => Instruction::IFuncProbRound(self.i()?),
+            OP_LOG => Instruction::IFuncLog {
+                base: self.ic()?,
+                of: self.ic()?,
+            },
+            OP_ROUND => Instruction::IFuncRound {
+                modulus: self.ic()?,
+                of: self.ic()?,
+            },
+            OP_ROUND_HALF_UP => Instruction::IFuncRoundHalfUp {
+                modulus: self.ic()?,
+                of: self.ic()?,
+            },
+            OP_ROUNDZ => Instruction::IFuncRoundZ {
+                modulus: self.ic()?,
+                of: self.ic()?,
+            },
+            OP_MIN => Instruction::IFuncMin(self.i()?, self.ic()?),
+            OP_MAX => Instruction::IFuncMax(self.i()?, self.ic()?),
+            OP_MIN_FIRST => Instruction::IFuncMinFirst(self.i()?, self.ic()?),
+            OP_MAX_FIRST => Instruction::IFuncMaxFirst(self.i()?, self.ic()?),
+            OP_NAN_MIN => Instruction::IFuncNanMin(self.i()?, self.ic()?),
+            OP_NAN_MAX => Instruction::IFuncNanMax(self.i()?, self.ic()?),
+            OP_BLEND => Instruction::IFuncBlend(self.ics()?),
+            OP_AVG => Instruction::IFuncAvg(self.ics()?),
+            OP_SUM => Instruction::IFuncSum(self.ics()?),
+            OP_NORM => Instruction::IFuncNorm(self.ics()?),
+            OP_SUMSQ => Instruction::IFuncSumSq(self.ics()?),
+            OP_POLY => Instruction::IFuncPoly(self.ics()?),
+            OP_SEQ => Instruction::IFuncSeq(self.ics()?),
+            OP_MIN_N => Instruction::IFuncMinN(self.ics()?),
+            OP_MAX_N => Instruction::IFuncMaxN(self.ics()?),
+            OP_BUCKET => Instruction::IFuncBucket {
+                x: self.ic()?,
+                lo: self.ic()?,
+                hi: self.ic()?,
+                n: self.ic()?,
+            },
+            OP_POWMOD => Instruction::IFuncPowMod {
+                base: self.ic()?,
+                exp: self.ic()?,
+                modulus: self.ic()?,
+            },
+            OP_SMOOTHSTEP => Instruction::IFuncSmoothstep {
+                edge0: self.ic()?,
+                edge1: self.ic()?,
+                x: self.ic()?,
+            },
+            OP_CLAMP => Instruction::IFuncClamp {
+                val: self.ic()?,
+                lo: self.ic()?,
+                hi: self.ic()?,
+            },
+            OP_SELECT => Instruction::IFuncSelect {
+                index: self.ic()?,
+                options: self.ics()?,
+            },
+            OP_SIN => Instruction::IFuncSin(self.i()?),
+            OP_COS => Instruction::IFuncCos(self.i()?),
+            OP_TAN => Instruction::IFuncTan(self.i()?),
+            OP_ASIN => Instruction::IFuncASin(self.i()?),
+            OP_ACOS => Instruction::IFuncACos(self.i()?),
+            OP_ATAN => Instruction::IFuncATan(self.i()?),
+            OP_ASIND => Instruction::IFuncASinD(self.i()?),
+            OP_ACOSD => Instruction::IFuncACosD(self.i()?),
+            OP_ATAND => Instruction::IFuncATanD(self.i()?),
+            OP_ATAN2 => Instruction::IFuncATan2 {
+                y: self.ic()?,
+                x: self.ic()?,
+            },
+            OP_ATAN2D => Instruction::IFuncATan2D {
+                y: self.ic()?,
+                x: self.ic()?,
+            },
+            OP_SINH => Instruction::IFuncSinH(self.i()?),
+            OP_COSH => Instruction::IFuncCosH(self.i()?),
+            OP_TANH => Instruction::IFuncTanH(self.i()?),
+            OP_ASINH => Instruction::IFuncASinH(self.i()?),
+            OP_ACOSH => Instruction::IFuncACosH(self.i()?),
+            OP_ATANH => Instruction::IFuncATanH(self.i()?),
+            OP_PRINT => {
+                return Err(Error::InvalidBytecode(String::from(
+                    "print() is not supported",
+                )))
+            }
+            _ => return Err(Error::InvalidBytecode(format!("unknown opcode {op}"))),
+        })
+    }
+}
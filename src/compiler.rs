@@ -26,37 +26,43 @@
 
 use std::cell::RefCell;
 
+use crate::evaler::{call_func, Evaler};
+use crate::evalns::EmptyNamespace;
 #[cfg(feature = "unsafe-vars")]
 use crate::parser::StdFunc::EUnsafeVar;
-use crate::slab::{CompileSlab, ParseSlab};
-use crate::Error;
+#[cfg(feature = "print-builtin")]
+use crate::parser::{ExpressionOrString, PrintFunc};
+use crate::slab::{CompileSlab, ParseSlab, Slab};
 use crate::{
     parser::{
+        check_arity,
         BinaryOp::{
             self, EAdd, EDiv, EExp, EMod, EMul, ESub, EAND, EEQ, EGT, EGTE, ELT, ELTE, ENE, EOR,
+            EXOR,
         },
-        ExprPair, Expression, PrintFunc,
+        ExprPair, Expression,
         StdFunc::{
-            self, EFunc, EFuncACos, EFuncACosH, EFuncASin, EFuncASinH, EFuncATan, EFuncATanH,
-            EFuncAbs, EFuncCeil, EFuncCos, EFuncCosH, EFuncE, EFuncFloor, EFuncInt, EFuncLog,
-            EFuncMax, EFuncMin, EFuncPi, EFuncRound, EFuncSign, EFuncSin, EFuncSinH, EFuncTan,
+            self, EFunc, EFuncACos, EFuncACosD, EFuncACosH, EFuncASin, EFuncASinD, EFuncASinH,
+            EFuncATan, EFuncATan2, EFuncATan2D, EFuncATanD, EFuncATanH, EFuncAbs, EFuncAvg,
+            EFuncBlend, EFuncBucket, EFuncCbrt, EFuncCeil, EFuncClamp, EFuncCos, EFuncCosH, EFuncE,
+            EFuncFloor, EFuncInt, EFuncLog, EFuncMax, EFuncMaxFirst, EFuncMin, EFuncMinFirst,
+            EFuncNanMax, EFuncNanMin, EFuncNorm, EFuncPi, EFuncPoly, EFuncPowMod, EFuncProbRound,
+            EFuncProd, EFuncRound, EFuncRoundHalfUp, EFuncRoundZ, EFuncSelect, EFuncSeq, EFuncSign,
+            EFuncSin, EFuncSinH, EFuncSmoothstep, EFuncSqrt, EFuncSum, EFuncSumSq, EFuncTan,
             EFuncTanH, EVar,
         },
         UnaryOp::{self, ENeg, ENot, EParentheses, EPos},
-        Value,
+        Value, Warning,
     },
     ExpressionI,
 };
+use crate::{Error, Num};
 
 /// `true` --> `1.0`,  `false` --> `0.0`
 #[macro_export]
 macro_rules! bool_to_f32 {
     ($b:expr) => {
-        if $b {
-            1.0_f32
-        } else {
-            0.0_f32
-        }
+        $crate::Num::from(u8::from($b))
     };
 }
 
@@ -67,11 +73,47 @@ macro_rules! bool_to_f32 {
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct InstructionI(pub usize);
 
+/// A `Program` is a list of compiled statements that share one `Slab`.
+///
+/// Evaluating a `Program` evaluates each `Instruction` in order, and returns
+/// the result of the last one (or NaN if the `Program` is empty).  Use it to
+/// store and run several statements as one unit:
+///
+/// ```
+/// use fasteval3::{Compiler, Evaler, Program};
+///
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let parser = fasteval3::Parser::new();
+///     let mut slab = fasteval3::Slab::new();
+///     let mut ns = fasteval3::EmptyNamespace;
+///     let mut program = Program::default();
+///     for stmt in ["1 + 2", "3 * 4"] {
+///         let instr = parser
+///             .parse_noclear(stmt, &mut slab.ps)?
+///             .from(&slab.ps)
+///             .compile(&slab.ps, &mut slab.cs, &mut ns);
+///         program.push(instr, &mut slab.cs);
+///     }
+///     assert_eq!(program.eval(&slab, &mut ns)?, 12.0);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Program(pub Vec<InstructionI>);
+
+impl Program {
+    /// Stores `instr` in `cslab` and appends it to the end of the `Program`.
+    #[inline]
+    pub fn push(&mut self, instr: Instruction, cslab: &mut CompileSlab) {
+        self.0.push(cslab.push_instr(instr));
+    }
+}
+
 /// This enumeration boosts performance because it eliminates expensive function calls for constant values.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum IC {
     I(InstructionI),
-    C(f32),
+    C(Num),
 }
 
 macro_rules! instr_to_ic {
@@ -95,10 +137,10 @@ macro_rules! ic_to_instr {
 }
 
 /// An `Instruction` is an optimized AST node resulting from compilation.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Instruction {
     //---- Primitive Value Types:
-    IConst(f32),
+    IConst(Num),
 
     //---- Unary Ops:
     // Parentheses is a noop
@@ -131,6 +173,7 @@ pub enum Instruction {
 
     //---- Binary Logic Ops:
     IOR(InstructionI, IC),
+    IXOR(InstructionI, IC),
     IAND(InstructionI, IC),
 
     //---- Callables:
@@ -138,7 +181,7 @@ pub enum Instruction {
     #[cfg(feature = "unsafe-vars")]
     IUnsafeVar {
         name: String,
-        ptr: *const f32,
+        ptr: *const Num,
     },
     IFunc {
         name: String,
@@ -150,6 +193,9 @@ pub enum Instruction {
     IFuncFloor(InstructionI),
     IFuncAbs(InstructionI),
     IFuncSign(InstructionI),
+    IFuncSqrt(InstructionI),
+    IFuncCbrt(InstructionI),
+    IFuncProbRound(InstructionI),
     IFuncLog {
         base: IC,
         of: IC,
@@ -158,8 +204,55 @@ pub enum Instruction {
         modulus: IC,
         of: IC,
     },
+    IFuncRoundHalfUp {
+        modulus: IC,
+        of: IC,
+    },
+    IFuncRoundZ {
+        modulus: IC,
+        of: IC,
+    },
     IFuncMin(InstructionI, IC),
     IFuncMax(InstructionI, IC),
+    IFuncMinFirst(InstructionI, IC),
+    IFuncMaxFirst(InstructionI, IC),
+    IFuncNanMin(InstructionI, IC),
+    IFuncNanMax(InstructionI, IC),
+    IFuncMinN(Vec<IC>),
+    IFuncMaxN(Vec<IC>),
+    IFuncBlend(Vec<IC>),
+    IFuncAvg(Vec<IC>),
+    IFuncSum(Vec<IC>),
+    IFuncNorm(Vec<IC>),
+    IFuncSumSq(Vec<IC>),
+    IFuncPoly(Vec<IC>),
+    IFuncSeq(Vec<IC>),
+    IFuncBucket {
+        x: IC,
+        lo: IC,
+        hi: IC,
+        n: IC,
+    },
+    IFuncPowMod {
+        base: IC,
+        exp: IC,
+        modulus: IC,
+    },
+    IFuncSmoothstep {
+        edge0: IC,
+        edge1: IC,
+        x: IC,
+    },
+    IFuncClamp {
+        val: IC,
+        lo: IC,
+        hi: IC,
+    },
+    // Only the chosen option is evaluated, like the right side of IAND and IOR.
+    IFuncSelect {
+        index: IC,
+        options: Vec<IC>,
+    },
 
     IFuncSin(InstructionI),
     IFuncCos(InstructionI),
@@ -167,6 +260,17 @@ pub enum Instruction {
     IFuncASin(InstructionI),
     IFuncACos(InstructionI),
     IFuncATan(InstructionI),
+    IFuncASinD(InstructionI),
+    IFuncACosD(InstructionI),
+    IFuncATanD(InstructionI),
+    IFuncATan2 {
+        y: IC,
+        x: IC,
+    },
+    IFuncATan2D {
+        y: IC,
+        x: IC,
+    },
     IFuncSinH(InstructionI),
     IFuncCosH(InstructionI),
     IFuncTanH(InstructionI),
@@ -174,21 +278,136 @@ pub enum Instruction {
     IFuncACosH(InstructionI),
     IFuncATanH(InstructionI),
 
+    #[cfg(feature = "print-builtin")]
     IPrintFunc(PrintFunc), // Not optimized (it would be pointless because of i/o bottleneck).
 }
-use crate::{eval_var, EvalNamespace};
+use crate::EvalNamespace;
+#[cfg(feature = "print-builtin")]
+use Instruction::IPrintFunc;
 #[cfg(feature = "unsafe-vars")]
 use Instruction::IUnsafeVar;
 use Instruction::{
-    IAdd, IConst, IExp, IFunc, IFuncACos, IFuncACosH, IFuncASin, IFuncASinH, IFuncATan, IFuncATanH,
-    IFuncAbs, IFuncCeil, IFuncCos, IFuncCosH, IFuncFloor, IFuncInt, IFuncLog, IFuncMax, IFuncMin,
-    IFuncRound, IFuncSign, IFuncSin, IFuncSinH, IFuncTan, IFuncTanH, IInv, IMod, IMul, INeg, INot,
-    IPrintFunc, IVar, IAND, IEQ, IGT, IGTE, ILT, ILTE, INE, IOR,
+    IAdd, IConst, IExp, IFunc, IFuncACos, IFuncACosD, IFuncACosH, IFuncASin, IFuncASinD,
+    IFuncASinH, IFuncATan, IFuncATan2, IFuncATan2D, IFuncATanD, IFuncATanH, IFuncAbs, IFuncAvg,
+    IFuncBlend, IFuncBucket, IFuncCbrt, IFuncCeil, IFuncClamp, IFuncCos, IFuncCosH, IFuncFloor,
+    IFuncInt, IFuncLog, IFuncMax, IFuncMaxFirst, IFuncMaxN, IFuncMin, IFuncMinFirst, IFuncMinN,
+    IFuncNanMax, IFuncNanMin, IFuncNorm, IFuncPoly, IFuncPowMod, IFuncProbRound, IFuncRound,
+    IFuncRoundHalfUp, IFuncRoundZ, IFuncSelect, IFuncSeq, IFuncSign, IFuncSin, IFuncSinH,
+    IFuncSmoothstep, IFuncSqrt, IFuncSum, IFuncSumSq, IFuncTan, IFuncTanH, IInv, IMod, IMul, INeg,
+    INot, IVar, IAND, IEQ, IGT, IGTE, ILT, ILTE, INE, IOR, IXOR,
 };
 
 impl Default for Instruction {
     fn default() -> Self {
-        IConst(f32::NAN)
+        IConst(Num::NAN)
+    }
+}
+
+impl Instruction {
+    /// Returns `true` if every operand of this `Instruction` is a constant, which
+    /// means that it can be folded by evaluating it.
+    ///
+    /// Constants and callables are never foldable.
+    pub(crate) fn has_const_operands(&self, cslab: &CompileSlab) -> bool {
+        match self {
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => false,
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(_) => false,
+            IConst(_) | IVar(_) | IFunc { .. } | IFuncProbRound(_) => false,
+            _ => self.all_operands(|ic| match ic {
+                IC::C(_) => true,
+                IC::I(ii) => matches!(cslab.get_instr(*ii), IConst(_)),
+            }),
+        }
+    }
+
+    /// Returns `true` if `pred` holds for every operand of this `Instruction`,
+    /// including the arguments of custom functions.  `InstructionI` operands are
+    /// passed as `IC::I`.
+    pub(crate) fn all_operands(&self, mut pred: impl FnMut(&IC) -> bool) -> bool {
+        match self {
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => true,
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(_) => true,
+            IConst(_) | IVar(_) => true,
+
+            INeg(ii) | INot(ii) | IInv(ii) | IFuncInt(ii) | IFuncCeil(ii) | IFuncFloor(ii)
+            | IFuncAbs(ii) | IFuncSqrt(ii) | IFuncCbrt(ii) | IFuncSign(ii) | IFuncProbRound(ii)
+            | IFuncSin(ii) | IFuncCos(ii) | IFuncTan(ii) | IFuncASin(ii) | IFuncACos(ii)
+            | IFuncATan(ii) | IFuncASinD(ii) | IFuncACosD(ii) | IFuncATanD(ii) | IFuncSinH(ii)
+            | IFuncCosH(ii) | IFuncTanH(ii) | IFuncASinH(ii) | IFuncACosH(ii) | IFuncATanH(ii) => {
+                pred(&IC::I(*ii))
+            }
+
+            ILT(left_ic, right_ic)
+            | ILTE(left_ic, right_ic)
+            | IEQ(left_ic, right_ic)
+            | INE(left_ic, right_ic)
+            | IGTE(left_ic, right_ic)
+            | IGT(left_ic, right_ic)
+            | IMod {
+                dividend: left_ic,
+                divisor: right_ic,
+            }
+            | IExp {
+                base: left_ic,
+                power: right_ic,
+            }
+            | IFuncLog {
+                base: left_ic,
+                of: right_ic,
+            }
+            | IFuncRound {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncRoundHalfUp {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncRoundZ {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncATan2 {
+                y: left_ic,
+                x: right_ic,
+            }
+            | IFuncATan2D {
+                y: left_ic,
+                x: right_ic,
+            } => pred(left_ic) && pred(right_ic),
+
+            IAdd(li, ric)
+            | IMul(li, ric)
+            | IOR(li, ric)
+            | IXOR(li, ric)
+            | IAND(li, ric)
+            | IFuncMin(li, ric)
+            | IFuncMax(li, ric)
+            | IFuncMinFirst(li, ric)
+            | IFuncMaxFirst(li, ric)
+            | IFuncNanMin(li, ric)
+            | IFuncNanMax(li, ric) => pred(&IC::I(*li)) && pred(ric),
+
+            IFunc { args: ics, .. }
+            | IFuncBlend(ics)
+            | IFuncAvg(ics)
+            | IFuncSum(ics)
+            | IFuncNorm(ics)
+            | IFuncSumSq(ics)
+            | IFuncPoly(ics)
+            | IFuncSeq(ics)
+            | IFuncMinN(ics)
+            | IFuncMaxN(ics) => ics.iter().all(pred),
+            IFuncBucket { x, lo, hi, n } => [x, lo, hi, n].into_iter().all(pred),
+            IFuncPowMod { base, exp, modulus } => [base, exp, modulus].into_iter().all(pred),
+            IFuncSmoothstep { edge0, edge1, x } => [edge0, edge1, x].into_iter().all(pred),
+            IFuncClamp { val, lo, hi } => [val, lo, hi].into_iter().all(pred),
+            IFuncSelect { index, options } => pred(index) && options.iter().all(pred),
+        }
     }
 }
 
@@ -266,12 +485,12 @@ impl<'s> ExprSlice<'s> {
         let mut ops = Vec::<&BinaryOp>::with_capacity(4);
         let mut xss = Vec::<ExprSlice>::with_capacity(ops.len() + 1);
         self.split_multi(&[EEQ, ENE, ELT, EGT, ELTE, EGTE], &mut xss, &mut ops);
-        let mut out: Instruction = xss.first().map_or(IConst(f32::NAN), |xs| {
+        let mut out: Instruction = xss.first().map_or(IConst(Num::NAN), |xs| {
             xs.compile(parsed_slab, compiled_slab, namespace)
         });
 
         for (i, op) in ops.into_iter().enumerate() {
-            let instruction: Instruction = xss.get(i + 1).map_or(IConst(f32::NAN), |xs| {
+            let instruction: Instruction = xss.get(i + 1).map_or(IConst(Num::NAN), |xs| {
                 xs.compile(parsed_slab, compiled_slab, namespace)
             });
 
@@ -284,7 +503,7 @@ impl<'s> ExprSlice<'s> {
                         EGT => IConst(bool_to_f32!(l > r)),
                         ELTE => IConst(bool_to_f32!(l <= r)),
                         EGTE => IConst(bool_to_f32!(l >= r)),
-                        _ => IConst(f32::NAN), // unreachable
+                        _ => IConst(Num::NAN), // unreachable
                     };
                     continue;
                 }
@@ -314,7 +533,7 @@ impl<'s> ExprSlice<'s> {
                     instr_to_ic!(compiled_slab, out),
                     instr_to_ic!(compiled_slab, instruction),
                 ),
-                _ => IConst(f32::NAN), // unreachable
+                _ => IConst(Num::NAN), // unreachable
             };
         }
         out
@@ -351,6 +570,36 @@ impl<'s> ExprSlice<'s> {
         out
     }
 
+    /// XOR processing step during compilation
+    #[inline]
+    fn process_xor(
+        &self,
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+    ) -> Instruction {
+        let mut xss = Vec::<ExprSlice>::with_capacity(4);
+        self.split(EXOR, &mut xss);
+        let mut instrs = Vec::<Instruction>::with_capacity(xss.len());
+        for xs in &xss {
+            instrs.push(xs.compile(parsed_slab, compiled_slab, namespace));
+        }
+        let mut instrs = instrs.into_iter();
+        let mut out = instrs.next().unwrap_or(IConst(Num::NAN));
+        for instr in instrs {
+            out = match (&out, &instr) {
+                (IConst(l), IConst(r)) => IConst(bool_to_f32!(
+                    crate::f32_eq!(*l, 0.0) != crate::f32_eq!(*r, 0.0)
+                )),
+                _ => IXOR(
+                    compiled_slab.push_instr(out),
+                    instr_to_ic!(compiled_slab, instr),
+                ),
+            };
+        }
+        out
+    }
+
     /// AND processing step during compilation
     #[inline]
     fn process_and(
@@ -478,41 +727,59 @@ macro_rules! process_fn {
             }
         }
     };
+    // Applies '$then' to the result of '$operation', like 'asin' then 'to_degrees':
+    ($name:ident, $operation:ident, $then:ident, $fallback:ident) => {
+        #[inline]
+        fn $name(
+            parsed_slab: &ParseSlab,
+            compiled_slab: &mut CompileSlab,
+            namespace: &mut impl EvalNamespace,
+            expr: ExpressionI,
+        ) -> Instruction {
+            let instruction =
+                get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
+            if let IConst(target) = instruction {
+                IConst(target.$operation().$then())
+            } else {
+                $fallback(compiled_slab.push_instr(instruction))
+            }
+        }
+    };
 }
 
-/// Uses [`EPSILON`](https://doc.rust-lang.org/core/f32/constant.EPSILON.html) to determine equality of two `f32`s.
+/// Uses the `EPSILON` of [`Num`](../type.Num.html) to determine equality of two `Num`s.
 #[macro_export]
 macro_rules! f32_eq {
     ($l:ident, $r:literal) => {
-        ($l - $r).abs() <= 8.0 * f32::EPSILON
+        ($l - $r).abs() <= 8.0 * $crate::Num::EPSILON
     };
     ($l:ident, $r:ident) => {
-        ($l - $r).abs() <= 8.0 * f32::EPSILON
+        ($l - $r).abs() <= 8.0 * $crate::Num::EPSILON
     };
     ($l:expr, $r:literal) => {
-        ($l - $r).abs() <= 8.0 * f32::EPSILON
+        ($l - $r).abs() <= 8.0 * $crate::Num::EPSILON
     };
     ($l:expr, $r:expr) => {
-        (($l) - ($r)).abs() <= 8.0 * f32::EPSILON
+        (($l) - ($r)).abs() <= 8.0 * $crate::Num::EPSILON
     };
 }
 
-/// Uses [`EPSILON`](https://doc.rust-lang.org/core/f32/constant.EPSILON.html) to determine inequality of two `f32`s.
+/// Uses the `EPSILON` of [`Num`](../type.Num.html) to determine inequality of two `Num`s.
 ///
 /// This is exactly the same as saying `!f32_eq(x,y)` but it is slightly more efficient.
 #[macro_export]
 macro_rules! f32_ne {
     ($l:ident, $r:literal) => {
-        ($l - $r).abs() > 8.0 * f32::EPSILON
+        ($l - $r).abs() > 8.0 * $crate::Num::EPSILON
     };
     ($l:ident, $r:ident) => {
-        ($l - $r).abs() > 8.0 * f32::EPSILON
+        ($l - $r).abs() > 8.0 * $crate::Num::EPSILON
     };
     ($l:expr, $r:literal) => {
-        ($l - $r).abs() > 8.0 * f32::EPSILON
+        ($l - $r).abs() > 8.0 * $crate::Num::EPSILON
     };
     ($l:expr, $r:expr) => {
-        (($l) - ($r)).abs() > 8.0 * f32::EPSILON
+        (($l) - ($r)).abs() > 8.0 * $crate::Num::EPSILON
     };
 }
 fn neg_wrap(instr: Instruction, cslab: &mut CompileSlab) -> Instruction {
@@ -520,6 +787,9 @@ fn neg_wrap(instr: Instruction, cslab: &mut CompileSlab) -> Instruction {
         IConst(-c)
     } else if let INeg(i) = instr {
         cslab.take_instr(i)
+    } else if let IMul(li, IC::C(c)) = instr {
+        // Negation only flips the sign bit, so '-(x * c)' is exactly 'x * -c':
+        IMul(li, IC::C(-c))
     } else {
         INeg(cslab.push_instr(instr))
     }
@@ -542,7 +812,117 @@ fn inv_wrap(instr: Instruction, cslab: &mut CompileSlab) -> Instruction {
         IInv(cslab.push_instr(instr))
     }
 }
+/// Returns `true` if `instr` always evaluates to `0.0` or `1.0`, and its operands
+/// are only constants or unsafe variables (so it can't fail or have side
+/// effects like `print()`).
+///
+/// Normal variables are not accepted: they might be undefined, and optimizing
+/// the `Instruction` away would hide that error.
+fn is_simple_bool(instr: &Instruction, cslab: &CompileSlab) -> bool {
+    let is_simple = |ic: &IC| match ic {
+        IC::C(_) => true,
+        IC::I(i) => match get_instr!(cslab, i) {
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => true,
+            _ => false,
+        },
+    };
+    match instr {
+        ILT(left, right)
+        | ILTE(left, right)
+        | IEQ(left, right)
+        | INE(left, right)
+        | IGTE(left, right)
+        | IGT(left, right) => is_simple(left) && is_simple(right),
+        INot(i) => is_simple(&IC::I(*i)),
+        _ => false,
+    }
+}
+/// Frees the operands of an `Instruction` accepted by `is_simple_bool()`.
+fn free_simple_bool(instr: Instruction, cslab: &mut CompileSlab) {
+    match instr {
+        ILT(left, right)
+        | ILTE(left, right)
+        | IEQ(left, right)
+        | INE(left, right)
+        | IGTE(left, right)
+        | IGT(left, right) => {
+            // Take 'right' before 'left' because it was pushed last:
+            for ic in [right, left] {
+                if let IC::I(i) = ic {
+                    cslab.take_instr(i);
+                }
+            }
+        }
+        INot(i) => {
+            cslab.take_instr(i);
+        }
+        _ => (),
+    }
+}
+/// Combines the operands of `min()` or `max()`, which have already had their
+/// constants folded.  Two operands use the pairwise `IFuncMin`/`IFuncMax`, and
+/// more are reduced in a single flat `IFuncMinN`/`IFuncMaxN`, in order.
+fn compile_min_max(
+    operands: Vec<Instruction>,
+    cslab: &mut CompileSlab,
+    missing: Option<Num>,
+    is_min: bool,
+) -> Instruction {
+    if operands.len() > 2 {
+        let mut ics = Vec::<IC>::with_capacity(operands.len());
+        for instr in operands {
+            ics.push(instr_to_ic!(cslab, instr));
+        }
+        return if is_min {
+            IFuncMinN(ics)
+        } else {
+            IFuncMaxN(ics)
+        };
+    }
+    let mut operands = operands.into_iter();
+    // If every argument was missing:
+    let first = operands
+        .next()
+        .unwrap_or(IConst(missing.unwrap_or(Num::NAN)));
+    match operands.next() {
+        Some(instr) => {
+            let li = cslab.push_instr(first);
+            let ric = instr_to_ic!(cslab, instr);
+            if is_min {
+                IFuncMin(li, ric)
+            } else {
+                IFuncMax(li, ric)
+            }
+        }
+        None => first,
+    }
+}
 fn compile_mul(instrs: Vec<Instruction>, cslab: &mut CompileSlab) -> Instruction {
+    // 'x * 0' can't be folded to '0' in general, because IEEE 754 says that
+    // 'inf * 0' and 'NaN * 0' are 'NaN'.  But if every non-constant factor is
+    // known to be '0' or '1', the product is exactly the product of the constants:
+    let const_prod: Num = instrs
+        .iter()
+        .filter_map(|instr| {
+            if let IConst(c) = instr {
+                Some(*c)
+            } else {
+                None
+            }
+        })
+        .product();
+    if const_prod == 0.0
+        && instrs
+            .iter()
+            .all(|instr| matches!(instr, IConst(_)) || is_simple_bool(instr, cslab))
+    {
+        for instr in instrs.into_iter().rev() {
+            free_simple_bool(instr, cslab);
+        }
+        return IConst(const_prod);
+    }
+
     let mut out = IConst(1.0);
     let mut out_set = false;
     let mut const_prod = 1.0;
@@ -556,7 +936,7 @@ fn compile_mul(instrs: Vec<Instruction>, cslab: &mut CompileSlab) -> Instruction
             out_set = true;
         }
     }
-    if f32_ne!(const_prod, 1.0) {
+    if const_prod.is_nan() || f32_ne!(const_prod, 1.0) {
         if out_set {
             out = IMul(cslab.push_instr(out), IC::C(const_prod));
         } else {
@@ -588,7 +968,7 @@ fn compile_add(instrs: Vec<Instruction>, cslab: &mut CompileSlab) -> Instruction
     }
     out
 }
-pub(crate) fn log(base: f32, n: f32) -> f32 {
+pub(crate) fn log(base: Num, n: Num) -> Num {
     // Can't use floating point in 'match' patterns.  :(
     if f32_eq!(base, 2.0) {
         return n.log2();
@@ -599,6 +979,215 @@ pub(crate) fn log(base: f32, n: f32) -> f32 {
     n.log(base)
 }
 
+/// Returns the weighted average of `(value, weight)` pairs:
+/// `(a*wa + b*wb + ...) / (wa + wb + ...)`
+pub(crate) fn blend(pairs: &[Num]) -> Num {
+    let mut sum = 0.0;
+    let mut total_weight = 0.0;
+    for pair in pairs.chunks_exact(2) {
+        sum += pair[0] * pair[1];
+        total_weight += pair[1];
+    }
+    sum / total_weight
+}
+
+/// Returns `true` if a finite input `x` produced a non-finite `result`.
+pub(crate) fn overflowed(x: Num, result: Num) -> bool {
+    x.is_finite() && !result.is_finite()
+}
+
+/// Rounds `x` to the nearest integer, with ties rounded up toward `+inf`.
+///
+/// Unlike `f32::round`, which rounds ties away from zero, this gives
+/// `round_half_up(2.5) == 3` but `round_half_up(-2.5) == -2`.
+pub(crate) fn round_half_up(x: Num) -> Num {
+    let floor = x.floor();
+    if x - floor >= 0.5 {
+        floor + 1.0
+    } else {
+        floor
+    }
+}
+
+/// Rounds `x` to the nearest integer, with ties rounded toward zero.
+///
+/// This gives `round_ties_to_zero(2.5) == 2` and `round_ties_to_zero(-2.5) == -2`.
+pub(crate) fn round_ties_to_zero(x: Num) -> Num {
+    let trunc = x.trunc();
+    if (x - trunc).abs() == 0.5 {
+        trunc
+    } else {
+        x.round()
+    }
+}
+
+/// Rounds `x` up with a probability equal to its fractional part, and down
+/// otherwise.  `r` is a random number in `[0, 1)`.
+pub(crate) fn probround(x: Num, r: Num) -> Num {
+    let floor = x.floor();
+    if r < x - floor {
+        floor + 1.0
+    } else {
+        floor
+    }
+}
+
+/// Returns `true` if `x` is the `missing` sentinel.  A NaN sentinel matches any NaN.
+pub(crate) fn is_missing(x: Num, missing: Option<Num>) -> bool {
+    missing.is_some_and(|m| x == m || (m.is_nan() && x.is_nan()))
+}
+
+/// Returns the smaller (if `is_min`) or larger of `left` and `right` for
+/// `nanmin()` and `nanmax()`.  A NaN or `missing` value loses to anything, so
+/// the result is only NaN if both are NaN, whatever `Slab.nan_policy` is.
+///
+/// On ties, `right` is returned, like `min()` and `max()`.
+pub(crate) fn nan_min_max(missing: Option<Num>, left: Num, right: Num, is_min: bool) -> Num {
+    if left.is_nan() || is_missing(left, missing) {
+        return right;
+    }
+    if right.is_nan() || is_missing(right, missing) {
+        return left;
+    }
+    let left_wins = if is_min { left < right } else { left > right };
+    if left_wins {
+        left
+    } else {
+        right
+    }
+}
+
+/// Returns the mean of `vals`, skipping `missing` values.  If every value is
+/// missing, the sentinel is returned.
+pub(crate) fn avg(vals: &[Num], missing: Option<Num>) -> Num {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for &val in vals {
+        if !is_missing(val, missing) {
+            sum += val;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return missing.unwrap_or(Num::NAN);
+    }
+    sum / count as Num
+}
+
+/// Returns the sum of `vals`, skipping `missing` values.  If every value is
+/// missing, the sentinel is returned.
+pub(crate) fn sum(vals: &[Num], missing: Option<Num>) -> Num {
+    let mut present = vals
+        .iter()
+        .filter(|&&val| !is_missing(val, missing))
+        .peekable();
+    if present.peek().is_none() {
+        return missing.unwrap_or(Num::NAN);
+    }
+    present.sum()
+}
+
+/// Returns the sum of squares of `vals`: `a^2 + b^2 + ...`
+pub(crate) fn sumsq(vals: &[Num]) -> Num {
+    vals.iter().map(|v| v * v).sum()
+}
+
+/// Evaluates the polynomial `c0 + c1*x + c2*x^2 + ...` with Horner's method,
+/// where `args` is `[x, c0, c1, c2, ...]`.
+pub(crate) fn poly(args: &[Num]) -> Num {
+    let Some((&x, coeffs)) = args.split_first() else {
+        return Num::NAN;
+    };
+    let mut rev = coeffs.iter().rev();
+    let Some(&last) = rev.next() else {
+        return Num::NAN;
+    };
+    rev.fold(last, |acc, &c| acc * x + c)
+}
+
+/// Returns the index of the bucket that `x` falls into when the range `lo..hi`
+/// is split into `n` equal buckets.  The result is clamped to `[0, n-1]`.
+///
+/// Returns NaN if any input is NaN, if `n < 1`, or if `hi <= lo`.
+pub(crate) fn bucket(x: Num, lo: Num, hi: Num, n: Num) -> Num {
+    let n = n.floor();
+    if x.is_nan() || lo.is_nan() || hi.is_nan() || n.is_nan() || n < 1.0 || hi <= lo {
+        return Num::NAN;
+    }
+    ((x - lo) / (hi - lo) * n).floor().clamp(0.0, n - 1.0)
+}
+
+/// Returns the angle (in degrees) of the point `(x, y)`, like `f32::atan2`.
+pub(crate) fn atan2d(y: Num, x: Num) -> Num {
+    y.atan2(x).to_degrees()
+}
+
+/// Returns `base^exp mod modulus`, with each input truncated to an integer.
+///
+/// Uses exponentiation by squaring on integers, so intermediate results never
+/// overflow.  Returns NaN if any input is non-finite, if `base` or `exp` is
+/// negative, or if `modulus < 1`.
+///
+/// The inputs and result are `Num`s, which can only represent integers exactly
+/// up to `2^24` (16777216), or `2^53` with the `f64` feature, so results with
+/// larger moduli may be imprecise.
+pub(crate) fn powmod(base: Num, exp: Num, modulus: Num) -> Num {
+    let (base, exp, modulus) = (base.trunc(), exp.trunc(), modulus.trunc());
+    if !(base.is_finite() && exp.is_finite() && modulus.is_finite())
+        || base < 0.0
+        || exp < 0.0
+        || modulus < 1.0
+    {
+        return Num::NAN;
+    }
+    let (mut base, mut exp, modulus) = (base as u64, exp as u64, modulus as u64);
+    let mut result = 1 % modulus;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result as Num
+}
+
+/// GLSL-style `smoothstep()`: `0` when `x <= edge0`, `1` when `x >= edge1`,
+/// and a smooth Hermite curve in between.
+///
+/// Like in GLSL, the result is unspecified when `edge0 >= edge1`.
+pub(crate) fn smoothstep(edge0: Num, edge1: Num, x: Num) -> Num {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// `clamp()`, computed as `val.max(lo).min(hi)`.
+///
+/// Unlike `Num::clamp()` this never panics: when `lo > hi` the result is `hi`.
+/// A NaN `val` gives `lo` (or `hi` if that is smaller), and a NaN bound is ignored.
+pub(crate) fn clamp(val: Num, lo: Num, hi: Num) -> Num {
+    val.max(lo).min(hi)
+}
+
+/// Returns the position of the option that `select()` chooses: the truncated
+/// `index`, or `None` if that is not in `0..len`.
+pub(crate) fn select_index(index: Num, len: usize) -> Option<usize> {
+    let index = index.trunc();
+    if index >= 0.0 && index < len as Num {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+/// Returns `a * b mod modulus` without overflowing.
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    let product = u128::from(a) * u128::from(b) % u128::from(modulus);
+    // The remainder is less than 'modulus', so it fits:
+    u64::try_from(product).unwrap_or(0)
+}
+
 // Can't inline recursive functions:
 fn push_mul_leaves(
     instrs: &mut Vec<Instruction>,
@@ -699,6 +1288,7 @@ impl Compiler for ExprSlice<'_> {
 
         match lowest_op {
             EOR => self.process_or(parsed_slab, compiled_slab, namespace),
+            EXOR => self.process_xor(parsed_slab, compiled_slab, namespace),
             EAND => self.process_and(parsed_slab, compiled_slab, namespace),
             EAdd => self.process_addition(parsed_slab, compiled_slab, namespace),
             ESub => self.process_subtraction(parsed_slab, compiled_slab, namespace),
@@ -788,23 +1378,31 @@ impl Compiler for ExprSlice<'_> {
                 out
             }
             EExp => {
-                // Right-to-Left Associativity
+                // Right-to-Left Associativity, unless the 'left-assoc-exp' feature is enabled.
                 let mut xss = Vec::<ExprSlice>::with_capacity(2);
                 self.split(EExp, &mut xss);
+                #[cfg(not(feature = "left-assoc-exp"))]
+                let xss = xss.into_iter().rev();
+                #[cfg(feature = "left-assoc-exp")]
+                let xss = xss.into_iter();
                 let mut out = IConst(0.0);
                 let mut out_set = false;
-                for xs in xss.into_iter().rev() {
+                for xs in xss {
                     let instr = xs.compile(parsed_slab, compiled_slab, namespace);
                     if out_set {
-                        if let IConst(power) = out {
-                            if let IConst(base) = instr {
-                                out = IConst(base.powf(power));
+                        #[cfg(not(feature = "left-assoc-exp"))]
+                        let (base, power) = (instr, out);
+                        #[cfg(feature = "left-assoc-exp")]
+                        let (base, power) = (out, instr);
+                        if let IConst(p) = power {
+                            if let IConst(b) = base {
+                                out = IConst(b.powf(p));
                                 continue;
                             }
                         }
                         out = IExp {
-                            base: instr_to_ic!(compiled_slab, instr),
-                            power: instr_to_ic!(compiled_slab, out),
+                            base: instr_to_ic!(compiled_slab, base),
+                            power: instr_to_ic!(compiled_slab, power),
                         };
                     } else {
                         out = instr;
@@ -834,7 +1432,7 @@ impl Compiler for ExprSlice<'_> {
             //              }
             //              IExp{base:cslab.push_instr(base), power:cslab.push_instr(power)}
             //          }
-            ENE | EEQ | EGTE | ELTE | EGT | ELT => IConst(f32::NAN), // unreachable
+            ENE | EEQ | EGTE | ELTE | EGT | ELT => IConst(Num::NAN), // unreachable
         }
     }
 }
@@ -851,65 +1449,598 @@ impl Compiler for Expression {
     }
 }
 
-impl Compiler for Value {
-    fn compile(
+impl Expression {
+    /// Like [`compile()`](trait.Compiler.html#tymethod.compile), but reports
+    /// mistakes that `compile()` would silently accept:
+    /// * A division or modulo by a literal zero (like `1/0` or `x % 0`), which
+    ///   would otherwise be folded to `inf` or NaN.
+    /// * A custom function called with a different number of args than
+    ///   [`ns.arity()`](../evalns/trait.EvalNamespace.html#method.arity)
+    ///   declares, which would otherwise be passed to the Namespace as-is.
+    ///   A name used without parentheses counts as a call with zero args.
+    ///
+    /// Only literal divisors are checked.  A divisor that merely evaluates to
+    /// zero (like `1/(2-2)` or `1/x`) is compiled normally.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DivideByZero` if a literal zero divisor is found, or
+    /// `Error::WrongArgs` if a custom function has the wrong number of args.
+    /// In both cases, nothing is added to `cslab`.
+    pub fn compile_checked(
         &self,
-        parsed_slab: &ParseSlab,
-        compiled_slab: &mut CompileSlab,
+        pslab: &ParseSlab,
+        cslab: &mut CompileSlab,
         ns: &mut impl EvalNamespace,
-    ) -> Instruction {
-        match self {
-            Self::EConstant(c) => IConst(*c),
-            Self::EUnaryOp(u) => u.compile(parsed_slab, compiled_slab, ns),
-            Self::EStdFunc(f) => f.compile(parsed_slab, compiled_slab, ns),
-            Self::EPrintFunc(pf) => IPrintFunc(pf.clone()),
-        }
+    ) -> Result<Instruction, Error> {
+        check_expr(self, pslab, &*ns)?;
+        Ok(self.compile(pslab, cslab, ns))
     }
-}
 
-impl Compiler for UnaryOp {
-    fn compile(
+    /// Like [`compile()`](trait.Compiler.html#tymethod.compile), but also
+    /// returns [`Warning`](../parser/enum.Warning.html)s about comparison
+    /// chains that don't depend on their variable:
+    /// * [`Contradiction`](../parser/enum.Warning.html#variant.Contradiction)
+    ///   for an `&&` chain that can never be true, like `x > 5 && x < 3`.
+    /// * [`Tautology`](../parser/enum.Warning.html#variant.Tautology) for an
+    ///   `||` chain that is always true, like `x >= 0 || x < 0`.
+    ///
+    /// The check is conservative: it only looks at `<`, `<=`, `>` and `>=`
+    /// comparisons of a single variable with a constant, which are directly
+    /// joined by the same operator.  Other parts of the chain are ignored.
+    /// `==` and `!=` are not checked, because they allow for rounding errors.
+    ///
+    /// ```
+    /// use fasteval3::{EmptyNamespace, Parser, Slab, Warning};
+    ///
+    /// let mut slab = Slab::new();
+    /// let expr_i = Parser::new().parse("y > 1 && x > 5 && x < 3", &mut slab.ps).unwrap();
+    /// let (_, warnings) = slab
+    ///     .ps
+    ///     .get_expr(expr_i)
+    ///     .compile_with_warnings(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+    /// assert_eq!(warnings, vec![Warning::Contradiction(String::from("x"))]);
+    /// ```
+    pub fn compile_with_warnings(
         &self,
-        parsed_slab: &ParseSlab,
-        compiled_slab: &mut CompileSlab,
+        pslab: &ParseSlab,
+        cslab: &mut CompileSlab,
         ns: &mut impl EvalNamespace,
-    ) -> Instruction {
-        match self {
-            EPos(i) => get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns),
-            ENeg(i) => {
-                let instr = get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns);
-                if let IConst(c) = instr {
-                    IConst(-c)
-                } else {
-                    neg_wrap(instr, compiled_slab)
-                }
-            }
-            ENot(i) => {
-                let instr = get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns);
-                if let IConst(c) = instr {
-                    IConst(bool_to_f32!(f32_eq!(c, 0.0)))
-                } else {
-                    not_wrap(instr, compiled_slab)
+    ) -> (Instruction, Vec<Warning>) {
+        let start = cslab.instrs.len();
+        let instr = self.compile(pslab, cslab, ns);
+        let warnings = logic_warnings(&instr, cslab, start);
+        (instr, warnings)
+    }
+
+    /// Like [`compile()`](trait.Compiler.html#tymethod.compile), but fails if
+    /// more than `max_instrs` `Instruction`s are added to `cslab`.
+    ///
+    /// This bounds the compiled size of untrusted or generated expressions.
+    /// The returned root `Instruction` is not stored in `cslab`, so it does not
+    /// count: an expression like `x + 1` adds a single `Instruction` (for `x`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TooManyInstructions` if the budget is exceeded, in which
+    /// case the `Instruction`s added by this call are removed from `cslab` again.
+    pub fn compile_with_budget(
+        &self,
+        pslab: &ParseSlab,
+        cslab: &mut CompileSlab,
+        ns: &mut impl EvalNamespace,
+        max_instrs: usize,
+    ) -> Result<Instruction, Error> {
+        let start = cslab.instrs.len();
+        let instr = self.compile(pslab, cslab, ns);
+        if cslab.instrs.len() - start > max_instrs {
+            cslab.truncate(start);
+            return Err(Error::TooManyInstructions);
+        }
+        Ok(instr)
+    }
+
+    /// Returns a simplified copy of this `Expression`, pushed into `slab.ps`.
+    ///
+    /// Unlike `compile()`, which produces `Instruction`s, this produces a new
+    /// parse AST, so the result can still be inspected or rendered back to text.
+    /// The original `Expression` is left untouched.  These simplifications are
+    /// applied:
+    /// * Built-in functions and operators with constant arguments are evaluated.
+    /// * Constant terms are combined and moved to the end: `1 + x + 1` becomes `x + 2`.
+    /// * Identity terms are dropped: `x * 1 + 0` becomes `x`.
+    /// * Parentheses around a single value are removed.
+    ///
+    /// Variables, custom functions and `print()` are never evaluated.  Like
+    /// `compile()`, combining constants may reorder floating-point additions,
+    /// which can change the result in the last few bits.
+    ///
+    /// Since the `Expression` usually lives inside `slab`, clone it first:
+    /// `slab.ps.get_expr(expr_i).clone().simplify(&mut slab)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SlabOverflow` if `slab.ps` runs out of space.
+    pub fn simplify(&self, slab: &mut Slab) -> Result<ExpressionI, Error> {
+        let expr = simplify_expr(self, slab)?;
+        slab.ps.push_expr(expr)
+    }
+}
+
+/// A run of `*`, `/`, `%` and `^` operations, added or subtracted as one term.
+struct Term {
+    neg: bool,
+    vals: Vec<Value>,
+    ops: Vec<BinaryOp>,
+}
+
+fn simplify_expr(expr: &Expression, slab: &mut Slab) -> Result<Expression, Error> {
+    // Arithmetic is evaluated before comparisons and logical operators, so each
+    // run of arithmetic between them can be simplified on its own:
+    let mut runs = vec![(vec![simplify_val(&expr.first, slab)?], Vec::new())];
+    let mut joins = Vec::new();
+    for ExprPair(op, val) in &expr.pairs {
+        let val = simplify_val(val, slab)?;
+        if *op < EAdd {
+            joins.push(*op);
+            runs.push((vec![val], Vec::new()));
+        } else if let Some((vals, ops)) = runs.last_mut() {
+            vals.push(val);
+            ops.push(*op);
+        }
+    }
+
+    let mut out = Expression::default();
+    for (i, (vals, ops)) in runs.into_iter().enumerate() {
+        let run = simplify_sum(vals, ops, slab);
+        match i.checked_sub(1).and_then(|j| joins.get(j)) {
+            Some(op) => out.pairs.push(ExprPair(*op, run.first)),
+            None => out.first = run.first,
+        }
+        out.pairs.extend(run.pairs);
+    }
+    Ok(fold_expr(out, slab))
+}
+
+/// Simplifies a run of arithmetic by combining its constant terms.
+fn simplify_sum(vals: Vec<Value>, ops: Vec<BinaryOp>, slab: &Slab) -> Expression {
+    let mut terms: Vec<Term> = Vec::with_capacity(vals.len());
+    for (i, val) in vals.into_iter().enumerate() {
+        match i.checked_sub(1).and_then(|j| ops.get(j)) {
+            Some(op @ (EMul | EDiv | EMod | EExp)) => {
+                if let Some(term) = terms.last_mut() {
+                    term.vals.push(val);
+                    term.ops.push(*op);
                 }
             }
-            EParentheses(i) => get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns),
+            op => terms.push(Term {
+                neg: op == Some(&ESub),
+                vals: vec![val],
+                ops: Vec::new(),
+            }),
         }
     }
-}
 
-impl StdFunc {
-    /// Custom Function processing step during compilation.
-    #[inline]
-    fn process_custom_fn(
-        parsed_slab: &ParseSlab,
+    let mut sum = 0.0;
+    let mut rest = Vec::with_capacity(terms.len());
+    for term in terms {
+        let term = simplify_term(term, slab);
+        match term.vals.as_slice() {
+            [Value::EConstant(c)] => sum += if term.neg { -c } else { *c },
+            _ => rest.push(term),
+        }
+    }
+
+    let mut out = Expression::default();
+    // Put the constant first if it is needed as the left operand of a subtraction:
+    let sum_first = rest.first().is_none_or(|term| term.neg);
+    if sum_first {
+        out.first = Value::EConstant(sum);
+    }
+    for (i, term) in rest.into_iter().enumerate() {
+        let mut vals = term.vals.into_iter();
+        let first = vals.next().unwrap_or_default();
+        if i == 0 && !sum_first {
+            out.first = first;
+        } else {
+            out.pairs
+                .push(ExprPair(if term.neg { ESub } else { EAdd }, first));
+        }
+        out.pairs.extend(
+            term.ops
+                .into_iter()
+                .zip(vals)
+                .map(|(op, val)| ExprPair(op, val)),
+        );
+    }
+    if !sum_first && sum != 0.0 {
+        out.pairs.push(if sum < 0.0 {
+            ExprPair(ESub, Value::EConstant(-sum))
+        } else {
+            ExprPair(EAdd, Value::EConstant(sum))
+        });
+    }
+    out
+}
+
+/// Drops identity operations like `x * 1` from a term, and folds constant terms.
+fn simplify_term(mut term: Term, slab: &Slab) -> Term {
+    // A leading '1 *' can always be dropped because '*' binds loosest within a term:
+    if matches!(term.vals.first(), Some(Value::EConstant(c)) if *c == 1.0)
+        && term.ops.first() == Some(&EMul)
+    {
+        term.vals.remove(0);
+        term.ops.remove(0);
+    }
+    // A trailing operand of 1 can be dropped unless the next operator binds tighter:
+    let mut i = 0;
+    while let Some(&op) = term.ops.get(i) {
+        let next = term.ops.get(i + 1);
+        let identity = matches!(term.vals.get(i + 1), Some(Value::EConstant(c)) if *c == 1.0)
+            && match op {
+                EMul => matches!(next, None | Some(EMul)),
+                EDiv => matches!(next, None | Some(EMul | EDiv)),
+                EExp => next != Some(&EExp),
+                _ => false,
+            };
+        if identity {
+            term.ops.remove(i);
+            term.vals.remove(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut vals = term.vals.into_iter();
+    let expr = Expression {
+        first: vals.next().unwrap_or_default(),
+        pairs: term
+            .ops
+            .into_iter()
+            .zip(vals)
+            .map(|(op, val)| ExprPair(op, val))
+            .collect(),
+    };
+    let expr = fold_expr(expr, slab);
+    let mut vals = vec![expr.first];
+    let mut ops = Vec::with_capacity(expr.pairs.len());
+    for ExprPair(op, val) in expr.pairs {
+        ops.push(op);
+        vals.push(val);
+    }
+    Term {
+        neg: term.neg,
+        vals,
+        ops,
+    }
+}
+
+/// Evaluates an `Expression` that only contains constants.
+fn fold_expr(expr: Expression, slab: &Slab) -> Expression {
+    if expr.pairs.is_empty()
+        || !matches!(expr.first, Value::EConstant(_))
+        || !expr
+            .pairs
+            .iter()
+            .all(|ExprPair(_, val)| matches!(val, Value::EConstant(_)))
+    {
+        return expr;
+    }
+    match expr.eval(slab, &mut EmptyNamespace) {
+        Ok(c) => Expression {
+            first: Value::EConstant(c),
+            pairs: Vec::new(),
+        },
+        Err(_) => expr,
+    }
+}
+
+fn simplify_val(val: &Value, slab: &mut Slab) -> Result<Value, Error> {
+    let out = match val {
+        Value::EConstant(c) => return Ok(Value::EConstant(*c)),
+        Value::EUnaryOp(EPos(vi)) => {
+            let inner = slab.ps.get_val(*vi).clone();
+            return simplify_val(&inner, slab);
+        }
+        Value::EUnaryOp(ENeg(vi)) => {
+            let inner = slab.ps.get_val(*vi).clone();
+            match simplify_val(&inner, slab)? {
+                Value::EConstant(c) => return Ok(Value::EConstant(-c)),
+                inner => Value::EUnaryOp(ENeg(slab.ps.push_val(inner)?)),
+            }
+        }
+        Value::EUnaryOp(ENot(vi)) => {
+            let inner = slab.ps.get_val(*vi).clone();
+            let inner = simplify_val(&inner, slab)?;
+            let is_const = matches!(inner, Value::EConstant(_));
+            let out = Value::EUnaryOp(ENot(slab.ps.push_val(inner)?));
+            if !is_const {
+                return Ok(out);
+            }
+            out
+        }
+        Value::EUnaryOp(EParentheses(xi)) => {
+            let inner = slab.ps.get_expr(*xi).clone();
+            let inner = simplify_expr(&inner, slab)?;
+            if inner.pairs.is_empty() {
+                return Ok(inner.first);
+            }
+            return Ok(Value::EUnaryOp(EParentheses(slab.ps.push_expr(inner)?)));
+        }
+        Value::EStdFunc(f) => {
+            let mut f = f.clone();
+            let mut is_const = f.call_name().is_some() && !matches!(f, EFunc { .. });
+            for xi in f.args_mut() {
+                let arg = slab.ps.get_expr(*xi).clone();
+                let arg = simplify_expr(&arg, slab)?;
+                is_const &= arg.pairs.is_empty() && matches!(arg.first, Value::EConstant(_));
+                *xi = slab.ps.push_expr(arg)?;
+            }
+            if !is_const {
+                return Ok(Value::EStdFunc(f));
+            }
+            Value::EStdFunc(f)
+        }
+        // print() has side effects, so it is kept as-is:
+        #[cfg(feature = "print-builtin")]
+        Value::EPrintFunc(pf) => return Ok(Value::EPrintFunc(pf.clone())),
+    };
+    // All operands are constant, so the result is too:
+    Ok(out
+        .eval(slab, &mut EmptyNamespace)
+        .map_or(out, Value::EConstant))
+}
+
+/// Recursively searches an `Expression` for literal zero divisors.
+/// Performs the checks of `compile_checked()`.
+fn check_expr(expr: &Expression, pslab: &ParseSlab, ns: &impl EvalNamespace) -> Result<(), Error> {
+    check_val(&expr.first, pslab, ns)?;
+    for ExprPair(op, val) in &expr.pairs {
+        if let (EDiv | EMod, Value::EConstant(c)) = (op, val) {
+            if *c == 0.0 {
+                return Err(Error::DivideByZero);
+            }
+        }
+        check_val(val, pslab, ns)?;
+    }
+    Ok(())
+}
+
+fn check_val(val: &Value, pslab: &ParseSlab, ns: &impl EvalNamespace) -> Result<(), Error> {
+    match val {
+        Value::EConstant(_) => Ok(()),
+        Value::EUnaryOp(EPos(vi) | ENeg(vi) | ENot(vi)) => {
+            check_val(get_val!(pslab, vi), pslab, ns)
+        }
+        Value::EUnaryOp(EParentheses(xi)) => check_expr(get_expr!(pslab, xi), pslab, ns),
+        Value::EStdFunc(f) => {
+            match f {
+                EVar(name) => check_arity(name, ns.arity(name), 0)?,
+                EFunc { name, args } => check_arity(name, ns.arity(name), args.len())?,
+                _ => (),
+            }
+            for xi in f.args() {
+                check_expr(get_expr!(pslab, xi), pslab, ns)?;
+            }
+            Ok(())
+        }
+        #[cfg(feature = "print-builtin")]
+        Value::EPrintFunc(pf) => {
+            for x_or_s in &pf.0 {
+                if let ExpressionOrString::EExpr(xi) = x_or_s {
+                    check_expr(get_expr!(pslab, xi), pslab, ns)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A comparison of a variable with a constant, like `x < 3`.
+struct Bound<'a> {
+    name: &'a str,
+    /// `true` for a lower bound, like `x > c`.
+    lower: bool,
+    inclusive: bool,
+    c: Num,
+}
+
+impl Bound<'_> {
+    /// Returns `true` if no value satisfies both bounds.
+    fn excludes(&self, other: &Self) -> bool {
+        if self.name != other.name || self.lower == other.lower {
+            return false;
+        }
+        let (lo, hi) = if self.lower {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let overlap = lo.c < hi.c || (lo.inclusive && hi.inclusive && lo.c <= hi.c);
+        !overlap
+    }
+}
+
+/// Returns the `Bound` that `instr` places on a variable, or `None` if it is
+/// not a `<`, `<=`, `>` or `>=` comparison of a variable with a constant.
+/// With `negate`, the `Bound` for `!instr` is returned instead.
+fn var_bound<'a>(
+    instr: &'a Instruction,
+    cslab: &'a CompileSlab,
+    negate: bool,
+) -> Option<Bound<'a>> {
+    let (l, r, lower, inclusive) = match instr {
+        ILT(l, r) => (l, r, false, false),
+        ILTE(l, r) => (l, r, false, true),
+        IGT(l, r) => (l, r, true, false),
+        IGTE(l, r) => (l, r, true, true),
+        _ => return None,
+    };
+    let var_name = |ic: &'a IC| match ic {
+        IC::I(i) => match get_instr!(cslab, i) {
+            IVar(name) => Some(name.as_str()),
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { name, .. } => Some(name.as_str()),
+            _ => None,
+        },
+        IC::C(_) => None,
+    };
+    // Flip `c < x` into `x > c`:
+    let (name, c, lower) = match (l, r) {
+        (_, IC::C(c)) => (var_name(l)?, *c, lower),
+        (IC::C(c), _) => (var_name(r)?, *c, !lower),
+        _ => return None,
+    };
+    if c.is_nan() {
+        return None;
+    }
+    Some(Bound {
+        name,
+        lower: lower != negate,
+        inclusive: inclusive != negate,
+        c,
+    })
+}
+
+/// Pushes the operands of a chain of `IAND` (if `is_and`) or `IOR`
+/// instructions into `dst`.
+fn chain_operands<'a>(
+    instr: &'a Instruction,
+    cslab: &'a CompileSlab,
+    is_and: bool,
+    dst: &mut Vec<&'a Instruction>,
+) {
+    match instr {
+        IAND(li, ric) | IOR(li, ric) if matches!(instr, IAND(..)) == is_and => {
+            chain_operands(get_instr!(cslab, li), cslab, is_and, dst);
+            if let IC::I(ri) = ric {
+                chain_operands(get_instr!(cslab, ri), cslab, is_and, dst);
+            }
+        }
+        _ => dst.push(instr),
+    }
+}
+
+/// Performs the checks of `compile_with_warnings()` on `root` and the
+/// `Instruction`s that were added to `cslab` after `start`.
+fn logic_warnings(root: &Instruction, cslab: &CompileSlab, start: usize) -> Vec<Warning> {
+    // Chains that are part of a longer chain are checked with it:
+    let mut nested = vec![false; cslab.instrs.len()];
+    let instrs = cslab.instrs.iter().skip(start).chain(Some(root));
+    for instr in instrs.clone() {
+        if let IAND(li, ric) | IOR(li, ric) = instr {
+            let is_and = matches!(instr, IAND(..));
+            for ii in Some(*li).into_iter().chain(match ric {
+                IC::I(ri) => Some(*ri),
+                IC::C(_) => None,
+            }) {
+                let same_op = match get_instr!(cslab, ii) {
+                    IAND(..) => is_and,
+                    IOR(..) => !is_and,
+                    _ => false,
+                };
+                if same_op {
+                    if let Some(flag) = nested.get_mut(ii.0) {
+                        *flag = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for (i, instr) in instrs.enumerate() {
+        let is_and = match instr {
+            IAND(..) => true,
+            IOR(..) => false,
+            _ => continue,
+        };
+        if nested.get(start + i).copied().unwrap_or(false) {
+            continue;
+        }
+        let mut operands = Vec::new();
+        chain_operands(instr, cslab, is_and, &mut operands);
+        // An `||` chain is always true if the negated bounds contradict:
+        let bounds: Vec<Bound> = operands
+            .into_iter()
+            .filter_map(|operand| var_bound(operand, cslab, !is_and))
+            .collect();
+        let found = bounds
+            .iter()
+            .enumerate()
+            .find(|(j, b)| bounds[j + 1..].iter().any(|other| b.excludes(other)));
+        if let Some((_, bound)) = found {
+            let name = String::from(bound.name);
+            warnings.push(if is_and {
+                Warning::Contradiction(name)
+            } else {
+                Warning::Tautology(name)
+            });
+        }
+    }
+    warnings
+}
+
+impl Compiler for Value {
+    fn compile(
+        &self,
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        ns: &mut impl EvalNamespace,
+    ) -> Instruction {
+        match self {
+            Self::EConstant(c) => IConst(*c),
+            Self::EUnaryOp(u) => u.compile(parsed_slab, compiled_slab, ns),
+            Self::EStdFunc(f) => f.compile(parsed_slab, compiled_slab, ns),
+            #[cfg(feature = "print-builtin")]
+            Self::EPrintFunc(pf) => IPrintFunc(pf.clone()),
+        }
+    }
+}
+
+impl Compiler for UnaryOp {
+    fn compile(
+        &self,
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        ns: &mut impl EvalNamespace,
+    ) -> Instruction {
+        match self {
+            EPos(i) => get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns),
+            ENeg(i) => {
+                let instr = get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns);
+                if let IConst(c) = instr {
+                    IConst(-c)
+                } else {
+                    neg_wrap(instr, compiled_slab)
+                }
+            }
+            ENot(i) => {
+                let instr = get_val!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns);
+                if let IConst(c) = instr {
+                    IConst(bool_to_f32!(f32_eq!(c, 0.0)))
+                } else {
+                    not_wrap(instr, compiled_slab)
+                }
+            }
+            EParentheses(i) => get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, ns),
+        }
+    }
+}
+
+impl StdFunc {
+    /// Custom Function processing step during compilation.
+    #[inline]
+    fn process_custom_fn(
+        parsed_slab: &ParseSlab,
         compiled_slab: &mut CompileSlab,
         namespace: &mut impl EvalNamespace,
-        name: &String,
+        name: &str,
         expressions: &Vec<ExpressionI>,
         celled_parsed_slab: &RefCell<String>,
     ) -> Instruction {
         let mut args = Vec::<IC>::with_capacity(expressions.len());
-        let mut f32_args = Vec::<f32>::with_capacity(expressions.len());
+        let mut f32_args = Vec::<Num>::with_capacity(expressions.len());
         let mut is_all_const = true;
         for expr in expressions {
             let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
@@ -921,22 +2052,22 @@ impl StdFunc {
             args.push(instr_to_ic!(compiled_slab, instr));
         }
         if is_all_const {
-            let computed_value = eval_var!(
+            let computed_value = call_func(
                 namespace,
                 name,
                 f32_args,
-                &mut celled_parsed_slab.borrow_mut()
+                &mut celled_parsed_slab.borrow_mut(),
             );
             computed_value.map_or_else(
-                |_| IFunc {
-                    name: name.clone(),
+                || IFunc {
+                    name: String::from(name),
                     args,
                 },
                 IConst,
             )
         } else {
             IFunc {
-                name: name.clone(),
+                name: String::from(name),
                 args,
             }
         }
@@ -952,10 +2083,12 @@ impl StdFunc {
     ) -> Instruction {
         let instr =
             get_expr!(parsed_slab, expression).compile(parsed_slab, compiled_slab, namespace);
-        if let IConst(c) = instr {
-            IConst(c.trunc())
-        } else {
-            IFuncInt(compiled_slab.push_instr(instr))
+        match instr {
+            // Non-finite values are left for eval(), which reports them in strict mode:
+            IConst(c) if c.is_finite() => IConst(c.trunc()),
+            // int(int(x)) == int(x):
+            IFuncInt(_) => instr,
+            _ => IFuncInt(compiled_slab.push_instr(instr)),
         }
     }
 
@@ -968,10 +2101,12 @@ impl StdFunc {
         expr: ExpressionI,
     ) -> Instruction {
         let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
-        if let IConst(c) = instr {
-            IConst(c.ceil())
-        } else {
-            IFuncCeil(compiled_slab.push_instr(instr))
+        match instr {
+            // Non-finite values are left for eval(), which reports them in strict mode:
+            IConst(c) if c.is_finite() => IConst(c.ceil()),
+            // ceil(ceil(x)) == ceil(x):
+            IFuncCeil(_) => instr,
+            _ => IFuncCeil(compiled_slab.push_instr(instr)),
         }
     }
 
@@ -984,10 +2119,12 @@ impl StdFunc {
         expr: ExpressionI,
     ) -> Instruction {
         let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
-        if let IConst(c) = instr {
-            IConst(c.floor())
-        } else {
-            IFuncFloor(compiled_slab.push_instr(instr))
+        match instr {
+            // Non-finite values are left for eval(), which reports them in strict mode:
+            IConst(c) if c.is_finite() => IConst(c.floor()),
+            // floor(floor(x)) == floor(x):
+            IFuncFloor(_) => instr,
+            _ => IFuncFloor(compiled_slab.push_instr(instr)),
         }
     }
 
@@ -1000,10 +2137,11 @@ impl StdFunc {
         expr: ExpressionI,
     ) -> Instruction {
         let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
-        if let IConst(c) = instr {
-            IConst(c.abs())
-        } else {
-            IFuncAbs(compiled_slab.push_instr(instr))
+        match instr {
+            IConst(c) => IConst(c.abs()),
+            // abs(abs(x)) == abs(x):
+            IFuncAbs(_) => instr,
+            _ => IFuncAbs(compiled_slab.push_instr(instr)),
         }
     }
 
@@ -1016,10 +2154,11 @@ impl StdFunc {
         expr: ExpressionI,
     ) -> Instruction {
         let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
-        if let IConst(c) = instr {
-            IConst(c.signum())
-        } else {
-            IFuncSign(compiled_slab.push_instr(instr))
+        match instr {
+            IConst(c) => IConst(c.signum()),
+            // sign(sign(x)) == sign(x):
+            IFuncSign(_) => instr,
+            _ => IFuncSign(compiled_slab.push_instr(instr)),
         }
     }
 
@@ -1041,6 +2180,9 @@ impl StdFunc {
                 return IConst(log(b, n));
             }
         }
+        // 'log(b, b) == 1' and 'log(b, 1) == 0' are not used: they only hold for
+        // a valid base (positive and not 1), and a base that is not a constant
+        // could be invalid, which gives NaN.
         IFuncLog {
             base: instr_to_ic!(compiled_slab, base),
             of: instr_to_ic!(compiled_slab, instr),
@@ -1055,6 +2197,8 @@ impl StdFunc {
         namespace: &mut impl EvalNamespace,
         mod_option: &Option<ExpressionI>,
         expr: ExpressionI,
+        rounder: fn(Num) -> Num,
+        to_instr: fn(IC, IC) -> Instruction,
     ) -> Instruction {
         let modulus: Instruction = mod_option.as_ref().map_or(IConst(1.0), |mi| {
             get_expr!(parsed_slab, mi).compile(parsed_slab, compiled_slab, namespace)
@@ -1062,13 +2206,17 @@ impl StdFunc {
         let instr = get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
         if let IConst(m) = modulus {
             if let IConst(n) = instr {
-                return IConst((n / m).round() * m); // Floats don't overflow.
+                // Floats don't overflow.
+                let rounded = rounder(n / m) * m;
+                // Non-finite results are left for eval(), which reports them in strict mode:
+                if rounded.is_finite() {
+                    return IConst(rounded);
+                }
             }
         }
-        IFuncRound {
-            modulus: instr_to_ic!(compiled_slab, modulus),
-            of: instr_to_ic!(compiled_slab, instr),
-        }
+        let modulus = instr_to_ic!(compiled_slab, modulus);
+        let of = instr_to_ic!(compiled_slab, instr);
+        to_instr(modulus, of)
     }
 
     /// Min processing step during compilation.
@@ -1085,46 +2233,163 @@ impl StdFunc {
         for i in is {
             rest.push(get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
         }
-        let mut out = IConst(0.0);
-        let mut out_set = false;
+        let mut operands = Vec::<Instruction>::with_capacity(is.len() + 1);
         let mut const_min = 0.0;
         let mut const_min_set = false;
-        if let IConst(f) = first {
-            const_min = f;
-            const_min_set = true;
-        } else {
-            out = first;
-            out_set = true;
-        }
-        for instr in rest {
+        // NaN constants are not folded, so that eval() can apply `Slab.nan_policy`:
+        let mut nan_consts = 0;
+        let missing = namespace.missing();
+        for instr in Some(first).into_iter().chain(rest) {
             if let IConst(f) = instr {
-                if const_min_set {
-                    if f < const_min {
+                if is_missing(f, missing) {
+                    // Skipped.
+                } else if f.is_nan() {
+                    nan_consts += 1;
+                } else if const_min_set {
+                    // Like eval(), the later argument wins ties (only visible for 0 and -0):
+                    if f <= const_min {
                         const_min = f;
                     }
                 } else {
                     const_min = f;
                     const_min_set = true;
                 }
-            } else if out_set {
-                out = IFuncMin(
-                    compiled_slab.push_instr(out),
-                    IC::I(compiled_slab.push_instr(instr)),
-                );
             } else {
-                out = instr;
-                out_set = true;
+                operands.push(instr);
             }
         }
         if const_min_set {
-            if out_set {
-                out = IFuncMin(compiled_slab.push_instr(out), IC::C(const_min));
+            operands.push(IConst(const_min));
+        }
+        for _ in 0..nan_consts {
+            operands.push(IConst(Num::NAN));
+        }
+        compile_min_max(operands, compiled_slab, missing, true)
+    }
+
+    /// Product processing step during compilation.
+    ///
+    /// Compiles like the equivalent chain of `*` operations, so the constant
+    /// factors are folded together.
+    #[inline]
+    fn process_prod(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        fi: ExpressionI,
+        is: &Vec<ExpressionI>,
+    ) -> Instruction {
+        let mut instrs = Vec::<Instruction>::with_capacity(is.len() + 1);
+        for i in Some(&fi).into_iter().chain(is) {
+            let instr = get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace);
+            if let IMul(li, ric) = instr {
+                push_mul_leaves(&mut instrs, compiled_slab, li, &ric);
             } else {
-                out = IConst(const_min);
-                // out_set = true;  // Comment out so the compiler doesn't complain about unused assignments.
+                instrs.push(instr);
+            }
+        }
+        compile_mul(instrs, compiled_slab)
+    }
+
+    /// `min_first()`/`max_first()` processing step during compilation.
+    ///
+    /// Unlike `min()` and `max()`, the arguments are never reordered, so ties
+    /// are broken the same way as in `eval()`.  The call is only folded if all
+    /// arguments are non-NaN constants.
+    #[inline]
+    fn process_min_max_first(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        fi: ExpressionI,
+        is: &Vec<ExpressionI>,
+        is_min: bool,
+    ) -> Instruction {
+        let mut instrs = Vec::<Instruction>::with_capacity(is.len() + 1);
+        for i in Some(&fi).into_iter().chain(is) {
+            instrs.push(get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        }
+
+        let missing = namespace.missing();
+        if instrs
+            .iter()
+            .all(|instr| matches!(instr, IConst(f) if !f.is_nan()))
+        {
+            let mut out: Option<Num> = None;
+            for instr in &instrs {
+                if let IConst(f) = *instr {
+                    if is_missing(f, missing) {
+                        continue;
+                    }
+                    out = Some(match out {
+                        Some(acc) if is_min && f < acc => f,
+                        Some(acc) if !is_min && f > acc => f,
+                        Some(acc) => acc,
+                        None => f,
+                    });
+                }
+            }
+            // If every argument was missing:
+            return IConst(out.or(missing).unwrap_or(Num::NAN));
+        }
+
+        let mut instrs = instrs.into_iter();
+        let mut out = instrs.next().unwrap_or(IConst(Num::NAN));
+        for instr in instrs {
+            let li = compiled_slab.push_instr(out);
+            let ric = instr_to_ic!(compiled_slab, instr);
+            out = if is_min {
+                IFuncMinFirst(li, ric)
+            } else {
+                IFuncMaxFirst(li, ric)
+            };
+        }
+        out
+    }
+
+    /// `nanmin()`/`nanmax()` processing step during compilation.
+    ///
+    /// The call is folded if all arguments are constants.  Otherwise, NaN and
+    /// `missing` constants are dropped, since they never change the result.
+    /// The remaining arguments keep their order, so ties are broken the same
+    /// way as in `eval()`.
+    #[inline]
+    fn process_nan_min_max(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        fi: ExpressionI,
+        is: &Vec<ExpressionI>,
+        is_min: bool,
+    ) -> Instruction {
+        let mut instrs = Vec::<Instruction>::with_capacity(is.len() + 1);
+        for i in Some(&fi).into_iter().chain(is) {
+            instrs.push(get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        }
+
+        let missing = namespace.missing();
+        if instrs.iter().all(|instr| matches!(instr, IConst(_))) {
+            let mut out: Option<Num> = None;
+            for instr in &instrs {
+                if let IConst(f) = *instr {
+                    out = Some(out.map_or(f, |acc| nan_min_max(missing, acc, f, is_min)));
+                }
             }
+            return IConst(out.unwrap_or(Num::NAN));
+        }
+        instrs.retain(|instr| !matches!(instr, IConst(f) if f.is_nan() || is_missing(*f, missing)));
+
+        let mut instrs = instrs.into_iter();
+        let mut out = instrs.next().unwrap_or(IConst(Num::NAN));
+        for instr in instrs {
+            let li = compiled_slab.push_instr(out);
+            let ric = instr_to_ic!(compiled_slab, instr);
+            out = if is_min {
+                IFuncNanMin(li, ric)
+            } else {
+                IFuncNanMax(li, ric)
+            };
         }
-        //assert!(out_set);
         out
     }
 
@@ -1142,55 +2407,336 @@ impl StdFunc {
         for i in is {
             rest.push(get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
         }
-        let mut out = IConst(0.0);
-        let mut out_set = false;
+        let mut operands = Vec::<Instruction>::with_capacity(is.len() + 1);
         let mut const_max = 0.0;
         let mut const_max_set = false;
-        if let IConst(f) = first {
-            const_max = f;
-            const_max_set = true;
-        } else {
-            out = first;
-            out_set = true;
-        }
-        for instr in rest {
+        // NaN constants are not folded, so that eval() can apply `Slab.nan_policy`:
+        let mut nan_consts = 0;
+        let missing = namespace.missing();
+        for instr in Some(first).into_iter().chain(rest) {
             if let IConst(f) = instr {
-                if const_max_set {
-                    if f > const_max {
+                if is_missing(f, missing) {
+                    // Skipped.
+                } else if f.is_nan() {
+                    nan_consts += 1;
+                } else if const_max_set {
+                    // Like eval(), the later argument wins ties (only visible for 0 and -0):
+                    if f >= const_max {
                         const_max = f;
                     }
                 } else {
                     const_max = f;
                     const_max_set = true;
                 }
-            } else if out_set {
-                out = IFuncMax(
-                    compiled_slab.push_instr(out),
-                    IC::I(compiled_slab.push_instr(instr)),
-                );
             } else {
-                out = instr;
-                out_set = true;
+                operands.push(instr);
             }
         }
         if const_max_set {
-            if out_set {
-                out = IFuncMax(compiled_slab.push_instr(out), IC::C(const_max));
+            operands.push(IConst(const_max));
+        }
+        for _ in 0..nan_consts {
+            operands.push(IConst(Num::NAN));
+        }
+        compile_min_max(operands, compiled_slab, missing, false)
+    }
+
+    /// Blend processing step during compilation.
+    #[inline]
+    fn process_blend(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &Vec<ExpressionI>,
+    ) -> Instruction {
+        let mut args = Vec::<IC>::with_capacity(xis.len());
+        let mut f32_args = Vec::<Num>::with_capacity(xis.len());
+        let mut is_all_const = true;
+        for xi in xis {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            if let IConst(c) = instr {
+                f32_args.push(c);
             } else {
-                out = IConst(const_max);
-                // out_set = true;  // Comment out so the compiler doesn't complain about unused assignments.
+                is_all_const = false;
             }
+            args.push(instr_to_ic!(compiled_slab, instr));
+        }
+        if is_all_const {
+            IConst(blend(&f32_args))
+        } else {
+            IFuncBlend(args)
+        }
+    }
+
+    /// Average and sum processing step during compilation.
+    #[inline]
+    fn process_avg_sum(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &Vec<ExpressionI>,
+        folder: fn(&[Num], Option<Num>) -> Num,
+        to_instr: fn(Vec<IC>) -> Instruction,
+    ) -> Instruction {
+        let mut args = Vec::<IC>::with_capacity(xis.len());
+        let mut f32_args = Vec::<Num>::with_capacity(xis.len());
+        let mut is_all_const = true;
+        for xi in xis {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            if let IConst(c) = instr {
+                f32_args.push(c);
+            } else {
+                is_all_const = false;
+            }
+            args.push(instr_to_ic!(compiled_slab, instr));
+        }
+        if is_all_const {
+            IConst(folder(&f32_args, namespace.missing()))
+        } else {
+            to_instr(args)
+        }
+    }
+
+    /// Norm processing step during compilation.
+    #[inline]
+    fn process_norm(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &Vec<ExpressionI>,
+    ) -> Instruction {
+        match Self::process_sumsq(parsed_slab, compiled_slab, namespace, xis) {
+            IConst(c) => IConst(c.sqrt()),
+            IFuncSumSq(args) => IFuncNorm(args),
+            _ => IConst(Num::NAN), // unreachable
+        }
+    }
+
+    /// Sum of squares processing step during compilation.
+    #[inline]
+    fn process_sumsq(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &Vec<ExpressionI>,
+    ) -> Instruction {
+        let mut args = Vec::<IC>::with_capacity(xis.len());
+        let mut f32_args = Vec::<Num>::with_capacity(xis.len());
+        let mut is_all_const = true;
+        for xi in xis {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            if let IConst(c) = instr {
+                f32_args.push(c);
+            } else {
+                is_all_const = false;
+            }
+            args.push(instr_to_ic!(compiled_slab, instr));
+        }
+        if is_all_const {
+            IConst(sumsq(&f32_args))
+        } else {
+            IFuncSumSq(args)
+        }
+    }
+
+    /// Poly processing step during compilation.
+    #[inline]
+    fn process_poly(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &Vec<ExpressionI>,
+    ) -> Instruction {
+        let mut args = Vec::<IC>::with_capacity(xis.len());
+        let mut f32_args = Vec::<Num>::with_capacity(xis.len());
+        let mut is_all_const = true;
+        for xi in xis {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            if let IConst(c) = instr {
+                f32_args.push(c);
+            } else {
+                is_all_const = false;
+            }
+            args.push(instr_to_ic!(compiled_slab, instr));
+        }
+        if is_all_const {
+            IConst(poly(&f32_args))
+        } else {
+            IFuncPoly(args)
+        }
+    }
+
+    /// Seq processing step during compilation.
+    ///
+    /// Constant arguments have no side effects, so they are dropped unless they
+    /// are the last one.  If only the last argument is left, it is returned directly.
+    #[inline]
+    fn process_seq(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        xis: &[ExpressionI],
+    ) -> Instruction {
+        let mut instrs = Vec::<Instruction>::with_capacity(xis.len());
+        for (i, xi) in xis.iter().enumerate() {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            if i + 1 == xis.len() || !matches!(instr, IConst(_)) {
+                instrs.push(instr);
+            }
+        }
+        if instrs.len() == 1 {
+            return instrs.pop().unwrap_or(IConst(Num::NAN));
+        }
+        let mut args = Vec::<IC>::with_capacity(instrs.len());
+        for instr in instrs {
+            args.push(instr_to_ic!(compiled_slab, instr));
+        }
+        IFuncSeq(args)
+    }
+
+    /// Bucket processing step during compilation.
+    #[inline]
+    fn process_bucket(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        args: [ExpressionI; 4],
+    ) -> Instruction {
+        let [x, lo, hi, n] =
+            args.map(|i| get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        if let (IConst(x), IConst(lo), IConst(hi), IConst(n)) = (&x, &lo, &hi, &n) {
+            return IConst(bucket(*x, *lo, *hi, *n));
+        }
+        IFuncBucket {
+            x: instr_to_ic!(compiled_slab, x),
+            lo: instr_to_ic!(compiled_slab, lo),
+            hi: instr_to_ic!(compiled_slab, hi),
+            n: instr_to_ic!(compiled_slab, n),
+        }
+    }
+
+    /// Modular exponentiation processing step during compilation.
+    #[inline]
+    fn process_powmod(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        args: [ExpressionI; 3],
+    ) -> Instruction {
+        let [base, exp, modulus] =
+            args.map(|i| get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        if let (IConst(base), IConst(exp), IConst(modulus)) = (&base, &exp, &modulus) {
+            return IConst(powmod(*base, *exp, *modulus));
+        }
+        IFuncPowMod {
+            base: instr_to_ic!(compiled_slab, base),
+            exp: instr_to_ic!(compiled_slab, exp),
+            modulus: instr_to_ic!(compiled_slab, modulus),
+        }
+    }
+
+    /// Smoothstep processing step during compilation.
+    #[inline]
+    fn process_smoothstep(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        args: [ExpressionI; 3],
+    ) -> Instruction {
+        let [edge0, edge1, x] =
+            args.map(|i| get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        if let (IConst(edge0), IConst(edge1), IConst(x)) = (&edge0, &edge1, &x) {
+            return IConst(smoothstep(*edge0, *edge1, *x));
+        }
+        IFuncSmoothstep {
+            edge0: instr_to_ic!(compiled_slab, edge0),
+            edge1: instr_to_ic!(compiled_slab, edge1),
+            x: instr_to_ic!(compiled_slab, x),
         }
-        //assert!(out_set);
-        out
     }
 
+    /// Clamp processing step during compilation.
+    #[inline]
+    fn process_clamp(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        args: [ExpressionI; 3],
+    ) -> Instruction {
+        let [val, lo, hi] =
+            args.map(|i| get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace));
+        if let (IConst(val), IConst(lo), IConst(hi)) = (&val, &lo, &hi) {
+            return IConst(clamp(*val, *lo, *hi));
+        }
+        IFuncClamp {
+            val: instr_to_ic!(compiled_slab, val),
+            lo: instr_to_ic!(compiled_slab, lo),
+            hi: instr_to_ic!(compiled_slab, hi),
+        }
+    }
+
+    /// Select processing step during compilation.
+    ///
+    /// A constant index is resolved here, so only the chosen option is compiled.
+    /// Out-of-range indices are left for eval(), which reports them in strict mode.
+    #[inline]
+    fn process_select(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        index: ExpressionI,
+        options: &[ExpressionI],
+    ) -> Instruction {
+        let index = get_expr!(parsed_slab, index).compile(parsed_slab, compiled_slab, namespace);
+        if let IConst(c) = index {
+            if let Some(xi) = select_index(c, options.len()).and_then(|i| options.get(i)) {
+                return get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            }
+        }
+        let index = instr_to_ic!(compiled_slab, index);
+        let mut ics = Vec::<IC>::with_capacity(options.len());
+        for xi in options {
+            let instr = get_expr!(parsed_slab, xi).compile(parsed_slab, compiled_slab, namespace);
+            ics.push(instr_to_ic!(compiled_slab, instr));
+        }
+        IFuncSelect {
+            index,
+            options: ics,
+        }
+    }
+
+    process_fn!(process_sqrt, sqrt, IFuncSqrt);
+    process_fn!(process_cbrt, cbrt, IFuncCbrt);
     process_fn!(process_sin, sin, IFuncSin);
     process_fn!(process_cos, cos, IFuncCos);
     process_fn!(process_tan, tan, IFuncTan);
     process_fn!(process_asin, asin, IFuncASin);
     process_fn!(process_acos, acos, IFuncACos);
     process_fn!(process_atan, atan, IFuncATan);
+    process_fn!(process_asind, asin, to_degrees, IFuncASinD);
+    process_fn!(process_acosd, acos, to_degrees, IFuncACosD);
+    process_fn!(process_atand, atan, to_degrees, IFuncATanD);
+
+    /// Two-argument arctangent (in radians or degrees) processing step during compilation.
+    #[inline]
+    fn process_atan2(
+        parsed_slab: &ParseSlab,
+        compiled_slab: &mut CompileSlab,
+        namespace: &mut impl EvalNamespace,
+        y: ExpressionI,
+        x: ExpressionI,
+        atan2: fn(Num, Num) -> Num,
+        to_instr: fn(IC, IC) -> Instruction,
+    ) -> Instruction {
+        let y = get_expr!(parsed_slab, y).compile(parsed_slab, compiled_slab, namespace);
+        let x = get_expr!(parsed_slab, x).compile(parsed_slab, compiled_slab, namespace);
+        if let (IConst(y), IConst(x)) = (&y, &x) {
+            return IConst(atan2(*y, *x));
+        }
+        let y = instr_to_ic!(compiled_slab, y);
+        let x = instr_to_ic!(compiled_slab, x);
+        to_instr(y, x)
+    }
 }
 
 impl Compiler for StdFunc {
@@ -1224,6 +2770,14 @@ impl Compiler for StdFunc {
             }
             EFuncAbs(expr) => Self::process_abs_fn(parsed_slab, compiled_slab, namespace, *expr),
             EFuncSign(expr) => Self::process_signum(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncSqrt(expr) => Self::process_sqrt(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncCbrt(expr) => Self::process_cbrt(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncProbRound(expr) => {
+                // Never folded, because every evaluation draws a new random number:
+                let instr =
+                    get_expr!(parsed_slab, expr).compile(parsed_slab, compiled_slab, namespace);
+                IFuncProbRound(compiled_slab.push_instr(instr))
+            }
             EFuncLog {
                 base: base_option,
                 expr,
@@ -1231,7 +2785,39 @@ impl Compiler for StdFunc {
             EFuncRound {
                 modulus: mod_option,
                 expr,
-            } => Self::process_round(parsed_slab, compiled_slab, namespace, mod_option, *expr),
+            } => Self::process_round(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                mod_option,
+                *expr,
+                Num::round,
+                |modulus, of| IFuncRound { modulus, of },
+            ),
+            EFuncRoundHalfUp {
+                modulus: mod_option,
+                expr,
+            } => Self::process_round(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                mod_option,
+                *expr,
+                round_half_up,
+                |modulus, of| IFuncRoundHalfUp { modulus, of },
+            ),
+            EFuncRoundZ {
+                modulus: mod_option,
+                expr,
+            } => Self::process_round(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                mod_option,
+                *expr,
+                round_ties_to_zero,
+                |modulus, of| IFuncRoundZ { modulus, of },
+            ),
             EFuncMin {
                 first: fi,
                 rest: is,
@@ -1240,9 +2826,61 @@ impl Compiler for StdFunc {
                 first: fi,
                 rest: is,
             } => Self::process_max(parsed_slab, compiled_slab, namespace, *fi, is),
+            EFuncMinFirst {
+                first: fi,
+                rest: is,
+            } => Self::process_min_max_first(parsed_slab, compiled_slab, namespace, *fi, is, true),
+            EFuncMaxFirst {
+                first: fi,
+                rest: is,
+            } => Self::process_min_max_first(parsed_slab, compiled_slab, namespace, *fi, is, false),
+            EFuncNanMin {
+                first: fi,
+                rest: is,
+            } => Self::process_nan_min_max(parsed_slab, compiled_slab, namespace, *fi, is, true),
+            EFuncNanMax {
+                first: fi,
+                rest: is,
+            } => Self::process_nan_min_max(parsed_slab, compiled_slab, namespace, *fi, is, false),
+            EFuncProd {
+                first: fi,
+                rest: is,
+            } => Self::process_prod(parsed_slab, compiled_slab, namespace, *fi, is),
+            EFuncBlend(xis) => Self::process_blend(parsed_slab, compiled_slab, namespace, xis),
+            EFuncAvg(xis) => {
+                Self::process_avg_sum(parsed_slab, compiled_slab, namespace, xis, avg, IFuncAvg)
+            }
+            EFuncSum(xis) => {
+                Self::process_avg_sum(parsed_slab, compiled_slab, namespace, xis, sum, IFuncSum)
+            }
+            EFuncNorm(xis) => Self::process_norm(parsed_slab, compiled_slab, namespace, xis),
+            EFuncSumSq(xis) => Self::process_sumsq(parsed_slab, compiled_slab, namespace, xis),
+            EFuncPoly(xis) => Self::process_poly(parsed_slab, compiled_slab, namespace, xis),
+            EFuncSeq(xis) => Self::process_seq(parsed_slab, compiled_slab, namespace, xis),
+            EFuncBucket { x, lo, hi, n } => {
+                Self::process_bucket(parsed_slab, compiled_slab, namespace, [*x, *lo, *hi, *n])
+            }
+            EFuncPowMod { base, exp, modulus } => Self::process_powmod(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                [*base, *exp, *modulus],
+            ),
+            EFuncSmoothstep { edge0, edge1, x } => Self::process_smoothstep(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                [*edge0, *edge1, *x],
+            ),
+            EFuncClamp { val, lo, hi } => {
+                Self::process_clamp(parsed_slab, compiled_slab, namespace, [*val, *lo, *hi])
+            }
+            EFuncSelect { index, options } => {
+                Self::process_select(parsed_slab, compiled_slab, namespace, *index, options)
+            }
 
-            EFuncE => IConst(std::f32::consts::E),
-            EFuncPi => IConst(std::f32::consts::PI),
+            EFuncE => IConst(crate::consts::E),
+            EFuncPi => IConst(crate::consts::PI),
 
             EFuncSin(expr) => Self::process_sin(parsed_slab, compiled_slab, namespace, *expr),
             EFuncCos(expr) => Self::process_cos(parsed_slab, compiled_slab, namespace, *expr),
@@ -1250,22 +2888,43 @@ impl Compiler for StdFunc {
             EFuncASin(expr) => Self::process_asin(parsed_slab, compiled_slab, namespace, *expr),
             EFuncACos(expr) => Self::process_acos(parsed_slab, compiled_slab, namespace, *expr),
             EFuncATan(expr) => Self::process_atan(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncASinD(expr) => Self::process_asind(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncACosD(expr) => Self::process_acosd(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncATanD(expr) => Self::process_atand(parsed_slab, compiled_slab, namespace, *expr),
+            EFuncATan2 { y, x } => Self::process_atan2(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                *y,
+                *x,
+                Num::atan2,
+                |y, x| IFuncATan2 { y, x },
+            ),
+            EFuncATan2D { y, x } => Self::process_atan2(
+                parsed_slab,
+                compiled_slab,
+                namespace,
+                *y,
+                *x,
+                atan2d,
+                |y, x| IFuncATan2D { y, x },
+            ),
             EFuncSinH(i) => {
                 let instr =
                     get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace);
-                if let IConst(c) = instr {
-                    IConst(c.sinh())
-                } else {
-                    IFuncSinH(compiled_slab.push_instr(instr))
+                match instr {
+                    // Overflows are left for eval(), which reports them in strict mode:
+                    IConst(c) if !overflowed(c, c.sinh()) => IConst(c.sinh()),
+                    _ => IFuncSinH(compiled_slab.push_instr(instr)),
                 }
             }
             EFuncCosH(i) => {
                 let instr =
                     get_expr!(parsed_slab, i).compile(parsed_slab, compiled_slab, namespace);
-                if let IConst(c) = instr {
-                    IConst(c.cosh())
-                } else {
-                    IFuncCosH(compiled_slab.push_instr(instr))
+                match instr {
+                    // Overflows are left for eval(), which reports them in strict mode:
+                    IConst(c) if !overflowed(c, c.cosh()) => IConst(c.cosh()),
+                    _ => IFuncCosH(compiled_slab.push_instr(instr)),
                 }
             }
             EFuncTanH(i) => {
@@ -38,7 +38,8 @@ pub enum Error {
     /// when the UTF8 error occurred.
     Utf8ErrorWhileParsing(String),
 
-    /// The expression string input was too long.
+    /// The expression string input was too long, or an integer range
+    /// function argument expanded to too many values.
     ///
     /// This is a safety check that prevents malicious inputs that would
     /// be expensive to parse.
@@ -50,19 +51,41 @@ pub enum Error {
     /// be expensive to parse.
     TooDeep,
 
+    /// The expression referred to more distinct variables and custom functions
+    /// than [`Parser.var_limit`](../parser/struct.Parser.html#structfield.var_limit)
+    /// allows.
+    ///
+    /// This is a safety check that limits the number of Namespace lookups
+    /// that an untrusted expression can cause.
+    TooManyVariables,
+
     /// An expression was parsed, but there is still input data remaining.
     ///
-    /// The `String` field contains the un-parsed input data.
-    UnparsedTokensRemaining(String),
+    /// `rest` contains the un-parsed input data, which starts at byte offset
+    /// `pos` of the expression string.
+    UnparsedTokensRemaining { rest: String, pos: usize },
 
-    /// A value was expected, but invalid input data was found.
-    InvalidValue,
+    /// A value was expected, but invalid input data was found at byte offset
+    /// `pos` of the expression string.
+    InvalidValue { pos: usize },
 
-    /// An error occurred during the parsing of a f32 number.
+    /// An error occurred during the parsing of a `Num`.
     ///
     /// The `String` field contains the data that caused the error.
     ParseF32(String),
 
+    /// A number literal is malformed, like `1e`, `1.2.3` or `1kk`.
+    ///
+    /// The `String` field contains the literal and the reason, like
+    /// `"1e: missing exponent digits"`.
+    MalformedNumber(String),
+
+    /// A number literal is larger in magnitude than the `Parser`'s
+    /// `literal_limit`.
+    ///
+    /// The `String` field contains the literal, like `"1e38"`.
+    LiteralOutOfRange(String),
+
     /// The expected input data was not found.
     ///
     /// The `String` field tells you what was expected.
@@ -73,11 +96,76 @@ pub enum Error {
     /// The `String` field contains information about the expected arguments.
     WrongArgs(String),
 
-    /// The expression tried to use an undefined variable/function.
+    /// The expression tried to use an undefined variable, or a custom function
+    /// without parentheses (like `x`).
     ///
     /// You can define variables/functions with a Namespace.
     Undefined(String),
 
+    /// The expression called an undefined function with parentheses (like `f(1)`
+    /// or `f()`).
+    ///
+    /// You can define variables/functions with a Namespace.
+    UndefinedFunction(String),
+
+    /// A unary operator was directly followed by another unary operator (like
+    /// `++4` or `--x`), but the `Parser` was configured with
+    /// `reject_repeated_unary`.
+    ///
+    /// The `String` field contains the two operators, like `"--"`.
+    UnexpectedOperator(String),
+
+    /// The expression referenced a variable/function, but the `Parser` was
+    /// configured with `reject_variables`.
+    VariablesNotAllowed,
+
+    /// In strict mode (`Slab.strict`), a function overflowed to infinity even
+    /// though its input was finite.
+    Overflow,
+
+    /// In strict mode (`Slab.strict`), `int()`, `ceil()`, `floor()` or one of
+    /// the `round()` functions produced NaN or infinity, usually because its
+    /// input was NaN or infinite.
+    ///
+    /// The `String` field contains the name of the function.
+    NonFinite(String),
+
+    /// Returned by `Expression::compile_checked()` when a division or modulo
+    /// has a literal zero divisor, like `1/0` or `x % 0`.
+    DivideByZero,
+
+    /// Returned by `Expression::compile_with_budget()` when the compiled
+    /// expression needs more `Instruction`s than the budget allows.
+    TooManyInstructions,
+
+    /// Returned by `Instruction::from_bytecode()` when the bytecode is malformed
+    /// or unsupported.
+    ///
+    /// The `String` field describes the problem.
+    InvalidBytecode(String),
+
+    /// Returned by `Slab::from_parts()` when the parts contain an index that
+    /// is out of bounds.
+    ///
+    /// The `String` field names the entry with the bad index, like `"cs.instrs[3]"`.
+    InvalidSlab(String),
+
+    /// The expression used a built-in function that was disabled at build time.
+    ///
+    /// The `String` field contains the name of the function.
+    DisabledFunction(String),
+
+    /// `min()` or `max()` got a NaN argument while `Slab.nan_policy` is
+    /// [`NanPolicy::Error`](../slab/enum.NanPolicy.html#variant.Error).
+    ///
+    /// The `String` field contains the name of the function.
+    NanArgument(String),
+
+    /// `probround()` was evaluated with a Namespace that does not provide
+    /// random numbers.  See
+    /// [`EvalNamespace::random()`](../evalns/trait.EvalNamespace.html#method.random).
+    NoRandomSource,
+
     /// This error should never occur because it is only produced by code paths
     /// that should never execute.  This is more performant than using the
     /// `unreachable!()` macro.
@@ -6,43 +6,61 @@
 
 use crate as fasteval3;
 
+#[cfg(feature = "print-builtin")]
+use crate::compiler::Instruction::IPrintFunc;
 #[cfg(feature = "unsafe-vars")]
 use crate::compiler::Instruction::IUnsafeVar;
 use crate::compiler::{
-    log,
+    atan2d, avg, blend, bucket, clamp, is_missing, log, nan_min_max, overflowed, poly, powmod,
+    probround, round_half_up, round_ties_to_zero, select_index, smoothstep, sum,
     Instruction::{
-        self, IAdd, IConst, IExp, IFunc, IFuncACos, IFuncACosH, IFuncASin, IFuncASinH, IFuncATan,
-        IFuncATanH, IFuncAbs, IFuncCeil, IFuncCos, IFuncCosH, IFuncFloor, IFuncInt, IFuncLog,
-        IFuncMax, IFuncMin, IFuncRound, IFuncSign, IFuncSin, IFuncSinH, IFuncTan, IFuncTanH, IInv,
-        IMod, IMul, INeg, INot, IPrintFunc, IVar, IAND, IEQ, IGT, IGTE, ILT, ILTE, INE, IOR,
+        self, IAdd, IConst, IExp, IFunc, IFuncACos, IFuncACosD, IFuncACosH, IFuncASin, IFuncASinD,
+        IFuncASinH, IFuncATan, IFuncATan2, IFuncATan2D, IFuncATanD, IFuncATanH, IFuncAbs, IFuncAvg,
+        IFuncBlend, IFuncBucket, IFuncCbrt, IFuncCeil, IFuncClamp, IFuncCos, IFuncCosH, IFuncFloor,
+        IFuncInt, IFuncLog, IFuncMax, IFuncMaxFirst, IFuncMaxN, IFuncMin, IFuncMinFirst, IFuncMinN,
+        IFuncNanMax, IFuncNanMin, IFuncNorm, IFuncPoly, IFuncPowMod, IFuncProbRound, IFuncRound,
+        IFuncRoundHalfUp, IFuncRoundZ, IFuncSelect, IFuncSeq, IFuncSign, IFuncSin, IFuncSinH,
+        IFuncSmoothstep, IFuncSqrt, IFuncSum, IFuncSumSq, IFuncTan, IFuncTanH, IInv, IMod, IMul,
+        INeg, INot, IVar, IAND, IEQ, IGT, IGTE, ILT, ILTE, INE, IOR, IXOR,
     },
-    IC,
+    InstructionI, Program, IC,
 };
 use crate::error::Error;
-use crate::evalns::EvalNamespace;
+use crate::evalns::{Cached, EvalNamespace};
 #[cfg(feature = "unsafe-vars")]
 use crate::parser::StdFunc::EUnsafeVar;
 use crate::parser::{
     remove_no_panic,
     BinaryOp::{
-        self, EAdd, EDiv, EExp, EMod, EMul, ESub, EAND, EEQ, EGT, EGTE, ELT, ELTE, ENE, EOR,
+        self, EAdd, EDiv, EExp, EMod, EMul, ESub, EAND, EEQ, EGT, EGTE, ELT, ELTE, ENE, EOR, EXOR,
     },
-    Expression,
-    ExpressionOrString::{EExpr, EStr},
-    PrintFunc,
+    ExprPair, Expression,
     StdFunc::{
-        self, EFunc, EFuncACos, EFuncACosH, EFuncASin, EFuncASinH, EFuncATan, EFuncATanH, EFuncAbs,
-        EFuncCeil, EFuncCos, EFuncCosH, EFuncE, EFuncFloor, EFuncInt, EFuncLog, EFuncMax, EFuncMin,
-        EFuncPi, EFuncRound, EFuncSign, EFuncSin, EFuncSinH, EFuncTan, EFuncTanH, EVar,
+        self, EFunc, EFuncACos, EFuncACosD, EFuncACosH, EFuncASin, EFuncASinD, EFuncASinH,
+        EFuncATan, EFuncATan2, EFuncATan2D, EFuncATanD, EFuncATanH, EFuncAbs, EFuncAvg, EFuncBlend,
+        EFuncBucket, EFuncCbrt, EFuncCeil, EFuncClamp, EFuncCos, EFuncCosH, EFuncE, EFuncFloor,
+        EFuncInt, EFuncLog, EFuncMax, EFuncMaxFirst, EFuncMin, EFuncMinFirst, EFuncNanMax,
+        EFuncNanMin, EFuncNorm, EFuncPi, EFuncPoly, EFuncPowMod, EFuncProbRound, EFuncProd,
+        EFuncRound, EFuncRoundHalfUp, EFuncRoundZ, EFuncSelect, EFuncSeq, EFuncSign, EFuncSin,
+        EFuncSinH, EFuncSmoothstep, EFuncSqrt, EFuncSum, EFuncSumSq, EFuncTan, EFuncTanH, EVar,
     },
     UnaryOp::{self, ENeg, ENot, EParentheses, EPos},
-    Value::{self, EConstant, EPrintFunc, EStdFunc, EUnaryOp},
+    Value::{self, EConstant, EStdFunc, EUnaryOp},
+};
+#[cfg(feature = "print-builtin")]
+use crate::parser::{
+    ExpressionOrString::{EExpr, EStr},
+    PrintFunc,
+    Value::EPrintFunc,
 };
-use crate::slab::Slab;
+#[cfg(feature = "print-builtin")]
+use crate::slab::PrintReturn;
+use crate::slab::{NanPolicy, Slab};
+use crate::{consts, Num};
 
-use std::f32::consts;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use std::{cell::RefCell, collections::BTreeSet};
 
 /// The same as `evaler.eval(&slab, &mut ns)`, but more efficient for common cases.
 ///
@@ -118,6 +136,75 @@ macro_rules! eval_compiled_ref {
     }};
 }
 
+/// Evaluates a compiled expression once per item of a loop, like calling
+/// [`eval_compiled!()`](macro.eval_compiled.html) in the loop body, but with
+/// the checks for constants and Unsafe Variables hoisted out of the loop.
+///
+/// The optional `setup` block runs at the start of each iteration, before
+/// evaluation, so it can update variables.  Then the result is bound to `val`
+/// and the `body` block runs.  A constant expression is only evaluated once,
+/// and an Unsafe Variable is read directly from its pointer.  For other
+/// expressions, `eval()` is called on every iteration.
+///
+/// Like the other `eval_compiled*!()` macros, this uses `?`, so it must be
+/// used in a function that returns `Result<_, fasteval3::Error>`.
+///
+/// ```
+/// use fasteval3::{Compiler, Evaler, Parser, Slab};
+/// use std::collections::BTreeMap;
+///
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let mut slab = Slab::new();
+///     let mut map = BTreeMap::new();
+///     let compiled = Parser::new()
+///         .parse("deg / 2", &mut slab.ps)?
+///         .from(&slab.ps)
+///         .compile(&slab.ps, &mut slab.cs, &mut map);
+///
+///     let mut sum = 0.0;
+///     fasteval3::eval_compiled_many!(compiled, &slab, &mut map, for deg in 0..4 => {
+///         map.insert(String::from("deg"), deg as fasteval3::Num);
+///     } |val| {
+///         sum += val;
+///     });
+///     assert_eq!(sum, 3.0);
+///
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! eval_compiled_many {
+    ($evaler:expr, $slab_ref:expr, $ns_mut:expr, for $pat:pat in $iter:expr => |$val:ident| $body:block) => {
+        $crate::eval_compiled_many!($evaler, $slab_ref, $ns_mut, for $pat in $iter => {} |$val| $body)
+    };
+    ($evaler:expr, $slab_ref:expr, $ns_mut:expr, for $pat:pat in $iter:expr => $setup:block |$val:ident| $body:block) => {
+        match &$evaler {
+            fasteval3::IConst(c) => {
+                let $val = *c;
+                for $pat in $iter {
+                    $setup
+                    $body
+                }
+            }
+            #[cfg(feature = "unsafe-vars")]
+            fasteval3::IUnsafeVar { ptr, .. } => {
+                for $pat in $iter {
+                    $setup
+                    let $val = unsafe { **ptr };
+                    $body
+                }
+            }
+            evaler => {
+                for $pat in $iter {
+                    $setup
+                    let $val = evaler.eval($slab_ref, $ns_mut)?;
+                    $body
+                }
+            }
+        }
+    };
+}
+
 macro_rules! eval_ic_ref {
     ($ic:ident, $slab_ref:ident, $ns_mut:expr) => {
         match $ic {
@@ -141,203 +228,729 @@ macro_rules! eval_ic_ref {
     };
 }
 
+/// Applies `f` to `x`, returning `Error::Overflow` in strict mode if a finite
+/// `x` produces a non-finite result.
+#[inline]
+fn check_overflow(slab: &Slab, x: Num, f: fn(Num) -> Num) -> Result<Num, Error> {
+    let result = f(x);
+    if slab.strict && overflowed(x, result) {
+        return Err(Error::Overflow);
+    }
+    Ok(result)
+}
+
+/// Returns the `result` of the rounding function `name`, or
+/// `Error::NonFinite` in strict mode if `result` is NaN or infinite.
+#[inline]
+fn check_finite(slab: &Slab, name: &str, result: Num) -> Result<Num, Error> {
+    if slab.strict && !result.is_finite() {
+        return Err(Error::NonFinite(String::from(name)));
+    }
+    Ok(result)
+}
+
+/// Returns the position of the option that `select()` chooses, or `None` if
+/// `index` is out of range, which is an `Error::WrongArgs` in strict mode.
+#[inline]
+fn select_option(slab: &Slab, index: Num, len: usize) -> Result<Option<usize>, Error> {
+    match select_index(index, len) {
+        None if slab.strict => Err(Error::WrongArgs(format!(
+            "select: index {index} is out of range"
+        ))),
+        i => Ok(i),
+    }
+}
+
+/// Returns the smaller (if `is_min`) or larger of `left` and `right`, handling
+/// NaN according to `Slab.nan_policy`.  A `missing` value loses to anything.
+///
+/// On ties, `right` is returned.  Ties can only be told apart for `0.0` and
+/// `-0.0`: `max(0, -0) == -0`.
+#[inline]
+fn min_max(
+    slab: &Slab,
+    missing: Option<Num>,
+    left: Num,
+    right: Num,
+    is_min: bool,
+) -> Result<Num, Error> {
+    min_max_impl(slab, missing, left, right, is_min, false)
+}
+
+/// Like `min_max()`, but `left` is returned on ties.  Used by `min_first()`
+/// and `max_first()`.
+#[inline]
+fn min_max_first(
+    slab: &Slab,
+    missing: Option<Num>,
+    left: Num,
+    right: Num,
+    is_min: bool,
+) -> Result<Num, Error> {
+    min_max_impl(slab, missing, left, right, is_min, true)
+}
+
+/// Returns `true` if `acc`, the running result of `min()` (if `is_min`) or
+/// `max()`, can't be changed by the remaining arguments, so they don't need to
+/// be evaluated: `-inf` for `min()` and `inf` for `max()`.
+///
+/// This only applies with `NanPolicy::Ignore`, because with the other policies
+/// a later NaN argument would still change the result.
+#[inline]
+fn min_max_saturated(slab: &Slab, missing: Option<Num>, acc: Num, is_min: bool) -> bool {
+    slab.nan_policy == NanPolicy::Ignore
+        && acc.is_infinite()
+        && (acc < 0.0) == is_min
+        && !is_missing(acc, missing)
+}
+
+#[inline]
+fn min_max_impl(
+    slab: &Slab,
+    missing: Option<Num>,
+    left: Num,
+    right: Num,
+    is_min: bool,
+    left_wins_ties: bool,
+) -> Result<Num, Error> {
+    if is_missing(left, missing) {
+        return Ok(right);
+    }
+    if is_missing(right, missing) {
+        return Ok(left);
+    }
+    // I need to implement NAN checks myself because the f32.min() function says that if one number is NaN, the other will be returned.
+    if left.is_nan() || right.is_nan() {
+        return match slab.nan_policy {
+            NanPolicy::Propagate => Ok(Num::NAN),
+            NanPolicy::Ignore => Ok(if left.is_nan() { right } else { left }),
+            NanPolicy::Error => Err(Error::NanArgument(String::from(
+                match (is_min, left_wins_ties) {
+                    (true, false) => "min",
+                    (false, false) => "max",
+                    (true, true) => "min_first",
+                    (false, true) => "max_first",
+                },
+            ))),
+        };
+    }
+    let left_wins = if is_min { left < right } else { left > right }
+        || (left_wins_ties && f32_eq!(left, right));
+    if left_wins {
+        Ok(left)
+    } else {
+        Ok(right)
+    }
+}
+
+/// The largest number of steps that [`eval_sweep()`](fn.eval_sweep.html) takes.
+const MAX_SWEEP_STEPS: usize = 1 << 24;
+
+/// Evaluates `evaler` once for each value of `var_name` from `start` to `end`,
+/// stepping by `step`, and returns the results in order.
+///
+/// The variable is set with [`Cached::cache_set()`](../evalns/trait.Cached.html#tymethod.cache_set)
+/// before each evaluation.  `end` is included if a step lands on it (allowing
+/// for a little floating-point error), and a negative `step` sweeps downward.
+///
+/// ```
+/// use fasteval3::{Compiler, Evaler};
+///
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let mut slab = fasteval3::Slab::new();
+///     let mut ns = fasteval3::CachedCallbackNamespace::new(|_, _| None);
+///     let instr = fasteval3::Parser::new()
+///         .parse("x * 2", &mut slab.ps)?
+///         .from(&slab.ps)
+///         .compile(&slab.ps, &mut slab.cs, &mut ns);
+///
+///     let vals = fasteval3::eval_sweep(&instr, &slab, &mut ns, "x", 0.0, 1.0, 0.25)?;
+///     assert_eq!(vals, vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns `Error::WrongArgs` if `step` is zero or not finite, if `start` or
+/// `end` is not finite, or if the sweep has more than 2^24 steps (the largest
+/// step count that an `f32` can count exactly).  Also returns any error from
+/// the evaluations.
+pub fn eval_sweep(
+    evaler: &impl Evaler,
+    slab: &Slab,
+    ns: &mut (impl EvalNamespace + Cached),
+    var_name: &str,
+    start: Num,
+    end: Num,
+    step: Num,
+) -> Result<Vec<Num>, Error> {
+    if step == 0.0 || !step.is_finite() {
+        return Err(Error::WrongArgs(String::from(
+            "eval_sweep: step must be finite and non-zero",
+        )));
+    }
+    if !start.is_finite() || !end.is_finite() {
+        return Err(Error::WrongArgs(String::from(
+            "eval_sweep: start and end must be finite",
+        )));
+    }
+    // Multiplying (instead of accumulating) keeps the rounding error from growing:
+    let steps = ((end - start) / step + 1e-4).floor();
+    if steps < 0.0 {
+        return Ok(Vec::new());
+    }
+    if steps > MAX_SWEEP_STEPS as Num {
+        return Err(Error::WrongArgs(format!(
+            "eval_sweep: more than {MAX_SWEEP_STEPS} steps"
+        )));
+    }
+    let steps = steps as usize;
+    let mut out = Vec::with_capacity(steps + 1);
+    for i in 0..=steps {
+        ns.cache_set(String::from(var_name), (i as Num).mul_add(step, start));
+        out.push(evaler.eval(slab, ns)?);
+    }
+    Ok(out)
+}
+
+/// The number of rows that [`eval_columns()`](fn.eval_columns.html) evaluates at once.
+const COLUMN_BLOCK: usize = 256;
+
+/// Evaluates `instr` once for each row of `columns`, writing the results to `out`.
+///
+/// `columns` maps variable names to their values, one per row, so row `i`
+/// evaluates with each variable set to `columns[name][i]`.  Every column must
+/// have `out.len()` rows.
+///
+/// Rows are processed in blocks: each `Instruction` is applied to a whole block
+/// of values before moving on to the next `Instruction`, which is much faster
+/// than evaluating one row at a time.  `&&`, `||`, `print()` and custom
+/// functions are evaluated one row at a time, because they can't be split into
+/// independent steps.  The results are the same as with
+/// [`eval()`](trait.Evaler.html#tymethod.eval), but if several rows fail, the
+/// error that is returned may come from a later row.
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use fasteval3::Compiler;
+/// use fasteval3::Num;
+///
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let mut slab = fasteval3::Slab::new();
+///     let instr = fasteval3::Parser::new()
+///         .parse("x * y + 1", &mut slab.ps)?
+///         .from(&slab.ps)
+///         .compile(&slab.ps, &mut slab.cs, &mut fasteval3::EmptyNamespace);
+///
+///     let mut columns = BTreeMap::<String, &[Num]>::new();
+///     columns.insert(String::from("x"), &[1.0, 2.0, 3.0]);
+///     columns.insert(String::from("y"), &[10.0, 20.0, 30.0]);
+///     let mut out = [0.0; 3];
+///     fasteval3::eval_columns(&instr, &slab, &columns, &mut out)?;
+///     assert_eq!(out, [11.0, 41.0, 91.0]);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns `Error::WrongArgs` if a column doesn't have `out.len()` rows,
+/// `Error::Undefined` if a variable has no column, and any other error from
+/// the evaluation.  Custom functions are always `Error::UndefinedFunction`.
+/// On error, `out` may be partially written.
+pub fn eval_columns(
+    instr: &Instruction,
+    slab: &Slab,
+    columns: &BTreeMap<String, &[Num]>,
+    out: &mut [Num],
+) -> Result<(), Error> {
+    if let Some((name, col)) = columns.iter().find(|(_, col)| col.len() != out.len()) {
+        return Err(Error::WrongArgs(format!(
+            "eval_columns: column '{name}' has {} rows, expected {}",
+            col.len(),
+            out.len()
+        )));
+    }
+    for (block, dst) in out.chunks_mut(COLUMN_BLOCK).enumerate() {
+        instr.eval_block(slab, columns, block * COLUMN_BLOCK, dst)?;
+    }
+    Ok(())
+}
+
+/// A Namespace that looks up the variables of one row of `eval_columns()`.
+struct ColumnRow<'a, 'b> {
+    columns: &'a BTreeMap<String, &'b [Num]>,
+    row: usize,
+}
+
+impl EvalNamespace for ColumnRow<'_, '_> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
+        if !args.is_empty() {
+            return None;
+        }
+        self.columns
+            .get(name)
+            .and_then(|col| col.get(self.row))
+            .copied()
+    }
+}
+
+impl Instruction {
+    /// Evaluates the rows of `columns` from `start` on, filling `dst`.
+    fn eval_block(
+        &self,
+        slab: &Slab,
+        columns: &BTreeMap<String, &[Num]>,
+        start: usize,
+        dst: &mut [Num],
+    ) -> Result<(), Error> {
+        match self {
+            IConst(c) => {
+                dst.fill(*c);
+                return Ok(());
+            }
+            IVar(name) => {
+                let src = columns
+                    .get(name)
+                    .and_then(|col| col.get(start..start + dst.len()))
+                    .ok_or_else(|| Error::Undefined(name.clone()))?;
+                dst.copy_from_slice(src);
+                return Ok(());
+            }
+            IFunc { .. } | IAND(..) | IOR(..) | IFuncSelect { .. } => {
+                return self.eval_rows(slab, columns, start, dst);
+            }
+            // These can skip operands, like in eval():
+            IFuncMin(..) | IFuncMax(..) | IFuncMinN(_) | IFuncMaxN(_)
+                if slab.nan_policy == NanPolicy::Ignore =>
+            {
+                return self.eval_rows(slab, columns, start, dst);
+            }
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(_) => return self.eval_rows(slab, columns, start, dst),
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => return self.eval_rows(slab, columns, start, dst),
+            _ => (),
+        }
+
+        let mut frames = Vec::new();
+        self.push_operands(slab, &mut frames);
+        let mut operands = Vec::with_capacity(frames.len());
+        for frame in frames {
+            let mut vals = vec![0.0; dst.len()];
+            match frame {
+                Frame::Push(c) => vals.fill(c),
+                Frame::Eval(instr) => instr.eval_block(slab, columns, start, &mut vals)?,
+                Frame::ShortCircuit(_) | Frame::MinMax(..) | Frame::Apply(..) => {
+                    return Err(Error::Unreachable)
+                }
+            }
+            operands.push(vals);
+        }
+
+        // Simple loops for the most common Instructions, which the compiler can vectorize:
+        match (self, operands.as_slice()) {
+            (IAdd(..), [a, b]) => {
+                for (d, (a, b)) in dst.iter_mut().zip(a.iter().zip(b)) {
+                    *d = a + b;
+                }
+            }
+            (IMul(..), [a, b]) => {
+                for (d, (a, b)) in dst.iter_mut().zip(a.iter().zip(b)) {
+                    *d = a * b;
+                }
+            }
+            (INeg(_), [a]) => {
+                for (d, a) in dst.iter_mut().zip(a) {
+                    *d = -a;
+                }
+            }
+            (IInv(_), [a]) => {
+                for (d, a) in dst.iter_mut().zip(a) {
+                    *d = 1.0 / a;
+                }
+            }
+            _ => {
+                let mut keybuf = String::new();
+                let mut args = Vec::with_capacity(operands.len());
+                for (i, d) in dst.iter_mut().enumerate() {
+                    args.clear();
+                    args.extend(
+                        operands
+                            .iter()
+                            .map(|vals| vals.get(i).copied().unwrap_or(Num::NAN)),
+                    );
+                    let mut ns = ColumnRow {
+                        columns,
+                        row: start + i,
+                    };
+                    *d = self.apply(&args, slab, &mut ns, &mut keybuf)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates the rows of `columns` from `start` on one at a time, filling `dst`.
+    fn eval_rows(
+        &self,
+        slab: &Slab,
+        columns: &BTreeMap<String, &[Num]>,
+        start: usize,
+        dst: &mut [Num],
+    ) -> Result<(), Error> {
+        for (i, d) in dst.iter_mut().enumerate() {
+            let mut ns = ColumnRow {
+                columns,
+                row: start + i,
+            };
+            *d = self.eval(slab, &mut ns)?;
+        }
+        Ok(())
+    }
+}
+
+// Implements `Evaler::_var_names()` with the more detailed `_var_arities()`:
+macro_rules! var_names_from_arities {
+    () => {
+        fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+            let mut map = BTreeMap::new();
+            self._var_arities(slab, &mut map);
+            dst.extend(map.into_keys());
+        }
+    };
+}
+
 /// You must `use` this trait so you can call `.eval()`.
 pub trait Evaler: fmt::Debug {
-    /// Evaluate this `Expression`/`Instruction` and return an `f32`.
+    /// Evaluate this `Expression`/`Instruction` and return a `Num`.
     ///
     /// # Errors
     ///
     /// Returns a `fasteval3::Error` if there are any problems, such as undefined variables.
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error>;
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error>;
+
+    /// Like `eval()`, but any `Error` is replaced by `default`.
+    ///
+    /// This is convenient for calculator-style apps that display a sentinel
+    /// (like NaN or 0) instead of reporting errors.
+    #[inline]
+    fn eval_or(&self, slab: &Slab, ns: &mut impl EvalNamespace, default: Num) -> Num {
+        self.eval(slab, ns).unwrap_or(default)
+    }
 
-    /// Don't call this directly.  Use `var_names()` instead.
+    /// Don't call this directly.  Use `var_names()` or `var_names_into()` instead.
     ///
     /// This exists because of ternary short-circuits; they prevent us from
     /// getting a complete list of vars just by doing eval() with a clever
     /// callback.
     fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>);
 
+    /// Don't call this directly.  Use `required_signatures()` instead.
+    ///
+    /// Like `_var_names()`, but `dst` maps each variable/custom-function name
+    /// to the set of argument counts that it is called with.  The default
+    /// implementation records the names from `_var_names()` without any
+    /// argument counts.
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
+        let mut names = BTreeSet::new();
+        self._var_names(slab, &mut names);
+        for name in names {
+            dst.entry(name).or_default();
+        }
+    }
+
     /// Returns a list of variables and custom functions that are used by this `Expression`/`Instruction`.
     fn var_names(&self, slab: &Slab) -> BTreeSet<String> {
-        let mut set = BTreeSet::new();
-        self._var_names(slab, &mut set);
-        set
+        let mut names = BTreeSet::new();
+        self.var_names_into(slab, &mut names);
+        names
+    }
+
+    /// Like `var_names()`, but adds the names to `dst` instead of returning a
+    /// new set.
+    ///
+    /// `dst` is not cleared first, so one set can collect the names of several
+    /// expressions, or be cleared and reused between them.
+    fn var_names_into(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+        self._var_names(slab, dst);
+    }
+
+    /// Returns each variable and custom function that is used by this
+    /// `Expression`/`Instruction`, along with the argument counts that it is called with.
+    ///
+    /// Variables are reported with an argument count of `0`.  This is useful for
+    /// checking that a namespace is compatible with an expression before evaluating it.
+    fn required_signatures(&self, slab: &Slab) -> BTreeMap<String, BTreeSet<usize>> {
+        let mut map = BTreeMap::new();
+        self._var_arities(slab, &mut map);
+        map
     }
 }
 
 #[allow(clippy::inline_always)] // TODO: Check to see if always inlining here is ok.
 impl Evaler for Expression {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
-        self.first._var_names(slab, dst);
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
+        self.first._var_arities(slab, dst);
         for pair in &self.pairs {
-            pair.1._var_names(slab, dst);
-        }
-    }
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
-        #[inline(always)]
-        fn rtol(vals: &mut Vec<f32>, ops: &mut Vec<BinaryOp>, search: BinaryOp) {
-            for i in (0..ops.len()).rev() {
-                let op = ops.get(i).map_or(EOR, |op| *op);
-                if op == search {
-                    let res = op.binaryop_eval(vals.get(i), vals.get(i + 1));
-                    if let Some(value_ref) = vals.get_mut(i) {
-                        *value_ref = res;
-                    }
-                    remove_no_panic(vals, i + 1);
-                    remove_no_panic(ops, i);
-                }
-            }
-        }
-        #[inline(always)]
-        fn ltor(vals: &mut Vec<f32>, ops: &mut Vec<BinaryOp>, search: BinaryOp) {
-            let mut i = 0;
-            loop {
-                match ops.get(i) {
-                    None => break,
-                    Some(op) => {
-                        if *op == search {
-                            let res = op.binaryop_eval(vals.get(i), vals.get(i + 1));
-                            if let Some(value_ref) = vals.get_mut(i) {
-                                *value_ref = res;
-                            }
-                            remove_no_panic(vals, i + 1);
-                            remove_no_panic(ops, i);
-                        } else {
-                            i += 1;
+            pair.1._var_arities(slab, dst);
+        }
+    }
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
+        eval_expr(&self.first, &self.pairs, slab, ns)
+    }
+}
+
+impl Expression {
+    /// Evaluates each top-level operand of this `Expression` separately,
+    /// without combining them.
+    ///
+    /// The first entry is `first` (with no operator), followed by one entry
+    /// for each `(op, value)` pair.  Operator precedence is not applied, so
+    /// `1 + 2 * 3` returns `[(None, 1), (Some(EAdd), 2), (Some(EMul), 3)]`.
+    /// Parenthesized sub-expressions are evaluated as a single operand.
+    ///
+    /// This is useful for UIs that show a breakdown of a formula.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first `Error` encountered while evaluating an operand.
+    pub fn eval_operands(
+        &self,
+        slab: &Slab,
+        ns: &mut impl EvalNamespace,
+    ) -> Result<Vec<(Option<BinaryOp>, Num)>, Error> {
+        let mut out = Vec::with_capacity(self.pairs.len() + 1);
+        out.push((None, self.first.eval(slab, ns)?));
+        for ExprPair(op, val) in &self.pairs {
+            out.push((Some(*op), val.eval(slab, ns)?));
+        }
+        Ok(out)
+    }
+}
+
+/// Evaluates `first` followed by the binary operations in `pairs`.
+#[allow(clippy::inline_always)] // TODO: Check to see if always inlining here is ok.
+fn eval_expr(
+    first: &Value,
+    pairs: &[ExprPair],
+    slab: &Slab,
+    ns: &mut impl EvalNamespace,
+) -> Result<Num, Error> {
+    #[inline(always)]
+    fn rtol(slab: &Slab, vals: &mut Vec<Num>, ops: &mut Vec<BinaryOp>, search: BinaryOp) {
+        for i in (0..ops.len()).rev() {
+            let op = ops.get(i).map_or(EOR, |op| *op);
+            if op == search {
+                let res = op.dispatch_eval(slab, vals.get(i), vals.get(i + 1));
+                if let Some(value_ref) = vals.get_mut(i) {
+                    *value_ref = res;
+                }
+                remove_no_panic(vals, i + 1);
+                remove_no_panic(ops, i);
+            }
+        }
+    }
+    #[inline(always)]
+    fn ltor(slab: &Slab, vals: &mut Vec<Num>, ops: &mut Vec<BinaryOp>, search: BinaryOp) {
+        let mut i = 0;
+        loop {
+            match ops.get(i) {
+                None => break,
+                Some(op) => {
+                    if *op == search {
+                        let res = op.dispatch_eval(slab, vals.get(i), vals.get(i + 1));
+                        if let Some(value_ref) = vals.get_mut(i) {
+                            *value_ref = res;
                         }
+                        remove_no_panic(vals, i + 1);
+                        remove_no_panic(ops, i);
+                    } else {
+                        i += 1;
                     }
                 }
             }
         }
-        #[inline(always)]
-        fn ltor_multi(vals: &mut Vec<f32>, ops: &mut Vec<BinaryOp>, search: &[BinaryOp]) {
-            let mut i = 0;
-            loop {
-                match ops.get(i) {
-                    None => break,
-                    Some(op) => {
-                        if search.contains(op) {
-                            let res = op.binaryop_eval(vals.get(i), vals.get(i + 1));
-                            if let Some(value_ref) = vals.get_mut(i) {
-                                *value_ref = res;
-                            }
-                            remove_no_panic(vals, i + 1);
-                            remove_no_panic(ops, i);
-                        } else {
-                            i += 1;
+    }
+    #[inline(always)]
+    fn ltor_multi(slab: &Slab, vals: &mut Vec<Num>, ops: &mut Vec<BinaryOp>, search: &[BinaryOp]) {
+        let mut i = 0;
+        loop {
+            match ops.get(i) {
+                None => break,
+                Some(op) => {
+                    if search.contains(op) {
+                        let res = op.dispatch_eval(slab, vals.get(i), vals.get(i + 1));
+                        if let Some(value_ref) = vals.get_mut(i) {
+                            *value_ref = res;
                         }
+                        remove_no_panic(vals, i + 1);
+                        remove_no_panic(ops, i);
+                    } else {
+                        i += 1;
                     }
                 }
             }
         }
+    }
 
-        // Order of operations: 1) ^  2) */  3) +-
-        // Exponentiation should be processed right-to-left.  Think of what 2^3^4 should mean:
-        //     2^(3^4)=2417851639229258349412352   <--- I choose this one.  https://codeplea.com/exponentiation-associativity-options
-        //     (2^3)^4=4096
-        // Direction of processing doesn't matter for Addition and Multiplication:
-        //     (((3+4)+5)+6)==(3+(4+(5+6))), (((3*4)*5)*6)==(3*(4*(5*6)))
-        // ...But Subtraction and Division must be processed left-to-right:
-        //     (((6-5)-4)-3)!=(6-(5-(4-3))), (((6/5)/4)/3)!=(6/(5/(4/3)))
-
-        // // ---- Go code, for comparison ----
-        // // vals,ops:=make([]float64, len(e)/2+1),make([]BinaryOp, len(e)/2)
-        // // for i:=0; i<len(e); i+=2 {
-        // //     vals[i/2]=ns.EvalBubble(e[i].(evaler))
-        // //     if i<len(e)-1 { ops[i/2]=e[i+1].(BinaryOp) }
-        // // }
-
-        // if self.0.len()%2!=1 { return Err(KErr::new("Expression len should always be odd")) }
-        // let mut vals : Vec<f32>      = Vec::with_capacity(self.0.len()/2+1);
-        // let mut ops  : Vec<BinaryOp> = Vec::with_capacity(self.0.len()/2  );
-        // for (i,tok) in self.0.iter().enumerate() {
-        //     match tok {
-        //         EValue(val) => {
-        //             if i%2==1 { return Err(KErr::new("Found value at odd index")) }
-        //             match ns.eval_bubble(val) {
-        //                 Ok(f) => vals.push(f),
-        //                 Err(e) => return Err(e.pre(&format!("eval_bubble({:?})",val))),
-        //             }
-        //         }
-        //         EBinaryOp(bop) => {
-        //             if i%2==0 { return Err(KErr::new("Found binaryop at even index")) }
-        //             ops.push(*bop);
-        //         }
-        //     }
-        // }
-
-        // Code for new Expression data structure:
-        let mut vals = Vec::<f32>::with_capacity(self.pairs.len() + 1);
-        let mut ops = Vec::<BinaryOp>::with_capacity(self.pairs.len());
-        vals.push(self.first.eval(slab, ns)?);
-        for pair in &self.pairs {
-            ops.push(pair.0);
-            vals.push(pair.1.eval(slab, ns)?);
-        }
+    // Order of operations: 1) ^  2) */  3) +-
+    // Exponentiation should be processed right-to-left.  Think of what 2^3^4 should mean:
+    //     2^(3^4)=2417851639229258349412352   <--- I choose this one.  https://codeplea.com/exponentiation-associativity-options
+    //     (2^3)^4=4096
+    // Direction of processing doesn't matter for Addition and Multiplication:
+    //     (((3+4)+5)+6)==(3+(4+(5+6))), (((3*4)*5)*6)==(3*(4*(5*6)))
+    // ...But Subtraction and Division must be processed left-to-right:
+    //     (((6-5)-4)-3)!=(6-(5-(4-3))), (((6/5)/4)/3)!=(6/(5/(4/3)))
 
-        // ---- Go code, for comparison ----
-        // evalOp:=func(i int) {
-        //     result:=ops[i]._Eval(vals[i], vals[i+1])
-        //     vals=append(append(vals[:i], result), vals[i+2:]...)
-        //     ops=append(ops[:i], ops[i+1:]...)
-        // }
-        // rtol:=func(s BinaryOp) { for i:=len(ops)-1; i>=0; i-- { if ops[i]==s { evalOp(i) } } }
-        // ltor:=func(s BinaryOp) {
-        //     loop:
-        //     for i:=0; i<len(ops); i++ { if ops[i]==s { evalOp(i); goto loop } }  // Need to restart processing when modifying from the left.
-        // }
+    // // ---- Go code, for comparison ----
+    // // vals,ops:=make([]float64, len(e)/2+1),make([]BinaryOp, len(e)/2)
+    // // for i:=0; i<len(e); i+=2 {
+    // //     vals[i/2]=ns.EvalBubble(e[i].(evaler))
+    // //     if i<len(e)-1 { ops[i/2]=e[i+1].(BinaryOp) }
+    // // }
 
-        // Keep the order of these statements in-sync with parser.rs BinaryOp priority values:
-        rtol(&mut vals, &mut ops, EExp); // https://codeplea.com/exponentiation-associativity-options
-        ltor(&mut vals, &mut ops, EMod);
-        ltor(&mut vals, &mut ops, EDiv);
-        rtol(&mut vals, &mut ops, EMul);
-        ltor(&mut vals, &mut ops, ESub);
-        rtol(&mut vals, &mut ops, EAdd);
-        ltor_multi(&mut vals, &mut ops, &[ELT, EGT, ELTE, EGTE, EEQ, ENE]); // TODO: Implement Python-style a<b<c ternary comparison... might as well generalize to N comparisons.
-        ltor(&mut vals, &mut ops, EAND);
-        ltor(&mut vals, &mut ops, EOR);
+    // if self.0.len()%2!=1 { return Err(KErr::new("Expression len should always be odd")) }
+    // let mut vals : Vec<f32>      = Vec::with_capacity(self.0.len()/2+1);
+    // let mut ops  : Vec<BinaryOp> = Vec::with_capacity(self.0.len()/2  );
+    // for (i,tok) in self.0.iter().enumerate() {
+    //     match tok {
+    //         EValue(val) => {
+    //             if i%2==1 { return Err(KErr::new("Found value at odd index")) }
+    //             match ns.eval_bubble(val) {
+    //                 Ok(f) => vals.push(f),
+    //                 Err(e) => return Err(e.pre(&format!("eval_bubble({:?})",val))),
+    //             }
+    //         }
+    //         EBinaryOp(bop) => {
+    //             if i%2==0 { return Err(KErr::new("Found binaryop at even index")) }
+    //             ops.push(*bop);
+    //         }
+    //     }
+    // }
 
-        if !ops.is_empty() {
-            return Err(Error::Unreachable);
-        }
-        if vals.len() != 1 {
-            return Err(Error::Unreachable);
+    // Fast path for bare values like `x` or `sin(y)` -- no need to allocate anything:
+    if pairs.is_empty() {
+        return first.eval(slab, ns);
+    }
+
+    // The logical operators have the lowest precedence, so the loosest one present
+    // splits the expression into operands that are evaluated left-to-right.  Like
+    // the compiled IAND and IOR instructions, operands that can't change the result
+    // are never evaluated.  ('xor' always needs every operand.)  An operator
+    // with a `Slab.op_dispatch` override is left to the general path below.
+    if let Some(low) = [EOR, EXOR, EAND]
+        .into_iter()
+        .find(|op| pairs.iter().any(|pair| pair.0 == *op))
+        .filter(|op| slab.op_dispatch.as_ref().and_then(|d| d.get(*op)).is_none())
+    {
+        let mut operand = first;
+        let mut start = 0;
+        let mut acc: Option<Num> = None;
+        for end in 0..=pairs.len() {
+            let next = pairs.get(end);
+            if next.is_some_and(|pair| pair.0 != low) {
+                continue;
+            }
+            match (low, acc) {
+                (EAND, Some(prev)) if f32_eq!(prev, 0.0) => return Ok(prev),
+                (EOR, Some(prev)) if f32_ne!(prev, 0.0) => return Ok(prev),
+                _ => (),
+            }
+            let val = eval_expr(operand, pairs.get(start..end).unwrap_or(&[]), slab, ns)?;
+            acc = Some(match (low, acc) {
+                (EXOR, Some(prev)) => EXOR.binaryop_eval(Some(&prev), Some(&val)),
+                _ => val,
+            });
+            if let Some(pair) = next {
+                operand = &pair.1;
+                start = end + 1;
+            }
         }
+        return acc.ok_or(Error::Unreachable);
+    }
+
+    // Code for new Expression data structure:
+    let mut vals = Vec::<Num>::with_capacity(pairs.len() + 1);
+    let mut ops = Vec::<BinaryOp>::with_capacity(pairs.len());
+    vals.push(first.eval(slab, ns)?);
+    for pair in pairs {
+        ops.push(pair.0);
+        vals.push(pair.1.eval(slab, ns)?);
+    }
+
+    // ---- Go code, for comparison ----
+    // evalOp:=func(i int) {
+    //     result:=ops[i]._Eval(vals[i], vals[i+1])
+    //     vals=append(append(vals[:i], result), vals[i+2:]...)
+    //     ops=append(ops[:i], ops[i+1:]...)
+    // }
+    // rtol:=func(s BinaryOp) { for i:=len(ops)-1; i>=0; i-- { if ops[i]==s { evalOp(i) } } }
+    // ltor:=func(s BinaryOp) {
+    //     loop:
+    //     for i:=0; i<len(ops); i++ { if ops[i]==s { evalOp(i); goto loop } }  // Need to restart processing when modifying from the left.
+    // }
 
-        vals.first().map_or(Err(Error::Unreachable), |val| Ok(*val))
+    // Keep the order of these statements in-sync with parser.rs BinaryOp priority values:
+    #[cfg(not(feature = "left-assoc-exp"))]
+    rtol(slab, &mut vals, &mut ops, EExp); // https://codeplea.com/exponentiation-associativity-options
+    #[cfg(feature = "left-assoc-exp")]
+    ltor(slab, &mut vals, &mut ops, EExp);
+    ltor(slab, &mut vals, &mut ops, EMod);
+    ltor(slab, &mut vals, &mut ops, EDiv);
+    rtol(slab, &mut vals, &mut ops, EMul);
+    ltor(slab, &mut vals, &mut ops, ESub);
+    rtol(slab, &mut vals, &mut ops, EAdd);
+    ltor_multi(slab, &mut vals, &mut ops, &[ELT, EGT, ELTE, EGTE, EEQ, ENE]); // TODO: Implement Python-style a<b<c ternary comparison... might as well generalize to N comparisons.
+    ltor(slab, &mut vals, &mut ops, EAND);
+    ltor(slab, &mut vals, &mut ops, EXOR);
+    ltor(slab, &mut vals, &mut ops, EOR);
+
+    if !ops.is_empty() {
+        return Err(Error::Unreachable);
+    }
+    if vals.len() != 1 {
+        return Err(Error::Unreachable);
     }
+
+    vals.first().map_or(Err(Error::Unreachable), |val| Ok(*val))
 }
 
 impl Evaler for Value {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
         match self {
             EConstant(_) => (),
-            EUnaryOp(u) => u._var_names(slab, dst),
-            EStdFunc(f) => f._var_names(slab, dst),
-            EPrintFunc(f) => f._var_names(slab, dst),
+            EUnaryOp(u) => u._var_arities(slab, dst),
+            EStdFunc(f) => f._var_arities(slab, dst),
+            #[cfg(feature = "print-builtin")]
+            EPrintFunc(f) => f._var_arities(slab, dst),
         };
     }
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
         match self {
             EConstant(c) => Ok(*c),
             EUnaryOp(u) => u.eval(slab, ns),
             EStdFunc(f) => f.eval(slab, ns),
+            #[cfg(feature = "print-builtin")]
             EPrintFunc(f) => f.eval(slab, ns),
         }
     }
 }
 
 impl Evaler for UnaryOp {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
         match self {
             EPos(val_i) | ENeg(val_i) | ENot(val_i) => {
-                get_val!(slab.ps, val_i)._var_names(slab, dst);
+                get_val!(slab.ps, val_i)._var_arities(slab, dst);
             }
-            EParentheses(expr_i) => get_expr!(slab.ps, expr_i)._var_names(slab, dst),
+            EParentheses(expr_i) => get_expr!(slab.ps, expr_i)._var_arities(slab, dst),
         }
     }
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
         match self {
             EPos(val_i) => get_val!(slab.ps, val_i).eval(slab, ns),
             ENeg(val_i) => Ok(-get_val!(slab.ps, val_i).eval(slab, ns)?),
@@ -351,16 +964,30 @@ impl Evaler for UnaryOp {
 }
 
 impl BinaryOp {
+    /// Like `binaryop_eval()`, but uses the `Slab.op_dispatch` override if there is one.
+    #[inline]
+    fn dispatch_eval(self, slab: &Slab, left_opt: Option<&Num>, right_opt: Option<&Num>) -> Num {
+        match (
+            slab.op_dispatch.as_ref().and_then(|d| d.get(self)),
+            left_opt,
+            right_opt,
+        ) {
+            (Some(f), Some(left), Some(right)) => f(*left, *right),
+            (Some(_), _, _) => Num::NAN,
+            (None, _, _) => self.binaryop_eval(left_opt, right_opt),
+        }
+    }
+
     // Non-standard eval interface (not generalized yet):
-    fn binaryop_eval(self, left_opt: Option<&f32>, right_opt: Option<&f32>) -> f32 {
+    fn binaryop_eval(self, left_opt: Option<&Num>, right_opt: Option<&Num>) -> Num {
         // Passing 'self' by value is more efficient than pass-by-reference.
         let left = match left_opt {
             Some(l) => *l,
-            None => return f32::NAN,
+            None => return Num::NAN,
         };
         let right = match right_opt {
             Some(r) => *r,
-            None => return f32::NAN,
+            None => return Num::NAN,
         };
         match self {
             EAdd => left + right, // Floats don't overflow.
@@ -389,61 +1016,126 @@ impl BinaryOp {
                     right
                 }
             }
+            EXOR => bool_to_f32!(f32_eq!(left, 0.0) != f32_eq!(right, 0.0)),
         }
     }
 }
 
+/// Calls the custom function `name`, as a reducer if `ns` has one by that name.
+#[inline]
+pub(crate) fn call_func(
+    ns: &mut impl EvalNamespace,
+    name: &str,
+    args: Vec<Num>,
+    keybuf: &mut String,
+) -> Option<Num> {
+    ns.reduce(name, &args)
+        .or_else(|| ns.lookup(name, args, keybuf))
+}
+
 #[macro_export]
 macro_rules! eval_var {
     ($ns:ident, $name:ident, $args:expr, $keybuf:expr) => {
+        eval_var!($ns, $name, $args, $keybuf, Error::Undefined)
+    };
+    ($ns:ident, $name:ident, $args:expr, $keybuf:expr, $undefined:path) => {
         match $ns.lookup($name, $args, $keybuf) {
             Some(f) => Ok(f),
-            None => Err(Error::Undefined($name.to_string())),
+            None => Err($undefined($name.to_string())),
         }
     };
 }
 
 impl Evaler for StdFunc {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
         match self {
             #[cfg(feature = "unsafe-vars")]
             EUnsafeVar { name, .. } => {
-                dst.insert(name.clone());
+                dst.entry(name.clone()).or_default().insert(0);
             }
 
             EVar(s) => {
-                dst.insert(s.clone());
+                dst.entry(s.clone()).or_default().insert(0);
             }
             EFunc { name, args } => {
-                dst.insert(name.clone());
+                dst.entry(name.clone()).or_default().insert(args.len());
                 for arg in args {
-                    get_expr!(slab.ps, arg)._var_names(slab, dst);
+                    get_expr!(slab.ps, arg)._var_arities(slab, dst);
                 }
             }
 
-            EFuncInt(xi) | EFuncCeil(xi) | EFuncFloor(xi) | EFuncAbs(xi) | EFuncSign(xi)
-            | EFuncSin(xi) | EFuncCos(xi) | EFuncTan(xi) | EFuncASin(xi) | EFuncACos(xi)
-            | EFuncATan(xi) | EFuncSinH(xi) | EFuncCosH(xi) | EFuncTanH(xi) | EFuncASinH(xi)
-            | EFuncACosH(xi) | EFuncATanH(xi) => get_expr!(slab.ps, xi)._var_names(slab, dst),
+            EFuncInt(xi) | EFuncCeil(xi) | EFuncFloor(xi) | EFuncAbs(xi) | EFuncSqrt(xi)
+            | EFuncCbrt(xi) | EFuncSign(xi) | EFuncProbRound(xi) | EFuncSin(xi) | EFuncCos(xi)
+            | EFuncTan(xi) | EFuncASin(xi) | EFuncACos(xi) | EFuncATan(xi) | EFuncASinD(xi)
+            | EFuncACosD(xi) | EFuncATanD(xi) | EFuncSinH(xi) | EFuncCosH(xi) | EFuncTanH(xi)
+            | EFuncASinH(xi) | EFuncACosH(xi) | EFuncATanH(xi) => {
+                get_expr!(slab.ps, xi)._var_arities(slab, dst);
+            }
+            EFuncATan2 { y, x } | EFuncATan2D { y, x } => {
+                get_expr!(slab.ps, y)._var_arities(slab, dst);
+                get_expr!(slab.ps, x)._var_arities(slab, dst);
+            }
 
             EFuncE | EFuncPi => (),
-            EFuncLog { base: opt, expr } | EFuncRound { modulus: opt, expr } => {
+            EFuncLog { base: opt, expr }
+            | EFuncRound { modulus: opt, expr }
+            | EFuncRoundHalfUp { modulus: opt, expr }
+            | EFuncRoundZ { modulus: opt, expr } => {
                 if let Some(xi) = opt.as_ref() {
-                    get_expr!(slab.ps, xi)._var_names(slab, dst)
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst)
                 }
-                get_expr!(slab.ps, expr)._var_names(slab, dst);
+                get_expr!(slab.ps, expr)._var_arities(slab, dst);
             }
-            EFuncMin { first, rest } | EFuncMax { first, rest } => {
-                get_expr!(slab.ps, first)._var_names(slab, dst);
+            EFuncMin { first, rest }
+            | EFuncMax { first, rest }
+            | EFuncMinFirst { first, rest }
+            | EFuncMaxFirst { first, rest }
+            | EFuncNanMin { first, rest }
+            | EFuncNanMax { first, rest }
+            | EFuncProd { first, rest } => {
+                get_expr!(slab.ps, first)._var_arities(slab, dst);
                 for xi in rest {
-                    get_expr!(slab.ps, xi)._var_names(slab, dst);
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncBlend(xis) | EFuncAvg(xis) | EFuncSum(xis) | EFuncNorm(xis) | EFuncSumSq(xis)
+            | EFuncPoly(xis) | EFuncSeq(xis) => {
+                for xi in xis {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncBucket { x, lo, hi, n } => {
+                for xi in [x, lo, hi, n] {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncPowMod { base, exp, modulus } => {
+                for xi in [base, exp, modulus] {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncSmoothstep { edge0, edge1, x } => {
+                for xi in [edge0, edge1, x] {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncClamp { val, lo, hi } => {
+                for xi in [val, lo, hi] {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
+                }
+            }
+            EFuncSelect { index, options } => {
+                get_expr!(slab.ps, index)._var_arities(slab, dst);
+                for xi in options {
+                    get_expr!(slab.ps, xi)._var_arities(slab, dst);
                 }
             }
         };
     }
 
     #[allow(clippy::cognitive_complexity)]
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
         let celled_slab = RefCell::from(slab.ps.char_buf.clone());
         match self {
             // These match arms are ordered in a way that I feel should deliver good performance.
@@ -457,7 +1149,8 @@ impl Evaler for StdFunc {
                 for xi in xis {
                     args.push(get_expr!(slab.ps, xi).eval(slab, ns)?);
                 }
-                eval_var!(ns, name, args, &mut *celled_slab.borrow_mut())
+                call_func(ns, name, args, &mut celled_slab.borrow_mut())
+                    .ok_or_else(|| Error::UndefinedFunction(name.clone()))
             }
 
             EFuncLog {
@@ -478,8 +1171,31 @@ impl Evaler for StdFunc {
             EFuncASin(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.asin()),
             EFuncACos(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.acos()),
             EFuncATan(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.atan()),
-            EFuncSinH(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.sinh()),
-            EFuncCosH(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.cosh()),
+            EFuncASinD(expr_i) => Ok(get_expr!(slab.ps, expr_i)
+                .eval(slab, ns)?
+                .asin()
+                .to_degrees()),
+            EFuncACosD(expr_i) => Ok(get_expr!(slab.ps, expr_i)
+                .eval(slab, ns)?
+                .acos()
+                .to_degrees()),
+            EFuncATanD(expr_i) => Ok(get_expr!(slab.ps, expr_i)
+                .eval(slab, ns)?
+                .atan()
+                .to_degrees()),
+            EFuncATan2 { y, x } => Ok(get_expr!(slab.ps, y)
+                .eval(slab, ns)?
+                .atan2(get_expr!(slab.ps, x).eval(slab, ns)?)),
+            EFuncATan2D { y, x } => Ok(atan2d(
+                get_expr!(slab.ps, y).eval(slab, ns)?,
+                get_expr!(slab.ps, x).eval(slab, ns)?,
+            )),
+            EFuncSinH(expr_i) => {
+                check_overflow(slab, get_expr!(slab.ps, expr_i).eval(slab, ns)?, Num::sinh)
+            }
+            EFuncCosH(expr_i) => {
+                check_overflow(slab, get_expr!(slab.ps, expr_i).eval(slab, ns)?, Num::cosh)
+            }
             EFuncTanH(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.tanh()),
             EFuncASinH(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.asinh()),
             EFuncACosH(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.acosh()),
@@ -493,83 +1209,295 @@ impl Evaler for StdFunc {
                     Some(m_expr_i) => get_expr!(slab.ps, m_expr_i).eval(slab, ns)?,
                     None => 1.0,
                 };
-                Ok((get_expr!(slab.ps, expr_i).eval(slab, ns)? / modulus).round() * modulus)
+                check_finite(
+                    slab,
+                    "round",
+                    (get_expr!(slab.ps, expr_i).eval(slab, ns)? / modulus).round() * modulus,
+                )
+            }
+            EFuncRoundHalfUp {
+                modulus: modulus_opt,
+                expr: expr_i,
+            } => {
+                let modulus = match modulus_opt {
+                    Some(m_expr_i) => get_expr!(slab.ps, m_expr_i).eval(slab, ns)?,
+                    None => 1.0,
+                };
+                check_finite(
+                    slab,
+                    "round_half_up",
+                    round_half_up(get_expr!(slab.ps, expr_i).eval(slab, ns)? / modulus) * modulus,
+                )
+            }
+            EFuncRoundZ {
+                modulus: modulus_opt,
+                expr: expr_i,
+            } => {
+                let modulus = match modulus_opt {
+                    Some(m_expr_i) => get_expr!(slab.ps, m_expr_i).eval(slab, ns)?,
+                    None => 1.0,
+                };
+                check_finite(
+                    slab,
+                    "roundz",
+                    round_ties_to_zero(get_expr!(slab.ps, expr_i).eval(slab, ns)? / modulus)
+                        * modulus,
+                )
             }
 
             EFuncAbs(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.abs()),
             EFuncSign(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.signum()),
-            EFuncInt(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.trunc()),
-            EFuncCeil(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.ceil()),
-            EFuncFloor(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.floor()),
+            EFuncSqrt(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.sqrt()),
+            EFuncCbrt(expr_i) => Ok(get_expr!(slab.ps, expr_i).eval(slab, ns)?.cbrt()),
+            EFuncProbRound(expr_i) => {
+                let x = get_expr!(slab.ps, expr_i).eval(slab, ns)?;
+                Ok(probround(x, ns.random().ok_or(Error::NoRandomSource)?))
+            }
+            EFuncInt(expr_i) => check_finite(
+                slab,
+                "int",
+                get_expr!(slab.ps, expr_i).eval(slab, ns)?.trunc(),
+            ),
+            EFuncCeil(expr_i) => check_finite(
+                slab,
+                "ceil",
+                get_expr!(slab.ps, expr_i).eval(slab, ns)?.ceil(),
+            ),
+            EFuncFloor(expr_i) => check_finite(
+                slab,
+                "floor",
+                get_expr!(slab.ps, expr_i).eval(slab, ns)?.floor(),
+            ),
             EFuncMin {
                 first: first_i,
                 rest,
             } => {
                 let mut min = get_expr!(slab.ps, first_i).eval(slab, ns)?;
-                let mut saw_nan = min.is_nan();
                 for x_i in rest {
-                    min = min.min(get_expr!(slab.ps, x_i).eval(slab, ns)?);
-                    saw_nan = saw_nan || min.is_nan();
-                }
-                if saw_nan {
-                    Ok(std::f32::NAN)
-                } else {
-                    Ok(min)
+                    if min_max_saturated(slab, ns.missing(), min, true) {
+                        break;
+                    }
+                    let val = get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                    min = min_max(slab, ns.missing(), min, val, true)?;
                 }
+                Ok(min)
             }
             EFuncMax {
                 first: first_i,
                 rest,
             } => {
                 let mut max = get_expr!(slab.ps, first_i).eval(slab, ns)?;
-                let mut saw_nan = max.is_nan();
                 for x_i in rest {
-                    max = max.max(get_expr!(slab.ps, x_i).eval(slab, ns)?);
-                    saw_nan = saw_nan || max.is_nan();
+                    if min_max_saturated(slab, ns.missing(), max, false) {
+                        break;
+                    }
+                    let val = get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                    max = min_max(slab, ns.missing(), max, val, false)?;
                 }
-                if saw_nan {
-                    Ok(f32::NAN)
-                } else {
-                    Ok(max)
+                Ok(max)
+            }
+            EFuncMinFirst {
+                first: first_i,
+                rest,
+            } => {
+                let mut min = get_expr!(slab.ps, first_i).eval(slab, ns)?;
+                for x_i in rest {
+                    let val = get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                    min = min_max_first(slab, ns.missing(), min, val, true)?;
                 }
+                Ok(min)
             }
-
-            EFuncE => Ok(consts::E),
-            EFuncPi => Ok(consts::PI),
-        }
-    }
-}
-
-impl Evaler for PrintFunc {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
-        for x_or_s in &self.0 {
-            match x_or_s {
-                EExpr(xi) => get_expr!(slab.ps, xi)._var_names(slab, dst),
-                EStr(_) => (),
-            };
-        }
-    }
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
-        fn process_str(s: &str) -> String {
-            s.replace("\\n", "\n").replace("\\t", "\t")
-        }
-
-        let mut val = 0f32;
-
-        if let Some(EStr(fmtstr)) = self.0.first() {
-            if fmtstr.contains('%') {
-                // printf mode:
-
-                //let fmtstr = process_str(fmtstr);
-
-                return Err(Error::WrongArgs(String::from(
-                    "printf formatting is not yet implemented",
-                ))); // TODO: Make a pure-rust sprintf libarary.
-
-                //return Ok(val);
+            EFuncMaxFirst {
+                first: first_i,
+                rest,
+            } => {
+                let mut max = get_expr!(slab.ps, first_i).eval(slab, ns)?;
+                for x_i in rest {
+                    let val = get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                    max = min_max_first(slab, ns.missing(), max, val, false)?;
+                }
+                Ok(max)
             }
-        }
-
+            EFuncNanMin {
+                first: first_i,
+                rest,
+            }
+            | EFuncNanMax {
+                first: first_i,
+                rest,
+            } => {
+                let is_min = matches!(self, EFuncNanMin { .. });
+                let mut acc = get_expr!(slab.ps, first_i).eval(slab, ns)?;
+                for x_i in rest {
+                    let val = get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                    acc = nan_min_max(ns.missing(), acc, val, is_min);
+                }
+                Ok(acc)
+            }
+            EFuncProd {
+                first: first_i,
+                rest,
+            } => {
+                let mut prod = get_expr!(slab.ps, first_i).eval(slab, ns)?;
+                for x_i in rest {
+                    prod *= get_expr!(slab.ps, x_i).eval(slab, ns)?;
+                }
+                Ok(prod)
+            }
+
+            EFuncBlend(xis) => {
+                let mut args = Vec::with_capacity(xis.len());
+                for xi in xis {
+                    args.push(get_expr!(slab.ps, xi).eval(slab, ns)?);
+                }
+                Ok(blend(&args))
+            }
+            EFuncAvg(xis) | EFuncSum(xis) => {
+                let mut args = Vec::with_capacity(xis.len());
+                for xi in xis {
+                    args.push(get_expr!(slab.ps, xi).eval(slab, ns)?);
+                }
+                if matches!(self, EFuncAvg(_)) {
+                    Ok(avg(&args, ns.missing()))
+                } else {
+                    Ok(sum(&args, ns.missing()))
+                }
+            }
+            EFuncNorm(xis) | EFuncSumSq(xis) => {
+                let mut sum = 0.0;
+                for xi in xis {
+                    let val = get_expr!(slab.ps, xi).eval(slab, ns)?;
+                    sum += val * val;
+                }
+                if let EFuncNorm(_) = self {
+                    Ok(sum.sqrt())
+                } else {
+                    Ok(sum)
+                }
+            }
+            EFuncPoly(xis) => {
+                let mut args = Vec::with_capacity(xis.len());
+                for xi in xis {
+                    args.push(get_expr!(slab.ps, xi).eval(slab, ns)?);
+                }
+                Ok(poly(&args))
+            }
+            EFuncSeq(xis) => {
+                let mut last = Num::NAN;
+                for xi in xis {
+                    last = get_expr!(slab.ps, xi).eval(slab, ns)?;
+                }
+                Ok(last)
+            }
+            EFuncBucket { x, lo, hi, n } => Ok(bucket(
+                get_expr!(slab.ps, x).eval(slab, ns)?,
+                get_expr!(slab.ps, lo).eval(slab, ns)?,
+                get_expr!(slab.ps, hi).eval(slab, ns)?,
+                get_expr!(slab.ps, n).eval(slab, ns)?,
+            )),
+            EFuncPowMod { base, exp, modulus } => Ok(powmod(
+                get_expr!(slab.ps, base).eval(slab, ns)?,
+                get_expr!(slab.ps, exp).eval(slab, ns)?,
+                get_expr!(slab.ps, modulus).eval(slab, ns)?,
+            )),
+            EFuncSmoothstep { edge0, edge1, x } => Ok(smoothstep(
+                get_expr!(slab.ps, edge0).eval(slab, ns)?,
+                get_expr!(slab.ps, edge1).eval(slab, ns)?,
+                get_expr!(slab.ps, x).eval(slab, ns)?,
+            )),
+            EFuncClamp { val, lo, hi } => Ok(clamp(
+                get_expr!(slab.ps, val).eval(slab, ns)?,
+                get_expr!(slab.ps, lo).eval(slab, ns)?,
+                get_expr!(slab.ps, hi).eval(slab, ns)?,
+            )),
+            EFuncSelect { index, options } => {
+                let index = get_expr!(slab.ps, index).eval(slab, ns)?;
+                match select_option(slab, index, options.len())?.and_then(|i| options.get(i)) {
+                    Some(xi) => get_expr!(slab.ps, xi).eval(slab, ns),
+                    None => Ok(Num::NAN),
+                }
+            }
+
+            EFuncE => Ok(consts::E),
+            EFuncPi => Ok(consts::PI),
+        }
+    }
+}
+
+#[cfg(feature = "print-builtin")]
+impl Evaler for PrintFunc {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
+        for x_or_s in &self.0 {
+            match x_or_s {
+                EExpr(xi) => get_expr!(slab.ps, xi)._var_arities(slab, dst),
+                EStr(_) => (),
+            };
+        }
+    }
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
+        let (out, val) = self.format(slab, ns)?;
+        eprintln!("{out}");
+
+        Ok(match slab.print_return {
+            PrintReturn::LastValue => val,
+            PrintReturn::NaN => Num::NAN,
+            PrintReturn::Zero => 0.0,
+        })
+    }
+}
+
+#[cfg(feature = "print-builtin")]
+impl PrintFunc {
+    /// Evaluates the arguments, and returns the line that `print()` writes to
+    /// stderr (without the newline) along with the last value, or `0` if there
+    /// are no values.
+    ///
+    /// Normally the arguments are joined with spaces.  But if the first
+    /// argument is a string that contains a `%`, it is a printf-style format
+    /// string, which is filled in with the values of the other arguments, in
+    /// order.  It supports the `%d` (or `%i`), `%f`, `%e`, `%g` and `%x`
+    /// conversions (and their uppercase forms), with the `-`, `+`, ` ` and `0`
+    /// flags, a width and a precision, like `%08.3f`, each up to 256.  `%%` is a
+    /// literal `%`.
+    /// `%d` and `%x` truncate values to integers, and NaN and infinities are
+    /// written as `NaN` and `inf` by every conversion.  Values left over after
+    /// the last conversion are evaluated, but not written.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from evaluating the arguments.  In printf mode,
+    /// returns `Error::WrongArgs` if the format string has an unsupported
+    /// conversion or a width or precision above 256, if there are fewer values
+    /// than conversions, or if another string follows the format string.
+    pub fn format(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<(String, Num), Error> {
+        fn process_str(s: &str) -> String {
+            s.replace("\\n", "\n").replace("\\t", "\t")
+        }
+
+        let mut val: Num = 0.0;
+
+        if let Some(EStr(fmtstr)) = self.0.first() {
+            if fmtstr.contains('%') {
+                // printf mode:
+                let mut vals = Vec::with_capacity(self.0.len());
+                for a in self.0.iter().skip(1) {
+                    match a {
+                        EExpr(e_i) => {
+                            val = get_expr!(slab.ps, e_i).eval(slab, ns)?;
+                            vals.push(val);
+                        }
+                        EStr(_) => {
+                            return Err(Error::WrongArgs(String::from(
+                                "print: only values can follow a format string",
+                            )))
+                        }
+                    }
+                }
+                return Ok((sprintf(&process_str(fmtstr), &vals)?, val));
+            }
+        }
+
         // Normal Mode:
         let mut out = String::with_capacity(16);
         for (i, a) in self.0.iter().enumerate() {
@@ -584,38 +1512,213 @@ impl Evaler for PrintFunc {
                 EStr(s) => out.push_str(&process_str(s)),
             }
         }
-        eprintln!("{out}");
+        Ok((out, val))
+    }
+}
 
-        Ok(val)
+/// Fills in a printf-style format string for `print()`.  See
+/// [`PrintFunc::format()`](../parser/struct.PrintFunc.html#method.format).
+#[cfg(feature = "print-builtin")]
+fn sprintf(fmt: &str, vals: &[Num]) -> Result<String, Error> {
+    let mut out = String::with_capacity(fmt.len() + 8 * vals.len());
+    let mut vals = vals.iter().copied();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            out.push('%');
+            continue;
+        }
+
+        let (mut left, mut plus, mut space, mut zero) = (false, false, false, false);
+        while let Some(flag) = chars.next_if(|c| matches!(c, '-' | '+' | ' ' | '0')) {
+            match flag {
+                '-' => left = true,
+                '+' => plus = true,
+                ' ' => space = true,
+                _ => zero = true,
+            }
+        }
+        let width = read_usize(&mut chars, "width")?;
+        let precision = if chars.next_if_eq(&'.').is_some() {
+            Some(read_usize(&mut chars, "precision")?.unwrap_or(0))
+        } else {
+            None
+        };
+        let conv = chars.next().ok_or_else(|| {
+            Error::WrongArgs(String::from(
+                "print: incomplete conversion at end of format",
+            ))
+        })?;
+        if !matches!(
+            conv,
+            'd' | 'i' | 'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'x' | 'X'
+        ) {
+            return Err(Error::WrongArgs(format!(
+                "print: unsupported conversion '%{conv}'"
+            )));
+        }
+        let x = vals.next().ok_or_else(|| {
+            Error::WrongArgs(String::from("print: not enough values for format string"))
+        })?;
+
+        let is_int = matches!(conv, 'd' | 'i' | 'x' | 'X');
+        let negative = if is_int {
+            x.trunc() < 0.0
+        } else {
+            x.is_sign_negative() && !x.is_nan()
+        };
+        let body = if x.is_nan() {
+            String::from("NaN")
+        } else if x.is_infinite() {
+            String::from("inf")
+        } else {
+            format_magnitude(x.abs(), conv, precision)
+        };
+        let sign = if negative {
+            "-"
+        } else if plus && !x.is_nan() {
+            "+"
+        } else if space && !x.is_nan() {
+            " "
+        } else {
+            ""
+        };
+
+        let pad = width.unwrap_or(0).saturating_sub(sign.len() + body.len());
+        if left {
+            out.push_str(sign);
+            out.push_str(&body);
+            out.push_str(&" ".repeat(pad));
+        } else if zero && x.is_finite() {
+            out.push_str(sign);
+            out.push_str(&"0".repeat(pad));
+            out.push_str(&body);
+        } else {
+            out.push_str(&" ".repeat(pad));
+            out.push_str(sign);
+            out.push_str(&body);
+        }
+    }
+    Ok(out)
+}
+
+/// The largest printf width or precision that `print()` accepts, so that a
+/// format string can't make it allocate huge amounts of padding.
+#[cfg(feature = "print-builtin")]
+const PRINTF_LIMIT: usize = 256;
+
+/// Reads the digits of a printf width or precision (the `what`), which must
+/// not be larger than `PRINTF_LIMIT`.
+#[cfg(feature = "print-builtin")]
+fn read_usize(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    what: &str,
+) -> Result<Option<usize>, Error> {
+    let mut n: Option<usize> = None;
+    while let Some(d) = chars.next_if(char::is_ascii_digit) {
+        let d = d.to_digit(10).unwrap_or(0) as usize;
+        n = Some(n.unwrap_or(0).saturating_mul(10).saturating_add(d));
+    }
+    match n {
+        Some(n) if n > PRINTF_LIMIT => Err(Error::WrongArgs(format!(
+            "print: {what} is larger than {PRINTF_LIMIT}"
+        ))),
+        n => Ok(n),
     }
 }
 
+/// Formats the finite, non-negative `x` for a printf conversion, without sign
+/// or padding.
+#[cfg(feature = "print-builtin")]
+fn format_magnitude(x: Num, conv: char, precision: Option<usize>) -> String {
+    let upper = conv.is_ascii_uppercase();
+    match conv {
+        'd' | 'i' | 'x' | 'X' => {
+            let digits = match conv {
+                'x' => format!("{:x}", x.trunc() as u128),
+                'X' => format!("{:X}", x.trunc() as u128),
+                _ => format!("{:.0}", x.trunc()),
+            };
+            // For integers, the precision is the minimum number of digits:
+            let min = precision.unwrap_or(0);
+            format!("{}{digits}", "0".repeat(min.saturating_sub(digits.len())))
+        }
+        'e' | 'E' => format_exp(x, precision.unwrap_or(6), upper),
+        'g' | 'G' => {
+            // The precision is the number of significant digits:
+            let p = precision.unwrap_or(6).max(1);
+            let exp = format!("{:.*e}", p - 1, x)
+                .split_once('e')
+                .and_then(|(_, exp)| exp.parse::<i32>().ok())
+                .unwrap_or(0);
+            let out = if exp < -4 || exp >= p as i32 {
+                format_exp(x, p - 1, upper)
+            } else {
+                format!("{:.*}", (p as i32 - 1 - exp) as usize, x)
+            };
+            strip_fraction_zeros(&out, upper)
+        }
+        _ => format!("{:.*}", precision.unwrap_or(6), x),
+    }
+}
+
+/// Formats like C's `%e`: `1.500000e+03`.
+#[cfg(feature = "print-builtin")]
+fn format_exp(x: Num, precision: usize, upper: bool) -> String {
+    let out = format!("{x:.precision$e}");
+    let (mant, exp) = out.split_once('e').unwrap_or((&out, "0"));
+    let exp = exp.parse::<i32>().unwrap_or(0);
+    let e = if upper { 'E' } else { 'e' };
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{mant}{e}{sign}{:02}", exp.unsigned_abs())
+}
+
+/// Removes trailing zeros (and a trailing `.`) from the fraction of a `%g`
+/// result, like `1.500000e+03` to `1.5e+03`.
+#[cfg(feature = "print-builtin")]
+fn strip_fraction_zeros(out: &str, upper: bool) -> String {
+    let e = if upper { 'E' } else { 'e' };
+    let (mant, exp) = out.split_once(e).map_or((out, None), |(m, x)| (m, Some(x)));
+    let mant = if mant.contains('.') {
+        mant.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mant
+    };
+    exp.map_or_else(|| mant.to_owned(), |exp| format!("{mant}{e}{exp}"))
+}
+
 impl Evaler for Instruction {
-    fn _var_names(&self, slab: &Slab, dst: &mut BTreeSet<String>) {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
         match self {
             #[cfg(feature = "unsafe-vars")]
             IUnsafeVar { name, .. } => {
-                dst.insert(name.clone());
+                dst.entry(name.clone()).or_default().insert(0);
             }
 
             IVar(s) => {
-                dst.insert(s.clone());
+                dst.entry(s.clone()).or_default().insert(0);
             }
             IFunc { name, args } => {
-                dst.insert(name.clone());
+                dst.entry(name.clone()).or_default().insert(args.len());
                 for ic in args {
                     let iconst: Self;
-                    ic_to_instr!(slab.cs, iconst, ic)._var_names(slab, dst);
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
                 }
             }
 
             IConst(_) => (),
 
             INeg(ii) | INot(ii) | IInv(ii) | IFuncInt(ii) | IFuncCeil(ii) | IFuncFloor(ii)
-            | IFuncAbs(ii) | IFuncSign(ii) | IFuncSin(ii) | IFuncCos(ii) | IFuncTan(ii)
-            | IFuncASin(ii) | IFuncACos(ii) | IFuncATan(ii) | IFuncSinH(ii) | IFuncCosH(ii)
-            | IFuncTanH(ii) | IFuncASinH(ii) | IFuncACosH(ii) | IFuncATanH(ii) => {
-                get_instr!(slab.cs, ii)._var_names(slab, dst);
+            | IFuncAbs(ii) | IFuncSqrt(ii) | IFuncCbrt(ii) | IFuncSign(ii) | IFuncProbRound(ii)
+            | IFuncSin(ii) | IFuncCos(ii) | IFuncTan(ii) | IFuncASin(ii) | IFuncACos(ii)
+            | IFuncATan(ii) | IFuncASinD(ii) | IFuncACosD(ii) | IFuncATanD(ii) | IFuncSinH(ii)
+            | IFuncCosH(ii) | IFuncTanH(ii) | IFuncASinH(ii) | IFuncACosH(ii) | IFuncATanH(ii) => {
+                get_instr!(slab.cs, ii)._var_arities(slab, dst);
             }
 
             ILT(left_ic, right_ic)
@@ -639,29 +1742,89 @@ impl Evaler for Instruction {
             | IFuncRound {
                 modulus: left_ic,
                 of: right_ic,
+            }
+            | IFuncRoundHalfUp {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncRoundZ {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncATan2 {
+                y: left_ic,
+                x: right_ic,
+            }
+            | IFuncATan2D {
+                y: left_ic,
+                x: right_ic,
             } => {
                 let mut iconst: Self;
-                ic_to_instr!(slab.cs, iconst, left_ic)._var_names(slab, dst);
-                ic_to_instr!(slab.cs, iconst, right_ic)._var_names(slab, dst);
+                ic_to_instr!(slab.cs, iconst, left_ic)._var_arities(slab, dst);
+                ic_to_instr!(slab.cs, iconst, right_ic)._var_arities(slab, dst);
             }
 
             IAdd(li, ric)
             | IMul(li, ric)
             | IOR(li, ric)
+            | IXOR(li, ric)
             | IAND(li, ric)
             | IFuncMin(li, ric)
-            | IFuncMax(li, ric) => {
-                get_instr!(slab.cs, li)._var_names(slab, dst);
+            | IFuncMax(li, ric)
+            | IFuncMinFirst(li, ric)
+            | IFuncMaxFirst(li, ric)
+            | IFuncNanMin(li, ric)
+            | IFuncNanMax(li, ric) => {
+                get_instr!(slab.cs, li)._var_arities(slab, dst);
                 let iconst: Self;
-                ic_to_instr!(slab.cs, iconst, ric)._var_names(slab, dst);
+                ic_to_instr!(slab.cs, iconst, ric)._var_arities(slab, dst);
+            }
+
+            IFuncBlend(ics) | IFuncAvg(ics) | IFuncSum(ics) | IFuncNorm(ics) | IFuncSumSq(ics)
+            | IFuncPoly(ics) | IFuncSeq(ics) | IFuncMinN(ics) | IFuncMaxN(ics) => {
+                for ic in ics {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
+            }
+            IFuncBucket { x, lo, hi, n } => {
+                for ic in [x, lo, hi, n] {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
+            }
+            IFuncPowMod { base, exp, modulus } => {
+                for ic in [base, exp, modulus] {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
+            }
+            IFuncSmoothstep { edge0, edge1, x } => {
+                for ic in [edge0, edge1, x] {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
+            }
+            IFuncClamp { val, lo, hi } => {
+                for ic in [val, lo, hi] {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
+            }
+            IFuncSelect { index, options } => {
+                for ic in Some(index).into_iter().chain(options) {
+                    let iconst: Self;
+                    ic_to_instr!(slab.cs, iconst, ic)._var_arities(slab, dst);
+                }
             }
 
-            IPrintFunc(pf) => pf._var_names(slab, dst),
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(pf) => pf._var_arities(slab, dst),
         }
     }
 
     #[allow(clippy::too_many_lines, clippy::cognitive_complexity)] // This is pretty simple on its own.
-    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
         let celled_slab = RefCell::from(slab.ps.char_buf.clone());
         match self {
             // I have manually ordered these match arms in a way that I feel should deliver good performance.
@@ -687,7 +1850,8 @@ impl Evaler for Instruction {
                 for ic in ics {
                     args.push(eval_ic_ref!(ic, slab, ns));
                 }
-                eval_var!(ns, name, args, &mut celled_slab.borrow_mut())
+                call_func(ns, name, args, &mut celled_slab.borrow_mut())
+                    .ok_or_else(|| Error::UndefinedFunction(name.clone()))
             }
 
             IFuncLog {
@@ -705,8 +1869,29 @@ impl Evaler for Instruction {
             IFuncASin(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).asin()),
             IFuncACos(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).acos()),
             IFuncATan(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).atan()),
-            IFuncSinH(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).sinh()),
-            IFuncCosH(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).cosh()),
+            IFuncASinD(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns)
+                .asin()
+                .to_degrees()),
+            IFuncACosD(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns)
+                .acos()
+                .to_degrees()),
+            IFuncATanD(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns)
+                .atan()
+                .to_degrees()),
+            IFuncATan2 { y, x } => Ok(eval_ic_ref!(y, slab, ns).atan2(eval_ic_ref!(x, slab, ns))),
+            IFuncATan2D { y, x } => {
+                Ok(atan2d(eval_ic_ref!(y, slab, ns), eval_ic_ref!(x, slab, ns)))
+            }
+            IFuncSinH(i) => check_overflow(
+                slab,
+                eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns),
+                Num::sinh,
+            ),
+            IFuncCosH(i) => check_overflow(
+                slab,
+                eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns),
+                Num::cosh,
+            ),
             IFuncTanH(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).tanh()),
             IFuncASinH(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).asinh()),
             IFuncACosH(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).acosh()),
@@ -718,7 +1903,23 @@ impl Evaler for Instruction {
             } => {
                 let modulus = eval_ic_ref!(modic, slab, ns);
                 let of = eval_ic_ref!(ofic, slab, ns);
-                Ok((of / modulus).round() * modulus)
+                check_finite(slab, "round", (of / modulus).round() * modulus)
+            }
+            IFuncRoundHalfUp {
+                modulus: modic,
+                of: ofic,
+            } => {
+                let modulus = eval_ic_ref!(modic, slab, ns);
+                let of = eval_ic_ref!(ofic, slab, ns);
+                check_finite(slab, "round_half_up", round_half_up(of / modulus) * modulus)
+            }
+            IFuncRoundZ {
+                modulus: modic,
+                of: ofic,
+            } => {
+                let modulus = eval_ic_ref!(modic, slab, ns);
+                let of = eval_ic_ref!(ofic, slab, ns);
+                check_finite(slab, "roundz", round_ties_to_zero(of / modulus) * modulus)
             }
             IMod { dividend, divisor } => {
                 Ok(eval_ic_ref!(dividend, slab, ns) % eval_ic_ref!(divisor, slab, ns))
@@ -726,32 +1927,79 @@ impl Evaler for Instruction {
 
             IFuncAbs(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).abs()),
             IFuncSign(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).signum()),
-            IFuncInt(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).trunc()),
-            IFuncCeil(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).ceil()),
-            IFuncFloor(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).floor()),
+            IFuncSqrt(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).sqrt()),
+            IFuncCbrt(i) => Ok(eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).cbrt()),
+            IFuncProbRound(i) => {
+                let x = eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns);
+                Ok(probround(x, ns.random().ok_or(Error::NoRandomSource)?))
+            }
+            IFuncInt(i) => check_finite(
+                slab,
+                "int",
+                eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).trunc(),
+            ),
+            IFuncCeil(i) => check_finite(
+                slab,
+                "ceil",
+                eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).ceil(),
+            ),
+            IFuncFloor(i) => check_finite(
+                slab,
+                "floor",
+                eval_compiled_ref!(get_instr!(slab.cs, i), slab, ns).floor(),
+            ),
             IFuncMin(li, ric) => {
                 let left = eval_compiled_ref!(get_instr!(slab.cs, li), slab, ns);
-                let right = eval_ic_ref!(ric, slab, ns);
-                if left.is_nan() || right.is_nan() {
-                    return Ok(std::f32::NAN);
-                } // I need to implement NAN checks myself because the f32.min() function says that if one number is NaN, the other will be returned.
-                if left < right {
-                    Ok(left)
-                } else {
-                    Ok(right)
+                if min_max_saturated(slab, ns.missing(), left, true) {
+                    return Ok(left);
                 }
+                let right = eval_ic_ref!(ric, slab, ns);
+                min_max(slab, ns.missing(), left, right, true)
             }
             IFuncMax(li, ric) => {
                 let left = eval_compiled_ref!(get_instr!(slab.cs, li), slab, ns);
-                let right = eval_ic_ref!(ric, slab, ns);
-                if left.is_nan() || right.is_nan() {
-                    return Ok(std::f32::NAN);
+                if min_max_saturated(slab, ns.missing(), left, false) {
+                    return Ok(left);
                 }
-                if left > right {
-                    Ok(left)
-                } else {
-                    Ok(right)
+                let right = eval_ic_ref!(ric, slab, ns);
+                min_max(slab, ns.missing(), left, right, false)
+            }
+            IFuncMinFirst(li, ric) => {
+                let left = eval_compiled_ref!(get_instr!(slab.cs, li), slab, ns);
+                let right = eval_ic_ref!(ric, slab, ns);
+                min_max_first(slab, ns.missing(), left, right, true)
+            }
+            IFuncMaxFirst(li, ric) => {
+                let left = eval_compiled_ref!(get_instr!(slab.cs, li), slab, ns);
+                let right = eval_ic_ref!(ric, slab, ns);
+                min_max_first(slab, ns.missing(), left, right, false)
+            }
+            IFuncNanMin(li, ric) | IFuncNanMax(li, ric) => {
+                let left = eval_compiled_ref!(get_instr!(slab.cs, li), slab, ns);
+                let right = eval_ic_ref!(ric, slab, ns);
+                Ok(nan_min_max(
+                    ns.missing(),
+                    left,
+                    right,
+                    matches!(self, IFuncNanMin(..)),
+                ))
+            }
+            IFuncMinN(ics) | IFuncMaxN(ics) => {
+                // Reduces like the equivalent chain of IFuncMin or IFuncMax:
+                let is_min = matches!(self, IFuncMinN(_));
+                let mut acc = Num::NAN;
+                for (i, ic) in ics.iter().enumerate() {
+                    if i > 0 && min_max_saturated(slab, ns.missing(), acc, is_min) {
+                        break;
+                    }
+                    let val = eval_ic_ref!(ic, slab, ns);
+                    acc = if i == 0 {
+                        val
+                    } else {
+                        min_max(slab, ns.missing(), acc, val, is_min)?
+                    };
                 }
+                Ok(acc)
             }
 
             IEQ(left, right) => Ok(bool_to_f32!(f32_eq!(
@@ -796,6 +2044,86 @@ impl Evaler for Instruction {
                 }
             }
 
+            IXOR(lefti, rightic) => {
+                let left = eval_compiled_ref!(get_instr!(slab.cs, lefti), slab, ns);
+                let right = eval_ic_ref!(rightic, slab, ns);
+                Ok(EXOR.binaryop_eval(Some(&left), Some(&right)))
+            }
+
+            IFuncBlend(ics) => {
+                let mut args = Vec::with_capacity(ics.len());
+                for ic in ics {
+                    args.push(eval_ic_ref!(ic, slab, ns));
+                }
+                Ok(blend(&args))
+            }
+            IFuncAvg(ics) | IFuncSum(ics) => {
+                let mut args = Vec::with_capacity(ics.len());
+                for ic in ics {
+                    args.push(eval_ic_ref!(ic, slab, ns));
+                }
+                if matches!(self, IFuncAvg(_)) {
+                    Ok(avg(&args, ns.missing()))
+                } else {
+                    Ok(sum(&args, ns.missing()))
+                }
+            }
+            IFuncPoly(ics) => {
+                let mut args = Vec::with_capacity(ics.len());
+                for ic in ics {
+                    args.push(eval_ic_ref!(ic, slab, ns));
+                }
+                Ok(poly(&args))
+            }
+            IFuncNorm(ics) | IFuncSumSq(ics) => {
+                let mut sum = 0.0;
+                for ic in ics {
+                    let val = eval_ic_ref!(ic, slab, ns);
+                    sum += val * val;
+                }
+                if let IFuncNorm(_) = self {
+                    Ok(sum.sqrt())
+                } else {
+                    Ok(sum)
+                }
+            }
+            IFuncSeq(ics) => {
+                let mut last = Num::NAN;
+                for ic in ics {
+                    last = eval_ic_ref!(ic, slab, ns);
+                }
+                Ok(last)
+            }
+            IFuncBucket { x, lo, hi, n } => Ok(bucket(
+                eval_ic_ref!(x, slab, ns),
+                eval_ic_ref!(lo, slab, ns),
+                eval_ic_ref!(hi, slab, ns),
+                eval_ic_ref!(n, slab, ns),
+            )),
+            IFuncPowMod { base, exp, modulus } => Ok(powmod(
+                eval_ic_ref!(base, slab, ns),
+                eval_ic_ref!(exp, slab, ns),
+                eval_ic_ref!(modulus, slab, ns),
+            )),
+            IFuncSmoothstep { edge0, edge1, x } => Ok(smoothstep(
+                eval_ic_ref!(edge0, slab, ns),
+                eval_ic_ref!(edge1, slab, ns),
+                eval_ic_ref!(x, slab, ns),
+            )),
+            IFuncClamp { val, lo, hi } => Ok(clamp(
+                eval_ic_ref!(val, slab, ns),
+                eval_ic_ref!(lo, slab, ns),
+                eval_ic_ref!(hi, slab, ns),
+            )),
+            IFuncSelect { index, options } => {
+                let index = eval_ic_ref!(index, slab, ns);
+                match select_option(slab, index, options.len())?.and_then(|i| options.get(i)) {
+                    Some(ic) => Ok(eval_ic_ref!(ic, slab, ns)),
+                    None => Ok(Num::NAN),
+                }
+            }
+
+            #[cfg(feature = "print-builtin")]
             IPrintFunc(pf) => pf.eval(slab, ns),
 
             // Put these last because you should be using the eval_compiled*!() macros to eliminate function calls.
@@ -805,3 +2133,484 @@ impl Evaler for Instruction {
         }
     }
 }
+
+/// A unit of work for [`Instruction::eval_iterative()`](../compiler/enum.Instruction.html#method.eval_iterative).
+enum Frame<'a> {
+    /// Push a known value.
+    Push(Num),
+    /// Evaluate an `Instruction`, leaving its value on the value stack.
+    Eval(&'a Instruction),
+    /// The left side of an `IAND` or `IOR`, or the index of an `IFuncSelect`,
+    /// is on the value stack.  Decide which operand is needed, if any.
+    ShortCircuit(&'a Instruction),
+    /// The operand at the given index of an `IFuncMin`, `IFuncMax`,
+    /// `IFuncMinN` or `IFuncMaxN` is on the value stack, above the result so
+    /// far.  Combine them, and decide whether the next operand is needed.
+    MinMax(&'a Instruction, usize),
+    /// The operands of an `Instruction` are on the value stack, starting at
+    /// the given index.  Replace them with the result.
+    Apply(&'a Instruction, usize),
+}
+
+impl<'a> Frame<'a> {
+    fn from_ic(ic: &'a IC, slab: &'a Slab) -> Self {
+        match ic {
+            IC::C(c) => Frame::Push(*c),
+            IC::I(i) => Frame::Eval(get_instr!(slab.cs, i)),
+        }
+    }
+
+    fn from_i(i: &InstructionI, slab: &'a Slab) -> Self {
+        Frame::Eval(get_instr!(slab.cs, i))
+    }
+}
+
+impl Instruction {
+    /// Like [`eval()`](../evaler/trait.Evaler.html#tymethod.eval), but uses an
+    /// explicit work stack on the heap instead of recursing through the
+    /// child `Instruction`s.
+    ///
+    /// Very deep expressions (for example, with a raised
+    /// [`Parser.expr_depth_limit`](../parser/struct.Parser.html#structfield.expr_depth_limit))
+    /// can overflow the native stack with the recursive `eval()`.  This method
+    /// can't, but it is somewhat slower, so only use it when that is a concern.
+    /// `print()` arguments are still evaluated recursively.
+    ///
+    /// The results, errors, and Namespace lookups are the same as with `eval()`,
+    /// including the short-circuiting of `&&`, `||`, `select()`, `min()` and `max()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `eval()`.
+    pub fn eval_iterative(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
+        self.eval_stack(slab, ns, &mut 0)
+    }
+
+    /// Like [`eval_iterative()`](#method.eval_iterative), but also returns the
+    /// number of operations that were executed, for billing or profiling
+    /// untrusted expressions.
+    ///
+    /// Every operator and function call that is evaluated counts as one
+    /// operation, including custom functions and `print()`.  Constants and
+    /// variables are free, and operands that are skipped by the
+    /// short-circuiting of `&&`, `||`, `select()`, `min()` and `max()` don't count.  Compile first, because
+    /// constant folding changes the count: `2 * 3 * x` is a single operation.
+    ///
+    /// The normal `eval()` doesn't count anything, so it has no overhead.
+    ///
+    /// ```
+    /// use fasteval3::Compiler;
+    ///
+    /// fn main() -> Result<(), fasteval3::Error> {
+    ///     let mut slab = fasteval3::Slab::new();
+    ///     let mut ns = fasteval3::EmptyNamespace;
+    ///     let instr = fasteval3::Parser::new()
+    ///         .parse("sin(2) * 3 + 1 > 0 || 1/0", &mut slab.ps)?
+    ///         .from(&slab.ps)
+    ///         .compile(&slab.ps, &mut slab.cs, &mut ns);
+    ///     assert_eq!(instr.eval_metered(&slab, &mut ns)?, (1.0, 0));  // Folded.
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `eval()`.
+    pub fn eval_metered(
+        &self,
+        slab: &Slab,
+        ns: &mut impl EvalNamespace,
+    ) -> Result<(Num, u64), Error> {
+        let mut ops = 0;
+        let val = self.eval_stack(slab, ns, &mut ops)?;
+        Ok((val, ops))
+    }
+
+    /// Returns every constant in this `Instruction` and its children, in the
+    /// order that `eval()` would first reach them.  This is like
+    /// [`var_names()`](../evaler/trait.Evaler.html#method.var_names), but for
+    /// constants, and it is useful for building a constant pool.
+    ///
+    /// Constants that appear more than once are returned each time.  The
+    /// arguments of `print()` are not compiled, so their constants aren't
+    /// included.
+    ///
+    /// ```
+    /// use fasteval3::Compiler;
+    ///
+    /// fn main() -> Result<(), fasteval3::Error> {
+    ///     let mut slab = fasteval3::Slab::new();
+    ///     let instr = fasteval3::Parser::new()
+    ///         .parse("(x + 2) * 3", &mut slab.ps)?
+    ///         .from(&slab.ps)
+    ///         .compile(&slab.ps, &mut slab.cs, &mut fasteval3::EmptyNamespace);
+    ///     assert_eq!(instr.constants(&slab), vec![2.0, 3.0]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn constants(&self, slab: &Slab) -> Vec<Num> {
+        let mut consts = Vec::new();
+        let mut work = vec![Frame::Eval(self)];
+        while let Some(frame) = work.pop() {
+            match frame {
+                Frame::Push(c) | Frame::Eval(&IConst(c)) => consts.push(c),
+                Frame::Eval(instr) => {
+                    let start = work.len();
+                    instr.push_operands(slab, &mut work);
+                    // Visit the operands in order, so the first one must be on top:
+                    if let Some(operands) = work.get_mut(start..) {
+                        operands.reverse();
+                    }
+                }
+                Frame::ShortCircuit(_) | Frame::MinMax(..) | Frame::Apply(..) => (),
+            }
+        }
+        consts
+    }
+
+    /// Evaluates with an explicit work stack, adding the number of executed
+    /// operations to `ops`.
+    fn eval_stack(
+        &self,
+        slab: &Slab,
+        ns: &mut impl EvalNamespace,
+        ops: &mut u64,
+    ) -> Result<Num, Error> {
+        let mut keybuf = String::new();
+        let mut work = vec![Frame::Eval(self)];
+        let mut vals = Vec::<Num>::new();
+        while let Some(frame) = work.pop() {
+            match frame {
+                Frame::Push(c) | Frame::Eval(&IConst(c)) => vals.push(c),
+                Frame::Eval(instr @ (IAND(li, _) | IOR(li, _))) => {
+                    work.push(Frame::ShortCircuit(instr));
+                    work.push(Frame::from_i(li, slab));
+                }
+                Frame::Eval(instr @ IFuncSelect { index, .. }) => {
+                    work.push(Frame::ShortCircuit(instr));
+                    work.push(Frame::from_ic(index, slab));
+                }
+                Frame::Eval(
+                    instr @ (IFuncMin(..) | IFuncMax(..) | IFuncMinN(_) | IFuncMaxN(_)),
+                ) => {
+                    work.push(Frame::MinMax(instr, 0));
+                    // Like eval(), an empty IFuncMinN or IFuncMaxN is NaN:
+                    work.push(
+                        instr
+                            .min_max_operand(0, slab)
+                            .unwrap_or(Frame::Push(Num::NAN)),
+                    );
+                }
+                Frame::Eval(instr) => {
+                    work.push(Frame::Apply(instr, vals.len()));
+                    let start = work.len();
+                    instr.push_operands(slab, &mut work);
+                    // Operands are evaluated in order, so the first one must be on top:
+                    if let Some(operands) = work.get_mut(start..) {
+                        operands.reverse();
+                    }
+                }
+                Frame::ShortCircuit(instr) => {
+                    *ops += 1;
+                    let first = vals.pop().ok_or(Error::Unreachable)?;
+                    match instr {
+                        IAND(_, ric) if f32_ne!(first, 0.0) => work.push(Frame::from_ic(ric, slab)),
+                        IOR(_, ric) if f32_eq!(first, 0.0) => work.push(Frame::from_ic(ric, slab)),
+                        IFuncSelect { options, .. } => {
+                            match select_option(slab, first, options.len())?
+                                .and_then(|i| options.get(i))
+                            {
+                                Some(ic) => work.push(Frame::from_ic(ic, slab)),
+                                None => vals.push(Num::NAN),
+                            }
+                        }
+                        _ => vals.push(first),
+                    }
+                }
+                Frame::MinMax(instr, i) => {
+                    let is_min = matches!(instr, IFuncMin(..) | IFuncMinN(_));
+                    let val = vals.pop().ok_or(Error::Unreachable)?;
+                    let acc = if i == 0 {
+                        val
+                    } else {
+                        let acc = vals.pop().ok_or(Error::Unreachable)?;
+                        min_max(slab, ns.missing(), acc, val, is_min)?
+                    };
+                    vals.push(acc);
+                    match instr.min_max_operand(i + 1, slab) {
+                        Some(next) if !min_max_saturated(slab, ns.missing(), acc, is_min) => {
+                            work.push(Frame::MinMax(instr, i + 1));
+                            work.push(next);
+                        }
+                        _ => *ops += 1,
+                    }
+                }
+                Frame::Apply(instr, base) => {
+                    if !instr.is_load() {
+                        *ops += 1;
+                    }
+                    let val =
+                        instr.apply(vals.get(base..).unwrap_or(&[]), slab, ns, &mut keybuf)?;
+                    vals.truncate(base);
+                    vals.push(val);
+                }
+            }
+        }
+        match vals.as_slice() {
+            [val] => Ok(*val),
+            _ => Err(Error::Unreachable),
+        }
+    }
+
+    /// Returns `true` for the `Instruction`s that just load a value, which
+    /// `eval_metered()` doesn't count.
+    const fn is_load(&self) -> bool {
+        match self {
+            IConst(_) | IVar(_) => true,
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the operand at index `i` of an `IFuncMin`, `IFuncMax`,
+    /// `IFuncMinN` or `IFuncMaxN`, or `None` past the last one.
+    fn min_max_operand<'a>(&'a self, i: usize, slab: &'a Slab) -> Option<Frame<'a>> {
+        match (self, i) {
+            (IFuncMin(li, _) | IFuncMax(li, _), 0) => Some(Frame::from_i(li, slab)),
+            (IFuncMin(_, ric) | IFuncMax(_, ric), 1) => Some(Frame::from_ic(ric, slab)),
+            (IFuncMinN(ics) | IFuncMaxN(ics), _) => ics.get(i).map(|ic| Frame::from_ic(ic, slab)),
+            _ => None,
+        }
+    }
+
+    /// Pushes the operands that `eval()` evaluates before combining them, in order.
+    fn push_operands<'a>(&'a self, slab: &'a Slab, work: &mut Vec<Frame<'a>>) {
+        match self {
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { .. } => (),
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(_) => (),
+            IConst(_) | IVar(_) => (),
+
+            INeg(ii) | INot(ii) | IInv(ii) | IFuncInt(ii) | IFuncCeil(ii) | IFuncFloor(ii)
+            | IFuncAbs(ii) | IFuncSqrt(ii) | IFuncCbrt(ii) | IFuncSign(ii) | IFuncProbRound(ii)
+            | IFuncSin(ii) | IFuncCos(ii) | IFuncTan(ii) | IFuncASin(ii) | IFuncACos(ii)
+            | IFuncATan(ii) | IFuncASinD(ii) | IFuncACosD(ii) | IFuncATanD(ii) | IFuncSinH(ii)
+            | IFuncCosH(ii) | IFuncTanH(ii) | IFuncASinH(ii) | IFuncACosH(ii) | IFuncATanH(ii) => {
+                work.push(Frame::from_i(ii, slab))
+            }
+
+            ILT(left_ic, right_ic)
+            | ILTE(left_ic, right_ic)
+            | IEQ(left_ic, right_ic)
+            | INE(left_ic, right_ic)
+            | IGTE(left_ic, right_ic)
+            | IGT(left_ic, right_ic)
+            | IMod {
+                dividend: left_ic,
+                divisor: right_ic,
+            }
+            | IExp {
+                base: left_ic,
+                power: right_ic,
+            }
+            | IFuncLog {
+                base: left_ic,
+                of: right_ic,
+            }
+            | IFuncRound {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncRoundHalfUp {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncRoundZ {
+                modulus: left_ic,
+                of: right_ic,
+            }
+            | IFuncATan2 {
+                y: left_ic,
+                x: right_ic,
+            }
+            | IFuncATan2D {
+                y: left_ic,
+                x: right_ic,
+            } => {
+                work.push(Frame::from_ic(left_ic, slab));
+                work.push(Frame::from_ic(right_ic, slab));
+            }
+
+            IAdd(li, ric)
+            | IMul(li, ric)
+            | IOR(li, ric)
+            | IXOR(li, ric)
+            | IAND(li, ric)
+            | IFuncMin(li, ric)
+            | IFuncMax(li, ric)
+            | IFuncMinFirst(li, ric)
+            | IFuncMaxFirst(li, ric)
+            | IFuncNanMin(li, ric)
+            | IFuncNanMax(li, ric) => {
+                work.push(Frame::from_i(li, slab));
+                work.push(Frame::from_ic(ric, slab));
+            }
+
+            IFunc { args: ics, .. }
+            | IFuncBlend(ics)
+            | IFuncAvg(ics)
+            | IFuncSum(ics)
+            | IFuncNorm(ics)
+            | IFuncSumSq(ics)
+            | IFuncPoly(ics)
+            | IFuncSeq(ics)
+            | IFuncMinN(ics)
+            | IFuncMaxN(ics) => work.extend(ics.iter().map(|ic| Frame::from_ic(ic, slab))),
+            IFuncBucket { x, lo, hi, n } => {
+                work.extend(
+                    [x, lo, hi, n]
+                        .into_iter()
+                        .map(|ic| Frame::from_ic(ic, slab)),
+                );
+            }
+            IFuncPowMod { base, exp, modulus } => {
+                work.extend(
+                    [base, exp, modulus]
+                        .into_iter()
+                        .map(|ic| Frame::from_ic(ic, slab)),
+                );
+            }
+            IFuncSmoothstep { edge0, edge1, x } => {
+                work.extend(
+                    [edge0, edge1, x]
+                        .into_iter()
+                        .map(|ic| Frame::from_ic(ic, slab)),
+                );
+            }
+            IFuncClamp { val, lo, hi } => {
+                work.extend([val, lo, hi].into_iter().map(|ic| Frame::from_ic(ic, slab)));
+            }
+            IFuncSelect { index, options } => {
+                work.push(Frame::from_ic(index, slab));
+                work.extend(options.iter().map(|ic| Frame::from_ic(ic, slab)));
+            }
+        }
+    }
+
+    /// Combines the already-evaluated `args` (from `push_operands()`) into the
+    /// value of this `Instruction`, like `eval()` does.
+    fn apply(
+        &self,
+        args: &[Num],
+        slab: &Slab,
+        ns: &mut impl EvalNamespace,
+        keybuf: &mut String,
+    ) -> Result<Num, Error> {
+        let arg = |n: usize| args.get(n).copied().unwrap_or(Num::NAN);
+        let (a, b) = (arg(0), arg(1));
+        Ok(match self {
+            IConst(c) => *c,
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { ptr, .. } => unsafe { **ptr },
+            #[cfg(feature = "print-builtin")]
+            IPrintFunc(pf) => pf.eval(slab, ns)?,
+            IVar(name) => eval_var!(ns, name, Vec::new(), keybuf)?,
+            IFunc { name, .. } => call_func(ns, name, args.to_vec(), keybuf)
+                .ok_or_else(|| Error::UndefinedFunction(name.clone()))?,
+
+            INeg(_) => -a,
+            INot(_) => bool_to_f32!(f32_eq!(a, 0.0)),
+            IInv(_) => 1.0 / a,
+            IAdd(..) => a + b,
+            IMul(..) => a * b,
+            IMod { .. } => a % b,
+            IExp { .. } => a.powf(b),
+
+            ILT(..) => bool_to_f32!(a < b),
+            ILTE(..) => bool_to_f32!(a <= b),
+            IEQ(..) => bool_to_f32!(f32_eq!(a, b)),
+            INE(..) => bool_to_f32!(f32_ne!(a, b)),
+            IGTE(..) => bool_to_f32!(a >= b),
+            IGT(..) => bool_to_f32!(a > b),
+            IXOR(..) => EXOR.binaryop_eval(Some(&a), Some(&b)),
+            // These are handled by Frame::ShortCircuit:
+            IAND(..) | IOR(..) | IFuncSelect { .. } => return Err(Error::Unreachable),
+
+            IFuncInt(_) => check_finite(slab, "int", a.trunc())?,
+            IFuncCeil(_) => check_finite(slab, "ceil", a.ceil())?,
+            IFuncFloor(_) => check_finite(slab, "floor", a.floor())?,
+            IFuncAbs(_) => a.abs(),
+            IFuncSign(_) => a.signum(),
+            IFuncSqrt(_) => a.sqrt(),
+            IFuncCbrt(_) => a.cbrt(),
+            IFuncProbRound(_) => probround(a, ns.random().ok_or(Error::NoRandomSource)?),
+            IFuncLog { .. } => log(a, b),
+            IFuncRound { .. } => check_finite(slab, "round", (b / a).round() * a)?,
+            IFuncRoundHalfUp { .. } => {
+                check_finite(slab, "round_half_up", round_half_up(b / a) * a)?
+            }
+            IFuncRoundZ { .. } => check_finite(slab, "roundz", round_ties_to_zero(b / a) * a)?,
+            IFuncMin(..) => min_max(slab, ns.missing(), a, b, true)?,
+            IFuncMax(..) => min_max(slab, ns.missing(), a, b, false)?,
+            IFuncMinFirst(..) => min_max_first(slab, ns.missing(), a, b, true)?,
+            IFuncMaxFirst(..) => min_max_first(slab, ns.missing(), a, b, false)?,
+            IFuncNanMin(..) => nan_min_max(ns.missing(), a, b, true),
+            IFuncNanMax(..) => nan_min_max(ns.missing(), a, b, false),
+            IFuncMinN(_) | IFuncMaxN(_) => {
+                let is_min = matches!(self, IFuncMinN(_));
+                let mut acc = a;
+                for &val in args.iter().skip(1) {
+                    acc = min_max(slab, ns.missing(), acc, val, is_min)?;
+                }
+                acc
+            }
+            IFuncBlend(_) => blend(args),
+            IFuncAvg(_) => avg(args, ns.missing()),
+            IFuncSum(_) => sum(args, ns.missing()),
+            IFuncPoly(_) => poly(args),
+            IFuncNorm(_) => args.iter().fold(0.0, |sum, val| sum + val * val).sqrt(),
+            IFuncSumSq(_) => args.iter().fold(0.0, |sum, val| sum + val * val),
+            IFuncSeq(_) => args.last().copied().unwrap_or(Num::NAN),
+            IFuncBucket { .. } => bucket(a, b, arg(2), arg(3)),
+            IFuncPowMod { .. } => powmod(a, b, arg(2)),
+            IFuncSmoothstep { .. } => smoothstep(a, b, arg(2)),
+            IFuncClamp { .. } => clamp(a, b, arg(2)),
+
+            IFuncSin(_) => a.sin(),
+            IFuncCos(_) => a.cos(),
+            IFuncTan(_) => a.tan(),
+            IFuncASin(_) => a.asin(),
+            IFuncACos(_) => a.acos(),
+            IFuncATan(_) => a.atan(),
+            IFuncASinD(_) => a.asin().to_degrees(),
+            IFuncACosD(_) => a.acos().to_degrees(),
+            IFuncATanD(_) => a.atan().to_degrees(),
+            IFuncATan2 { .. } => a.atan2(b),
+            IFuncATan2D { .. } => atan2d(a, b),
+            IFuncSinH(_) => check_overflow(slab, a, Num::sinh)?,
+            IFuncCosH(_) => check_overflow(slab, a, Num::cosh)?,
+            IFuncTanH(_) => a.tanh(),
+            IFuncASinH(_) => a.asinh(),
+            IFuncACosH(_) => a.acosh(),
+            IFuncATanH(_) => a.atanh(),
+        })
+    }
+}
+
+impl Evaler for Program {
+    var_names_from_arities!();
+    fn _var_arities(&self, slab: &Slab, dst: &mut BTreeMap<String, BTreeSet<usize>>) {
+        for instr_i in &self.0 {
+            get_instr!(slab.cs, instr_i)._var_arities(slab, dst);
+        }
+    }
+    fn eval(&self, slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
+        let mut last = Num::NAN;
+        for instr_i in &self.0 {
+            last = get_instr!(slab.cs, instr_i).eval(slab, ns)?;
+        }
+        Ok(last)
+    }
+}
@@ -7,12 +7,39 @@
 //!   Type aliases: [`StringTof32Namespace`](#stringtof32namespace),
 //!   [`StrTof32Namespace`](#strtof32namespace),
 //!   [`StringToCallbackNamespace`](#stringtocallbacknamespace),
-//!   [`StrToCallbackNamespace`](#strtocallbacknamespace)
+//!   [`StrToCallbackNamespace`](#strtocallbacknamespace).
+//!   [`unit_conversions()`](fn.unit_conversions.html) builds a
+//!   `StrToCallbackNamespace` of linear unit conversions from a table.
+//! * [`HashMap`](#hashmap) -- `HashMap<String,f32>` and
+//!   `HashMap<&'static str,f32>` (with any hasher) work just like
+//!   `StringTof32Namespace` and `StrTof32Namespace`.
 //! * [`FnMut(&str,Vec<f32>) -> Option<f32>`](#callback-fnmutstrvec---option) --
 //!   Define variables and custom functions using a callback function.
 //! * [`CachedCallbackNamespace`](#cachedcallbacknamespace) -- Like the above
 //!   callback-based Namespace, but results are cached so the callback is not
 //!   queried more than once for a given variable.
+//! * [`BudgetedNamespace`](#budgetednamespace) -- Wraps another Namespace and
+//!   limits the number of lookups, to guard against expressions that look up
+//!   expensive variables over and over.
+//! * [`MissingValueNamespace`](#missingvaluenamespace) -- Wraps another
+//!   Namespace and sets a sentinel value that `min()`, `max()`, `avg()` and
+//!   `sum()` skip, for datasets that mark missing values with something like `-9999`.
+//! * [`RandomNamespace`](#randomnamespace) -- Wraps another Namespace and
+//!   provides the random numbers used by `probround()`.
+//! * [`ArityNamespace`](#aritynamespace) -- Wraps another Namespace and
+//!   registers the argument counts of custom functions, so that
+//!   `compile_checked()` can reject calls like `f(1, 2)` to a 1-arg `f`.
+//! * [`ReduceNamespace`](#reducenamespace) -- Wraps another Namespace and
+//!   registers reducers: custom aggregations like `mymax(a, b, c)` that fold
+//!   their args with a binary operation.
+//! * [`ContextNamespace`](#contextnamespace) -- Define variables and custom
+//!   functions using a callback that also reads a borrowed context, like the
+//!   current frame of a simulation, so nothing needs to be mutated between
+//!   evaluations.
+//! * `BTreeMap<String,NestedValue>` -- Resolve dotted variable names like
+//!   `sensor.temp` by walking a tree of nested maps.  Parse with
+//!   [`Parser.dotted_names`](../parser/struct.Parser.html#structfield.dotted_names).
+//!   Type alias: [`NestedNamespace`](#nestednamespace)
 //! * `Vec<BTreeMap<String,f32>>` -- Define variables with layered maps.
 //!   Each layer is a separate 'scope'.  Higher layers take precedence
 //!   over lower layers.  Very useful for creating scoped higher-level-languages.
@@ -58,11 +85,30 @@
 //! }
 //! ```
 //!
+//! ## `HashMap`
+//! ```
+//! use std::collections::HashMap;
+//! use fasteval3::Num;
+//!
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let mut ns = HashMap::<String, Num>::new();
+//!     ns.insert("x".to_string(), 2.0);
+//!     // Functions are looked up by their name and args, joined with " , ":
+//!     ns.insert("f , 3".to_string(), 10.0);
+//!
+//!     let val = fasteval3::ez_eval("x * (x + 1) + f(3)", &mut ns)?;
+//!     assert_eq!(val, 16.0);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Callback: FnMut(&str,Vec<f32>) -> Option<f32>
 //! ```
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     let mut num_lookups = 0;
-//!     let mut cb = |name:&str, args:Vec<f32>| -> Option<f32> {
+//!     let mut cb = |name:&str, args:Vec<Num>| -> Option<Num> {
 //!         num_lookups += 1;
 //!         match name {
 //!             "x" => Some(2.0),
@@ -80,11 +126,12 @@
 //!
 //! ## `StringToCallbackNamespace`
 //! ```
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     let mut ns = fasteval3::StringToCallbackNamespace::new();
 //!     ns.insert("x".to_string(), Box::new(|_args| 2.0));
 //!     ns.insert("double".to_string(), Box::new(|args| {
-//!         args.get(0).map(|arg0| arg0*2.0).unwrap_or(std::f32::NAN)
+//!         args.get(0).map(|arg0| arg0*2.0).unwrap_or(Num::NAN)
 //!     }));
 //!
 //!     let val = fasteval3::ez_eval("double(x + 1) + 1", &mut ns)?;
@@ -96,11 +143,12 @@
 //!
 //! ## `StrToCallbackNamespace`
 //! ```
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     let mut ns = fasteval3::StrToCallbackNamespace::new();
 //!     ns.insert("x", Box::new(|_args| 2.0));
 //!     ns.insert("double", Box::new(|args| {
-//!         args.get(0).map(|arg0| arg0*2.0).unwrap_or(std::f32::NAN)
+//!         args.get(0).map(|arg0| arg0*2.0).unwrap_or(Num::NAN)
 //!     }));
 //!
 //!     let val = fasteval3::ez_eval("double(x + 1) + 1", &mut ns)?;
@@ -112,10 +160,11 @@
 //!
 //! ## `CachedCallbackNamespace`
 //! ```
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     let mut num_lookups = 0;
 //!     let val = {
-//!         let cb = |name:&str, args:Vec<f32>| -> Option<f32> {
+//!         let cb = |name:&str, args:Vec<Num>| -> Option<Num> {
 //!             num_lookups += 1;
 //!             match name {
 //!                 "x" => {
@@ -139,6 +188,162 @@
 //! }
 //! ```
 //!
+//! ## `BudgetedNamespace`
+//! ```
+//! use fasteval3::Num;
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let cb = |name:&str, args:Vec<Num>| -> Option<Num> {
+//!         match name {
+//!             "x" => Some(2.0),
+//!             _ => None,
+//!         }
+//!     };
+//!     let mut ns = fasteval3::BudgetedNamespace::new(cb, 2);
+//!
+//!     let val = fasteval3::ez_eval("x * (x + 1)", &mut ns)?;
+//!     assert_eq!(val, 6.0);
+//!     assert_eq!(ns.used(), 2);
+//!
+//!     // Reset the count before each evaluation to get a per-eval budget:
+//!     ns.reset();
+//!     let err = fasteval3::ez_eval("x * x * x", &mut ns).unwrap_err();
+//!     assert_eq!(err, fasteval3::Error::Undefined("x".to_string()));
+//!     assert!(ns.exhausted());
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## `MissingValueNamespace`
+//! ```
+//! use fasteval3::Num;
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let cb = |name:&str, args:Vec<Num>| -> Option<Num> {
+//!         match name {
+//!             "a" => Some(3.0),
+//!             "b" => Some(-9999.0),  // No data.
+//!             "c" => Some(5.0),
+//!             _ => None,
+//!         }
+//!     };
+//!     let mut ns = fasteval3::MissingValueNamespace::new(cb, -9999.0);
+//!
+//!     assert_eq!(fasteval3::ez_eval("min(a, b, c)", &mut ns)?, 3.0);
+//!     assert_eq!(fasteval3::ez_eval("avg(a, b, c)", &mut ns)?, 4.0);
+//!     assert_eq!(fasteval3::ez_eval("sum(a, b, c)", &mut ns)?, 8.0);
+//!
+//!     // Other operations still see the sentinel:
+//!     assert_eq!(fasteval3::ez_eval("a + b", &mut ns)?, -9996.0);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## `RandomNamespace`
+//! ```
+//! use fasteval3::Num;
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     // A tiny xorshift generator.  Use a real RNG crate in practice.
+//!     let mut state = 0x2545_f491_u32;
+//!     let rng = move || {
+//!         state ^= state << 13;
+//!         state ^= state >> 17;
+//!         state ^= state << 5;
+//!         (state >> 8) as Num / (1 << 24) as Num
+//!     };
+//!     let mut ns = fasteval3::RandomNamespace::new(fasteval3::EmptyNamespace, rng);
+//!
+//!     let val = fasteval3::ez_eval("probround(2.25)", &mut ns)?;
+//!     assert!(val == 2.0 || val == 3.0);
+//!
+//!     // Without a random source, probround() fails:
+//!     let err = fasteval3::ez_eval("probround(2.25)", &mut fasteval3::EmptyNamespace).unwrap_err();
+//!     assert_eq!(err, fasteval3::Error::NoRandomSource);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## `ArityNamespace`
+//! ```
+//! use fasteval3::{ArityNamespace, Error, Num, Parser, Slab};
+//! use std::collections::BTreeMap;
+//!
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let cb = |name:&str, args:Vec<Num>| -> Option<Num> {
+//!         match name {
+//!             "double" => args.first().map(|x| x * 2.0),
+//!             _ => None,
+//!         }
+//!     };
+//!     let mut arities = BTreeMap::new();
+//!     arities.insert("double".to_string(), 1);
+//!     let mut ns = ArityNamespace::new(cb, arities);
+//!
+//!     let mut slab = Slab::new();
+//!     let expr_i = Parser::new().parse("double(1, 2)", &mut slab.ps)?;
+//!     let res = slab.ps.get_expr(expr_i).compile_checked(&slab.ps, &mut slab.cs, &mut ns);
+//!     assert_eq!(res, Err(Error::WrongArgs("double: expected 1 arg".to_string())));
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## `ReduceNamespace`
+//! ```
+//! use fasteval3::Num;
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let mut ns = fasteval3::ReduceNamespace::new(fasteval3::EmptyNamespace);
+//!     ns.register("hypot".to_string(), Num::hypot);
+//!     ns.register("gcd".to_string(), |mut a, mut b| {
+//!         while b != 0.0 {
+//!             (a, b) = (b, a % b);
+//!         }
+//!         a
+//!     });
+//!
+//!     assert_eq!(fasteval3::ez_eval("hypot(2, 3, 6)", &mut ns)?, 7.0);
+//!     assert_eq!(fasteval3::ez_eval("gcd(12, 18, 27)", &mut ns)?, 3.0);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## `ContextNamespace`
+//! ```
+//! use fasteval3::{Compiler, ContextNamespace, Evaler, Num, Parser, Slab};
+//!
+//! struct Frame {
+//!     t: Num,
+//!     speed: Num,
+//! }
+//!
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let mut slab = Slab::new();
+//!     let instr = Parser::new()
+//!         .parse("speed * t", &mut slab.ps)?
+//!         .from(&slab.ps)
+//!         .compile(&slab.ps, &mut slab.cs, &mut fasteval3::EmptyNamespace);
+//!
+//!     let frames = [Frame { t: 1.0, speed: 2.0 }, Frame { t: 2.0, speed: 3.0 }];
+//!     let mut ns = ContextNamespace::new(
+//!         |frame: &Frame, name, _args| match name {
+//!             "t" => Some(frame.t),
+//!             "speed" => Some(frame.speed),
+//!             _ => None,
+//!         },
+//!         &frames[0],
+//!     );
+//!     assert_eq!(instr.eval(&slab, &mut ns)?, 2.0);
+//!
+//!     // Move on to the next frame:
+//!     ns.set_context(&frames[1]);
+//!     assert_eq!(instr.eval(&slab, &mut ns)?, 6.0);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## `LayeredStringTof32Namespace`
 //! ```
 //! fn main() -> Result<(), fasteval3::Error> {
@@ -169,6 +374,25 @@
 //! }
 //! ```
 //!
+//! ## `NestedNamespace`
+//! ```
+//! use fasteval3::{NestedNamespace, NestedValue, Parser, Evaler, Slab};
+//!
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let mut sensor = NestedNamespace::new();
+//!     sensor.insert("temp".to_string(), NestedValue::Value(21.5));
+//!     let mut ns = NestedNamespace::new();
+//!     ns.insert("sensor".to_string(), NestedValue::Map(sensor));
+//!
+//!     let parser = Parser { dotted_names: true, ..Parser::new() };
+//!     let mut slab = Slab::new();
+//!     let expr_i = parser.parse("sensor.temp * 2", &mut slab.ps)?;
+//!     assert_eq!(slab.ps.get_expr(expr_i).eval(&slab, &mut ns)?, 43.0);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Custom Namespace Types
 //!
 //! If the pre-defined Namespace types aren't perfect for your application, you
@@ -186,8 +410,10 @@
 //!   layer.  Good for expensive look-ups.
 
 use crate::error::Error;
+use crate::Num;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
 
 //---- Types:
 
@@ -196,7 +422,55 @@ pub trait EvalNamespace {
     /// Perform a variable/function lookup.
     ///
     /// May return cached values.
-    fn lookup(&mut self, name: &str, args: Vec<f32>, keybuf: &mut String) -> Option<f32>;
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num>;
+
+    /// Returns the sentinel value that marks missing data, if there is one.
+    ///
+    /// `min()`, `max()`, `avg()` and `sum()` skip arguments that are equal to
+    /// it (a NaN sentinel matches any NaN).  If every argument is missing, the
+    /// result is the sentinel itself.  The default is `None`, which means that
+    /// no arguments are skipped.
+    ///
+    /// The compiler also checks this when it folds constant arguments, so
+    /// compile and eval with Namespaces that agree on the sentinel.
+    fn missing(&self) -> Option<Num> {
+        None
+    }
+
+    /// Returns a random number in `[0, 1)`, used by `probround()`.
+    ///
+    /// `fasteval3` has no random number generator of its own, so the default
+    /// returns `None`, and `probround()` fails with
+    /// [`Error::NoRandomSource`](../error/enum.Error.html#variant.NoRandomSource).
+    /// Use [`RandomNamespace`](struct.RandomNamespace.html) to plug one in.
+    fn random(&mut self) -> Option<Num> {
+        None
+    }
+
+    /// Returns the number of args that the custom function `name` expects, if
+    /// it has a fixed arity.
+    ///
+    /// [`Expression::compile_checked()`](../parser/struct.Expression.html#method.compile_checked)
+    /// uses this to reject calls with the wrong number of args at compile time,
+    /// instead of passing them to `lookup()` at eval time.  The default returns
+    /// `None`, which means that any number of args is accepted.  Use
+    /// [`ArityNamespace`](struct.ArityNamespace.html) to register arities.
+    fn arity(&self, _name: &str) -> Option<usize> {
+        None
+    }
+
+    /// Evaluates the custom function `name` as a reducer over `args`, if this
+    /// Namespace has a reducer by that name.
+    ///
+    /// This is consulted before `lookup()` whenever a custom function is called
+    /// with parentheses (like `mymax(a, b, c)`), so returning `Some` skips the
+    /// `lookup()` (and its cache).  Unlike `lookup()`, it gets the args as a
+    /// slice, without allocating.  The default returns `None`.  Use
+    /// [`ReduceNamespace`](struct.ReduceNamespace.html) to register reducers
+    /// that fold the args with a binary operation.
+    fn reduce(&mut self, _name: &str, _args: &[Num]) -> Option<Num> {
+        None
+    }
 }
 
 /// Cache operations for `EvalNamespace`s.
@@ -209,15 +483,32 @@ pub trait Cached {
     /// 
     /// If an entry with the same name already
     /// exists, an [`AlreadyExists` Error](../error/enum.Error.html#variant.AlreadyExists) is returned.
-    fn cache_create(&mut self, name: String, val: f32) -> Result<(), Error>;
+    fn cache_create(&mut self, name: String, val: Num) -> Result<(), Error>;
 
     /// Sets a cached entry.  It doesn't matter whether or not a previous value
     /// existed with this name.
-    fn cache_set(&mut self, name: String, val: f32);
+    fn cache_set(&mut self, name: String, val: Num);
 
     /// Clear all cached entries.  Values will be recalculated and cached
     /// again the next time they are looked up.
     fn cache_clear(&mut self);
+
+    /// Returns the cached value for `name`, if there is one.
+    ///
+    /// Custom function calls are cached under a key that includes their
+    /// arguments, like `"f , 1 , 2"`, so use [`cache_iter()`](#method.cache_iter)
+    /// to inspect those.
+    fn cache_get(&self, name: &str) -> Option<Num> {
+        self.cache_iter().find(|(k, _)| *k == name).map(|(_, v)| v)
+    }
+
+    /// Iterates over all cached entries, as `(key, value)` pairs.
+    ///
+    /// The default implementation yields nothing (so `cache_get()` always
+    /// returns `None`); cached Namespaces should override it.
+    fn cache_iter(&self) -> Box<dyn Iterator<Item = (&str, Num)> + '_> {
+        Box::new(std::iter::empty())
+    }
 }
 
 // I don't want to put this into the public API until it is needed.
@@ -242,8 +533,100 @@ pub struct EmptyNamespace;
 /// [See module-level documentation for example.](index.html#cachedcallbacknamespace)
 ///
 pub struct CachedCallbackNamespace<'a> {
-    cache: BTreeMap<String, f32>,
-    cb: Box<dyn FnMut(&str, Vec<f32>) -> Option<f32> + 'a>, // I think a reference would be more efficient than a Box, but then I would need to use a funky 'let cb=|n|{}; Namespace::new(&cb)' syntax.  The Box results in a super convenient pass-the-cb-by-value API interface.
+    cache: BTreeMap<String, Num>,
+    cb: Box<dyn FnMut(&str, Vec<Num>) -> Option<Num> + 'a>, // I think a reference would be more efficient than a Box, but then I would need to use a funky 'let cb=|n|{}; Namespace::new(&cb)' syntax.  The Box results in a super convenient pass-the-cb-by-value API interface.
+}
+
+/// `BudgetedNamespace` wraps another Namespace and limits the number of lookups.
+///
+/// Each lookup counts against the budget, including cached ones, and so does
+/// each call of a [reducer](trait.EvalNamespace.html#method.reduce).  Once the
+/// budget is used up, lookups return `None`, so the evaluation fails with an
+/// `Error::Undefined` (or `Error::UndefinedFunction`).  Use
+/// [`exhausted()`](#method.exhausted) to tell this apart from a truly
+/// undefined variable.
+///
+/// The count is not reset automatically.  Call [`reset()`](#method.reset)
+/// before each evaluation to get a per-eval budget.
+///
+/// [See module-level documentation for example.](index.html#budgetednamespace)
+///
+pub struct BudgetedNamespace<NS> {
+    ns: NS,
+    budget: usize,
+    used: usize,
+    exhausted: bool,
+}
+
+/// `MissingValueNamespace` wraps another Namespace and sets the sentinel value
+/// that marks missing data.
+///
+/// Lookups are passed through unchanged.  See
+/// [`EvalNamespace::missing()`](trait.EvalNamespace.html#method.missing)
+/// for how the sentinel is used.
+///
+/// [See module-level documentation for example.](index.html#missingvaluenamespace)
+///
+pub struct MissingValueNamespace<NS> {
+    ns: NS,
+    missing: Num,
+}
+
+/// `RandomNamespace` wraps another Namespace and provides random numbers from
+/// a callback, which must return values in `[0, 1)`.
+///
+/// Lookups are passed through unchanged.  See
+/// [`EvalNamespace::random()`](trait.EvalNamespace.html#method.random).
+///
+/// [See module-level documentation for example.](index.html#randomnamespace)
+///
+pub struct RandomNamespace<NS, R> {
+    ns: NS,
+    rng: R,
+}
+
+/// `ArityNamespace` wraps another Namespace and declares the number of args
+/// that its custom functions expect.
+///
+/// Lookups are passed through unchanged.  See
+/// [`EvalNamespace::arity()`](trait.EvalNamespace.html#method.arity) for how
+/// the arities are used.
+///
+/// [See module-level documentation for example.](index.html#aritynamespace)
+///
+pub struct ArityNamespace<NS> {
+    ns: NS,
+    arities: BTreeMap<String, usize>,
+}
+
+/// `ReduceNamespace` wraps another Namespace and adds reducers: custom
+/// functions that fold their args with a binary operation, like
+/// `mymax(a, b, c) == op(op(a, b), c)`.
+///
+/// A reducer called with one arg returns it unchanged, and with no args it
+/// returns NaN.  Other lookups are passed through unchanged.  See
+/// [`EvalNamespace::reduce()`](trait.EvalNamespace.html#method.reduce).
+///
+/// [See module-level documentation for example.](index.html#reducenamespace)
+///
+pub struct ReduceNamespace<NS> {
+    ns: NS,
+    reducers: BTreeMap<String, fn(Num, Num) -> Num>,
+}
+
+/// `ContextNamespace` looks up variables and custom functions with a callback
+/// that also receives a borrowed context `C`, like the inputs of the current
+/// frame of a simulation.
+///
+/// The callback is `Fn`, so lookups can't change the context.  To evaluate
+/// with different inputs, point the Namespace at another context with
+/// [`set_context()`](#method.set_context), instead of mutating a map.
+///
+/// [See module-level documentation for example.](index.html#contextnamespace)
+///
+pub struct ContextNamespace<'a, C, F> {
+    ctx: &'a C,
+    cb: F,
 }
 
 // I am commenting these out until I need them in real-life.
@@ -261,7 +644,7 @@ pub struct CachedCallbackNamespace<'a> {
 
 #[allow(clippy::inline_always)] // TODO: Check to see if `always inlining here is ok.
 #[inline(always)]
-fn key_from_nameargs<'a: 'a>(keybuf: &'a mut String, name: &'a str, args: &[f32]) -> &'a str {
+fn key_from_nameargs<'a: 'a>(keybuf: &'a mut String, name: &'a str, args: &[Num]) -> &'a str {
     if args.is_empty() {
         name
     } else {
@@ -276,35 +659,51 @@ fn key_from_nameargs<'a: 'a>(keybuf: &'a mut String, name: &'a str, args: &[f32]
     }
 }
 
-/// Type alias for `BTreeMap<String,f32>`
-pub type StringTof32Namespace = BTreeMap<String, f32>;
+/// Type alias for `BTreeMap<String,Num>`
+pub type StringTof32Namespace = BTreeMap<String, Num>;
 impl EvalNamespace for StringTof32Namespace {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
         let key = key_from_nameargs(keybuf, name, &args);
         self.get(key).copied()
     }
 }
 
-/// Type alias for `BTreeMap<&'static str,f32>`
-pub type StrTof32Namespace = BTreeMap<&'static str, f32>;
+/// Type alias for `BTreeMap<&'static str,Num>`
+pub type StrTof32Namespace = BTreeMap<&'static str, Num>;
 impl EvalNamespace for StrTof32Namespace {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        let key = key_from_nameargs(keybuf, name, &args);
+        self.get(key).copied()
+    }
+}
+
+impl<S: BuildHasher> EvalNamespace for HashMap<String, Num, S> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        let key = key_from_nameargs(keybuf, name, &args);
+        self.get(key).copied()
+    }
+}
+
+impl<S: BuildHasher> EvalNamespace for HashMap<&'static str, Num, S> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
         let key = key_from_nameargs(keybuf, name, &args);
         self.get(key).copied()
     }
 }
 
-/// Type alias for `BTreeMap<String, Box<dyn FnMut(Vec<f32>)->f32>>`
+/// Type alias for `BTreeMap<String, Box<dyn FnMut(Vec<Num>)->Num>>`
 ///
 /// This namespace type provides a very convenient way to register variables
 /// and custom functions.  It is a bit slower than a pure callback, but it has
 /// isolation and composition advantages.
-pub type StringToCallbackNamespace<'a> = BTreeMap<String, Box<dyn FnMut(Vec<f32>) -> f32 + 'a>>;
+pub type StringToCallbackNamespace<'a> = BTreeMap<String, Box<dyn FnMut(Vec<Num>) -> Num + 'a>>;
 impl EvalNamespace for StringToCallbackNamespace<'_> {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, _keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
         self.get_mut(name).map(|function| function(args))
         /*if let Some(f) = self.get_mut(name) {
             Some(f(args))
@@ -314,15 +713,15 @@ impl EvalNamespace for StringToCallbackNamespace<'_> {
     }
 }
 
-/// Type alias for `BTreeMap<&'static str, Box<dyn FnMut(Vec<f32>)->f32>>`
+/// Type alias for `BTreeMap<&'static str, Box<dyn FnMut(Vec<Num>)->Num>>`
 ///
 /// This namespace type provides a very convenient way to register variables
 /// and custom functions.  It is a bit slower than a pure callback, but it has
 /// isolation and composition advantages.
-pub type StrToCallbackNamespace<'a> = BTreeMap<&'static str, Box<dyn FnMut(Vec<f32>) -> f32 + 'a>>;
+pub type StrToCallbackNamespace<'a> = BTreeMap<&'static str, Box<dyn FnMut(Vec<Num>) -> Num + 'a>>;
 impl EvalNamespace for StrToCallbackNamespace<'_> {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, _keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
         self.get_mut(name).map(|function| function(args))
         /*if let Some(f) = self.get_mut(name) {
             Some(f(args))
@@ -332,11 +731,46 @@ impl EvalNamespace for StrToCallbackNamespace<'_> {
     }
 }
 
-/// Type alias for `Vec<BTreeMap<String,f32>>`
-pub type LayeredStringTof32Namespace = Vec<BTreeMap<String, f32>>;
+/// Builds a `StrToCallbackNamespace` of linear unit conversions from a table of
+/// `(name, factor, offset)` entries.  Each entry becomes a one-arg function
+/// which returns `x*factor + offset` (or NaN if called with the wrong number of args).
+///
+/// More variables and functions can be inserted into the returned namespace.
+///
+/// # Examples
+///
+/// ```
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let mut ns = fasteval3::evalns::unit_conversions(&[
+///         ("ft_to_m", 0.3048, 0.0),
+///         ("c_to_f", 1.8, 32.0),
+///     ]);
+///
+///     let val = fasteval3::ez_eval("c_to_f(100)", &mut ns)?;
+///     assert_eq!(val, 212.0);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn unit_conversions(table: &[(&'static str, Num, Num)]) -> StrToCallbackNamespace<'static> {
+    let mut ns = StrToCallbackNamespace::new();
+    for &(name, factor, offset) in table {
+        ns.insert(
+            name,
+            Box::new(move |args: Vec<Num>| match args.as_slice() {
+                [x] => x * factor + offset,
+                _ => Num::NAN,
+            }),
+        );
+    }
+    ns
+}
+
+/// Type alias for `Vec<BTreeMap<String,Num>>`
+pub type LayeredStringTof32Namespace = Vec<BTreeMap<String, Num>>;
 impl EvalNamespace for LayeredStringTof32Namespace {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
         let key = key_from_nameargs(keybuf, name, &args);
 
         for map in self.iter().rev() {
@@ -348,13 +782,49 @@ impl EvalNamespace for LayeredStringTof32Namespace {
     }
 }
 
+/// A node of a [`NestedNamespace`](type.NestedNamespace.html) tree.
+#[derive(Debug, PartialEq, Clone)]
+pub enum NestedValue {
+    Value(Num),
+    Map(BTreeMap<String, NestedValue>),
+}
+
+/// Type alias for `BTreeMap<String,NestedValue>`
+///
+/// A dotted name like `sensor.temp` is split at each `.`, and the parts are
+/// looked up one level at a time.  The name is undefined if a part is
+/// missing, if it ends at a `Map`, or if it tries to go below a `Value`.
+/// Function calls are always undefined.
+///
+/// [See module-level documentation for example.](index.html#nestednamespace)
+pub type NestedNamespace = BTreeMap<String, NestedValue>;
+impl EvalNamespace for NestedNamespace {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
+        if !args.is_empty() {
+            return None;
+        }
+        let mut parts = name.split('.');
+        let mut node = self.get(parts.next()?)?;
+        for part in parts {
+            node = match node {
+                NestedValue::Map(map) => map.get(part)?,
+                NestedValue::Value(_) => return None,
+            };
+        }
+        match node {
+            NestedValue::Value(val) => Some(*val),
+            NestedValue::Map(_) => None,
+        }
+    }
+}
+
 // I'm not making a type alias for this because of the un-name-ability of closures:
 impl<F> EvalNamespace for F
 where
-    F: FnMut(&str, Vec<f32>) -> Option<f32>,
+    F: FnMut(&str, Vec<Num>) -> Option<Num>,
 {
     #[inline]
-    fn lookup(&mut self, name: &str, args: Vec<f32>, _keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
         self(name, args)
     }
 }
@@ -362,14 +832,14 @@ where
 impl EvalNamespace for EmptyNamespace {
     /// Always returns `None`, indicating that the variable is undefined.
     #[inline]
-    fn lookup(&mut self, _name: &str, _args: Vec<f32>, _keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, _name: &str, _args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
         None
     }
 }
 
 impl EvalNamespace for CachedCallbackNamespace<'_> {
     /// Returns a cached value if possible, otherwise delegates to the callback function.
-    fn lookup(&mut self, name: &str, args: Vec<f32>, keybuf: &mut String) -> Option<f32> {
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
         let key = key_from_nameargs(keybuf, name, &args);
 
         if let Some(&val) = self.cache.get(key) {
@@ -386,25 +856,31 @@ impl EvalNamespace for CachedCallbackNamespace<'_> {
     }
 }
 impl Cached for CachedCallbackNamespace<'_> {
-    fn cache_create(&mut self, name: String, val: f32) -> Result<(), Error> {
+    fn cache_create(&mut self, name: String, val: Num) -> Result<(), Error> {
         if self.cache.contains_key(&name) {
             return Err(Error::AlreadyExists);
         }
         self.cache.insert(name, val);
         Ok(())
     }
-    fn cache_set(&mut self, name: String, val: f32) {
+    fn cache_set(&mut self, name: String, val: Num) {
         self.cache.insert(name, val);
     }
     fn cache_clear(&mut self) {
         self.cache = BTreeMap::new();
     }
+    fn cache_get(&self, name: &str) -> Option<Num> {
+        self.cache.get(name).copied()
+    }
+    fn cache_iter(&self) -> Box<dyn Iterator<Item = (&str, Num)> + '_> {
+        Box::new(self.cache.iter().map(|(k, &v)| (k.as_str(), v)))
+    }
 }
 impl<'a> CachedCallbackNamespace<'a> {
     #[inline]
     pub fn new<F>(cb: F) -> Self
     where
-        F: FnMut(&str, Vec<f32>) -> Option<f32> + 'a,
+        F: FnMut(&str, Vec<Num>) -> Option<Num> + 'a,
     {
         CachedCallbackNamespace {
             cache: BTreeMap::new(),
@@ -413,6 +889,332 @@ impl<'a> CachedCallbackNamespace<'a> {
     }
 }
 
+impl<NS: EvalNamespace> EvalNamespace for BudgetedNamespace<NS> {
+    /// Delegates to the wrapped Namespace until the budget is used up.
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        if self.used >= self.budget {
+            self.exhausted = true;
+            return None;
+        }
+        self.used += 1;
+        self.ns.lookup(name, args, keybuf)
+    }
+    fn missing(&self) -> Option<Num> {
+        self.ns.missing()
+    }
+    fn random(&mut self) -> Option<Num> {
+        self.ns.random()
+    }
+    fn arity(&self, name: &str) -> Option<usize> {
+        self.ns.arity(name)
+    }
+    /// Delegates to the wrapped Namespace until the budget is used up.  Only
+    /// calls that a reducer handles are counted, because the others fall
+    /// through to `lookup()`, which counts them.
+    fn reduce(&mut self, name: &str, args: &[Num]) -> Option<Num> {
+        if self.used >= self.budget {
+            self.exhausted = true;
+            return None;
+        }
+        let val = self.ns.reduce(name, args)?;
+        self.used += 1;
+        Some(val)
+    }
+}
+impl<NS> BudgetedNamespace<NS> {
+    /// Wraps `ns`, allowing at most `budget` lookups.
+    #[inline]
+    pub const fn new(ns: NS, budget: usize) -> Self {
+        Self {
+            ns,
+            budget,
+            used: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Returns the number of lookups performed since the last `reset()`.
+    #[inline]
+    pub const fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Returns `true` if a lookup was refused since the last `reset()`.
+    #[inline]
+    pub const fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Resets the lookup count, making the full budget available again.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.used = 0;
+        self.exhausted = false;
+    }
+
+    /// Returns a reference to the wrapped Namespace.
+    #[inline]
+    pub const fn inner(&self) -> &NS {
+        &self.ns
+    }
+
+    /// Returns a mutable reference to the wrapped Namespace.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut NS {
+        &mut self.ns
+    }
+
+    /// Unwraps this `BudgetedNamespace`, returning the wrapped Namespace.
+    #[inline]
+    pub fn into_inner(self) -> NS {
+        self.ns
+    }
+}
+
+impl<NS: EvalNamespace> EvalNamespace for MissingValueNamespace<NS> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        self.ns.lookup(name, args, keybuf)
+    }
+    #[inline]
+    fn missing(&self) -> Option<Num> {
+        Some(self.missing)
+    }
+    #[inline]
+    fn random(&mut self) -> Option<Num> {
+        self.ns.random()
+    }
+    #[inline]
+    fn arity(&self, name: &str) -> Option<usize> {
+        self.ns.arity(name)
+    }
+    #[inline]
+    fn reduce(&mut self, name: &str, args: &[Num]) -> Option<Num> {
+        self.ns.reduce(name, args)
+    }
+}
+impl<NS> MissingValueNamespace<NS> {
+    /// Wraps `ns`, treating `missing` as the missing-data sentinel.
+    #[inline]
+    pub const fn new(ns: NS, missing: Num) -> Self {
+        Self { ns, missing }
+    }
+
+    /// Returns a reference to the wrapped Namespace.
+    #[inline]
+    pub const fn inner(&self) -> &NS {
+        &self.ns
+    }
+
+    /// Returns a mutable reference to the wrapped Namespace.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut NS {
+        &mut self.ns
+    }
+
+    /// Unwraps this `MissingValueNamespace`, returning the wrapped Namespace.
+    #[inline]
+    pub fn into_inner(self) -> NS {
+        self.ns
+    }
+}
+
+impl<NS: EvalNamespace, R: FnMut() -> Num> EvalNamespace for RandomNamespace<NS, R> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        self.ns.lookup(name, args, keybuf)
+    }
+    #[inline]
+    fn missing(&self) -> Option<Num> {
+        self.ns.missing()
+    }
+    #[inline]
+    fn random(&mut self) -> Option<Num> {
+        Some((self.rng)())
+    }
+    #[inline]
+    fn arity(&self, name: &str) -> Option<usize> {
+        self.ns.arity(name)
+    }
+    #[inline]
+    fn reduce(&mut self, name: &str, args: &[Num]) -> Option<Num> {
+        self.ns.reduce(name, args)
+    }
+}
+impl<NS, R> RandomNamespace<NS, R> {
+    /// Wraps `ns`, drawing random numbers from `rng`.
+    #[inline]
+    pub const fn new(ns: NS, rng: R) -> Self {
+        Self { ns, rng }
+    }
+
+    /// Returns a reference to the wrapped Namespace.
+    #[inline]
+    pub const fn inner(&self) -> &NS {
+        &self.ns
+    }
+
+    /// Returns a mutable reference to the wrapped Namespace.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut NS {
+        &mut self.ns
+    }
+
+    /// Unwraps this `RandomNamespace`, returning the wrapped Namespace.
+    #[inline]
+    pub fn into_inner(self) -> NS {
+        self.ns
+    }
+}
+
+impl<NS: EvalNamespace> EvalNamespace for ArityNamespace<NS> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        self.ns.lookup(name, args, keybuf)
+    }
+    #[inline]
+    fn missing(&self) -> Option<Num> {
+        self.ns.missing()
+    }
+    #[inline]
+    fn random(&mut self) -> Option<Num> {
+        self.ns.random()
+    }
+    #[inline]
+    fn arity(&self, name: &str) -> Option<usize> {
+        self.arities.get(name).copied()
+    }
+    #[inline]
+    fn reduce(&mut self, name: &str, args: &[Num]) -> Option<Num> {
+        self.ns.reduce(name, args)
+    }
+}
+impl<NS> ArityNamespace<NS> {
+    /// Wraps `ns`, declaring the arities of custom functions by name.
+    #[inline]
+    pub const fn new(ns: NS, arities: BTreeMap<String, usize>) -> Self {
+        Self { ns, arities }
+    }
+
+    /// Returns a mutable reference to the registered arities.
+    #[inline]
+    pub fn arities_mut(&mut self) -> &mut BTreeMap<String, usize> {
+        &mut self.arities
+    }
+
+    /// Returns a reference to the wrapped Namespace.
+    #[inline]
+    pub const fn inner(&self) -> &NS {
+        &self.ns
+    }
+
+    /// Returns a mutable reference to the wrapped Namespace.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut NS {
+        &mut self.ns
+    }
+
+    /// Unwraps this `ArityNamespace`, returning the wrapped Namespace.
+    #[inline]
+    pub fn into_inner(self) -> NS {
+        self.ns
+    }
+}
+
+impl<NS: EvalNamespace> EvalNamespace for ReduceNamespace<NS> {
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, keybuf: &mut String) -> Option<Num> {
+        self.ns.lookup(name, args, keybuf)
+    }
+    #[inline]
+    fn missing(&self) -> Option<Num> {
+        self.ns.missing()
+    }
+    #[inline]
+    fn random(&mut self) -> Option<Num> {
+        self.ns.random()
+    }
+    #[inline]
+    fn arity(&self, name: &str) -> Option<usize> {
+        self.ns.arity(name)
+    }
+    #[inline]
+    fn reduce(&mut self, name: &str, args: &[Num]) -> Option<Num> {
+        match self.reducers.get(name) {
+            Some(op) => Some(args.iter().copied().reduce(op).unwrap_or(Num::NAN)),
+            None => self.ns.reduce(name, args),
+        }
+    }
+}
+impl<NS> ReduceNamespace<NS> {
+    /// Wraps `ns`, without any reducers yet.
+    #[inline]
+    pub const fn new(ns: NS) -> Self {
+        Self {
+            ns,
+            reducers: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the reducer `name`, which folds its args with `op`.  This
+    /// replaces any previous reducer with the same name.
+    #[inline]
+    pub fn register(&mut self, name: String, op: fn(Num, Num) -> Num) {
+        self.reducers.insert(name, op);
+    }
+
+    /// Returns a reference to the wrapped Namespace.
+    #[inline]
+    pub const fn inner(&self) -> &NS {
+        &self.ns
+    }
+
+    /// Returns a mutable reference to the wrapped Namespace.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut NS {
+        &mut self.ns
+    }
+
+    /// Unwraps this `ReduceNamespace`, returning the wrapped Namespace.
+    #[inline]
+    pub fn into_inner(self) -> NS {
+        self.ns
+    }
+}
+
+impl<C, F> EvalNamespace for ContextNamespace<'_, C, F>
+where
+    F: Fn(&C, &str, &[Num]) -> Option<Num>,
+{
+    #[inline]
+    fn lookup(&mut self, name: &str, args: Vec<Num>, _keybuf: &mut String) -> Option<Num> {
+        (self.cb)(self.ctx, name, &args)
+    }
+}
+impl<'a, C, F> ContextNamespace<'a, C, F>
+where
+    F: Fn(&C, &str, &[Num]) -> Option<Num>,
+{
+    /// Looks up names with `cb`, which is passed `ctx` along with each name
+    /// and its args.
+    #[inline]
+    pub const fn new(cb: F, ctx: &'a C) -> Self {
+        Self { ctx, cb }
+    }
+
+    /// Returns the current context.
+    #[inline]
+    pub const fn context(&self) -> &'a C {
+        self.ctx
+    }
+
+    /// Makes subsequent lookups use `ctx`.
+    #[inline]
+    pub fn set_context(&mut self, ctx: &'a C) {
+        self.ctx = ctx;
+    }
+}
+
 //// I am not ready to make this part of the public API yet.
 // impl EvalNamespace for CachedLayeredNamespace<'_> {
 //     fn lookup(&mut self, name:&str, args:Vec<f32>, keybuf:&mut String) -> Option<f32> {
@@ -5,6 +5,9 @@ use crate::evaler::Evaler;
 use crate::evalns::EvalNamespace;
 use crate::parser::Parser;
 use crate::slab::Slab;
+use crate::Num;
+
+use std::collections::BTreeSet;
 
 /// The `ez_eval()` function provides a very simple way to perform expression evaluation with just one function call.
 ///
@@ -21,7 +24,7 @@ use crate::slab::Slab;
 /// # Examples
 ///
 /// [See the `fasteval3` top-level documentation for examples.](../index.html#easy-evaluation)
-pub fn ez_eval(expr_str: &str, ns: &mut impl EvalNamespace) -> Result<f32, Error> {
+pub fn ez_eval(expr_str: &str, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
     let mut slab = Slab::new(); // A big block of memory, so we don't need to perform many tiny (and slow!) allocations.
 
     // Here is a one-liner that performs the entire parse-and-eval process:
@@ -41,3 +44,82 @@ pub fn ez_eval(expr_str: &str, ns: &mut impl EvalNamespace) -> Result<f32, Error
     // Use the reference to the Expression object to perform the evaluation:
     expr_ref.eval(&slab, ns)
 }
+
+/// Like [`ez_eval()`](fn.ez_eval.html), but also returns the names of the
+/// variables and custom functions that the expression refers to.
+///
+/// The names come from [`var_names()`](../evaler/trait.Evaler.html#method.var_names),
+/// so they include names in branches that are skipped by short-circuiting.
+///
+/// # Errors
+///
+/// If there are any [`Error`](../error/enum.Error.html)s during the parse-eval process, they will be returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use fasteval3::Num;
+///
+/// fn main() -> Result<(), fasteval3::Error> {
+///     let mut map: BTreeMap<String, Num> = BTreeMap::new();
+///     map.insert(String::from("x"), 2.0);
+///     map.insert(String::from("y"), 3.0);
+///
+///     let (val, vars) = fasteval3::ez_eval_with_vars("x * y + 1", &mut map)?;
+///     assert_eq!(val, 7.0);
+///     assert_eq!(vars.into_iter().collect::<Vec<_>>(), vec!["x", "y"]);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn ez_eval_with_vars(
+    expr_str: &str,
+    ns: &mut impl EvalNamespace,
+) -> Result<(Num, BTreeSet<String>), Error> {
+    let mut slab = Slab::new();
+    let expr_i = Parser::new().parse_noclear(expr_str, &mut slab.ps)?;
+    let expr_ref = slab.ps.get_expr(expr_i);
+
+    let vars = expr_ref.var_names(&slab);
+    let val = expr_ref.eval(&slab, ns)?;
+    Ok((val, vars))
+}
+
+/// Parses and evaluates each `(name, expr_str)` formula independently, and
+/// returns every result (or `Error`) alongside its name, in order.
+///
+/// Unlike calling [`ez_eval()`](fn.ez_eval.html) with `?`, an `Error` in one
+/// formula doesn't stop the others from being evaluated, so a config loader
+/// can report all of its problems at once.  A single `Slab` is re-used for
+/// all of the formulas.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use fasteval3::Num;
+///
+/// let mut map: BTreeMap<String, Num> = BTreeMap::new();
+/// map.insert(String::from("x"), 2.0);
+///
+/// let results = fasteval3::eval_all(&[("double", "x * 2"), ("bad", "x +")], &mut map);
+/// assert_eq!(results[0], ("double", Ok(4.0)));
+/// assert!(results[1].1.is_err());
+/// ```
+pub fn eval_all<'a>(
+    formulas: &[(&'a str, &str)],
+    ns: &mut impl EvalNamespace,
+) -> Vec<(&'a str, Result<Num, Error>)> {
+    let mut slab = Slab::new();
+    let parser = Parser::new();
+    formulas
+        .iter()
+        .map(|&(name, expr_str)| {
+            let result = parser
+                .parse(expr_str, &mut slab.ps)
+                .and_then(|expr_i| slab.ps.get_expr(expr_i).eval(&slab, ns));
+            (name, result)
+        })
+        .collect()
+}
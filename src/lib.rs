@@ -20,13 +20,21 @@
 //!
 //! These are the built-in functions that `fasteval` expressions support.  (You
 //! can also add your own custom functions and variables -- see the
-//! [Examples](#advanced-variables-and-custom-functions) section.)
+//! [Examples](#advanced-variables-and-custom-functions) section.  A call of a
+//! built-in function never reaches a custom function with the same name.)
 //!
 //! ```text
 //!   * print(...strings and values...) -- Prints to stderr.  Very useful to 'probe' an expression.
 //!                                        Evaluates to the last value.
 //!                                        Example: `print("x is", x, "and y is", y)`
 //!                                        Example: `x + print("y:", y) + z == x+y+z`
+//!                                        If the first argument is a string with a '%', it's a
+//!                                        printf-style format for the values that follow.
+//!                                        Supports %d, %f, %e, %g, %x and %%, with flags,
+//!                                        width and precision (up to 256).  Too few values
+//!                                        is an error.
+//!                                        Example: `print("x=%d y=%.2f", x, y)`
+//!                                        Requires the `print-builtin` feature (enabled by default).
 //!
 //!   * log(base=10, val) -- Logarithm with optional 'base' as first argument.
 //!                          If not provided, 'base' defaults to '10'.
@@ -40,12 +48,75 @@
 //!   * floor(val)
 //!   * round(modulus=1, val) -- Round with optional 'modulus' as first argument.
 //!                              Example: `round(1.23456) == 1  &&  round(0.001, 1.23456) == 1.235`
+//!                              Ties round away from zero: `round(2.5) == 3  &&  round(-2.5) == -3`
+//!   * round_half_up(modulus=1, val) -- Like 'round', but ties round up toward +inf.
+//!                              Example: `round_half_up(2.5) == 3  &&  round_half_up(-2.5) == -2`
+//!   * roundz(modulus=1, val) -- Like 'round', but ties round toward zero.
+//!   * rounda(modulus=1, val) -- Same as 'round'.  Ties round away from zero.
+//!
+//!     The rounding functions only differ in how they handle ties:
+//!
+//!         val    round   rounda   roundz   round_half_up
+//!         2.5      3        3        2           3
+//!        -2.5     -3       -3       -2          -2
+//!
+//!   * probround(val) -- Stochastic rounding: rounds up with a probability equal to
+//!                       the fractional part of 'val', and down otherwise.  The random
+//!                       numbers come from the namespace; see 'RandomNamespace'.
+//!                       Never folded by the compiler.
 //!
 //!   * abs(val)
 //!   * sign(val)
+//!   * sqrt(val) -- Square root.  Faster and clearer than `val^0.5`.  NaN for negative 'val'.
+//!   * cbrt(val) -- Cube root.  More precise than `val^(1/3)`, and also works for negative 'val':
+//!                  `cbrt(-8) == -2`, while `(-8)^(1/3)` is NaN.
 //!
 //!   * min(val, ...) -- Example: `min(1, -2, 3, -4) == -4`
 //!   * max(val, ...) -- Example: `max(1, -2, 3, -4) == 3`
+//!                      On ties, the later argument is returned.  This is only
+//!                      visible for 0 and -0: `max(0, -0) == -0`.  Compiling may
+//!                      move constant arguments after the others.
+//!                      With 'NanPolicy::Ignore', 'min' stops evaluating its arguments
+//!                      at '-inf', and 'max' at 'inf'.
+//!   * minz(...), maxz(...) -- Like 'min' and 'max', but with zero args they
+//!                             return 'inf' and '-inf' respectively.
+//!                             NaN arguments are handled according to 'Slab.nan_policy'.
+//!   * min_first(val, ...), max_first(val, ...) -- Like 'min' and 'max', but the
+//!                             earliest argument is returned on ties, and the arguments
+//!                             are never reordered.  Example: `max_first(0, -0) == 0`
+//!   * nanmin(val, ...), nanmax(val, ...) -- Like 'min' and 'max', but NaN arguments
+//!                             are always skipped, whatever 'Slab.nan_policy' is.  The
+//!                             result is only NaN if every argument is NaN.
+//!                             Example: `nanmin(NaN, 2, 3) == 2`
+//!
+//!   * blend(val, weight, ...) -- Weighted average of 'val,weight' pairs.
+//!                                Example: `blend(1, 1, 4, 2) == 3`
+//!   * norm(val, ...) -- Euclidean norm: `sqrt(a^2 + b^2 + ...)`.  Example: `norm(3, 4) == 5`
+//!   * prod(val, ...) -- Product of all args, like 'a * b * ...'.  Example: `prod(2, 3, 4) == 24`
+//!   * sumsq(val, ...) -- Sum of squares: `a^2 + b^2 + ...`.  Example: `sumsq(1, 2, 3) == 14`
+//!   * poly(x, c0, c1, ...) -- Polynomial `c0 + c1*x + c2*x^2 + ...`, evaluated with Horner's method.  Example: `poly(2, 1, 2, 3) == 17`
+//!   * avg(val, ...) -- Arithmetic mean.  Example: `avg(1, 2, 6) == 3`
+//!   * sum(val, ...) -- Sum of all args.  Example: `sum(1, 2, 6) == 9`
+//!                      'min', 'max', 'avg' and 'sum' skip args equal to the namespace's
+//!                      missing-value sentinel; see 'MissingValueNamespace'.
+//!   * bucket(val, lo, hi, n) -- Index of the equal-width bucket that 'val' falls into
+//!                               when 'lo..hi' is split into 'n' buckets.  Clamped to '[0, n-1]'.
+//!                               Example: `bucket(2.5, 0, 10, 4) == 1`
+//!   * powmod(base, exp, mod) -- 'base^exp' modulo 'mod', with each arg truncated to an integer.
+//!                               Example: `powmod(2, 10, 1000) == 24`
+//!                               Results are only exact up to 2^24 because of f32 precision (2^53 with the `f64` feature).
+//!   * smoothstep(edge0, edge1, x) -- GLSL-style smooth interpolation: 0 when 'x <= edge0', 1 when 'x >= edge1',
+//!                                    and 't*t*(3-2*t)' with 't = (x-edge0)/(edge1-edge0)' in between.
+//!                                    Example: `smoothstep(0, 10, 5) == 0.5`
+//!   * clamp(val, lo, hi) -- 'val' limited to '[lo, hi]', computed as 'max(val, lo)' then 'min(_, hi)'.
+//!                           Gives 'hi' when 'lo > hi'.  A NaN 'val' gives 'lo'; NaN bounds are ignored.
+//!                           Example: `clamp(5, 0, 3) == 3`
+//!   * select(index, val0, val1, ...) -- The 'val' at the truncated 'index', counting from 0.
+//!                                       Only that 'val' is evaluated.  Out-of-range indices give NaN.
+//!                                       Example: `select(1.5, 10, 20, 30) == 20`
+//!   * seq(val, ...) -- Evaluates every arg from left to right and returns the last one.
+//!                      Useful for ordering custom functions with side effects.
+//!                      Example: `seq(1, 2, 3) == 3`
 //!
 //!   * sin(radians)    * asin(val)
 //!   * cos(radians)    * acos(val)
@@ -53,13 +124,63 @@
 //!   * sinh(val)       * asinh(val)
 //!   * cosh(val)       * acosh(val)
 //!   * tanh(val)       * atanh(val)
+//!
+//!   * asind(val), acosd(val), atand(val) -- Like 'asin', 'acos' and 'atan', but in degrees.
+//!                                           Example: `asind(1) == 90`
+//!   * atan2(y, x)  -- Angle of the point '(x, y)' in radians, from -π to π.  Same as Rust's 'atan2'.
+//!                     Example: `atan2(1, 1) == pi()/4`
+//!   * atan2d(y, x) -- Angle of the point '(x, y)' in degrees, from -180 to 180.
+//!                     Example: `atan2d(1, -1) == 135`
+//!
+//!   There is no global degrees mode.  'sin', 'cos' and 'tan' always take radians, and
+//!   'asin', 'acos', 'atan' and 'atan2' always return radians.  Use the 'deg' suffix for
+//!   angle literals in degrees, and the 'd' functions to get degrees back:
+//!   `atan2d(1, 1) == 45`, and `asind(sin(30deg))` is 30 up to rounding.
 //! ```
 //!
+//! A function argument can also be an inclusive integer range, which expands
+//! into one argument per value: `max(1..4)` is the same as `max(1, 2, 3, 4)`,
+//! and `5..3` counts down.  This is mostly useful with variadic custom
+//! functions, like `sum(1..100)`.  Ranges are limited to
+//! [`Parser.range_len_limit`](parser/struct.Parser.html#structfield.range_len_limit) values.
+//!
 //! ## Operators
 //!
-//! The `and` and `or` operators are enabled by default, but if your
-//! application wants to use those words for something else, they can be
-//! disabled by turning off the `alpha-keywords` feature (`cargo build --no-default-features`).
+//! The `and`, `or` and `xor` operators (and `not`, which is the same as
+//! unary `!`) are enabled by default, but if your application wants to use
+//! those words for something else, they can be disabled by turning off the
+//! `alpha-keywords` feature (`cargo build --no-default-features`).
+//! `a xor b` is `1` when exactly one of `a` and `b` is non-zero, and `0`
+//! otherwise.  Unlike `and` and `or`, it always evaluates both operands.
+//!
+//! Similarly, sandboxed applications that never want expressions to perform
+//! I/O can turn off the `print-builtin` feature.  `print()` then fails to parse
+//! with [`Error::DisabledFunction`](error/enum.Error.html#variant.DisabledFunction),
+//! and its code is left out of the binary.
+//!
+//! Exponentiation is right-associative (`2^3^2 == 2^(3^2) == 512`).  If your
+//! formulas were written for left-associative exponentiation (like tinyexpr),
+//! enable the `left-assoc-exp` feature to get `2^3^2 == (2^3)^2 == 64`.
+//!
+//! Domain-specific binary operators can be added with
+//! [`Parser.custom_ops`](parser/struct.Parser.html#structfield.custom_ops).
+//! Each one is a single character that is parsed as a call of a custom
+//! function, so `a @ b` is evaluated like `nearest(a, b)`.
+//!
+//! Named constants, like the speed of light `c` in a physics DSL, can be
+//! registered in [`Parser.constants`](parser/struct.Parser.html#structfield.constants).
+//! A bare `c` is then parsed like the number itself, so expressions using it
+//! are folded by `compile()`, while `pi` and `e` without parentheses remain
+//! ordinary variables.
+//!
+//! Formulas copied from typeset documents can be accepted by enabling the
+//! `unicode-ops` feature, which adds `×` and `÷` for `*` and `/`, `−` (U+2212)
+//! for binary `-`, and `≤`, `≥`, `≠` for `<=`, `>=`, `!=`.
+//!
+//! All values are [`Num`](type.Num.html)s, which are `f32` by default.  For
+//! scientific use, enable the `f64` feature to evaluate everything in double
+//! precision.  Namespaces then receive and return `f64`s too.  (The examples
+//! in this documentation use `Num`, so they work with either.)
 //!
 //! ```text
 //! Listed in order of precedence:
@@ -72,6 +193,7 @@
 //!                          +               Addition
 //!                          == != < <= >= > Comparisons (all have equal precedence)
 //!                          && and          Logical AND with short-circuit
+//!                          xor             Logical XOR
 //!     (Lowest Precedence)  || or           Logical OR with short-circuit
 //!
 //! ```
@@ -96,6 +218,10 @@
 //!             1.23M        = 1230000
 //!             1.23G        = 1230000000
 //!             1.23T        = 1230000000000
+//!
+//!     Angle Suffix (may follow the suffixes above):
+//!             90deg        = 1.5707964  (converted to radians)
+//!             1.5rad       = 1.5
 //! ```
 //!
 //! # Examples
@@ -138,8 +264,9 @@
 //!
 //! ```
 //! use std::collections::BTreeMap;
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
-//!     let mut map : BTreeMap<String,f32> = BTreeMap::new();
+//!     let mut map : BTreeMap<String,Num> = BTreeMap::new();
 //!     map.insert("x".to_string(), 1.0);
 //!     map.insert("y".to_string(), 2.0);
 //!     map.insert("z".to_string(), 3.0);
@@ -159,16 +286,17 @@
 //! which defines custom variables, functions, and array-like objects:
 //!
 //! ```
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
-//!     let mut cb = |name:&str, args:Vec<f32>| -> Option<f32> {
-//!         let mydata : [f32; 3] = [11.1, 22.2, 33.3];
+//!     let mut cb = |name:&str, args:Vec<Num>| -> Option<Num> {
+//!         let mydata : [Num; 3] = [11.1, 22.2, 33.3];
 //!         match name {
 //!             // Custom constants/variables:
 //!             "x" => Some(3.0),
 //!             "y" => Some(4.0),
 //!
 //!             // Custom function:
-//!             "sum" => Some(args.into_iter().sum()),
+//!             "total" => Some(args.into_iter().sum()),
 //!
 //!             // Custom array-like objects:
 //!             // The `args.get...` code is the same as:
@@ -181,10 +309,10 @@
 //!         }
 //!     };
 //!
-//!     let val = fasteval3::ez_eval("sum(x^2, y^2)^0.5 + data[0]",    &mut cb)?;
-//!     //                           |   |                   |
-//!     //                           |   |                   square-brackets act like parenthesis
-//!     //                           |   variables are like custom functions with zero args
+//!     let val = fasteval3::ez_eval("total(x^2, y^2)^0.5 + data[0]",    &mut cb)?;
+//!     //                           |     |                   |
+//!     //                           |     |                   square-brackets act like parenthesis
+//!     //                           |     variables are like custom functions with zero args
 //!     //                           custom function
 //!
 //!     assert_eq!(val, 16.1);
@@ -195,7 +323,7 @@
 //!     //       Therefore, variables can receive arguments too,
 //!     //       which will probably be ignored.
 //!     //       Therefore, these two expressions evaluate to the same thing:
-//!     //           eval("x + y")  ==  eval("x(1,2,3) + y(x, y, sum(x,y))")
+//!     //           eval("x + y")  ==  eval("x(1,2,3) + y(x, y, total(x,y))")
 //!     //                                      ^^^^^      ^^^^^^^^^^^^^^
 //!     //                                      All this stuff is ignored.
 //!     //
@@ -210,9 +338,11 @@
 //!     //           x         -- Uses the custom 'x' variable.
 //!     //           x()       -- Uses the custom 'x' variable because there is no 'x' builtin.
 //!     //           x(1,2,3)  -- Uses the custom 'x' variable.  The args are ignored.
-//!     //           sum       -- Uses the custom 'sum' function with no arguments.
-//!     //           sum()     -- Uses the custom 'sum' function with no arguments.
-//!     //           sum(1,2)  -- Uses the custom 'sum' function with two arguments.
+//!     //           total     -- Uses the custom 'total' function with no arguments.
+//!     //           total()   -- Uses the custom 'total' function with no arguments.
+//!     //           total(1,2) -- Uses the custom 'total' function with two arguments.
+//!     //
+//!     //       `classify_name()` applies the same rules, for tools like syntax highlighters.
 //!
 //!     Ok(())
 //! }
@@ -227,6 +357,7 @@
 //! ```
 //! use std::collections::BTreeMap;
 //! use fasteval3::Evaler;  // use this trait so we can call eval().
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     let parser = fasteval3::Parser::new();
 //!     let mut slab = fasteval3::Slab::new();
@@ -236,7 +367,7 @@
 //!
 //!     // Let's evaluate the expression a couple times with different 'x' values:
 //!
-//!     let mut map : BTreeMap<String,f32> = BTreeMap::new();
+//!     let mut map : BTreeMap<String,Num> = BTreeMap::new();
 //!     map.insert("x".to_string(), 1.0);
 //!     let val = expr_ref.eval(&slab, &mut map)?;
 //!     assert_eq!(val, 2.0);
@@ -260,6 +391,18 @@
 //! }
 //! ```
 //!
+//! For one-off evaluations, `Parser::parse_owned()` skips the `ExpressionI`
+//! indirection by returning a new `Slab` together with the root `Expression`:
+//!
+//! ```
+//! use fasteval3::Evaler;  // use this trait so we can call eval().
+//! fn main() -> Result<(), fasteval3::Error> {
+//!     let (slab, expr) = fasteval3::Parser::new().parse_owned("max(2, 3) * 10")?;
+//!     assert_eq!(expr.eval(&slab, &mut fasteval3::EmptyNamespace)?, 30.0);
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Compile to go super fast!
 //! If you plan to evaluate an expression just one or two times, then you
 //! should parse-eval as shown in previous examples.  But if you expect to
@@ -271,6 +414,7 @@
 //! use std::collections::BTreeMap;
 //! use fasteval3::Evaler;    // use this trait so we can call eval().
 //! use fasteval3::Compiler;  // use this trait so we can call compile().
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     use fasteval3::EmptyNamespace;
 //! let parser = fasteval3::Parser::new();
@@ -280,7 +424,7 @@
 //!     let expr_str = "sin(deg/360 * 2*pi())";
 //!     let compiled = parser.parse(expr_str, &mut slab.ps)?.from(&slab.ps).compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
 //!     for deg in 0..360 {
-//!         map.insert("deg".to_string(), deg as f32);
+//!         map.insert("deg".to_string(), deg as Num);
 //!         // When working with compiled constant expressions, you can use the
 //!         // eval_compiled*!() macros to save a function call:
 //!         let val = fasteval3::eval_compiled!(compiled, &slab, &mut map);
@@ -302,6 +446,7 @@
 //! use fasteval3::Evaler;    // use this trait so we can call eval().
 //! use fasteval3::Compiler;  // use this trait so we can call compile().
 //! #[cfg(not(feature = "unsafe-vars"))]
+//! use fasteval3::Num;
 //! fn main() -> Result<(), fasteval3::Error> {
 //!     Ok(())
 //! }
@@ -313,7 +458,7 @@
 //!     // The Unsafe Variable will use a pointer to read this memory location:
 //!     // You must make sure that this variable stays in-scope as long as the
 //!     // expression is in-use.
-//!     let mut deg : f32 = 0.0;
+//!     let mut deg : Num = 0.0;
 //!
 //!     // Unsafe Variables must be registered before 'parse()'.
 //!     // (Normal Variables only need definitions during the 'eval' phase.)
@@ -324,7 +469,7 @@
 //!     let compiled = parser.parse(expr_str, &mut slab.ps)?.from(&slab.ps).compile(&slab.ps, &mut slab.cs, &mut ns);
 //!
 //!     for d in 0..360 {
-//!         deg = d as f32;
+//!         deg = d as Num;
 //!         let val = fasteval3::eval_compiled!(compiled, &slab, &mut ns);
 //!         eprintln!("sin({}°) = {}", deg, val);
 //!     }
@@ -394,6 +539,11 @@
 //! * Expressions that are too-deeply nested (greater than 32 levels).
 //! * Expressions with too many values (greater than 64).
 //! * Expressions with too many sub-expressions (greater than 64).
+//! * Optionally, number literals that are too large in magnitude (see
+//!   [`Parser.literal_limit`](parser/struct.Parser.html#structfield.literal_limit)).
+//! * Optionally, expressions that refer to too many distinct variables and
+//!   custom functions (see
+//!   [`Parser.var_limit`](parser/struct.Parser.html#structfield.var_limit)).
 //!
 //! All limits can be customized at parse time.  If any limits are exceeded,
 //! [`parse()`](https://docs.rs/fasteval/latest/fasteval/parser/struct.Parser.html#method.parse) will return an
@@ -591,11 +741,10 @@
 //! # Future Work
 //! Here are some features that I might add in the future:
 //!
-//! * Dynamic `sprintf` string formatting for the `print()` built-in expression function.
 //! * FFI so this library can be used from other languages.
 //! * Ability to copy the contents of a `Slab` into a perfectly-sized container
 //!   (`PackedSlab`) to reduce wasted memory.
-//! * Support for other number types other than `f32`, such as Integers, Big Integers,
+//! * Support for other number types other than `f32` and `f64`, such as Integers, Big Integers,
 //!   Arbitrary Precision Numbers, Complex Numbers, etc. like [rclc](https://crates.io/crates/rclc).
 //!
 //! # List of Projects that use `fasteval`
@@ -619,25 +768,47 @@ pub mod slab;
 pub mod parser;
 #[macro_use]
 pub mod compiler;
+pub mod bytecode;
 pub mod evaler;
 pub mod evalns;
 pub mod ez;
 
+/// The floating-point type of all values: `f32`, or `f64` with the `f64`
+/// feature.
+#[cfg(not(feature = "f64"))]
+pub type Num = f32;
+/// The floating-point type of all values: `f32`, or `f64` with the `f64`
+/// feature.
+#[cfg(feature = "f64")]
+pub type Num = f64;
+
+/// Mathematical constants, like `PI`, with the precision of `Num`.
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+/// Mathematical constants, like `PI`, with the precision of `Num`.
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
 #[cfg(feature = "unsafe-vars")]
 pub use self::compiler::Instruction::IUnsafeVar;
 pub use self::compiler::{
     Compiler,
     Instruction::{self, IConst},
-    InstructionI,
+    InstructionI, Program,
 };
 pub use self::error::Error;
-pub use self::evaler::Evaler;
+pub use self::evaler::{eval_columns, eval_sweep, Evaler};
 pub use self::evalns::{
-    Cached, CachedCallbackNamespace, EmptyNamespace, EvalNamespace, LayeredStringTof32Namespace,
-    StrToCallbackNamespace, StrTof32Namespace, StringToCallbackNamespace, StringTof32Namespace,
+    ArityNamespace, BudgetedNamespace, Cached, CachedCallbackNamespace, ContextNamespace,
+    EmptyNamespace, EvalNamespace, LayeredStringTof32Namespace, MissingValueNamespace,
+    NestedNamespace, NestedValue, RandomNamespace, ReduceNamespace, StrToCallbackNamespace,
+    StrTof32Namespace, StringToCallbackNamespace, StringTof32Namespace,
+};
+pub use self::ez::{eval_all, ez_eval, ez_eval_with_vars};
+pub use self::parser::{
+    classify_name, CallSite, CustomOp, Expression, ExpressionI, NameKind, ParsedWithDoc, Parser,
+    Value, ValueI, ValueSite, Warning,
 };
-pub use self::ez::ez_eval;
-pub use self::parser::{Expression, ExpressionI, Parser, Value, ValueI};
-pub use self::slab::Slab;
+pub use self::slab::{NanPolicy, OpDispatch, PrintReturn, Slab, StableDebug};
 
 // TODO: Convert `match`es to `if let`s for performance boost.
@@ -8,13 +8,13 @@
 //!
 //! Constant: [+-]?[0-9]*(\.[0-9]+)?( ([eE][+-]?[0-9]+) || [pnuµmkKMGT] )?  || [+-]?(NaN || inf)
 //!
-//! UnaryOp: +Value || -Value || (Expression) || [Expression] || !Value
+//! UnaryOp: +Value || -Value || (Expression) || [Expression] || {Expression} || !Value || not Value
 //!
-//! BinaryOp: + || - || * || / || % || ^ || < || <= || == || != || >= || > || (or || '||') || (and || '&&')
+//! BinaryOp: + || - || * || / || % || ^ || < || <= || == || != || >= || > || (or || '||') || xor || (and || '&&')
 //!
-//! VarName: [a-zA-Z_][a-zA-Z_0-9]*
+//! VarName: [a-zA-Z_][a-zA-Z_0-9]*  (with Parser.dotted_names: [a-zA-Z_][a-zA-Z_0-9]*(\.[a-zA-Z_][a-zA-Z_0-9]*)*)
 //!
-//! StdFunc: VarName((Expression,)*)?  ||  VarName[(Expression,)*]?
+//! StdFunc: VarName((Expression,)*)?  ||  VarName[(Expression,)*]?  ||  VarName{(Expression,)*}?
 //!
 //! PrintFunc: print(ExpressionOrString,*)
 //!
@@ -24,8 +24,10 @@
 //! ```
 
 use crate::error::Error;
-use crate::slab::ParseSlab;
+use crate::slab::{ParseSlab, Slab};
+use crate::Num;
 
+use std::collections::BTreeMap;
 use std::ptr;
 use std::str::{from_utf8, from_utf8_unchecked};
 
@@ -33,40 +35,55 @@ use std::str::{from_utf8, from_utf8_unchecked};
 ///
 /// It behaves much like a pointer or reference, but it is 'safe' (unlike a raw
 /// pointer) and is not managed by the Rust borrow checker (unlike a reference).
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub struct ExpressionI(pub usize);
 
 /// A `ValueI` represents an index into `Slab.ps.vals`.
 ///
 /// It behaves much like a pointer or reference, but it is 'safe' (unlike a raw
 /// pointer) and is not managed by the Rust borrow checker (unlike a reference).
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub struct ValueI(pub usize);
 
+/// Identifies where a parsed `Value` is stored in a `ParseSlab`.
+///
+/// Used by [`ParseSlab::is_int_literal()`](../slab/struct.ParseSlab.html#method.is_int_literal).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum ValueSite {
+    /// Operand `n` of the `Expression` at `ExpressionI`.  Operand `0` is the
+    /// first value, and operand `n` is the value after the `n`th binary operator.
+    Operand(ExpressionI, usize),
+    /// A `Value` stored at `ValueI`, like the operand of a unary operator.
+    Val(ValueI),
+}
+
 /// An `Expression` is the top node of a parsed AST.
 ///
 /// It can be `compile()`d or `eval()`d.
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct Expression {
     pub(crate) first: Value,
     pub(crate) pairs: Vec<ExprPair>, // cap=8
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) struct ExprPair(pub(crate) BinaryOp, pub(crate) Value);
 
 /// A `Value` can be a Constant, a `UnaryOp`, a `StdFunc`, or a `PrintFunc`.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Value {
-    EConstant(f32),
+    EConstant(Num),
     EUnaryOp(UnaryOp),
     EStdFunc(StdFunc),
+    #[cfg(feature = "print-builtin")]
     EPrintFunc(PrintFunc),
 }
-use self::Value::{EConstant, EPrintFunc, EStdFunc, EUnaryOp};
+#[cfg(feature = "print-builtin")]
+use self::Value::EPrintFunc;
+use self::Value::{EConstant, EStdFunc, EUnaryOp};
 
 /// Unary Operators
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum UnaryOp {
     EPos(ValueI),
     ENeg(ValueI),
@@ -81,32 +98,33 @@ pub enum BinaryOp {
     // Sorted in order of precedence (low-priority to high-priority):
     // Keep this order in-sync with evaler.rs.  (Search for 'rtol' and 'ltor'.)
     EOR = 1, // Lowest Priority
-    EAND = 2,
-    ENE = 3,
-    EEQ = 4,
-    EGTE = 5,
-    ELTE = 6,
-    EGT = 7,
-    ELT = 8,
-    EAdd = 9,
-    ESub = 10,
-    EMul = 11,
-    EDiv = 12,
-    EMod = 13,
-    EExp = 14, // Highest Priority
+    EXOR = 2,
+    EAND = 3,
+    ENE = 4,
+    EEQ = 5,
+    EGTE = 6,
+    ELTE = 7,
+    EGT = 8,
+    ELT = 9,
+    EAdd = 10,
+    ESub = 11,
+    EMul = 12,
+    EDiv = 13,
+    EMod = 14,
+    EExp = 15, // Highest Priority
 }
 use self::BinaryOp::{
-    EAdd, EDiv, EExp, EMod, EMul, ESub, EAND, EEQ, EGT, EGTE, ELT, ELTE, ENE, EOR,
+    EAdd, EDiv, EExp, EMod, EMul, ESub, EAND, EEQ, EGT, EGTE, ELT, ELTE, ENE, EOR, EXOR,
 };
 
 /// A Function Call with Standard Syntax.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum StdFunc {
     EVar(String),
     #[cfg(feature = "unsafe-vars")]
     EUnsafeVar {
         name: String,
-        ptr: *const f32,
+        ptr: *const Num,
     },
     EFunc {
         name: String,
@@ -118,6 +136,9 @@ pub enum StdFunc {
     EFuncFloor(ExpressionI),
     EFuncAbs(ExpressionI),
     EFuncSign(ExpressionI),
+    EFuncSqrt(ExpressionI),
+    EFuncCbrt(ExpressionI),
+    EFuncProbRound(ExpressionI),
     EFuncLog {
         base: Option<ExpressionI>,
         expr: ExpressionI,
@@ -126,6 +147,14 @@ pub enum StdFunc {
         modulus: Option<ExpressionI>,
         expr: ExpressionI,
     },
+    EFuncRoundHalfUp {
+        modulus: Option<ExpressionI>,
+        expr: ExpressionI,
+    },
+    EFuncRoundZ {
+        modulus: Option<ExpressionI>,
+        expr: ExpressionI,
+    },
     EFuncMin {
         first: ExpressionI,
         rest: Vec<ExpressionI>,
@@ -134,6 +163,58 @@ pub enum StdFunc {
         first: ExpressionI,
         rest: Vec<ExpressionI>,
     }, // cap=4
+    EFuncMinFirst {
+        first: ExpressionI,
+        rest: Vec<ExpressionI>,
+    }, // cap=4
+    EFuncMaxFirst {
+        first: ExpressionI,
+        rest: Vec<ExpressionI>,
+    }, // cap=4
+    EFuncNanMin {
+        first: ExpressionI,
+        rest: Vec<ExpressionI>,
+    }, // cap=4
+    EFuncNanMax {
+        first: ExpressionI,
+        rest: Vec<ExpressionI>,
+    }, // cap=4
+    EFuncProd {
+        first: ExpressionI,
+        rest: Vec<ExpressionI>,
+    }, // cap=4
+    EFuncBlend(Vec<ExpressionI>), // cap=4
+    EFuncAvg(Vec<ExpressionI>),   // cap=4
+    EFuncSum(Vec<ExpressionI>),   // cap=4
+    EFuncNorm(Vec<ExpressionI>),  // cap=4
+    EFuncSumSq(Vec<ExpressionI>), // cap=4
+    EFuncPoly(Vec<ExpressionI>),  // cap=4
+    EFuncSeq(Vec<ExpressionI>),   // cap=4
+    EFuncBucket {
+        x: ExpressionI,
+        lo: ExpressionI,
+        hi: ExpressionI,
+        n: ExpressionI,
+    },
+    EFuncPowMod {
+        base: ExpressionI,
+        exp: ExpressionI,
+        modulus: ExpressionI,
+    },
+    EFuncSmoothstep {
+        edge0: ExpressionI,
+        edge1: ExpressionI,
+        x: ExpressionI,
+    },
+    EFuncClamp {
+        val: ExpressionI,
+        lo: ExpressionI,
+        hi: ExpressionI,
+    },
+    EFuncSelect {
+        index: ExpressionI,
+        options: Vec<ExpressionI>,
+    }, // cap=4
 
     EFuncE,
     EFuncPi,
@@ -144,6 +225,17 @@ pub enum StdFunc {
     EFuncASin(ExpressionI),
     EFuncACos(ExpressionI),
     EFuncATan(ExpressionI),
+    EFuncASinD(ExpressionI),
+    EFuncACosD(ExpressionI),
+    EFuncATanD(ExpressionI),
+    EFuncATan2 {
+        y: ExpressionI,
+        x: ExpressionI,
+    },
+    EFuncATan2D {
+        y: ExpressionI,
+        x: ExpressionI,
+    },
     EFuncSinH(ExpressionI),
     EFuncCosH(ExpressionI),
     EFuncTanH(ExpressionI),
@@ -154,23 +246,101 @@ pub enum StdFunc {
 #[cfg(feature = "unsafe-vars")]
 use StdFunc::EUnsafeVar;
 use StdFunc::{
-    EFunc, EFuncACos, EFuncACosH, EFuncASin, EFuncASinH, EFuncATan, EFuncATanH, EFuncAbs,
-    EFuncCeil, EFuncCos, EFuncCosH, EFuncE, EFuncFloor, EFuncInt, EFuncLog, EFuncMax, EFuncMin,
-    EFuncPi, EFuncRound, EFuncSign, EFuncSin, EFuncSinH, EFuncTan, EFuncTanH, EVar,
+    EFunc, EFuncACos, EFuncACosD, EFuncACosH, EFuncASin, EFuncASinD, EFuncASinH, EFuncATan,
+    EFuncATan2, EFuncATan2D, EFuncATanD, EFuncATanH, EFuncAbs, EFuncAvg, EFuncBlend, EFuncBucket,
+    EFuncCbrt, EFuncCeil, EFuncClamp, EFuncCos, EFuncCosH, EFuncE, EFuncFloor, EFuncInt, EFuncLog,
+    EFuncMax, EFuncMaxFirst, EFuncMin, EFuncMinFirst, EFuncNanMax, EFuncNanMin, EFuncNorm, EFuncPi,
+    EFuncPoly, EFuncPowMod, EFuncProbRound, EFuncProd, EFuncRound, EFuncRoundHalfUp, EFuncRoundZ,
+    EFuncSelect, EFuncSeq, EFuncSign, EFuncSin, EFuncSinH, EFuncSmoothstep, EFuncSqrt, EFuncSum,
+    EFuncSumSq, EFuncTan, EFuncTanH, EVar,
 };
 
+/// The result of [`Parser::parse_with_doc()`](struct.Parser.html#method.parse_with_doc).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParsedWithDoc {
+    /// The parsed root `Expression`.
+    pub expr: ExpressionI,
+    /// The text of the leading `# ...` comment lines, or `None` if there were none.
+    pub doc: Option<String>,
+}
+
+/// A non-fatal problem found by
+/// [`Parser::parse_with_warnings()`](struct.Parser.html#method.parse_with_warnings)
+/// or [`Expression::compile_with_warnings()`](struct.Expression.html#method.compile_with_warnings).
+#[derive(Debug, PartialEq, Clone)]
+pub enum Warning {
+    /// A bare name, like `pi` (without parentheses), is looked up as a variable,
+    /// even though a built-in function has the same name.  The built-in is only
+    /// used when it is called, like `pi()`.
+    ShadowsBuiltin(String),
+    /// A finite constant is at least `2^24` (or `2^53` with the `f64` feature)
+    /// in magnitude.  From there on, `Num` can't represent every integer, so
+    /// digits of the literal may have been lost.
+    LargeLiteral(Num),
+    /// An `&&` chain compares the variable `String` with constants in a way
+    /// that can never be true, like `x > 5 && x < 3`.
+    Contradiction(String),
+    /// An `||` chain compares the variable `String` with constants in a way
+    /// that is always true, like `x >= 0 || x < 0`.  (Except when the variable
+    /// is NaN, which makes every comparison false.)
+    Tautology(String),
+}
+
+/// A function call found by [`Expression::calls()`](struct.Expression.html#method.calls).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CallSite {
+    /// The name of the function, like `"log"` or `"myfunc"`.
+    pub name: String,
+    /// The argument `Expression`s, in order.
+    pub args: Vec<ExpressionI>,
+}
+
+/// A custom binary operator, registered in
+/// [`Parser.custom_ops`](struct.Parser.html#structfield.custom_ops).
+///
+/// `a @ b` is parsed as a call to the custom function `func` with the two
+/// operands as arguments, like `func(a, b)`.  The function is resolved through
+/// the Namespace at evaluation time, like any other custom function.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CustomOp {
+    /// The name of the custom function that implements this operator.
+    pub func: String,
+    /// The operator binds more tightly than this built-in operator, but less
+    /// tightly than the next-higher one.  For example, `EMul` makes
+    /// `1 + 2 @ 3 * 4` parse as `1 + (2 @ 3) * 4`, while `EAdd` makes
+    /// it parse as `1 + (2 @ (3 * 4))`.
+    ///
+    /// Custom operators of the same precedence are evaluated left-to-right.
+    pub precedence: BinaryOp,
+}
+
+/// A binary operator read by `read_expression()`, before custom operators
+/// are turned into function calls.
+enum AnyOp {
+    Std(BinaryOp),
+    Custom(char),
+}
+
+/// The operands (each with its integer-literal flag) and built-in operators
+/// of an expression, after its custom operators were turned into function calls.
+type Desugared = (Vec<(Value, bool)>, Vec<BinaryOp>);
+
 /// Represents a `print()` function call in the `fasteval3` expression AST.
+#[cfg(feature = "print-builtin")]
 #[derive(Debug, PartialEq, Eq)]
 pub struct PrintFunc(pub Vec<ExpressionOrString>); // cap=8
 
 /// Used by the `print()` function.  Can hold an `Expression` or a `String`.
+#[cfg(feature = "print-builtin")]
 #[derive(Debug, PartialEq, Eq)]
 pub enum ExpressionOrString {
     EExpr(ExpressionI),
     EStr(String), // cap=64
 }
+#[cfg(feature = "print-builtin")]
 use ExpressionOrString::{EExpr, EStr};
 
+#[cfg(feature = "print-builtin")]
 impl Clone for PrintFunc {
     fn clone(&self) -> Self {
         let mut vec = Vec::<ExpressionOrString>::with_capacity(self.0.len());
@@ -225,7 +395,7 @@ macro_rules! read {
             None => Err(Error::EOF),
         }
     };
-    ($bs:ident, $parsing:literal) => {
+    ($bs:ident, $parsing:expr) => {
         match $bs.first() {
             Some(b) => {
                 *$bs = &$bs[1..];
@@ -270,10 +440,200 @@ macro_rules! spaces {
 
 pub const DEFAULT_EXPR_LEN_LIMIT: usize = 4096;
 pub const DEFAULT_EXPR_DEPTH_LIMIT: usize = 32;
+pub const DEFAULT_RANGE_LEN_LIMIT: usize = 1024;
+
+/// The names of the built-in functions.  A variable with one of these names
+/// gets a [`Warning::ShadowsBuiltin`](enum.Warning.html#variant.ShadowsBuiltin).
+const BUILTIN_FUNCS: &[&str] = &[
+    "int",
+    "ceil",
+    "floor",
+    "abs",
+    "sign",
+    "sqrt",
+    "cbrt",
+    "probround",
+    "log",
+    "round",
+    "rounda",
+    "roundz",
+    "round_half_up",
+    "min",
+    "max",
+    "min_first",
+    "max_first",
+    "nanmin",
+    "nanmax",
+    "prod",
+    "minz",
+    "maxz",
+    "avg",
+    "sum",
+    "blend",
+    "norm",
+    "seq",
+    "sumsq",
+    "poly",
+    "bucket",
+    "powmod",
+    "smoothstep",
+    "clamp",
+    "select",
+    "e",
+    "pi",
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "asind",
+    "acosd",
+    "atand",
+    "atan2",
+    "atan2d",
+    "sinh",
+    "cosh",
+    "tanh",
+    "asinh",
+    "acosh",
+    "atanh",
+    "print",
+];
+
+/// What a name in an expression refers to.  Returned by
+/// [`classify_name()`](fn.classify_name.html).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NameKind {
+    /// A built-in function, like `sin` in `sin(x)`.
+    BuiltinFunction,
+    /// A built-in constant: `e()` and `pi()`, or `inf` and `NaN` with the
+    /// `alpha-keywords` feature.
+    BuiltinConstant,
+    /// A custom variable or function, which is looked up in the Namespace.
+    Custom,
+}
+
+/// Returns what `name` refers to, with the same precedence rules as the
+/// `Parser`: built-in functions only take precedence when they are `called`
+/// with parentheses.  So `pi` is a custom variable, but `pi()` is the built-in
+/// constant.  With the `alpha-keywords` feature, `inf` and `NaN` are always
+/// constants.
+///
+/// This is meant for tools like syntax highlighters.  It doesn't check that
+/// `name` is a valid name, or that a built-in gets the right number of
+/// arguments.  `print` is a built-in function even without the `print-builtin`
+/// feature, because it still can't be called as a custom function.
+///
+/// ```
+/// use fasteval3::{classify_name, NameKind};
+///
+/// assert_eq!(classify_name("pi", false), NameKind::Custom);
+/// assert_eq!(classify_name("pi", true), NameKind::BuiltinConstant);
+/// assert_eq!(classify_name("sin", true), NameKind::BuiltinFunction);
+/// assert_eq!(classify_name("x", true), NameKind::Custom);
+/// ```
+pub fn classify_name(name: &str, called: bool) -> NameKind {
+    if cfg!(feature = "alpha-keywords") && (name == "inf" || name == "NaN") {
+        return NameKind::BuiltinConstant;
+    }
+    if !called {
+        // Names without parentheses are always custom:
+        return NameKind::Custom;
+    }
+    match name {
+        "e" | "pi" => NameKind::BuiltinConstant,
+        _ if BUILTIN_FUNCS.contains(&name) => NameKind::BuiltinFunction,
+        _ => NameKind::Custom,
+    }
+}
+
+/// Constants at least this large in magnitude get a
+/// [`Warning::LargeLiteral`](enum.Warning.html#variant.LargeLiteral).
+const LARGE_LITERAL_LIMIT: Num = (1_u64 << Num::MANTISSA_DIGITS) as Num;
 
 pub struct Parser {
     pub expr_len_limit: usize,
     pub expr_depth_limit: usize,
+    /// Maximum number of values that an integer range function argument (like
+    /// `sum(1..5)`) may expand to.
+    pub range_len_limit: usize,
+    /// If `true`, a single `=` is parsed as the `==` equality operator, which is
+    /// less confusing for spreadsheet users.  (`==` continues to work.)
+    ///
+    /// This is off by default so that `=` stays available for other syntax.
+    pub single_equals: bool,
+    /// Expected argument counts of custom functions.
+    ///
+    /// If a function listed here is called with a different number of args,
+    /// parsing fails with an [`Error::WrongArgs`](../error/enum.Error.html#variant.WrongArgs).
+    /// (A name used without parentheses counts as a call with zero args.)
+    pub func_arities: BTreeMap<String, usize>,
+    /// If `true`, any reference to a variable or custom function fails with
+    /// [`Error::VariablesNotAllowed`](../error/enum.Error.html#variant.VariablesNotAllowed),
+    /// so the parsed expression always compiles down to a single constant.
+    pub reject_variables: bool,
+    /// If `true`, `[` and `]` can't be used in place of parentheses, so `[1+2]`
+    /// and `max[1,2]` fail to parse.  This leaves square brackets free for a
+    /// host language that embeds `fasteval3` expressions.
+    pub reject_square_brackets: bool,
+    /// If `true`, the end of the input closes any parentheses, brackets or
+    /// function calls that are still open, so `sin(x` parses like `sin(x)`
+    /// instead of failing with [`Error::EofWhileParsing`](../error/enum.Error.html#variant.EofWhileParsing).
+    /// Useful for forgiving calculator inputs.
+    pub auto_close: bool,
+    /// If `true`, a unary `+`, `-` or `!` can't be directly followed by another
+    /// one, so typos like `1 + ++4` or `--x` fail with
+    /// [`Error::UnexpectedOperator`](../error/enum.Error.html#variant.UnexpectedOperator).
+    /// Use parentheses (like `-(-x)`) to write these intentionally.
+    pub reject_repeated_unary: bool,
+    /// Custom binary operators, keyed by their single-character symbol.
+    ///
+    /// Built-in operators are matched first, so a symbol should not be a
+    /// built-in operator, a letter, a digit, `_`, a bracket or whitespace.
+    /// See [`CustomOp`](struct.CustomOp.html).
+    pub custom_ops: BTreeMap<char, CustomOp>,
+    /// Named constants, like `c` or `g` in a physics formula.
+    ///
+    /// A bare name (without parentheses) listed here is parsed as its value,
+    /// like a number literal, so `c * 2` compiles to a single constant.  The
+    /// Namespace is never asked for it, and it is allowed even with
+    /// `reject_variables`.  Called names, like `c()` or `g(1)`, are still
+    /// functions.  With `unsafe-vars`, named constants take precedence over
+    /// Unsafe Variables of the same name.
+    pub constants: BTreeMap<String, Num>,
+    /// If `true`, variable and function names may contain `.` between their
+    /// parts, like `sensor.temp`.  The whole dotted name is passed to the
+    /// Namespace, which can resolve it as a path into nested data (see
+    /// [`NestedNamespace`](../evalns/type.NestedNamespace.html)).
+    ///
+    /// `fasteval3` has no member-access operator, so `a.b` is always a single
+    /// name and never "field `b` of `a`".  A `.` must be followed by another
+    /// name part, so `a.` and `a..b` are not names, and `1.5` is still a number.
+    pub dotted_names: bool,
+    /// Maximum absolute value of a number literal, like `1e38`.
+    ///
+    /// A literal whose magnitude is larger than this fails with
+    /// [`Error::LiteralOutOfRange`](../error/enum.Error.html#variant.LiteralOutOfRange).
+    /// The check applies to the value after SI and angle suffixes, so with a
+    /// limit of `1000`, `2k` is rejected too.  `inf` is rejected by any limit,
+    /// while `NaN` is not.  `None` (the default) means no limit.
+    pub literal_limit: Option<Num>,
+    /// If `true`, number literals can't have an SI suffix (like the `k` in
+    /// `2k`), so the suffix is left for the rest of the grammar.  Then `1M`
+    /// fails with [`Error::UnparsedTokensRemaining`](../error/enum.Error.html#variant.UnparsedTokensRemaining)
+    /// instead of meaning `1000000`.  The `deg` and `rad` suffixes still work.
+    pub reject_si_suffixes: bool,
+    /// Maximum number of distinct variables and custom functions (the names
+    /// that [`var_names()`](../evaler/trait.Evaler.html#method.var_names)
+    /// would return) that an expression may refer to.
+    ///
+    /// An expression that refers to more fails with
+    /// [`Error::TooManyVariables`](../error/enum.Error.html#variant.TooManyVariables)
+    /// as soon as the first extra name is read.  Repeated names only count
+    /// once, and named `constants` don't count.  `None` (the default) means no
+    /// limit.
+    pub var_limit: Option<usize>,
 }
 
 impl Parser {
@@ -282,11 +642,78 @@ impl Parser {
         Self {
             expr_len_limit: DEFAULT_EXPR_LEN_LIMIT,
             expr_depth_limit: DEFAULT_EXPR_DEPTH_LIMIT,
+            range_len_limit: DEFAULT_RANGE_LEN_LIMIT,
+            single_equals: false,
+            func_arities: BTreeMap::new(),
+            reject_variables: false,
+            reject_square_brackets: false,
+            auto_close: false,
+            reject_repeated_unary: false,
+            custom_ops: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            dotted_names: false,
+            literal_limit: None,
+            reject_si_suffixes: false,
+            var_limit: None,
+        }
+    }
+
+    /// Returns a copy of this `Parser` with `expr_len_limit` set to `limit`.
+    ///
+    /// Longer expressions fail with [`Error::TooLong`](../error/enum.Error.html#variant.TooLong).
+    /// Like [`with_depth_limit()`](#method.with_depth_limit), this can be
+    /// chained: `Parser::new().with_len_limit(256).with_depth_limit(64)`.
+    #[inline]
+    #[must_use]
+    pub const fn with_len_limit(mut self, limit: usize) -> Self {
+        self.expr_len_limit = limit;
+        self
+    }
+
+    /// Returns a copy of this `Parser` with `expr_depth_limit` set to `limit`.
+    ///
+    /// More deeply-nested expressions fail with [`Error::TooDeep`](../error/enum.Error.html#variant.TooDeep).
+    #[inline]
+    #[must_use]
+    pub const fn with_depth_limit(mut self, limit: usize) -> Self {
+        self.expr_depth_limit = limit;
+        self
+    }
+
+    /// Returns a copy of this `Parser` with `var_limit` set to `limit`.
+    ///
+    /// Expressions with more distinct variables and custom functions fail with
+    /// [`Error::TooManyVariables`](../error/enum.Error.html#variant.TooManyVariables).
+    #[inline]
+    #[must_use]
+    pub const fn with_var_limit(mut self, limit: usize) -> Self {
+        self.var_limit = Some(limit);
+        self
+    }
+
+    /// Checks a custom function call against `func_arities`.
+    fn check_arity(&self, name: &str, nargs: usize) -> Result<(), Error> {
+        check_arity(name, self.func_arities.get(name).copied(), nargs)
+    }
+
+    /// Records a variable or custom function name, and checks the number of
+    /// distinct names against `var_limit`.
+    fn check_var_limit(&self, slab: &mut ParseSlab, name: &str) -> Result<(), Error> {
+        if let Some(limit) = self.var_limit {
+            if !slab.seen_names.contains(name) {
+                if slab.seen_names.len() >= limit {
+                    return Err(Error::TooManyVariables);
+                }
+                slab.seen_names.insert(name.to_owned());
+            }
         }
+        Ok(())
     }
 
     /// Checks if a given byte matches its character counterpart.
-    const fn is_varname_byte(b: u8, i: usize) -> bool {
+    ///
+    /// With `dotted`, a `.` is accepted after the first byte too.
+    const fn is_varname_byte(b: u8, i: usize, dotted: bool) -> bool {
         // Might be parser-breaking
         /*(b'A' <= b && b <= b'Z')
         || (b'a' <= b && b <= b'z')
@@ -296,12 +723,12 @@ impl Parser {
         b.is_ascii_uppercase()
             || b.is_ascii_lowercase()
             || b == b'_'
-            || (i > 0 && b.is_ascii_digit())
+            || (i > 0 && (b.is_ascii_digit() || (dotted && b == b'.')))
     }
 
     /// Checks if a given bytes matches its character counterpart, with the byte itself possibly being none.
     fn is_varname_byte_opt(bo: Option<u8>, i: usize) -> bool {
-        bo.map_or(false, |byte| Self::is_varname_byte(byte, i))
+        bo.map_or(false, |byte| Self::is_varname_byte(byte, i, false))
     }
 
     /// Use this function to parse an expression String.  The `Slab` will be cleared first.
@@ -335,9 +762,200 @@ impl Parser {
             return Err(Error::TooLong);
         } // Restrict length for safety
         let mut bs = expr_str.as_bytes();
+        slab.input_start = bs.as_ptr() as usize;
+        slab.seen_names.clear();
         self.read_expression(slab, &mut bs, 0, true)
     }
 
+    /// Like `parse()`, but accepts raw bytes, like input read from a socket or file.
+    ///
+    /// The length limit is checked before the bytes are validated as UTF-8,
+    /// so oversized untrusted inputs are rejected without scanning them.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if length of `expr_bytes` exceeds limit, if it is not
+    /// valid UTF-8, or if it fails to parse.
+    #[inline]
+    pub fn parse_bytes(
+        &self,
+        expr_bytes: &[u8],
+        slab: &mut ParseSlab,
+    ) -> Result<ExpressionI, Error> {
+        if expr_bytes.len() > self.expr_len_limit {
+            return Err(Error::TooLong);
+        } // Restrict length before paying for UTF-8 validation
+        let expr_str = from_utf8(expr_bytes)
+            .map_err(|_| Error::Utf8ErrorWhileParsing(String::from("expression")))?;
+        self.parse(expr_str, slab)
+    }
+
+    /// Parses an expression String into a new `Slab`, and returns the `Slab`
+    /// together with the root `Expression`.
+    ///
+    /// This is a convenience for one-off evaluations: you get an `Expression`
+    /// that you can `eval()` directly, without the `ExpressionI` + `from()`
+    /// indirection.  The `Expression` still refers to its sub-expressions by
+    /// index, so it must be evaluated with the returned `Slab`.
+    ///
+    /// The root `Expression` is moved out of the `Slab`, so it cannot be
+    /// reached through `slab.ps` afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `expr_str` fails to parse, like `parse()`.
+    pub fn parse_owned(&self, expr_str: &str) -> Result<(Slab, Expression), Error> {
+        let mut slab = Slab::new();
+        let expr_i = self.parse_noclear(expr_str, &mut slab.ps)?;
+        // The root is always pushed last, after its sub-expressions:
+        if expr_i.0 + 1 != slab.ps.exprs.len() {
+            return Err(Error::Unreachable);
+        }
+        let expr = slab.ps.exprs.pop().ok_or(Error::Unreachable)?;
+        // Otherwise the next expression pushed at this index would inherit
+        // the root's integer literals:
+        slab.ps
+            .int_literals
+            .retain(|site| !matches!(site, ValueSite::Operand(i, _) if *i == expr_i));
+        Ok((slab, expr))
+    }
+
+    /// Like `parse()`, but first captures any leading comment lines as a
+    /// description of the expression.
+    ///
+    /// A comment line starts with `#` (after optional whitespace) and runs to
+    /// the end of the line.  The text after each `#` (and one following space,
+    /// if present) is kept, and multiple lines are joined with `\n`.  Comments
+    /// are only recognized before the expression; a `#` anywhere else is still
+    /// a parse error.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), fasteval3::Error> {
+    /// let mut slab = fasteval3::Slab::new();
+    /// let parsed = fasteval3::Parser::new().parse_with_doc(
+    ///     "# Area of a circle.\n# 'r' is the radius.\npi() * r^2",
+    ///     &mut slab.ps,
+    /// )?;
+    /// assert_eq!(parsed.doc.as_deref(), Some("Area of a circle.\n'r' is the radius."));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `expr_str` fails to parse, like `parse()`.
+    pub fn parse_with_doc(
+        &self,
+        expr_str: &str,
+        slab: &mut ParseSlab,
+    ) -> Result<ParsedWithDoc, Error> {
+        if expr_str.len() > self.expr_len_limit {
+            return Err(Error::TooLong);
+        }
+        let mut doc_lines = Vec::new();
+        let mut rest = expr_str;
+        while let Some(comment) = rest.trim_start().strip_prefix('#') {
+            let (line, after) = comment.split_once('\n').unwrap_or((comment, ""));
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            doc_lines.push(line.strip_prefix(' ').unwrap_or(line));
+            rest = after;
+        }
+        slab.clear();
+        // Error offsets are relative to `expr_str`, including the comments:
+        slab.input_start = expr_str.as_ptr() as usize;
+        slab.seen_names.clear();
+        let expr = self.read_expression(slab, &mut rest.as_bytes(), 0, true)?;
+        let doc = if doc_lines.is_empty() {
+            None
+        } else {
+            Some(doc_lines.join("\n"))
+        };
+        Ok(ParsedWithDoc { expr, doc })
+    }
+
+    /// Like `parse()`, but also returns non-fatal [`Warning`](enum.Warning.html)s
+    /// about the expression, in the order that they appear in `expr_str`.  The
+    /// warnings don't affect parsing or evaluation; they are meant for linting.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), fasteval3::Error> {
+    /// use fasteval3::{Parser, Slab, Warning};
+    ///
+    /// let mut slab = Slab::new();
+    /// let (_, warnings) = Parser::new().parse_with_warnings("2 * pi * r", &mut slab.ps)?;
+    /// assert_eq!(warnings, vec![Warning::ShadowsBuiltin(String::from("pi"))]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `expr_str` fails to parse, like `parse()`.
+    pub fn parse_with_warnings(
+        &self,
+        expr_str: &str,
+        slab: &mut ParseSlab,
+    ) -> Result<(ExpressionI, Vec<Warning>), Error> {
+        let expr_i = self.parse(expr_str, slab)?;
+        let mut warnings = Vec::new();
+        slab.get_expr(expr_i).collect_warnings(slab, &mut warnings);
+        Ok((expr_i, warnings))
+    }
+
+    /// Like `parse()`, but on error also returns a best-effort partial
+    /// expression, for editors that render the tree while the user types.
+    ///
+    /// The partial expression is the longest prefix of `expr_str` that parses,
+    /// with any parentheses or function calls still open at its end closed
+    /// (like [`auto_close`](#structfield.auto_close)).  It may be incomplete
+    /// or mean something different from the finished expression, so only use
+    /// it for display.  Each attempt is a full parse, so recovery costs up to
+    /// one parse per character of `expr_str`.
+    ///
+    /// Returns `(Some(expr_i), None)` if `expr_str` parses, and otherwise the
+    /// partial expression (or `None` if no prefix parses) with the original
+    /// error.  The `Slab` is cleared first, like `parse()`.
+    ///
+    /// ```
+    /// use fasteval3::{Error, Parser, Slab};
+    ///
+    /// let mut slab = Slab::new();
+    /// let (expr_i, err) = Parser::new().parse_recover("sin(x) * (3 +", &mut slab.ps);
+    /// assert_eq!(err, Some(Error::EofWhileParsing(String::from("value"))));
+    /// assert!(expr_i.is_some()); // Parsed like "sin(x) * (3)".
+    /// ```
+    pub fn parse_recover(
+        &self,
+        expr_str: &str,
+        slab: &mut ParseSlab,
+    ) -> (Option<ExpressionI>, Option<Error>) {
+        let err = match self.parse(expr_str, slab) {
+            Ok(expr_i) => return (Some(expr_i), None),
+            Err(Error::TooLong) => {
+                slab.clear();
+                return (None, Some(Error::TooLong));
+            }
+            Err(err) => err,
+        };
+        let lenient = Self {
+            auto_close: true,
+            func_arities: self.func_arities.clone(),
+            custom_ops: self.custom_ops.clone(),
+            constants: self.constants.clone(),
+            ..*self
+        };
+        let ends = Some(expr_str.len())
+            .into_iter()
+            .chain(expr_str.char_indices().rev().map(|(i, _)| i));
+        for end in ends {
+            if let Ok(expr_i) = lenient.parse(&expr_str[..end], slab) {
+                return (Some(expr_i), Some(err));
+            }
+        }
+        slab.clear();
+        (None, Some(err))
+    }
+
     fn read_expression(
         &self,
         slab: &mut ParseSlab,
@@ -349,15 +967,29 @@ impl Parser {
             return Err(Error::TooDeep);
         }
 
-        let first = self.read_value(slab, bs, depth)?;
+        let (first, first_int) = self.read_value(slab, bs, depth)?;
         let mut pairs = Vec::<ExprPair>::with_capacity(8);
+        let mut customs = Vec::<(usize, char)>::new();
+        // The operand numbers of integer literals:
+        let mut ints = Vec::<usize>::new();
+        if first_int {
+            ints.push(0);
+        }
         loop {
-            match Self::read_binaryop(bs)? {
-                Pass => break,
-                Bite(bop) => {
-                    let val = self.read_value(slab, bs, depth)?;
-                    pairs.push(ExprPair(bop, val));
-                }
+            let bop = match self.read_binaryop(bs)? {
+                Pass => match self.read_custom_op(bs) {
+                    None => break,
+                    Some(sym) => {
+                        customs.push((pairs.len(), sym));
+                        EOR // The op is replaced below.
+                    }
+                },
+                Bite(bop) => bop,
+            };
+            let (val, is_int) = self.read_value(slab, bs, depth)?;
+            pairs.push(ExprPair(bop, val));
+            if is_int {
+                ints.push(pairs.len());
             }
         }
         spaces!(bs);
@@ -366,32 +998,234 @@ impl Parser {
                 Ok(s) => s,
                 Err(..) => "Utf8Error while handling UnparsedTokensRemaining error",
             };
-            return Err(Error::UnparsedTokensRemaining(bs_str.to_owned()));
+            return Err(Error::UnparsedTokensRemaining {
+                rest: bs_str.to_owned(),
+                pos: slab.offset_of(bs),
+            });
+        }
+        if customs.is_empty() {
+            let expr_i = slab.push_expr(Expression { first, pairs })?;
+            for n in ints {
+                slab.int_literals.insert(ValueSite::Operand(expr_i, n));
+            }
+            return Ok(expr_i);
+        }
+
+        let mut vals = Vec::with_capacity(pairs.len() + 1);
+        let mut ops = Vec::with_capacity(pairs.len());
+        vals.push((first, first_int));
+        for ExprPair(bop, val) in pairs {
+            ops.push(AnyOp::Std(bop));
+            let is_int = ints.contains(&vals.len());
+            vals.push((val, is_int));
+        }
+        for (i, sym) in customs {
+            if let Some(op) = ops.get_mut(i) {
+                *op = AnyOp::Custom(sym);
+            }
+        }
+        let (vals, ops) = self.desugar_custom_ops(slab, vals, ops)?;
+        Self::push_operands(slab, vals, ops)
+    }
+
+    /// Pushes the `Expression` made of `vals` joined by `ops`, and records
+    /// which of its operands are integer literals.
+    fn push_operands(
+        slab: &mut ParseSlab,
+        vals: Vec<(Value, bool)>,
+        ops: Vec<BinaryOp>,
+    ) -> Result<ExpressionI, Error> {
+        let mut ints = Vec::new();
+        let mut vals = vals.into_iter().enumerate().map(|(n, (val, is_int))| {
+            if is_int {
+                ints.push(n);
+            }
+            val
+        });
+        let first = vals.next().ok_or(Error::Unreachable)?;
+        let pairs = ops
+            .into_iter()
+            .zip(vals)
+            .map(|(bop, val)| ExprPair(bop, val))
+            .collect();
+        let expr_i = slab.push_expr(Expression { first, pairs })?;
+        for n in ints {
+            slab.int_literals.insert(ValueSite::Operand(expr_i, n));
+        }
+        Ok(expr_i)
+    }
+
+    /// Reads a single-character symbol registered in `custom_ops`.
+    fn read_custom_op(&self, bs: &mut &[u8]) -> Option<char> {
+        if self.custom_ops.is_empty() {
+            return None;
+        }
+        let len = match peek!(bs)? {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        let sym = from_utf8(bs.get(..len)?).ok()?.chars().next()?;
+        if !self.custom_ops.contains_key(&sym) {
+            return None;
+        }
+        skip_n!(bs, len);
+        Some(sym)
+    }
+
+    /// Ranks operators by how tightly they bind.  Built-in operators get even
+    /// ranks and custom operators get odd ranks, so they never tie.
+    fn op_rank(&self, op: &AnyOp) -> u8 {
+        let std_rank = |bop: BinaryOp| match bop {
+            // The comparisons are all evaluated together:
+            ENE | EEQ | EGTE | ELTE | EGT | ELT => ENE as u8 * 2,
+            _ => bop as u8 * 2,
+        };
+        match op {
+            AnyOp::Std(bop) => std_rank(*bop),
+            AnyOp::Custom(sym) => self
+                .custom_ops
+                .get(sym)
+                .map_or(0, |cop| std_rank(cop.precedence) + 1),
+        }
+    }
+
+    /// Turns the custom operators in `vals`/`ops` into calls of their
+    /// functions, and returns the remaining flat list of built-in operations.
+    /// Each value is paired with its integer-literal flag.
+    fn desugar_custom_ops(
+        &self,
+        slab: &mut ParseSlab,
+        mut vals: Vec<(Value, bool)>,
+        mut ops: Vec<AnyOp>,
+    ) -> Result<Desugared, Error> {
+        let Some(min_rank) = ops
+            .iter()
+            .filter(|op| matches!(op, AnyOp::Custom(_)))
+            .map(|op| self.op_rank(op))
+            .min()
+        else {
+            let ops = ops
+                .into_iter()
+                .map(|op| match op {
+                    AnyOp::Std(bop) => Ok(bop),
+                    AnyOp::Custom(_) => Err(Error::Unreachable),
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok((vals, ops));
+        };
+
+        // Built-in operators that bind less tightly than every remaining custom
+        // operator split the list into segments.  Within a segment, the custom
+        // operators of the lowest rank split it again into their operands.
+        let mut out_vals = Vec::new();
+        let mut out_ops = Vec::new();
+        while !vals.is_empty() {
+            let end = ops
+                .iter()
+                .position(|op| self.op_rank(op) < min_rank)
+                .unwrap_or(ops.len());
+            let rest_vals = vals.split_off(end + 1);
+            let rest_ops = ops.split_off(end);
+            let (seg_vals, seg_ops) = self.desugar_segment(slab, vals, ops, min_rank)?;
+            out_vals.extend(seg_vals);
+            out_ops.extend(seg_ops);
+
+            let mut rest_ops = rest_ops.into_iter();
+            match rest_ops.next() {
+                Some(AnyOp::Std(bop)) => out_ops.push(bop),
+                Some(AnyOp::Custom(_)) => return Err(Error::Unreachable),
+                None => (),
+            }
+            vals = rest_vals;
+            ops = rest_ops.collect();
+        }
+        Ok((out_vals, out_ops))
+    }
+
+    /// Desugars one segment of `desugar_custom_ops()`, which contains no
+    /// operators that bind less tightly than `min_rank`.
+    fn desugar_segment(
+        &self,
+        slab: &mut ParseSlab,
+        mut vals: Vec<(Value, bool)>,
+        mut ops: Vec<AnyOp>,
+        min_rank: u8,
+    ) -> Result<Desugared, Error> {
+        let is_split = |op: &AnyOp| matches!(op, AnyOp::Custom(_)) && self.op_rank(op) == min_rank;
+        if !ops.iter().any(is_split) {
+            return self.desugar_custom_ops(slab, vals, ops);
+        }
+
+        // Splits off the next operand and pushes it into the slab.  Returns the
+        // operand, and the custom operator that follows it (if any).
+        let mut next_operand = |slab: &mut ParseSlab| {
+            let end = ops.iter().position(is_split).unwrap_or(ops.len());
+            let rest_vals = vals.split_off(end + 1);
+            let mut rest_ops = ops.split_off(end).into_iter();
+            let next = rest_ops.next();
+            let (operand_vals, operand_ops) = self.desugar_custom_ops(
+                slab,
+                std::mem::replace(&mut vals, rest_vals),
+                std::mem::replace(&mut ops, rest_ops.collect()),
+            )?;
+            let func = match next {
+                Some(AnyOp::Custom(sym)) => Some(
+                    self.custom_ops
+                        .get(&sym)
+                        .ok_or(Error::Unreachable)?
+                        .func
+                        .clone(),
+                ),
+                Some(AnyOp::Std(_)) => return Err(Error::Unreachable),
+                None => None,
+            };
+            Ok((Self::push_operands(slab, operand_vals, operand_ops)?, func))
+        };
+
+        let (mut lhs, mut func) = next_operand(slab)?;
+        loop {
+            let name = func.ok_or(Error::Unreachable)?;
+            let (rhs, next_func) = next_operand(slab)?;
+            self.check_var_limit(slab, &name)?;
+            let call = EStdFunc(EFunc {
+                name,
+                args: vec![lhs, rhs],
+            });
+            if next_func.is_none() {
+                return Ok((vec![(call, false)], Vec::new()));
+            }
+            lhs = slab.push_expr(Expression {
+                first: call,
+                pairs: Vec::new(),
+            })?;
+            func = next_func;
         }
-        slab.push_expr(Expression { first, pairs })
     }
 
+    /// Reads a value, and whether it is an integer literal.
     fn read_value(
         &self,
         slab: &mut ParseSlab,
         bs: &mut &[u8],
         depth: usize,
-    ) -> Result<Value, Error> {
+    ) -> Result<(Value, bool), Error> {
         if depth > self.expr_depth_limit {
             return Err(Error::TooDeep);
         }
 
-        match Self::read_const(slab, bs)? {
+        match self.read_const(slab, bs)? {
             Pass => {}
-            Bite(c) => return Ok(EConstant(c)),
+            Bite((c, is_int)) => return Ok((EConstant(c), is_int)),
         }
         match self.read_unaryop(slab, bs, depth)? {
             Pass => {}
-            Bite(u) => return Ok(EUnaryOp(u)),
+            Bite(u) => return Ok((EUnaryOp(u), false)),
         }
         match self.read_callable(slab, bs, depth)? {
             Pass => {}
-            Bite(c) => return Ok(c),
+            Bite(c) => return Ok((c, false)),
         }
 
         // Improve the precision of this error case:
@@ -399,10 +1233,33 @@ impl Parser {
             return Err(Error::EofWhileParsing(String::from("value")));
         }
 
-        Err(Error::InvalidValue)
+        Err(Error::InvalidValue {
+            pos: slab.offset_of(bs),
+        })
+    }
+
+    /// Reads the operand of a unary operator and pushes it into the slab.
+    fn read_unary_operand(
+        &self,
+        slab: &mut ParseSlab,
+        bs: &mut &[u8],
+        depth: usize,
+    ) -> Result<ValueI, Error> {
+        let (v, is_int) = self.read_value(slab, bs, depth)?;
+        let val_i = slab.push_val(v)?;
+        if is_int {
+            slab.int_literals.insert(ValueSite::Val(val_i));
+        }
+        Ok(val_i)
     }
 
-    fn read_const(slab: &mut ParseSlab, bs: &mut &[u8]) -> Result<Token<f32>, Error> {
+    /// Reads a number literal.  The `bool` is `true` for integer literals,
+    /// which have no decimal point, exponent or suffix.
+    fn read_const(
+        &self,
+        slab: &mut ParseSlab,
+        bs: &mut &[u8],
+    ) -> Result<Token<(Num, bool)>, Error> {
         spaces!(bs);
 
         let mut toklen = 0;
@@ -452,42 +1309,85 @@ impl Parser {
             return Ok(Pass);
         }
 
-        let mut tok = unsafe { from_utf8_unchecked(&bs[..toklen]) };
+        let lit = unsafe { from_utf8_unchecked(&bs[..toklen]) };
+        let lit_len = toklen;
+        let lit_digits = lit
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'-' || b == b'+');
+        let mut tok = lit;
+        let si_suffix = |i: usize| match peek_n!(bs, i) {
+            Some(b'k' | b'K') => (3, 1),
+            Some(b'M') => (6, 1),
+            Some(b'G') => (9, 1),
+            Some(b'T') => (12, 1),
+            Some(b'm') => (-3, 1),
+            Some(b'u' | b'\xb5') => (-6, 1), // ASCII-encoded 'µ'
+            Some(b'\xc2') if peek_is!(bs, i + 1, b'\xb5') => (-6, 2), // UTF8-encoded 'µ'
+            Some(b'n') => (-9, 1),
+            Some(b'p') => (-12, 1),
+            _ => (0, 0),
+        };
+        let mut to_radians = false;
         if suffix_ok {
-            match peek_n!(bs, toklen) {
-                None => (),
-                Some(b) => {
-                    let (exp, suffixlen) = match b {
-                        b'k' | b'K' => (3, 1),
-                        b'M' => (6, 1),
-                        b'G' => (9, 1),
-                        b'T' => (12, 1),
-                        b'm' => (-3, 1),
-                        b'u' | b'\xb5' => (-6, 1), // ASCII-encoded 'µ'
-                        b'\xc2' if peek_is!(bs, toklen + 1, b'\xb5') => (-6, 2), // UTF8-encoded 'µ'
-                        b'n' => (-9, 1),
-                        b'p' => (-12, 1),
-                        _ => (0, 0),
-                    };
-                    if exp != 0 {
-                        slab.char_buf.clear();
-                        slab.char_buf.push_str(tok);
-                        slab.char_buf.push('e');
-                        slab.char_buf.push_str(&exp.to_string());
-                        tok = &slab.char_buf;
-
-                        toklen += suffixlen;
-                    }
+            let (exp, suffixlen) = if self.reject_si_suffixes {
+                (0, 0)
+            } else {
+                si_suffix(toklen)
+            };
+            if exp != 0 {
+                // Report a repeated suffix, like '1kk', unless it starts a name:
+                let (_, suffixlen2) = si_suffix(toklen + suffixlen);
+                if suffixlen2 != 0
+                    && !Self::is_varname_byte_opt(peek_n!(bs, toklen + suffixlen + suffixlen2), 1)
+                {
+                    return Err(Error::MalformedNumber(format!(
+                        "{}: more than one SI suffix",
+                        String::from_utf8_lossy(&bs[..toklen + suffixlen + suffixlen2])
+                    )));
                 }
+                slab.char_buf.clear();
+                slab.char_buf.push_str(tok);
+                slab.char_buf.push('e');
+                slab.char_buf.push_str(&exp.to_string());
+                tok = &slab.char_buf;
+
+                toklen += suffixlen;
+            }
+
+            // Angle suffixes may follow the SI suffixes, but only if they are not
+            // the start of a longer name (so that '90degrees' is not accepted):
+            let angle = |suffix: &[u8]| {
+                bs.get(toklen..toklen + 3) == Some(suffix)
+                    && !Self::is_varname_byte_opt(peek_n!(bs, toklen + 3), 3)
+            };
+            if angle(b"deg") {
+                to_radians = true;
+                toklen += 3;
+            } else if angle(b"rad") {
+                toklen += 3;
             }
         }
 
-        let val = tok
-            .parse::<f32>()
-            .map_err(|_| Error::ParseF32(tok.to_owned()))?;
+        let mut val = tok
+            .parse::<Num>()
+            .map_err(|_| match malformed_number(lit) {
+                Some(reason) => Error::MalformedNumber(format!("{lit}: {reason}")),
+                None => Error::ParseF32(tok.to_owned()),
+            })?;
+        if to_radians {
+            val = val.to_radians();
+        }
+        if let Some(limit) = self.literal_limit {
+            if val.abs() > limit {
+                return Err(Error::LiteralOutOfRange(
+                    String::from_utf8_lossy(&bs[..toklen]).into_owned(),
+                ));
+            }
+        }
+        let is_int = lit_digits && toklen == lit_len;
         skip_n!(bs, toklen);
 
-        Ok(Bite(val))
+        Ok(Bite((val, is_int)))
     }
 
     // // This implementation is beautiful and correct, but it is slow due to the fact that I am first parsing everything,
@@ -589,43 +1489,92 @@ impl Parser {
             Some(b) => match b {
                 b'+' => {
                     skip!(bs);
-                    let v = self.read_value(slab, bs, depth + 1)?;
-                    Ok(Bite(EPos(slab.push_val(v)?)))
+                    self.check_repeated_unary(bs, "+")?;
+                    Ok(Bite(EPos(self.read_unary_operand(slab, bs, depth + 1)?)))
                 }
                 b'-' => {
                     skip!(bs);
-                    let v = self.read_value(slab, bs, depth + 1)?;
-                    Ok(Bite(ENeg(slab.push_val(v)?)))
+                    self.check_repeated_unary(bs, "-")?;
+                    Ok(Bite(ENeg(self.read_unary_operand(slab, bs, depth + 1)?)))
                 }
                 b'(' => {
                     skip!(bs);
                     let xi = self.read_expression(slab, bs, depth + 1, false)?;
-                    spaces!(bs);
-                    if read!(bs, "parentheses")? != b')' {
-                        return Err(Error::Expected(String::from(")")));
-                    }
+                    self.read_close(bs, b')', "parentheses")?;
                     Ok(Bite(EParentheses(xi)))
                 }
-                b'[' => {
+                b'[' if !self.reject_square_brackets => {
                     skip!(bs);
                     let xi = self.read_expression(slab, bs, depth + 1, false)?;
-                    spaces!(bs);
-                    if read!(bs, "square brackets")? != b']' {
-                        return Err(Error::Expected(String::from("]")));
-                    }
+                    self.read_close(bs, b']', "square brackets")?;
+                    Ok(Bite(EParentheses(xi)))
+                }
+                b'{' => {
+                    skip!(bs);
+                    let xi = self.read_expression(slab, bs, depth + 1, false)?;
+                    self.read_close(bs, b'}', "curly braces")?;
                     Ok(Bite(EParentheses(xi)))
                 }
                 b'!' => {
                     skip!(bs);
-                    let v = self.read_value(slab, bs, depth + 1)?;
-                    Ok(Bite(ENot(slab.push_val(v)?)))
+                    self.check_repeated_unary(bs, "!")?;
+                    Ok(Bite(ENot(self.read_unary_operand(slab, bs, depth + 1)?)))
+                }
+                #[cfg(feature = "alpha-keywords")]
+                b'n' if Self::peek_keyword(bs, b"not") => {
+                    skip_n!(bs, 3);
+                    self.check_repeated_unary(bs, "not")?;
+                    Ok(Bite(ENot(self.read_unary_operand(slab, bs, depth + 1)?)))
                 }
                 _ => Ok(Pass),
             },
         }
     }
 
-    fn read_binaryop(bs: &mut &[u8]) -> Result<Token<BinaryOp>, Error> {
+    /// Checks whether `bs` starts with the word `kw`, rather than with a
+    /// longer variable name like `note`.
+    #[cfg(feature = "alpha-keywords")]
+    fn peek_keyword(bs: &[u8], kw: &[u8]) -> bool {
+        bs.starts_with(kw) && !Self::is_varname_byte_opt(bs.get(kw.len()).copied(), kw.len())
+    }
+
+    /// With `reject_repeated_unary`, fails if the unary operator `op` (which
+    /// was just read) is followed by another unary operator.
+    fn check_repeated_unary(&self, bs: &mut &[u8], op: &str) -> Result<(), Error> {
+        if self.reject_repeated_unary {
+            spaces!(bs);
+            let next = match peek!(bs) {
+                Some(b'+') => "+",
+                Some(b'-') => "-",
+                Some(b'!') => "!",
+                #[cfg(feature = "alpha-keywords")]
+                Some(b'n') if Self::peek_keyword(bs, b"not") => " not",
+                _ => return Ok(()),
+            };
+            let sep = if op == "not" && !next.starts_with(' ') {
+                " "
+            } else {
+                ""
+            };
+            return Err(Error::UnexpectedOperator(format!("{op}{sep}{next}")));
+        }
+        Ok(())
+    }
+
+    /// Reads the `close` byte that ends a group.  With `auto_close`, the end of
+    /// the input closes the group too.
+    fn read_close(&self, bs: &mut &[u8], close: u8, parsing: &str) -> Result<(), Error> {
+        spaces!(bs);
+        if self.auto_close && bs.is_empty() {
+            return Ok(());
+        }
+        if read!(bs, parsing)? != close {
+            return Err(Error::Expected(String::from(close as char)));
+        }
+        Ok(())
+    }
+
+    fn read_binaryop(&self, bs: &mut &[u8]) -> Result<Token<BinaryOp>, Error> {
         spaces!(bs);
         peek!(bs).map_or(Ok(Pass), |b| match b {
             b'+' => {
@@ -674,6 +1623,10 @@ impl Parser {
                 skip_n!(bs, 2);
                 Ok(Bite(EEQ))
             }
+            b'=' if self.single_equals => {
+                skip!(bs);
+                Ok(Bite(EEQ))
+            }
             b'!' if peek_is!(bs, 1, b'=') => {
                 skip_n!(bs, 2);
                 Ok(Bite(ENE))
@@ -696,22 +1649,55 @@ impl Parser {
                 skip_n!(bs, 2);
                 Ok(Bite(EAND))
             }
+            #[cfg(feature = "alpha-keywords")]
+            b'x' if peek_is!(bs, 1, b'o') && peek_is!(bs, 2, b'r') => {
+                skip_n!(bs, 3);
+                Ok(Bite(EXOR))
+            }
+            #[cfg(feature = "unicode-ops")]
+            b'\xc3' | b'\xe2' => Ok(Self::read_unicode_binaryop(bs)),
             _ => Ok(Pass),
         })
     }
 
+    /// Reads one of the typographic operators enabled by the `unicode-ops` feature.
+    /// These are all multi-byte UTF-8 sequences, so they can never be confused
+    /// with ASCII operators or variable names.
+    #[cfg(feature = "unicode-ops")]
+    fn read_unicode_binaryop(bs: &mut &[u8]) -> Token<BinaryOp> {
+        let (op, len) = match (peek_n!(bs, 0), peek_n!(bs, 1), peek_n!(bs, 2)) {
+            (Some(b'\xc3'), Some(b'\x97'), _) => (EMul, 2), // '×' U+00D7
+            (Some(b'\xc3'), Some(b'\xb7'), _) => (EDiv, 2), // '÷' U+00F7
+            (Some(b'\xe2'), Some(b'\x88'), Some(b'\x92')) => (ESub, 3), // '−' U+2212
+            (Some(b'\xe2'), Some(b'\x89'), Some(b'\xa0')) => (ENE, 3), // '≠' U+2260
+            (Some(b'\xe2'), Some(b'\x89'), Some(b'\xa4')) => (ELTE, 3), // '≤' U+2264
+            (Some(b'\xe2'), Some(b'\x89'), Some(b'\xa5')) => (EGTE, 3), // '≥' U+2265
+            _ => return Pass,
+        };
+        skip_n!(bs, len);
+        Bite(op)
+    }
+
     fn read_callable(
         &self,
         slab: &mut ParseSlab,
         bs: &mut &[u8],
         depth: usize,
     ) -> Result<Token<Value>, Error> {
-        match Self::read_varname(bs)? {
+        match self.read_varname(bs)? {
             Pass => Ok(Pass),
             Bite(varname) => {
-                match Self::read_open_parenthesis(bs)? {
+                match self.read_open_parenthesis(bs)? {
                     Pass => {
-                        // VarNames without Parenthesis are always treated as custom 0-arg functions.
+                        if let Some(&c) = self.constants.get(&varname) {
+                            return Ok(Bite(EConstant(c)));
+                        }
+                        // Other VarNames without Parenthesis are always treated as custom 0-arg functions.
+                        if self.reject_variables {
+                            return Err(Error::VariablesNotAllowed);
+                        }
+                        self.check_arity(&varname, 0)?;
+                        self.check_var_limit(slab, &varname)?;
 
                         #[cfg(feature = "unsafe-vars")]
                         match slab.unsafe_vars.get(&varname) {
@@ -725,12 +1711,15 @@ impl Parser {
                     Bite(open_parenth) => {
                         // VarNames with Parenthesis are first matched against builtins, then custom.
                         match varname.as_ref() {
+                            #[cfg(feature = "print-builtin")]
                             "print" => Ok(Bite(EPrintFunc(self.read_printfunc(
                                 slab,
                                 bs,
                                 depth,
                                 open_parenth,
                             )?))),
+                            #[cfg(not(feature = "print-builtin"))]
+                            "print" => Err(Error::DisabledFunction(varname)),
                             _ => Ok(Bite(EStdFunc(self.read_func(
                                 varname,
                                 slab,
@@ -745,11 +1734,18 @@ impl Parser {
         }
     }
 
-    fn read_varname(bs: &mut &[u8]) -> Result<Token<String>, Error> {
+    fn read_varname(&self, bs: &mut &[u8]) -> Result<Token<String>, Error> {
         spaces!(bs);
 
         let mut toklen = 0;
-        while Self::is_varname_byte_opt(peek_n!(bs, toklen), toklen) {
+        while let Some(b) = peek_n!(bs, toklen) {
+            if !Self::is_varname_byte(b, toklen, self.dotted_names) {
+                break;
+            }
+            // A '.' must start another part of the name:
+            if b == b'.' && !Self::is_varname_byte_opt(peek_n!(bs, toklen + 1), 0) {
+                break;
+            }
             toklen += 1;
         }
 
@@ -762,11 +1758,12 @@ impl Parser {
         Ok(Bite(out))
     }
 
-    fn read_open_parenthesis(bs: &mut &[u8]) -> Result<Token<u8>, Error> {
+    fn read_open_parenthesis(&self, bs: &mut &[u8]) -> Result<Token<u8>, Error> {
         spaces!(bs);
 
         match peek!(bs) {
-            Some(b'(' | b'[') => Ok(Bite(match read!(bs) {
+            Some(b'[') if self.reject_square_brackets => Ok(Pass),
+            Some(b'(' | b'[' | b'{') => Ok(Bite(match read!(bs) {
                 Ok(b) => b,
                 Err(..) => return Err(Error::Unreachable),
             })),
@@ -786,7 +1783,8 @@ impl Parser {
         let close_parenth = match open_parenth {
             b'(' => b')',
             b'[' => b']',
-            _ => return Err(Error::Expected(String::from("'(' or '['"))),
+            b'{' => b'}',
+            _ => return Err(Error::Expected(String::from("'(', '[' or '{'"))),
         };
         let mut args = Vec::<ExpressionI>::with_capacity(4);
         loop {
@@ -797,7 +1795,11 @@ impl Parser {
                         skip!(bs);
                         break;
                     }
+                    if matches!(b, b')' | b']' | b'}') {
+                        return Err(Error::Expected(String::from(close_parenth as char)));
+                    }
                 }
+                None if self.auto_close => break,
                 None => return Err(Error::EofWhileParsing(fname)),
             }
             if !args.is_empty() {
@@ -807,6 +1809,10 @@ impl Parser {
                     _ => return Err(Error::Expected(String::from("',' or ';'"))),
                 }
             }
+            if let Bite((lo, hi)) = Self::read_range(bs)? {
+                self.push_range(slab, &mut args, lo, hi)?;
+                continue;
+            }
             args.push(self.read_expression(slab, bs, depth + 1, false)?);
         }
 
@@ -852,14 +1858,46 @@ impl Parser {
                     Err(Error::WrongArgs(String::from("abs: expected one arg")))
                 }
             }
-            "sign" => {
+            "sqrt" => {
                 if args.len() == 1 {
-                    Ok(EFuncSign(match args.pop() {
+                    Ok(EFuncSqrt(match args.pop() {
                         Some(xi) => xi,
                         None => return Err(Error::Unreachable),
                     }))
                 } else {
-                    Err(Error::WrongArgs(String::from("sign: expected one arg")))
+                    Err(Error::WrongArgs(String::from("sqrt: expected one arg")))
+                }
+            }
+            "cbrt" => {
+                if args.len() == 1 {
+                    Ok(EFuncCbrt(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from("cbrt: expected one arg")))
+                }
+            }
+            "sign" => {
+                if args.len() == 1 {
+                    Ok(EFuncSign(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from("sign: expected one arg")))
+                }
+            }
+            "probround" => {
+                if args.len() == 1 {
+                    Ok(EFuncProbRound(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "probround: expected one arg",
+                    )))
                 }
             }
             "log" => {
@@ -888,30 +1926,36 @@ impl Parser {
                     )))
                 }
             }
-            "round" => {
-                if args.len() == 1 {
-                    Ok(EFuncRound {
-                        modulus: None,
-                        expr: match args.pop() {
+            "round" | "rounda" | "roundz" | "round_half_up" => {
+                let (modulus, expr) = if args.len() == 1 {
+                    (
+                        None,
+                        match args.pop() {
                             Some(xi) => xi,
                             None => return Err(Error::Unreachable),
                         },
-                    })
+                    )
                 } else if args.len() == 2 {
                     let Some(expr) = args.pop() else {
                         return Err(Error::Unreachable);
                     };
-                    Ok(EFuncRound {
-                        modulus: Some(match args.pop() {
+                    (
+                        Some(match args.pop() {
                             Some(xi) => xi,
                             None => return Err(Error::Unreachable),
                         }),
                         expr,
-                    })
+                    )
                 } else {
-                    Err(Error::WrongArgs(String::from(
-                        "round: expected round(x) or round(modulus,x)",
-                    )))
+                    return Err(Error::WrongArgs(format!(
+                        "{fname}: expected {fname}(x) or {fname}(modulus,x)"
+                    )));
+                };
+                match fname_str {
+                    // 'round' already rounds ties away from zero:
+                    "round" | "rounda" => Ok(EFuncRound { modulus, expr }),
+                    "roundz" => Ok(EFuncRoundZ { modulus, expr }),
+                    _ => Ok(EFuncRoundHalfUp { modulus, expr }),
                 }
             }
             "min" => {
@@ -936,6 +1980,199 @@ impl Parser {
                     })
                 }
             }
+            "min_first" | "max_first" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(format!(
+                        "{fname}: expected one or more args"
+                    )))
+                } else {
+                    remove_no_panic(&mut args, 0).map_or(Err(Error::Unreachable), |first| {
+                        if fname == "min_first" {
+                            Ok(EFuncMinFirst { first, rest: args })
+                        } else {
+                            Ok(EFuncMaxFirst { first, rest: args })
+                        }
+                    })
+                }
+            }
+            "nanmin" | "nanmax" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(format!(
+                        "{fname}: expected one or more args"
+                    )))
+                } else {
+                    remove_no_panic(&mut args, 0).map_or(Err(Error::Unreachable), |first| {
+                        if fname == "nanmin" {
+                            Ok(EFuncNanMin { first, rest: args })
+                        } else {
+                            Ok(EFuncNanMax { first, rest: args })
+                        }
+                    })
+                }
+            }
+            "prod" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "prod: expected one or more args",
+                    )))
+                } else {
+                    remove_no_panic(&mut args, 0).map_or(Err(Error::Unreachable), |first| {
+                        Ok(EFuncProd { first, rest: args })
+                    })
+                }
+            }
+            // 'minz' and 'maxz' also accept zero args, returning the identity element:
+            "minz" | "maxz" => {
+                let first = if args.is_empty() {
+                    let identity = if fname == "minz" {
+                        Num::INFINITY
+                    } else {
+                        Num::NEG_INFINITY
+                    };
+                    slab.push_expr(Expression {
+                        first: EConstant(identity),
+                        pairs: Vec::new(),
+                    })?
+                } else {
+                    remove_no_panic(&mut args, 0).ok_or(Error::Unreachable)?
+                };
+                if fname == "minz" {
+                    Ok(EFuncMin { first, rest: args })
+                } else {
+                    Ok(EFuncMax { first, rest: args })
+                }
+            }
+            "avg" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "avg: expected one or more args",
+                    )))
+                } else {
+                    Ok(EFuncAvg(args))
+                }
+            }
+            "sum" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "sum: expected one or more args",
+                    )))
+                } else {
+                    Ok(EFuncSum(args))
+                }
+            }
+            "blend" => {
+                if !args.is_empty() && args.len().is_multiple_of(2) {
+                    Ok(EFuncBlend(args))
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "blend: expected value,weight pairs",
+                    )))
+                }
+            }
+            "norm" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "norm: expected one or more args",
+                    )))
+                } else {
+                    Ok(EFuncNorm(args))
+                }
+            }
+            "seq" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "seq: expected one or more args",
+                    )))
+                } else {
+                    Ok(EFuncSeq(args))
+                }
+            }
+            "sumsq" => {
+                if args.is_empty() {
+                    Err(Error::WrongArgs(String::from(
+                        "sumsq: expected one or more args",
+                    )))
+                } else {
+                    Ok(EFuncSumSq(args))
+                }
+            }
+            "poly" => {
+                if args.len() >= 2 {
+                    Ok(EFuncPoly(args))
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "poly: expected poly(x, c0, c1, ...)",
+                    )))
+                }
+            }
+            "bucket" => {
+                if args.len() == 4 {
+                    let (Some(n), Some(hi), Some(lo), Some(x)) =
+                        (args.pop(), args.pop(), args.pop(), args.pop())
+                    else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncBucket { x, lo, hi, n })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "bucket: expected bucket(x,lo,hi,n)",
+                    )))
+                }
+            }
+            "powmod" => {
+                if args.len() == 3 {
+                    let (Some(modulus), Some(exp), Some(base)) =
+                        (args.pop(), args.pop(), args.pop())
+                    else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncPowMod { base, exp, modulus })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "powmod: expected powmod(base,exp,mod)",
+                    )))
+                }
+            }
+            "smoothstep" => {
+                if args.len() == 3 {
+                    let (Some(x), Some(edge1), Some(edge0)) = (args.pop(), args.pop(), args.pop())
+                    else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncSmoothstep { edge0, edge1, x })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "smoothstep: expected smoothstep(edge0,edge1,x)",
+                    )))
+                }
+            }
+            "clamp" => {
+                if args.len() == 3 {
+                    let (Some(hi), Some(lo), Some(val)) = (args.pop(), args.pop(), args.pop())
+                    else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncClamp { val, lo, hi })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "clamp: expected clamp(val,lo,hi)",
+                    )))
+                }
+            }
+            "select" => {
+                if args.len() >= 2 {
+                    remove_no_panic(&mut args, 0).map_or(Err(Error::Unreachable), |index| {
+                        Ok(EFuncSelect {
+                            index,
+                            options: args,
+                        })
+                    })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "select: expected select(index,option0,...)",
+                    )))
+                }
+            }
 
             "e" => {
                 if args.is_empty() {
@@ -1012,6 +2249,58 @@ impl Parser {
                     Err(Error::WrongArgs(String::from("atan: expected one arg")))
                 }
             }
+            "asind" => {
+                if args.len() == 1 {
+                    Ok(EFuncASinD(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from("asind: expected one arg")))
+                }
+            }
+            "acosd" => {
+                if args.len() == 1 {
+                    Ok(EFuncACosD(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from("acosd: expected one arg")))
+                }
+            }
+            "atand" => {
+                if args.len() == 1 {
+                    Ok(EFuncATanD(match args.pop() {
+                        Some(xi) => xi,
+                        None => return Err(Error::Unreachable),
+                    }))
+                } else {
+                    Err(Error::WrongArgs(String::from("atand: expected one arg")))
+                }
+            }
+            "atan2" => {
+                if args.len() == 2 {
+                    let (Some(x), Some(y)) = (args.pop(), args.pop()) else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncATan2 { y, x })
+                } else {
+                    Err(Error::WrongArgs(String::from("atan2: expected atan2(y,x)")))
+                }
+            }
+            "atan2d" => {
+                if args.len() == 2 {
+                    let (Some(x), Some(y)) = (args.pop(), args.pop()) else {
+                        return Err(Error::Unreachable);
+                    };
+                    Ok(EFuncATan2D { y, x })
+                } else {
+                    Err(Error::WrongArgs(String::from(
+                        "atan2d: expected atan2d(y,x)",
+                    )))
+                }
+            }
             "sinh" => {
                 if args.len() == 1 {
                     Ok(EFuncSinH(match args.pop() {
@@ -1074,6 +2363,12 @@ impl Parser {
             }
 
             _ => {
+                if self.reject_variables {
+                    return Err(Error::VariablesNotAllowed);
+                }
+                self.check_arity(fname_str, args.len())?;
+                self.check_var_limit(slab, fname_str)?;
+
                 #[cfg(feature = "unsafe-vars")]
                 match slab.unsafe_vars.get(fname_str) {
                     None => Ok(EFunc { name: fname, args }),
@@ -1086,6 +2381,79 @@ impl Parser {
         }
     }
 
+    /// Reads an integer range function argument, like `1..5`.
+    ///
+    /// Nothing is consumed unless the whole argument is a range.
+    fn read_range(bs: &mut &[u8]) -> Result<Token<(i64, i64)>, Error> {
+        fn int_len(bs: &[u8]) -> usize {
+            let sign = usize::from(bs.first() == Some(&b'-'));
+            let digits = bs
+                .iter()
+                .skip(sign)
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            if digits == 0 {
+                0
+            } else {
+                sign + digits
+            }
+        }
+        fn parse_int(bs: &[u8]) -> Result<i64, Error> {
+            let s =
+                from_utf8(bs).map_err(|_| Error::Utf8ErrorWhileParsing(String::from("range")))?;
+            s.parse::<i64>().map_err(|_| Error::ParseF32(s.to_owned()))
+        }
+
+        spaces!(bs);
+        let lo_len = int_len(bs);
+        if lo_len == 0 || bs.get(lo_len..lo_len + 2) != Some(b"..") {
+            return Ok(Pass);
+        }
+        let hi_len = bs.get(lo_len + 2..).map_or(0, int_len);
+        if hi_len == 0 {
+            return Ok(Pass);
+        }
+        let end = lo_len + 2 + hi_len;
+        let mut toklen = end;
+        while peek_n!(bs, toklen).is_some_and(|b| is_space!(b)) {
+            toklen += 1;
+        }
+        if !matches!(peek_n!(bs, toklen), Some(b',' | b';' | b')' | b']' | b'}')) {
+            return Ok(Pass);
+        }
+
+        let lo = parse_int(&bs[..lo_len])?;
+        let hi = parse_int(&bs[lo_len + 2..end])?;
+        skip_n!(bs, end);
+        Ok(Bite((lo, hi)))
+    }
+
+    /// Expands an inclusive integer range into constant function arguments.
+    fn push_range(
+        &self,
+        slab: &mut ParseSlab,
+        args: &mut Vec<ExpressionI>,
+        lo: i64,
+        hi: i64,
+    ) -> Result<(), Error> {
+        if lo.abs_diff(hi) >= self.range_len_limit as u64 {
+            return Err(Error::TooLong);
+        }
+        let step = if lo <= hi { 1 } else { -1 };
+        let mut i = lo;
+        loop {
+            args.push(slab.push_expr(Expression {
+                first: EConstant(i as Num),
+                pairs: Vec::new(),
+            })?);
+            if i == hi {
+                return Ok(());
+            }
+            i += step;
+        }
+    }
+
+    #[cfg(feature = "print-builtin")]
     fn read_printfunc(
         &self,
         slab: &mut ParseSlab,
@@ -1096,7 +2464,8 @@ impl Parser {
         let close_parenth = match open_parenth {
             b'(' => b')',
             b'[' => b']',
-            _ => return Err(Error::Expected(String::from("'(' or '['"))),
+            b'{' => b'}',
+            _ => return Err(Error::Expected(String::from("'(', '[' or '{'"))),
         };
         let mut args = Vec::<ExpressionOrString>::with_capacity(8);
         loop {
@@ -1107,7 +2476,11 @@ impl Parser {
                         skip!(bs);
                         break;
                     }
+                    if matches!(b, b')' | b']' | b'}') {
+                        return Err(Error::Expected(String::from(close_parenth as char)));
+                    }
                 }
+                None if self.auto_close => break,
                 None => {
                     return Err(Error::EofWhileParsing(String::from("print")));
                 }
@@ -1126,6 +2499,7 @@ impl Parser {
         Ok(PrintFunc(args))
     }
 
+    #[cfg(feature = "print-builtin")]
     fn read_expressionorstring(
         &self,
         slab: &mut ParseSlab,
@@ -1140,6 +2514,7 @@ impl Parser {
     }
 
     // TODO: Improve this logic, especially to handle embedded quotes:
+    #[cfg(feature = "print-builtin")]
     fn read_string(bs: &mut &[u8]) -> Result<Token<String>, Error> {
         spaces!(bs);
 
@@ -1182,7 +2557,457 @@ impl Default for Parser {
 
 impl Default for Value {
     fn default() -> Self {
-        EConstant(std::f32::NAN)
+        EConstant(Num::NAN)
+    }
+}
+
+impl Expression {
+    /// Returns every function call in this `Expression`, including custom
+    /// functions and calls nested inside arguments.
+    ///
+    /// Calls are listed in the order that they appear in the expression string,
+    /// with outer calls before the calls inside their arguments.  Variables
+    /// (names used without parentheses) are not calls; see
+    /// [`var_names()`](../evaler/trait.Evaler.html#method.var_names) for those.
+    ///
+    /// This is useful for static analysis, like finding all `log` calls so that
+    /// their arguments can be checked.
+    ///
+    /// Note: `minz` and `maxz` are reported as `min` and `max`.
+    pub fn calls(&self, slab: &Slab) -> Vec<CallSite> {
+        let mut dst = Vec::new();
+        self.collect_calls(slab, &mut dst);
+        dst
+    }
+
+    fn collect_calls(&self, slab: &Slab, dst: &mut Vec<CallSite>) {
+        self.first.collect_calls(slab, dst);
+        for pair in &self.pairs {
+            pair.1.collect_calls(slab, dst);
+        }
+    }
+
+    fn collect_warnings(&self, ps: &ParseSlab, dst: &mut Vec<Warning>) {
+        self.first.collect_warnings(ps, dst);
+        for pair in &self.pairs {
+            pair.1.collect_warnings(ps, dst);
+        }
+    }
+
+    /// Returns a JSON representation of this parsed `Expression`, for tools
+    /// (like a formula editor) that want to render the AST as a tree.
+    ///
+    /// Sub-expressions are resolved from the slab and nested inline.  Operator
+    /// precedence is not applied: like the AST itself, an `Expression` is a
+    /// `first` value followed by a flat list of `(op, value)` pairs.  Node kinds
+    /// are `"constant"`, `"var"`, `"unary"`, `"parentheses"`, `"call"` and
+    /// `"string"` (a `print()` string argument).  Non-finite constants are
+    /// written as the strings `"inf"`, `"-inf"` and `"NaN"`.
+    ///
+    /// ```
+    /// use fasteval3::{Parser, Slab};
+    ///
+    /// let mut slab = Slab::new();
+    /// let expr_i = Parser::new().parse("-x * 2", &mut slab.ps).unwrap();
+    /// assert_eq!(
+    ///     slab.ps.get_expr(expr_i).to_json(&slab),
+    ///     r#"{"first":{"kind":"unary","op":"-","operand":{"kind":"var","name":"x"}},"pairs":[{"op":"*","value":{"kind":"constant","value":2}}]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self, slab: &Slab) -> String {
+        let mut dst = String::new();
+        self.write_json(slab, &mut dst);
+        dst
+    }
+
+    fn write_json(&self, slab: &Slab, dst: &mut String) {
+        dst.push_str("{\"first\":");
+        self.first.write_json(slab, dst);
+        dst.push_str(",\"pairs\":[");
+        for (i, ExprPair(op, val)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                dst.push(',');
+            }
+            dst.push_str("{\"op\":");
+            write_json_str(op.symbol(), dst);
+            dst.push_str(",\"value\":");
+            val.write_json(slab, dst);
+            dst.push('}');
+        }
+        dst.push_str("]}");
+    }
+}
+
+// Writes 's' as a quoted JSON string:
+fn write_json_str(s: &str, dst: &mut String) {
+    dst.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => dst.push_str("\\\""),
+            '\\' => dst.push_str("\\\\"),
+            '\n' => dst.push_str("\\n"),
+            '\r' => dst.push_str("\\r"),
+            '\t' => dst.push_str("\\t"),
+            c if c < ' ' => dst.push_str(&format!("\\u{:04x}", c as u32)),
+            c => dst.push(c),
+        }
+    }
+    dst.push('"');
+}
+
+impl BinaryOp {
+    /// Returns the operator as it is written in an expression, like `"+"` or `"&&"`.
+    const fn symbol(self) -> &'static str {
+        match self {
+            EOR => "||",
+            EXOR => "xor",
+            EAND => "&&",
+            ENE => "!=",
+            EEQ => "==",
+            EGTE => ">=",
+            ELTE => "<=",
+            EGT => ">",
+            ELT => "<",
+            EAdd => "+",
+            ESub => "-",
+            EMul => "*",
+            EDiv => "/",
+            EMod => "%",
+            EExp => "^",
+        }
+    }
+}
+
+impl Value {
+    fn write_json(&self, slab: &Slab, dst: &mut String) {
+        match self {
+            EConstant(c) => {
+                dst.push_str("{\"kind\":\"constant\",\"value\":");
+                if c.is_finite() {
+                    dst.push_str(&c.to_string());
+                } else {
+                    write_json_str(&c.to_string(), dst);
+                }
+                dst.push('}');
+            }
+            EUnaryOp(unary @ (EPos(vi) | ENeg(vi) | ENot(vi))) => {
+                let op = match unary {
+                    EPos(_) => "+",
+                    ENeg(_) => "-",
+                    _ => "!",
+                };
+                dst.push_str("{\"kind\":\"unary\",\"op\":");
+                write_json_str(op, dst);
+                dst.push_str(",\"operand\":");
+                slab.ps.get_val(*vi).write_json(slab, dst);
+                dst.push('}');
+            }
+            EUnaryOp(EParentheses(xi)) => {
+                dst.push_str("{\"kind\":\"parentheses\",\"expr\":");
+                slab.ps.get_expr(*xi).write_json(slab, dst);
+                dst.push('}');
+            }
+            #[cfg(feature = "unsafe-vars")]
+            EStdFunc(EUnsafeVar { name, .. }) => {
+                dst.push_str("{\"kind\":\"var\",\"name\":");
+                write_json_str(name, dst);
+                dst.push('}');
+            }
+            EStdFunc(EVar(name)) => {
+                dst.push_str("{\"kind\":\"var\",\"name\":");
+                write_json_str(name, dst);
+                dst.push('}');
+            }
+            EStdFunc(f) => {
+                let name = f.call_name().unwrap_or_default();
+                dst.push_str("{\"kind\":\"call\",\"name\":");
+                write_json_str(name, dst);
+                dst.push_str(",\"args\":[");
+                for (i, xi) in f.args().into_iter().enumerate() {
+                    if i > 0 {
+                        dst.push(',');
+                    }
+                    slab.ps.get_expr(xi).write_json(slab, dst);
+                }
+                dst.push_str("]}");
+            }
+            #[cfg(feature = "print-builtin")]
+            EPrintFunc(pf) => {
+                dst.push_str("{\"kind\":\"call\",\"name\":\"print\",\"args\":[");
+                for (i, x_or_s) in pf.0.iter().enumerate() {
+                    if i > 0 {
+                        dst.push(',');
+                    }
+                    match x_or_s {
+                        EExpr(xi) => slab.ps.get_expr(*xi).write_json(slab, dst),
+                        EStr(s) => {
+                            dst.push_str("{\"kind\":\"string\",\"value\":");
+                            write_json_str(s, dst);
+                            dst.push('}');
+                        }
+                    }
+                }
+                dst.push_str("]}");
+            }
+        }
+    }
+
+    fn collect_calls(&self, slab: &Slab, dst: &mut Vec<CallSite>) {
+        match self {
+            EConstant(_) => (),
+            EUnaryOp(EPos(vi) | ENeg(vi) | ENot(vi)) => {
+                slab.ps.get_val(*vi).collect_calls(slab, dst);
+            }
+            EUnaryOp(EParentheses(xi)) => slab.ps.get_expr(*xi).collect_calls(slab, dst),
+            EStdFunc(f) => {
+                let Some(name) = f.call_name() else {
+                    return;
+                };
+                let args = f.args();
+                dst.push(CallSite {
+                    name: String::from(name),
+                    args: args.clone(),
+                });
+                for xi in args {
+                    slab.ps.get_expr(xi).collect_calls(slab, dst);
+                }
+            }
+            #[cfg(feature = "print-builtin")]
+            EPrintFunc(pf) => {
+                let args: Vec<ExpressionI> =
+                    pf.0.iter()
+                        .filter_map(|x_or_s| match x_or_s {
+                            EExpr(xi) => Some(*xi),
+                            EStr(_) => None,
+                        })
+                        .collect();
+                dst.push(CallSite {
+                    name: String::from("print"),
+                    args: args.clone(),
+                });
+                for xi in args {
+                    slab.ps.get_expr(xi).collect_calls(slab, dst);
+                }
+            }
+        }
+    }
+
+    fn collect_warnings(&self, ps: &ParseSlab, dst: &mut Vec<Warning>) {
+        match self {
+            EConstant(c) => {
+                if c.is_finite() && c.abs() >= LARGE_LITERAL_LIMIT {
+                    dst.push(Warning::LargeLiteral(*c));
+                }
+            }
+            EUnaryOp(EPos(vi) | ENeg(vi) | ENot(vi)) => ps.get_val(*vi).collect_warnings(ps, dst),
+            EUnaryOp(EParentheses(xi)) => ps.get_expr(*xi).collect_warnings(ps, dst),
+            EStdFunc(EVar(name)) => {
+                if BUILTIN_FUNCS.contains(&name.as_str()) {
+                    dst.push(Warning::ShadowsBuiltin(name.clone()));
+                }
+            }
+            EStdFunc(f) => {
+                for xi in f.args() {
+                    ps.get_expr(xi).collect_warnings(ps, dst);
+                }
+            }
+            #[cfg(feature = "print-builtin")]
+            EPrintFunc(pf) => {
+                for x_or_s in &pf.0 {
+                    if let EExpr(xi) = x_or_s {
+                        ps.get_expr(*xi).collect_warnings(ps, dst);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl StdFunc {
+    /// Returns the name that this function is called by, or `None` for variables.
+    pub(crate) fn call_name(&self) -> Option<&str> {
+        Some(match self {
+            #[cfg(feature = "unsafe-vars")]
+            EUnsafeVar { .. } => return None,
+            EVar(_) => return None,
+            EFunc { name, .. } => name,
+            EFuncInt(_) => "int",
+            EFuncCeil(_) => "ceil",
+            EFuncFloor(_) => "floor",
+            EFuncAbs(_) => "abs",
+            EFuncSign(_) => "sign",
+            EFuncSqrt(_) => "sqrt",
+            EFuncCbrt(_) => "cbrt",
+            EFuncProbRound(_) => "probround",
+            EFuncLog { .. } => "log",
+            EFuncRound { .. } => "round",
+            EFuncRoundHalfUp { .. } => "round_half_up",
+            EFuncRoundZ { .. } => "roundz",
+            EFuncMin { .. } => "min",
+            EFuncMax { .. } => "max",
+            EFuncMinFirst { .. } => "min_first",
+            EFuncMaxFirst { .. } => "max_first",
+            EFuncNanMin { .. } => "nanmin",
+            EFuncNanMax { .. } => "nanmax",
+            EFuncProd { .. } => "prod",
+            EFuncBlend(_) => "blend",
+            EFuncAvg(_) => "avg",
+            EFuncSum(_) => "sum",
+            EFuncNorm(_) => "norm",
+            EFuncSumSq(_) => "sumsq",
+            EFuncPoly(_) => "poly",
+            EFuncSeq(_) => "seq",
+            EFuncBucket { .. } => "bucket",
+            EFuncPowMod { .. } => "powmod",
+            EFuncSmoothstep { .. } => "smoothstep",
+            EFuncClamp { .. } => "clamp",
+            EFuncSelect { .. } => "select",
+            EFuncE => "e",
+            EFuncPi => "pi",
+            EFuncSin(_) => "sin",
+            EFuncCos(_) => "cos",
+            EFuncTan(_) => "tan",
+            EFuncASin(_) => "asin",
+            EFuncACos(_) => "acos",
+            EFuncATan(_) => "atan",
+            EFuncASinD(_) => "asind",
+            EFuncACosD(_) => "acosd",
+            EFuncATanD(_) => "atand",
+            EFuncATan2 { .. } => "atan2",
+            EFuncATan2D { .. } => "atan2d",
+            EFuncSinH(_) => "sinh",
+            EFuncCosH(_) => "cosh",
+            EFuncTanH(_) => "tanh",
+            EFuncASinH(_) => "asinh",
+            EFuncACosH(_) => "acosh",
+            EFuncATanH(_) => "atanh",
+        })
+    }
+
+    /// Returns the argument `Expression`s of this function call, in order.
+    pub(crate) fn args(&self) -> Vec<ExpressionI> {
+        match self {
+            #[cfg(feature = "unsafe-vars")]
+            EUnsafeVar { .. } => Vec::new(),
+            EVar(_) | EFuncE | EFuncPi => Vec::new(),
+
+            EFunc { args: xis, .. }
+            | EFuncBlend(xis)
+            | EFuncAvg(xis)
+            | EFuncSum(xis)
+            | EFuncNorm(xis)
+            | EFuncSumSq(xis)
+            | EFuncPoly(xis)
+            | EFuncSeq(xis) => xis.clone(),
+
+            EFuncInt(xi) | EFuncCeil(xi) | EFuncFloor(xi) | EFuncAbs(xi) | EFuncSqrt(xi)
+            | EFuncCbrt(xi) | EFuncSign(xi) | EFuncProbRound(xi) | EFuncSin(xi) | EFuncCos(xi)
+            | EFuncTan(xi) | EFuncASin(xi) | EFuncACos(xi) | EFuncATan(xi) | EFuncASinD(xi)
+            | EFuncACosD(xi) | EFuncATanD(xi) | EFuncSinH(xi) | EFuncCosH(xi) | EFuncTanH(xi)
+            | EFuncASinH(xi) | EFuncACosH(xi) | EFuncATanH(xi) => {
+                vec![*xi]
+            }
+            EFuncATan2 { y, x } | EFuncATan2D { y, x } => vec![*y, *x],
+
+            EFuncLog { base: opt, expr }
+            | EFuncRound { modulus: opt, expr }
+            | EFuncRoundHalfUp { modulus: opt, expr }
+            | EFuncRoundZ { modulus: opt, expr } => opt.iter().chain(Some(expr)).copied().collect(),
+            EFuncMin { first, rest }
+            | EFuncMax { first, rest }
+            | EFuncMinFirst { first, rest }
+            | EFuncMaxFirst { first, rest }
+            | EFuncNanMin { first, rest }
+            | EFuncNanMax { first, rest }
+            | EFuncProd { first, rest } => Some(first).into_iter().chain(rest).copied().collect(),
+            EFuncBucket { x, lo, hi, n } => vec![*x, *lo, *hi, *n],
+            EFuncPowMod { base, exp, modulus } => vec![*base, *exp, *modulus],
+            EFuncSmoothstep { edge0, edge1, x } => vec![*edge0, *edge1, *x],
+            EFuncClamp { val, lo, hi } => vec![*val, *lo, *hi],
+            EFuncSelect { index, options } => {
+                Some(index).into_iter().chain(options).copied().collect()
+            }
+        }
+    }
+
+    /// Like `args()`, but returns mutable references so the arguments can be replaced.
+    pub(crate) fn args_mut(&mut self) -> Vec<&mut ExpressionI> {
+        match self {
+            #[cfg(feature = "unsafe-vars")]
+            EUnsafeVar { .. } => Vec::new(),
+            EVar(_) | EFuncE | EFuncPi => Vec::new(),
+
+            EFunc { args: xis, .. }
+            | EFuncBlend(xis)
+            | EFuncAvg(xis)
+            | EFuncSum(xis)
+            | EFuncNorm(xis)
+            | EFuncSumSq(xis)
+            | EFuncPoly(xis)
+            | EFuncSeq(xis) => xis.iter_mut().collect(),
+
+            EFuncInt(xi) | EFuncCeil(xi) | EFuncFloor(xi) | EFuncAbs(xi) | EFuncSqrt(xi)
+            | EFuncCbrt(xi) | EFuncSign(xi) | EFuncProbRound(xi) | EFuncSin(xi) | EFuncCos(xi)
+            | EFuncTan(xi) | EFuncASin(xi) | EFuncACos(xi) | EFuncATan(xi) | EFuncASinD(xi)
+            | EFuncACosD(xi) | EFuncATanD(xi) | EFuncSinH(xi) | EFuncCosH(xi) | EFuncTanH(xi)
+            | EFuncASinH(xi) | EFuncACosH(xi) | EFuncATanH(xi) => {
+                vec![xi]
+            }
+            EFuncATan2 { y, x } | EFuncATan2D { y, x } => vec![y, x],
+
+            EFuncLog { base: opt, expr }
+            | EFuncRound { modulus: opt, expr }
+            | EFuncRoundHalfUp { modulus: opt, expr }
+            | EFuncRoundZ { modulus: opt, expr } => opt.iter_mut().chain(Some(expr)).collect(),
+            EFuncMin { first, rest }
+            | EFuncMax { first, rest }
+            | EFuncMinFirst { first, rest }
+            | EFuncMaxFirst { first, rest }
+            | EFuncNanMin { first, rest }
+            | EFuncNanMax { first, rest }
+            | EFuncProd { first, rest } => Some(first).into_iter().chain(rest).collect(),
+            EFuncBucket { x, lo, hi, n } => vec![x, lo, hi, n],
+            EFuncPowMod { base, exp, modulus } => vec![base, exp, modulus],
+            EFuncSmoothstep { edge0, edge1, x } => vec![edge0, edge1, x],
+            EFuncClamp { val, lo, hi } => vec![val, lo, hi],
+            EFuncSelect { index, options } => Some(index).into_iter().chain(options).collect(),
+        }
+    }
+}
+
+/// Checks a custom function call with `nargs` args against its `expected`
+/// arity, if it has one.
+pub(crate) fn check_arity(name: &str, expected: Option<usize>, nargs: usize) -> Result<(), Error> {
+    match expected {
+        Some(n) if n != nargs => Err(Error::WrongArgs(format!(
+            "{name}: expected {n} arg{}",
+            if n == 1 { "" } else { "s" }
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Explains why a number literal (without its suffixes) failed to parse:
+fn malformed_number(lit: &str) -> Option<&'static str> {
+    let (mantissa, exp) = match lit.find(['e', 'E']) {
+        Some(i) => (&lit[..i], Some(&lit[i + 1..])),
+        None => (lit, None),
+    };
+    if !mantissa.bytes().any(|b| b.is_ascii_digit()) {
+        return Some("no digits");
+    }
+    if mantissa.matches('.').count() > 1 {
+        return Some("more than one decimal point");
+    }
+    let digits = exp?.trim_start_matches(['+', '-']);
+    if digits.contains(['e', 'E']) {
+        Some("more than one exponent")
+    } else if digits.contains('.') {
+        Some("exponent must be an integer")
+    } else if digits.is_empty() {
+        Some("missing exponent digits")
+    } else {
+        None
     }
 }
 
@@ -1295,7 +3120,7 @@ mod internal_tests {
             let bs = &mut &bsarr[..];
             assert_eq!(
                 Parser::new().read_value(&mut slab.ps, bs, 0),
-                Ok(EConstant(12.34))
+                Ok((EConstant(12.34), false))
             );
         }
     }
@@ -51,19 +51,32 @@
 //! }
 //! ```
 
+#[cfg(feature = "print-builtin")]
+use crate::compiler::Instruction::IPrintFunc;
+#[cfg(feature = "unsafe-vars")]
+use crate::compiler::Instruction::IUnsafeVar;
 use crate::compiler::{
-    Instruction::{self, IConst},
-    InstructionI,
+    Instruction::{self, IConst, IFunc, IVar},
+    InstructionI, IC,
 };
 use crate::error::Error;
-use crate::parser::{Expression, ExpressionI, Value, ValueI};
+use crate::evaler::Evaler;
+use crate::evalns::EmptyNamespace;
+use crate::parser::{
+    BinaryOp, Expression, ExpressionI,
+    UnaryOp::{ENeg, ENot, EParentheses, EPos},
+    Value, ValueI, ValueSite,
+};
+#[cfg(feature = "print-builtin")]
+use crate::parser::{ExpressionOrString, PrintFunc};
+use crate::Num;
 
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::mem;
 
-#[cfg(feature = "unsafe-vars")]
-use std::collections::BTreeMap;
-
 // Eliminate function call overhead:
 macro_rules! get_expr {
     ($pslab:expr, $i_ref:ident) => {
@@ -120,6 +133,128 @@ impl ValueI {
 pub struct Slab {
     pub ps: ParseSlab,
     pub cs: CompileSlab,
+    /// If `true`, evaluation is stricter about problems that are normally
+    /// silent.  For example, `sinh()` and `cosh()` return
+    /// [`Error::Overflow`](../error/enum.Error.html#variant.Overflow) instead of
+    /// `inf` when a finite input overflows, `int()`, `ceil()`, `floor()` and
+    /// the `round()` functions return
+    /// [`Error::NonFinite`](../error/enum.Error.html#variant.NonFinite) instead
+    /// of a NaN or infinite result, and `select()` returns
+    /// [`Error::WrongArgs`](../error/enum.Error.html#variant.WrongArgs) instead
+    /// of NaN for an out-of-range index.  Off by default.
+    pub strict: bool,
+    /// How `min()` and `max()` treat NaN arguments.  Defaults to
+    /// [`NanPolicy::Propagate`](enum.NanPolicy.html#variant.Propagate).
+    pub nan_policy: NanPolicy,
+    /// Custom implementations of binary operators, used by `Expression::eval()`
+    /// instead of the built-in ones.  `None` (the default) uses the built-in
+    /// behavior for every operator.  See [`OpDispatch`](struct.OpDispatch.html).
+    pub op_dispatch: Option<OpDispatch>,
+    /// What `print()` evaluates to.  Defaults to
+    /// [`PrintReturn::LastValue`](enum.PrintReturn.html#variant.LastValue).
+    pub print_return: PrintReturn,
+}
+
+/// A table of functions that override how `Expression::eval()` evaluates
+/// specific `BinaryOp`s, like a saturating `+`.  Install it in
+/// [`Slab.op_dispatch`](struct.Slab.html#structfield.op_dispatch).
+///
+/// Operators without an override keep their built-in behavior.  Overriding
+/// `and` or `or` disables their short-circuiting, so both operands are always
+/// evaluated and passed to the override.
+///
+/// Only the parse-tree evaluator consults this table.  Compiled `Instruction`s
+/// (including constants folded at compile time) always use the built-in
+/// operators.
+///
+/// # Examples
+///
+/// ```
+/// use fasteval3::parser::BinaryOp;
+/// use fasteval3::{Evaler, OpDispatch, Parser, Slab};
+///
+/// let mut slab = Slab::new();
+/// let mut dispatch = OpDispatch::new();
+/// dispatch.set(BinaryOp::EAdd, |a, b| (a + b).min(100.0));
+/// slab.op_dispatch = Some(dispatch);
+///
+/// let expr_i = Parser::new().parse("70 + 50", &mut slab.ps).unwrap();
+/// let val = expr_i.from(&slab.ps).eval(&slab, &mut fasteval3::EmptyNamespace);
+/// assert_eq!(val, Ok(100.0));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpDispatch {
+    fns: [Option<BinaryFn>; 15],
+}
+
+/// A function that evaluates a binary operator.
+type BinaryFn = fn(Num, Num) -> Num;
+
+impl OpDispatch {
+    /// Creates a table without any overrides.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { fns: [None; 15] }
+    }
+
+    /// Makes `op` evaluate with `f(left, right)`, replacing any previous override.
+    #[inline]
+    pub fn set(&mut self, op: BinaryOp, f: fn(Num, Num) -> Num) {
+        if let Some(slot) = self.fns.get_mut(op as usize - 1) {
+            *slot = Some(f);
+        }
+    }
+
+    /// Removes the override of `op`, restoring its built-in behavior.
+    #[inline]
+    pub fn unset(&mut self, op: BinaryOp) {
+        if let Some(slot) = self.fns.get_mut(op as usize - 1) {
+            *slot = None;
+        }
+    }
+
+    /// Returns the override of `op`, if any.
+    #[inline]
+    pub fn get(&self, op: BinaryOp) -> Option<fn(Num, Num) -> Num> {
+        self.fns.get(op as usize - 1).copied().flatten()
+    }
+}
+
+/// Selects how `min()` and `max()` (and `minz()`/`maxz()`) treat NaN arguments.
+///
+/// The policy is applied each time two arguments are compared, so a
+/// single-argument call like `min(x)` returns `x` unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanPolicy {
+    /// Any NaN argument makes the result NaN: `min(1, NaN) == NaN`.
+    #[default]
+    Propagate,
+    /// NaN arguments are skipped: `min(1, NaN) == 1`.  The result is only NaN
+    /// if every argument is NaN.
+    ///
+    /// Because nothing can change the result after that, `min()` stops
+    /// evaluating its arguments once one of them is `-inf`, and `max()` once
+    /// one is `inf`.  This saves calls of expensive custom functions.
+    Ignore,
+    /// Any NaN argument returns an
+    /// [`Error::NanArgument`](../error/enum.Error.html#variant.NanArgument).
+    Error,
+}
+
+/// Selects what `print()` evaluates to, after printing its arguments.
+///
+/// Returning `NaN` or `0` makes `print()` a pure side effect, so formulas that
+/// accidentally depend on its value give obviously-wrong results in testing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrintReturn {
+    /// The value of the last expression argument, or `0` if there is none:
+    /// `print("x", 2) == 2`.
+    #[default]
+    LastValue,
+    /// Always `NaN`.
+    NaN,
+    /// Always `0`.
+    Zero,
 }
 
 /// `ParseSlab` is where `parse()` results are stored, located at `Slab.ps`.
@@ -135,7 +270,7 @@ pub struct Slab {
 /// Here is the function signature of the `add_unsafe_var()` method:
 ///
 /// ```text
-/// pub unsafe fn add_unsafe_var(&mut self, name: String, ptr: &f32)
+/// pub unsafe fn add_unsafe_var(&mut self, name: String, ptr: &Num)
 /// ```
 ///
 /// If you are using [Unsafe Variables](../index.html#unsafe-variables), you
@@ -164,11 +299,12 @@ pub struct Slab {
 /// ```
 /// use fasteval3::Evaler;    // use this trait so we can call eval().
 /// use fasteval3::Compiler;  // use this trait so we can call compile().
+/// use fasteval3::Num;
 ///
 /// // Here is an example of INCORRECT registration.  DO NOT DO THIS!
 /// #[cfg(feature = "unsafe-vars")]
 /// fn bad_unsafe_var(slab_mut:&mut fasteval3::Slab) {
-///     let bad : f32 = 0.0;
+///     let bad : Num = 0.0;
 ///
 ///     // Saves a pointer to 'bad':
 ///     unsafe { slab_mut.ps.add_unsafe_var("bad".to_string(), &bad); }  // `add_unsafe_var()` only exists if the `unsafe-vars` feature is enabled: `cargo test --features unsafe-vars`
@@ -187,7 +323,7 @@ pub struct Slab {
 ///     // The Unsafe Variable will use a pointer to read this memory location:
 ///     // You must make sure that this variable stays in-scope as long as the
 ///     // expression is in-use.
-///     let mut deg : f32 = 0.0;
+///     let mut deg : Num = 0.0;
 ///
 ///     // Unsafe Variables must be registered before 'parse()'.
 ///     // (Normal Variables only need definitions during the 'eval' phase.)
@@ -204,7 +340,7 @@ pub struct Slab {
 ///     let compiled = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
 ///
 ///     for d in 0..360 {
-///         deg = d as f32;
+///         deg = d as Num;
 ///         let val = fasteval3::eval_compiled!(compiled, &slab, &mut ns);
 ///         eprintln!("sin({}°) = {}", deg, val);
 ///     }
@@ -219,14 +355,112 @@ pub struct ParseSlab {
     pub(crate) def_expr: Expression,
     pub(crate) def_val: Value,
     pub(crate) char_buf: String,
+    pub(crate) int_literals: BTreeSet<ValueSite>,
+    /// Address of the first byte of the input that is being parsed, so that
+    /// errors can report byte offsets.
+    pub(crate) input_start: usize,
+    /// Distinct variable and custom function names seen so far in the
+    /// expression that is being parsed, for `Parser.var_limit`.
+    pub(crate) seen_names: BTreeSet<String>,
     #[cfg(feature = "unsafe-vars")]
-    pub(crate) unsafe_vars: BTreeMap<String, *const f32>,
+    pub(crate) unsafe_vars: BTreeMap<String, *const Num>,
 }
 
 /// `CompileSlab` is where `compile()` results are stored, located at `Slab.cs`.
+///
+/// # Instruction Interning
+///
+/// When many expressions are compiled into the same `CompileSlab`, identical
+/// sub-trees (like `IVar("x")`) are normally stored once per occurrence.  If
+/// you call [`set_interning(true)`](#method.set_interning), `push_instr()`
+/// will instead return the index of an existing equal `Instruction`, so
+/// identical sub-trees share storage.
+///
+/// While interning is enabled, instructions are never removed from the slab
+/// (because they might be shared), so the compiler's flattening optimizations
+/// leave a few unused entries behind.  Interning is off by default.
 pub struct CompileSlab {
     pub(crate) instrs: Vec<Instruction>,
     pub(crate) def_instr: Instruction,
+    pub(crate) interned: Option<HashMap<InternKey, InstructionI>>,
+    pub(crate) patched: BTreeMap<String, Vec<InstructionI>>,
+    pub(crate) folded: Vec<(InstructionI, Instruction)>,
+}
+
+/// An `Instruction` used as an interning key.
+///
+/// Unlike `Instruction`'s `PartialEq`, constants are compared by their bits, so
+/// `-0.0` and `0.0` are kept apart and `NaN` is equal to itself.
+pub(crate) struct InternKey(Instruction);
+
+impl InternKey {
+    /// Returns `true` if the data that is not an operand (like a constant or a
+    /// variable name) is the same.  The variants must match.
+    fn same_data(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (IConst(a), IConst(b)) => a.to_bits() == b.to_bits(),
+            (IVar(a), IVar(b)) | (IFunc { name: a, .. }, IFunc { name: b, .. }) => a == b,
+            #[cfg(feature = "unsafe-vars")]
+            (
+                IUnsafeVar {
+                    name: a,
+                    ptr: a_ptr,
+                },
+                IUnsafeVar {
+                    name: b,
+                    ptr: b_ptr,
+                },
+            ) => a == b && a_ptr == b_ptr,
+            #[cfg(feature = "print-builtin")]
+            (IPrintFunc(a), IPrintFunc(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+/// Returns an operand in a form that can be compared and hashed.
+fn operand_bits(ic: &IC) -> (bool, u64) {
+    match ic {
+        IC::I(InstructionI(i)) => (false, *i as u64),
+        IC::C(c) => (true, u64::from(c.to_bits())),
+    }
+}
+
+impl PartialEq for InternKey {
+    fn eq(&self, other: &Self) -> bool {
+        if mem::discriminant(&self.0) != mem::discriminant(&other.0) || !self.same_data(other) {
+            return false;
+        }
+        let mut operands = Vec::new();
+        self.0.all_operands(|ic| {
+            operands.push(operand_bits(ic));
+            true
+        });
+        let mut operands = operands.into_iter();
+        other
+            .0
+            .all_operands(|ic| operands.next() == Some(operand_bits(ic)))
+            && operands.next().is_none()
+    }
+}
+
+impl Eq for InternKey {}
+
+impl Hash for InternKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(&self.0).hash(state);
+        match &self.0 {
+            IConst(c) => c.to_bits().hash(state),
+            IVar(name) | IFunc { name, .. } => name.hash(state),
+            #[cfg(feature = "unsafe-vars")]
+            IUnsafeVar { name, ptr } => (name, ptr).hash(state),
+            _ => (),
+        }
+        self.0.all_operands(|ic| {
+            operand_bits(ic).hash(state);
+            true
+        });
+    }
 }
 
 impl ParseSlab {
@@ -287,18 +521,39 @@ impl ParseSlab {
         Ok(ValueI(i))
     }
 
-    /// Clears all data from `ParseSlab.exprs` and `ParseSlab.vals`.
+    /// Returns the byte offset of `bs`, which must be a sub-slice of the input
+    /// that is being parsed.
+    #[inline]
+    pub(crate) fn offset_of(&self, bs: &[u8]) -> usize {
+        (bs.as_ptr() as usize).wrapping_sub(self.input_start)
+    }
+
+    /// Clears all data from `ParseSlab.exprs` and `ParseSlab.vals`, and forgets
+    /// which of them were integer literals.
     #[inline]
     pub fn clear(&mut self) {
         self.exprs.clear();
         self.vals.clear();
+        self.int_literals.clear();
+    }
+
+    /// Returns `true` if the constant `Value` at `site` was written as an
+    /// integer literal, like `3` or `-42`.
+    ///
+    /// Literals with a decimal point, an exponent or a suffix (like `3.0`,
+    /// `3e0` or `3k`) are not integer literals, and neither are `NaN`, `inf`
+    /// or constants created by the parser.  This doesn't affect evaluation,
+    /// which always uses `Num`; it lets a language layer enforce integer-only
+    /// contexts.
+    pub fn is_int_literal(&self, site: ValueSite) -> bool {
+        self.int_literals.contains(&site)
     }
 
     /// [See the `add_unsafe_var()` documentation above.](#unsafe-variable-registration-with-add_unsafe_var)
     #[cfg(feature = "unsafe-vars")]
     #[allow(clippy::trivially_copy_pass_by_ref)]
-    pub unsafe fn add_unsafe_var(&mut self, name: String, ptr: &f32) {
-        self.unsafe_vars.insert(name, ptr as *const f32);
+    pub unsafe fn add_unsafe_var(&mut self, name: String, ptr: &Num) {
+        self.unsafe_vars.insert(name, ptr as *const Num);
     }
 }
 
@@ -316,34 +571,93 @@ impl CompileSlab {
     }
 
     /// Appends an `Instruction` to `CompileSlab.instrs`.
+    ///
+    /// Unlike `push_expr()` and `push_val()`, this never returns `SlabOverflow`:
+    /// `compile()` is infallible, so `CompileSlab.instrs` grows as needed instead
+    /// of being bounded by `Slab::with_capacity()`.
+    ///
+    /// If interning is enabled and an equal `Instruction` is already stored,
+    /// the index of the existing `Instruction` is returned instead.
     pub(crate) fn push_instr(&mut self, instr: Instruction) -> InstructionI {
         if self.instrs.capacity() == 0 {
             self.instrs.reserve(32);
         }
         let i = self.instrs.len();
-        self.instrs.push(instr);
+        if let Some(interned) = self.interned.as_mut() {
+            match interned.entry(InternKey(instr)) {
+                Entry::Occupied(entry) => return *entry.get(),
+                Entry::Vacant(entry) => {
+                    self.instrs.push(entry.key().0.clone());
+                    entry.insert(InstructionI(i));
+                }
+            }
+        } else {
+            self.instrs.push(instr);
+        }
         InstructionI(i)
     }
 
     /// Removes an `Instruction` from `CompileSlab.instrs` as efficiently as possible.
+    ///
+    /// If interning is enabled, the `Instruction` might be shared, so a copy is
+    /// returned and the original is left in place.
     pub(crate) fn take_instr(&mut self, i: InstructionI) -> Instruction {
+        if self.interned.is_some() {
+            return self.get_instr(i).clone();
+        }
         if i.0 == self.instrs.len() - 1 {
-            self.instrs
-                .pop()
-                .map_or(IConst(std::f32::NAN), |instr| instr)
+            self.instrs.pop().map_or(IConst(Num::NAN), |instr| instr)
         } else {
             self.instrs
                 .get_mut(i.0)
-                .map_or(IConst(std::f32::NAN), |instr_ref| {
-                    mem::replace(instr_ref, IConst(std::f32::NAN))
+                .map_or(IConst(Num::NAN), |instr_ref| {
+                    mem::replace(instr_ref, IConst(Num::NAN))
                 })
         }
     }
 
+    /// Removes the `Instruction`s from index `len` onward.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.instrs.truncate(len);
+        if let Some(interned) = self.interned.as_mut() {
+            interned.retain(|_, instr_i| instr_i.0 < len);
+        }
+    }
+
     /// Clears all data from `CompileSlab.instrs`.
     #[inline]
     pub fn clear(&mut self) {
         self.instrs.clear();
+        self.patched.clear();
+        self.folded.clear();
+        if let Some(interned) = self.interned.as_mut() {
+            interned.clear();
+        }
+    }
+
+    /// Enables or disables `Instruction` interning.
+    ///
+    /// [See the `CompileSlab` documentation.](#instruction-interning)
+    pub fn set_interning(&mut self, enabled: bool) {
+        if !enabled {
+            self.interned = None;
+            return;
+        }
+        if self.interned.is_none() {
+            let mut interned = HashMap::new();
+            for (i, instr) in self.instrs.iter().enumerate() {
+                interned
+                    .entry(InternKey(instr.clone()))
+                    .or_insert(InstructionI(i));
+            }
+            self.interned = Some(interned);
+        }
+    }
+
+    /// Returns `true` if `Instruction` interning is enabled.
+    #[inline]
+    pub const fn is_interning(&self) -> bool {
+        self.interned.is_some()
     }
 }
 
@@ -364,25 +678,219 @@ impl Slab {
                 def_expr: Expression::default(),
                 def_val: Value::default(),
                 char_buf: String::with_capacity(64),
+                int_literals: BTreeSet::new(),
+                input_start: 0,
+                seen_names: BTreeSet::new(),
                 #[cfg(feature = "unsafe-vars")]
                 unsafe_vars: BTreeMap::new(),
             },
             cs: CompileSlab {
                 instrs: Vec::new(), // Don't pre-allocate for compilation.
                 def_instr: Instruction::default(),
+                interned: None,
+                patched: BTreeMap::new(),
+                folded: Vec::new(),
             },
+            strict: false,
+            nan_policy: NanPolicy::Propagate,
+            op_dispatch: None,
+            print_return: PrintReturn::LastValue,
+        }
+    }
+
+    /// Assembles a `Slab` from a `ParseSlab` and a `CompileSlab` that were
+    /// loaded separately, for example by deserialization code.  The other
+    /// settings (like `strict`) get their defaults.
+    ///
+    /// The parts are validated first: every `ExpressionI` and `ValueI` must be
+    /// within `ps`, and every `InstructionI` in `cs` must refer to an earlier
+    /// instruction, because the compiler always stores operands before the
+    /// instructions that use them.  (This also rules out cycles.)  The root
+    /// `Instruction` returned by `compile()` is not part of `cs`, so it is not
+    /// checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSlab`](../error/enum.Error.html#variant.InvalidSlab)
+    /// if an index is out of bounds.
+    pub fn from_parts(ps: ParseSlab, cs: CompileSlab) -> Result<Self, Error> {
+        for (i, expr) in ps.exprs.iter().enumerate() {
+            if !Some(&expr.first)
+                .into_iter()
+                .chain(expr.pairs.iter().map(|pair| &pair.1))
+                .all(|val| val_in_bounds(val, &ps))
+            {
+                return Err(Error::InvalidSlab(format!("ps.exprs[{i}]")));
+            }
+        }
+        for (i, val) in ps.vals.iter().enumerate() {
+            if !val_in_bounds(val, &ps) {
+                return Err(Error::InvalidSlab(format!("ps.vals[{i}]")));
+            }
+        }
+
+        for (i, instr) in cs.instrs.iter().enumerate() {
+            let ok = instr.all_operands(|ic| match ic {
+                IC::C(_) => true,
+                IC::I(ii) => ii.0 < i,
+            });
+            #[cfg(feature = "print-builtin")]
+            let ok = ok && !matches!(instr, IPrintFunc(pf) if !print_in_bounds(pf, &ps));
+            if !ok {
+                return Err(Error::InvalidSlab(format!("cs.instrs[{i}]")));
+            }
+        }
+        let len = cs.instrs.len();
+        if cs.patched.values().flatten().any(|ii| ii.0 >= len)
+            || cs.folded.iter().any(|(ii, _)| ii.0 >= len)
+            || cs
+                .interned
+                .iter()
+                .flat_map(|m| m.values())
+                .any(|ii| ii.0 >= len)
+        {
+            return Err(Error::InvalidSlab(String::from("cs")));
+        }
+
+        Ok(Self {
+            ps,
+            cs,
+            strict: false,
+            nan_policy: NanPolicy::Propagate,
+            op_dispatch: None,
+            print_return: PrintReturn::LastValue,
+        })
+    }
+
+    /// Patches the variable `name` to the constant `val` in [`Slab.cs`](struct.CompileSlab.html),
+    /// then re-folds the instructions that now only depend on constants.
+    ///
+    /// This is a lighter alternative to re-compiling an expression when one
+    /// parameter changes (for example, in an interactive tuning UI).  The same
+    /// name can be patched again later, and several names can be patched.
+    ///
+    /// Returns the number of variable instructions that now refer to `name`'s
+    /// constant.  If this is `0`, `name` does not appear in `Slab.cs`.
+    ///
+    /// # Limitations
+    ///
+    /// Compared to a full re-compile:
+    ///
+    /// * The root `Instruction` returned by `compile()` is not stored in the
+    ///   slab, so it is never patched or folded itself.  (It still reads the
+    ///   patched constants through its operands.)  In particular, if the whole
+    ///   expression is just the variable, nothing changes.
+    /// * Folding is local: an instruction only becomes a constant once all of
+    ///   its operands are constants.  The compiler's algebraic simplifications
+    ///   are not re-applied, and the slab does not shrink.
+    /// * Unsafe Variables and custom functions with arguments are not patched.
+    ///   Neither are the arguments of `print()`, which are evaluated from the
+    ///   parse AST.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fasteval3::{Compiler, Evaler, Num};
+    /// fn main() -> Result<(), fasteval3::Error> {
+    ///     let mut slab = fasteval3::Slab::new();
+    ///     let mut ns = fasteval3::StrTof32Namespace::new();
+    ///     ns.insert("x", 1.0);
+    ///     let expr_i = fasteval3::Parser::new().parse("sin(x) + 2*x", &mut slab.ps)?;
+    ///     let compiled = slab.ps.get_expr(expr_i).compile(&slab.ps, &mut slab.cs, &mut ns);
+    ///
+    ///     slab.patch_const("x", 0.0);
+    ///     assert_eq!(compiled.eval(&slab, &mut fasteval3::EmptyNamespace)?, 0.0);
+    ///     slab.patch_const("x", 2.0);
+    ///     assert_eq!(compiled.eval(&slab, &mut fasteval3::EmptyNamespace)?, Num::sin(2.0) + 4.0);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn patch_const(&mut self, name: &str, val: Num) -> usize {
+        // Undo the previous foldings, so that every patched constant gets re-folded:
+        while let Some((i, instr)) = self.cs.folded.pop() {
+            if let Some(instr_ref) = self.cs.instrs.get_mut(i.0) {
+                *instr_ref = instr;
+            }
+        }
+
+        let slots = self.cs.patched.entry(name.to_owned()).or_default();
+        for (i, instr) in self.cs.instrs.iter().enumerate() {
+            if matches!(instr, IVar(s) if s == name) {
+                slots.push(InstructionI(i));
+            }
+        }
+        for i in slots.iter() {
+            if let Some(instr_ref) = self.cs.instrs.get_mut(i.0) {
+                *instr_ref = IConst(val);
+            }
         }
+        let count = slots.len();
+
+        // Operands are always stored before the instructions that use them,
+        // so a single forward pass folds entire sub-trees:
+        for i in 0..self.cs.instrs.len() {
+            let instr_i = InstructionI(i);
+            let instr = self.cs.get_instr(instr_i);
+            if !instr.has_const_operands(&self.cs) {
+                continue;
+            }
+            let Ok(folded) = instr.eval(self, &mut EmptyNamespace) else {
+                continue;
+            };
+            if let Some(instr_ref) = self.cs.instrs.get_mut(i) {
+                let orig = mem::replace(instr_ref, IConst(folded));
+                self.cs.folded.push((instr_i, orig));
+            }
+        }
+
+        if self.cs.is_interning() {
+            // The interned keys are stale now:
+            self.cs.set_interning(false);
+            self.cs.set_interning(true);
+        }
+        count
+    }
+
+    /// Returns the number of `Expression`s or `Value`s that can still be stored
+    /// in [`Slab.ps`](struct.ParseSlab.html) before `parse()` returns
+    /// [`SlabOverflow`](../error/enum.Error.html#variant.SlabOverflow).
+    ///
+    /// `Slab.cs` is not bounded, so it does not affect the result.
+    #[inline]
+    pub fn remaining_capacity(&self) -> usize {
+        let exprs = self.ps.exprs.capacity() - self.ps.exprs.len();
+        let vals = self.ps.vals.capacity() - self.ps.vals.len();
+        exprs.min(vals)
     }
 
     /// Clears all data from [`Slab.ps`](struct.ParseSlab.html) and [`Slab.cs`](struct.CompileSlab.html).
     #[inline]
     pub fn clear(&mut self) {
-        self.ps.exprs.clear();
-        self.ps.vals.clear();
-        self.cs.instrs.clear();
+        self.ps.clear();
+        self.cs.clear();
+    }
+}
+
+// Checks that the ExpressionIs and ValueIs that 'val' refers to are within 'ps':
+fn val_in_bounds(val: &Value, ps: &ParseSlab) -> bool {
+    match val {
+        Value::EConstant(_) => true,
+        Value::EUnaryOp(EPos(vi) | ENeg(vi) | ENot(vi)) => vi.0 < ps.vals.len(),
+        Value::EUnaryOp(EParentheses(xi)) => xi.0 < ps.exprs.len(),
+        Value::EStdFunc(f) => f.args().iter().all(|xi| xi.0 < ps.exprs.len()),
+        #[cfg(feature = "print-builtin")]
+        Value::EPrintFunc(pf) => print_in_bounds(pf, ps),
     }
 }
 
+#[cfg(feature = "print-builtin")]
+fn print_in_bounds(pf: &PrintFunc, ps: &ParseSlab) -> bool {
+    pf.0.iter().all(|x_or_s| match x_or_s {
+        ExpressionOrString::EExpr(xi) => xi.0 < ps.exprs.len(),
+        ExpressionOrString::EStr(_) => true,
+    })
+}
+
 fn write_indexed_list<T>(f: &mut fmt::Formatter, lst: &[T]) -> Result<(), fmt::Error>
 where
     T: fmt::Debug,
@@ -433,6 +941,133 @@ impl fmt::Debug for CompileSlab {
     }
 }
 
+/// Formats a value like `{:?}`, but with a fixed representation for every
+/// `Num` in the output, so snapshots of `Instruction`s or a `CompileSlab` stay
+/// the same across Rust versions.
+///
+/// Each float is written with the shortest digits that round-trip.  Values
+/// from `0.0001` up to (but not including) `1e16` are written positionally,
+/// always with a `.` (like `3.0` or `0.017453294`), and others in scientific
+/// notation (like `1e-5` or `-2.5e20`).  `NaN`, `inf` and `-inf` are written
+/// as-is.  Numbers inside string literals (like variable names) and integers
+/// (like `InstructionI` indexes) are left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use fasteval3::{Compiler, EmptyNamespace, Parser, Slab, StableDebug};
+///
+/// let mut slab = Slab::new();
+/// let expr_i = Parser::new().parse("x * 0.125 + 2.5e-5", &mut slab.ps).unwrap();
+/// let instr = expr_i.from(&slab.ps).compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+/// assert_eq!(
+///     StableDebug(&instr).to_string(),
+///     "IAdd(InstructionI(1), C(2.5e-5))"
+/// );
+/// assert_eq!(
+///     StableDebug(&slab.cs).to_string(),
+///     "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IMul(InstructionI(0), C(0.125)) } }"
+/// );
+/// ```
+pub struct StableDebug<'a, T: ?Sized>(pub &'a T);
+
+impl<T: fmt::Debug + ?Sized> fmt::Display for StableDebug<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let dbg = format!("{:?}", self.0);
+        let bs = dbg.as_bytes();
+        let digits_end = |mut i: usize| {
+            while bs.get(i).is_some_and(|b| b.is_ascii_digit() || *b == b'.') {
+                i += 1;
+            }
+            i
+        };
+
+        let mut done = 0;
+        let mut i = 0;
+        let mut in_str = false;
+        while let Some(&b) = bs.get(i) {
+            if in_str {
+                match b {
+                    b'\\' => i += 1, // Skip the escaped byte.
+                    b'"' => in_str = false,
+                    _ => (),
+                }
+                i += 1;
+                continue;
+            }
+            let word_before = i > 0
+                && bs
+                    .get(i - 1)
+                    .is_some_and(|p| p.is_ascii_alphanumeric() || *p == b'_');
+            if b == b'"' {
+                in_str = true;
+                i += 1;
+            } else if b.is_ascii_digit() && !word_before {
+                let mut end = digits_end(i);
+                if bs.get(end) == Some(&b'e') {
+                    let exp_start = end + 1 + usize::from(bs.get(end + 1) == Some(&b'-'));
+                    if bs.get(exp_start).is_some_and(u8::is_ascii_digit) {
+                        end = digits_end(exp_start);
+                    }
+                }
+                let tok = dbg.get(i..end).unwrap_or("");
+                if tok.contains(['.', 'e']) {
+                    if let Ok(x) = tok.parse::<Num>() {
+                        f.write_str(dbg.get(done..i).unwrap_or(""))?;
+                        write_num_stable(f, x)?;
+                        done = end;
+                    }
+                }
+                i = end;
+            } else {
+                i += 1;
+            }
+        }
+        f.write_str(dbg.get(done..).unwrap_or(""))
+    }
+}
+
+/// Writes `x` with the shortest round-trip digits, like `{:?}` currently does.
+fn write_num_stable(f: &mut fmt::Formatter, x: Num) -> Result<(), fmt::Error> {
+    if x.is_nan() {
+        return f.write_str("NaN");
+    }
+    if x.is_sign_negative() {
+        f.write_str("-")?;
+    }
+    if x.is_infinite() {
+        return f.write_str("inf");
+    }
+    if x == 0.0 {
+        return f.write_str("0.0");
+    }
+
+    // `{:e}` gives the shortest round-trip digits, like "1.2345e3" or "5e-1":
+    let sci = format!("{:e}", x.abs());
+    let (mant, exp) = sci.split_once('e').ok_or(fmt::Error)?;
+    let exp: i32 = exp.parse().map_err(|_| fmt::Error)?;
+    if !(-4..16).contains(&exp) {
+        return write!(f, "{mant}e{exp}");
+    }
+    let digits = mant.replace('.', "");
+    if exp < 0 {
+        return write!(
+            f,
+            "0.{}{digits}",
+            "0".repeat(exp.unsigned_abs() as usize - 1)
+        );
+    }
+    let point = exp.unsigned_abs() as usize + 1;
+    match (digits.get(..point), digits.get(point..)) {
+        (Some(int), Some(frac)) if !frac.is_empty() => write!(f, "{int}.{frac}"),
+        _ => write!(
+            f,
+            "{digits}{}.0",
+            "0".repeat(point - digits.len().min(point))
+        ),
+    }
+}
+
 impl Default for Slab {
     fn default() -> Self {
         Self::with_capacity(64)
@@ -1,6 +1,49 @@
+use fasteval3::{Compiler, Error, EvalNamespace, Evaler, Num, Parser, Slab};
+
 #[inline]
+#[allow(dead_code)]
 /// # Panics
 /// Panics when values' error margin reaches the EPSILON threshold.
-pub(crate) fn assert_error_margin(value_one: f32, value_two: f32) {
-    assert!((value_one - value_two).abs() < f32::EPSILON);
-}
\ No newline at end of file
+pub(crate) fn assert_error_margin(value_one: Num, value_two: Num) {
+    assert!((value_one - value_two).abs() < Num::EPSILON);
+}
+
+/// Parses and evaluates `expr_str`, and returns the result of its compiled form.
+///
+/// # Panics
+/// Panics if the parsed `Expression`, the compiled `Instruction` and
+/// `Instruction::eval_iterative()` don't give the same result.  The results are
+/// compared with `{:?}`, so NaNs and errors must match too.
+#[allow(dead_code)]
+pub(crate) fn eval_both(
+    slab: &mut Slab,
+    ns: &mut impl EvalNamespace,
+    expr_str: &str,
+) -> Result<Num, Error> {
+    eval_both_with(&Parser::new(), slab, ns, expr_str)
+}
+
+/// Like `eval_both()`, but parses with `parser`.
+#[allow(dead_code)]
+pub(crate) fn eval_both_with(
+    parser: &Parser,
+    slab: &mut Slab,
+    ns: &mut impl EvalNamespace,
+    expr_str: &str,
+) -> Result<Num, Error> {
+    let expr_i = parser.parse(expr_str, &mut slab.ps)?;
+    let expr_ref = slab.ps.get_expr(expr_i);
+    let instr = expr_ref.compile(&slab.ps, &mut slab.cs, ns);
+    let compiled = instr.eval(slab, ns);
+    assert_eq!(
+        format!("{:?}", expr_ref.eval(slab, ns)),
+        format!("{compiled:?}"),
+        "{expr_str}"
+    );
+    assert_eq!(
+        format!("{:?}", instr.eval_iterative(slab, ns)),
+        format!("{compiled:?}"),
+        "iterative {expr_str}"
+    );
+    compiled
+}
@@ -1,26 +1,38 @@
+#![cfg_attr(feature = "f64", allow(unused_imports, dead_code))] // all_instrs() is f32-only.
+
 #[cfg(feature = "eval-builtin")]
 use fasteval3::compiler::Instruction::IEvalFunc;
+#[cfg(feature = "print-builtin")]
+use fasteval3::compiler::Instruction::IPrintFunc;
 use fasteval3::compiler::Instruction::{
-    self, IAdd, IConst, IExp, IFuncACos, IFuncACosH, IFuncASin, IFuncASinH, IFuncATan, IFuncATanH,
-    IFuncAbs, IFuncCeil, IFuncCos, IFuncCosH, IFuncFloor, IFuncInt, IFuncLog, IFuncMax, IFuncMin,
-    IFuncRound, IFuncSign, IFuncSin, IFuncSinH, IFuncTan, IFuncTanH, IInv, IMod, IMul, INeg, INot,
-    IPrintFunc, IVar, IAND, IEQ, IGT, IGTE, ILT, ILTE, INE, IOR,
+    self, IAdd, IConst, IExp, IFuncACos, IFuncACosD, IFuncACosH, IFuncASin, IFuncASinD, IFuncASinH,
+    IFuncATan, IFuncATan2, IFuncATan2D, IFuncATanD, IFuncATanH, IFuncAbs, IFuncAvg, IFuncBlend,
+    IFuncBucket, IFuncCbrt, IFuncCeil, IFuncClamp, IFuncCos, IFuncCosH, IFuncFloor, IFuncInt,
+    IFuncLog, IFuncMax, IFuncMaxFirst, IFuncMaxN, IFuncMin, IFuncMinFirst, IFuncMinN, IFuncNanMax,
+    IFuncNanMin, IFuncNorm, IFuncPoly, IFuncPowMod, IFuncProbRound, IFuncRound, IFuncRoundHalfUp,
+    IFuncRoundZ, IFuncSelect, IFuncSeq, IFuncSign, IFuncSin, IFuncSinH, IFuncSmoothstep, IFuncSqrt,
+    IFuncSum, IFuncSumSq, IFuncTan, IFuncTanH, IInv, IMod, IMul, INeg, INot, IVar, IAND, IEQ, IGT,
+    IGTE, ILT, ILTE, INE, IOR, IXOR,
 };
 use fasteval3::compiler::IC;
 #[cfg(feature = "eval-builtin")]
 use fasteval3::parser::{EvalFunc, KWArg};
+#[cfg(feature = "print-builtin")]
 use fasteval3::parser::{
     ExpressionOrString::{EExpr, EStr},
     PrintFunc,
 };
 use fasteval3::{
-    eval_compiled, eval_compiled_ref, CachedCallbackNamespace, Compiler, EmptyNamespace, Error,
-    Evaler, ExpressionI, InstructionI, Parser, Slab,
+    eval_compiled, eval_compiled_many, eval_compiled_ref, ArityNamespace, CachedCallbackNamespace,
+    Compiler, EmptyNamespace, Error, EvalNamespace, Evaler, ExpressionI, InstructionI, NanPolicy,
+    Num, Parser, Program, Slab, StableDebug, Warning,
 };
 
+use std::collections::BTreeMap;
+
 pub(crate) mod common;
 
-use common::assert_error_margin;
+use common::{assert_error_margin, eval_both};
 
 #[test]
 fn slab_overflow() {
@@ -52,6 +64,732 @@ fn slab_overflow() {
     );
 }
 
+#[test]
+fn slab_overflow_exprs() {
+    let mut slab = Slab::with_capacity(2);
+    assert_eq!(slab.remaining_capacity(), 2);
+    assert_eq!(Parser::new().parse("(1)", &mut slab.ps), Ok(ExpressionI(1)));
+    assert_eq!(slab.remaining_capacity(), 0);
+
+    assert_eq!(
+        Parser::new().parse("((1))", &mut slab.ps),
+        Err(Error::SlabOverflow)
+    );
+    slab.clear();
+    assert_eq!(slab.remaining_capacity(), 2);
+}
+
+#[test]
+fn slab_overflow_instrs() {
+    // Compilation is not bounded by the Slab capacity, so it never overflows:
+    let mut slab = Slab::with_capacity(1);
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(1.0),
+        "y" => Some(2.0),
+        _ => None,
+    });
+    let expr_i = Parser::new()
+        .parse("x*y + x*y + x*y + x*y", &mut slab.ps)
+        .unwrap();
+    assert_eq!(slab.remaining_capacity(), 0);
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\"), 2:IVar(\"x\"), 3:IVar(\"y\"), 4:IVar(\"x\"), 5:IVar(\"y\"), 6:IVar(\"x\"), 7:IVar(\"y\"), 8:IMul(InstructionI(0), I(InstructionI(1))), 9:IMul(InstructionI(2), I(InstructionI(3))), 10:IAdd(InstructionI(8), I(InstructionI(9))), 11:IMul(InstructionI(4), I(InstructionI(5))), 12:IAdd(InstructionI(10), I(InstructionI(11))), 13:IMul(InstructionI(6), I(InstructionI(7))) } }");
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(8.0));
+}
+
+#[test]
+fn patch_const() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(1.0),
+        "y" => Some(2.0),
+        _ => None,
+    });
+    let expr_i = Parser::new()
+        .parse("sin(x) * 2 + x*y", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IFuncSin(InstructionI(0)), 2:IVar(\"x\"), 3:IVar(\"y\"), 4:IMul(InstructionI(1), C(2.0)), 5:IMul(InstructionI(2), I(InstructionI(3))) } }");
+
+    assert_eq!(slab.patch_const("x", 0.0), 2);
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{ 0:IConst(0.0), 1:IConst(0.0), 2:IConst(0.0), 3:IVar(\"y\"), 4:IConst(0.0), 5:IMul(InstructionI(2), I(InstructionI(3))) } }");
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(0.0));
+
+    assert_eq!(slab.patch_const("y", 3.0), 1);
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{ 0:IConst(0.0), 1:IConst(0.0), 2:IConst(0.0), 3:IConst(3.0), 4:IConst(0.0), 5:IConst(0.0) } }");
+
+    // Re-patching undoes the previous folding:
+    assert_eq!(slab.patch_const("x", 2.0), 2);
+    assert_eq!(
+        instr.eval(&slab, &mut EmptyNamespace),
+        Ok(Num::sin(2.0) * 2.0 + 6.0)
+    );
+
+    assert_eq!(slab.patch_const("nope", 1.0), 0);
+
+    // Side effects are never folded away:
+    #[cfg(feature = "print-builtin")]
+    {
+        slab.clear();
+        let expr_i = Parser::new().parse("print(1) + x", &mut slab.ps).unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(slab.patch_const("x", 5.0), 1);
+        assert_eq!(
+            format!("{:?}", slab.cs),
+            "CompileSlab{ instrs:{ 0:IPrintFunc(PrintFunc([EExpr(ExpressionI(0))])), 1:IConst(5.0) } }"
+        );
+        assert_eq!(instr.eval(&slab, &mut ns), Ok(6.0));
+    }
+}
+
+#[test]
+fn program() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(1.0),
+        "y" => Some(2.0),
+        _ => None,
+    });
+    let parser = Parser::new();
+    let mut program = Program::default();
+    assert!(program.eval(&slab, &mut ns).unwrap().is_nan());
+
+    for stmt in ["x + 1", "y * 10"] {
+        let expr_i = parser.parse_noclear(stmt, &mut slab.ps).unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        program.push(instr, &mut slab.cs);
+    }
+    assert_eq!(program.0, vec![InstructionI(1), InstructionI(3)]);
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IAdd(InstructionI(0), C(1.0)), 2:IVar(\"y\"), 3:IMul(InstructionI(2), C(10.0)) } }");
+    assert_eq!(program.eval(&slab, &mut ns), Ok(20.0));
+    assert_eq!(
+        program.var_names(&slab).into_iter().collect::<Vec<_>>(),
+        vec!["x", "y"]
+    );
+
+    // An error stops the program:
+    let expr_i = parser.parse_noclear("z", &mut slab.ps).unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    let mut failing = Program::default();
+    failing.push(instr, &mut slab.cs);
+    failing.0.extend(&program.0);
+    assert_eq!(
+        failing.eval(&slab, &mut ns),
+        Err(Error::Undefined(String::from("z")))
+    );
+}
+
+#[test]
+fn from_parts() {
+    let mut ns = |name: &str, _args: Vec<Num>| match name {
+        "x" => Some(2.0),
+        _ => None,
+    };
+    let mut slab = Slab::new();
+    let instr = Parser::new()
+        .parse("sin(x) * (x + 1) + max(x, 3)", &mut slab.ps)
+        .unwrap()
+        .from(&slab.ps)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    let expect = instr.eval(&slab, &mut ns).unwrap();
+
+    let Slab { ps, cs, .. } = slab;
+    let slab = Slab::from_parts(ps, cs).unwrap();
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(expect));
+
+    // print() instructions refer to the parse slab, so mismatched parts are rejected:
+    #[cfg(feature = "print-builtin")]
+    {
+        let mut slab = Slab::new();
+        Parser::new()
+            .parse("print(1, x + 1) + 1", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        let mut other = Slab::new();
+        Parser::new().parse("1", &mut other.ps).unwrap();
+        assert_eq!(
+            Slab::from_parts(other.ps, slab.cs).err(),
+            Some(Error::InvalidSlab(String::from("cs.instrs[0]")))
+        );
+    }
+}
+
+#[test]
+fn compile_checked() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut compile = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps)?;
+        slab.ps
+            .get_expr(expr_i)
+            .compile_checked(&slab.ps, &mut slab.cs, &mut ns)
+    };
+
+    assert_eq!(compile("1/0"), Err(Error::DivideByZero));
+    assert_eq!(compile("5 % 0"), Err(Error::DivideByZero));
+    assert_eq!(compile("x / 0.0"), Err(Error::DivideByZero));
+    assert_eq!(compile("x / -0"), Err(Error::DivideByZero));
+    assert_eq!(compile("1 + (2 * (x/0))"), Err(Error::DivideByZero));
+    assert_eq!(compile("sin(max(1, 2, x%0))"), Err(Error::DivideByZero));
+    assert_eq!(compile("-(1/0)"), Err(Error::DivideByZero));
+    #[cfg(feature = "print-builtin")]
+    assert_eq!(compile(r#"print("a", 1/0)"#), Err(Error::DivideByZero));
+
+    // Only literal divisors are checked:
+    assert_eq!(compile("1/2"), Ok(IConst(0.5)));
+    assert_eq!(compile("0/1"), Ok(IConst(0.0)));
+    assert_eq!(compile("0 % 5"), Ok(IConst(0.0)));
+    assert!(matches!(compile("1/(2-2)"), Ok(IConst(c)) if c.is_infinite()));
+    assert!(compile("1/x").is_ok());
+}
+
+#[test]
+fn compile_checked_arity() {
+    let mut slab = Slab::new();
+    let cb = |name: &str, args: Vec<Num>| match name {
+        "x" => Some(3.0),
+        "one" => args.first().copied(),
+        "two" => Some(args.iter().sum()),
+        "now" => Some(100.0),
+        _ => None,
+    };
+    let mut arities = BTreeMap::new();
+    arities.insert(String::from("one"), 1);
+    arities.insert(String::from("two"), 2);
+    arities.insert(String::from("now"), 0);
+    let mut ns = ArityNamespace::new(cb, arities);
+    let mut compile = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps)?;
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile_checked(&slab.ps, &mut slab.cs, &mut ns)?;
+        instr.eval(&slab, &mut ns)
+    };
+
+    // Matching arities:
+    assert_eq!(compile("one(5)"), Ok(5.0));
+    assert_eq!(compile("two(5, x)"), Ok(8.0));
+    assert_eq!(compile("now() + now"), Ok(200.0));
+    assert_eq!(compile("one(two(1, 2)) * x"), Ok(9.0));
+    // Functions without a registered arity accept anything:
+    assert_eq!(compile("x(1, 2, 3)"), Ok(3.0));
+
+    // Mismatching arities:
+    assert_eq!(
+        compile("one(1, 2)"),
+        Err(Error::WrongArgs(String::from("one: expected 1 arg")))
+    );
+    assert_eq!(
+        compile("two(1)"),
+        Err(Error::WrongArgs(String::from("two: expected 2 args")))
+    );
+    assert_eq!(
+        compile("one"),
+        Err(Error::WrongArgs(String::from("one: expected 1 arg")))
+    );
+    assert_eq!(
+        compile("now(1)"),
+        Err(Error::WrongArgs(String::from("now: expected 0 args")))
+    );
+    assert_eq!(
+        compile("1 + sin(max(x, (two(one(1)))))"),
+        Err(Error::WrongArgs(String::from("two: expected 2 args")))
+    );
+
+    // Plain compile() leaves the check to the Namespace:
+    let expr_i = Parser::new().parse("one(7, 8)", &mut slab.ps).unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(7.0));
+    assert_eq!(EmptyNamespace.arity("one"), None);
+}
+
+#[test]
+fn stable_debug() {
+    let mut slab = Slab::new();
+    let mut compile = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let instr = expr_i
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+        StableDebug(&instr).to_string()
+    };
+
+    assert_eq!(compile("3"), "IConst(3.0)");
+    assert_eq!(compile("-0"), "IConst(-0.0)");
+    #[cfg(not(feature = "f64"))]
+    assert_eq!(compile("1/3"), "IConst(0.33333334)");
+    #[cfg(feature = "f64")]
+    assert_eq!(compile("1/3"), "IConst(0.3333333333333333)");
+    assert_eq!(compile("1e-4"), "IConst(0.0001)");
+    assert_eq!(compile("1e-5"), "IConst(1e-5)");
+    #[cfg(not(feature = "f64"))]
+    assert_eq!(compile("123456789"), "IConst(123456790.0)");
+    #[cfg(feature = "f64")]
+    assert_eq!(compile("123456789"), "IConst(123456789.0)");
+    assert_eq!(compile("-1.5e16"), "IConst(-1.5e16)");
+    assert_eq!(compile("x * 1e20"), "IMul(InstructionI(0), C(1e20))");
+    assert_eq!(compile("1/0"), "IConst(inf)");
+    assert_eq!(compile("0/0"), "IConst(NaN)");
+    // Every digit of a `Num` is kept, so constants parse back to the same value:
+    for expr_str in ["0.1 + 0.2", "0.7 * 3", "2^0.5", "123456.789 * 1e-9"] {
+        let stable = compile(expr_str);
+        let digits = stable
+            .strip_prefix("IConst(")
+            .and_then(|s| s.strip_suffix(')'))
+            .unwrap();
+        assert_eq!(
+            digits.parse::<Num>(),
+            Ok(fasteval3::ez_eval(expr_str, &mut EmptyNamespace).unwrap()),
+            "{expr_str}"
+        );
+    }
+    // Numbers in names and indexes are not floats:
+    assert_eq!(compile("y7"), "IVar(\"y7\")");
+    assert_eq!(
+        compile("atan2d(x2, 2.50)"),
+        "IFuncATan2D { y: I(InstructionI(1)), x: C(2.5) }"
+    );
+}
+
+#[test]
+fn compile_with_warnings() {
+    let warnings = |expr_str: &str| {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let (instr, warnings) = slab.ps.get_expr(expr_i).compile_with_warnings(
+            &slab.ps,
+            &mut slab.cs,
+            &mut EmptyNamespace,
+        );
+        // The Instruction is the same as from compile():
+        let mut slab2 = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab2.ps).unwrap();
+        let expect =
+            slab2
+                .ps
+                .get_expr(expr_i)
+                .compile(&slab2.ps, &mut slab2.cs, &mut EmptyNamespace);
+        assert_eq!(instr, expect, "{expr_str}");
+        warnings
+    };
+    let contradiction = |name: &str| vec![Warning::Contradiction(String::from(name))];
+    let tautology = |name: &str| vec![Warning::Tautology(String::from(name))];
+
+    // Contradictions:
+    assert_eq!(warnings("x > 5 && x < 3"), contradiction("x"));
+    assert_eq!(warnings("x > 3 && x <= 3"), contradiction("x"));
+    assert_eq!(warnings("5 < x && (x <= 3)"), contradiction("x"));
+    assert_eq!(
+        warnings("y > 0 && x >= 1 && f(y) && x < 0"),
+        contradiction("x")
+    );
+    assert_eq!(warnings("1 + (x < 0 && x > 1)"), contradiction("x"));
+
+    // Tautologies:
+    assert_eq!(warnings("x >= 0 || x < 0"), tautology("x"));
+    assert_eq!(warnings("x <= 3 || 3 < x"), tautology("x"));
+    assert_eq!(warnings("x > 1 || y || x < 2"), tautology("x"));
+
+    // Each chain is reported:
+    assert_eq!(
+        warnings("(x > 1 && x < 0) || (y >= 0 || y < 0)"),
+        vec![
+            Warning::Contradiction(String::from("x")),
+            Warning::Tautology(String::from("y")),
+        ]
+    );
+
+    // Satisfiable, or not obvious enough:
+    for expr_str in [
+        "x >= 3 && x <= 3",
+        "x > 5 && y < 3",
+        "x < 3 || x > 3",
+        "x > 5 || x < 3",
+        "x > 1 && (x < 0 || y)",
+        "x == 2 && x != 2",
+        "x > 1 && -x > 1",
+        "x > NaN && x < NaN",
+    ] {
+        assert_eq!(warnings(expr_str), vec![], "{expr_str}");
+    }
+}
+
+#[test]
+fn compile_with_budget() {
+    let compile = |slab: &mut Slab, expr_str: &str, max_instrs: usize| {
+        let expr_i = Parser::new().parse_noclear(expr_str, &mut slab.ps)?;
+        slab.ps.get_expr(expr_i).compile_with_budget(
+            &slab.ps,
+            &mut slab.cs,
+            &mut EmptyNamespace,
+            max_instrs,
+        )
+    };
+    let mut slab = Slab::new();
+
+    // Constants fold away, and the root Instruction is not counted:
+    assert_eq!(compile(&mut slab, "1 + 2 * 3", 0), Ok(IConst(7.0)));
+    assert_eq!(
+        compile(&mut slab, "x + 1", 1),
+        Ok(IAdd(InstructionI(0), IC::C(1.0)))
+    );
+    assert_eq!(
+        compile(&mut slab, "sin(x) * cos(y) + z", 3),
+        Err(Error::TooManyInstructions)
+    );
+
+    // A failed compile leaves the slab as it was:
+    assert_eq!(
+        format!("{:?}", slab.cs),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }"
+    );
+    assert!(compile(&mut slab, "sin(x) * cos(y) + z", 6).is_ok());
+    assert_eq!(slab.cs.get_instr(InstructionI(1)), &IVar(String::from("x")));
+
+    // Interned Instructions are removed too:
+    slab.cs.clear();
+    slab.cs.set_interning(true);
+    assert_eq!(
+        compile(&mut slab, "x * y", 1),
+        Err(Error::TooManyInstructions)
+    );
+    assert!(compile(&mut slab, "x", 0).is_ok());
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{} }");
+}
+
+#[test]
+fn log_identities() {
+    let compile = |expr_str: &str| {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr = slab.ps.get_expr(expr_i);
+        StableDebug(&expr.compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace)).to_string()
+    };
+
+    // Constant bases are folded, including invalid ones:
+    assert_eq!(compile("log(2, 2)"), "IConst(1.0)");
+    assert_eq!(compile("log(0.5, 1)"), "IConst(-0.0)");
+    assert_eq!(compile("log(1, 1)"), "IConst(NaN)");
+    assert_eq!(compile("log(-2, -2)"), "IConst(NaN)");
+
+    // 'log(x, x) == 1' and 'log(x, 1) == 0' only hold for a valid base
+    // (positive and not 1), which can't be known for a variable:
+    assert_eq!(
+        compile("log(x, x)"),
+        "IFuncLog { base: I(InstructionI(0)), of: I(InstructionI(1)) }"
+    );
+    assert_eq!(
+        compile("log(x, 1)"),
+        "IFuncLog { base: I(InstructionI(0)), of: C(1.0) }"
+    );
+
+    // ...so compiled results must match eval for every base:
+    for interning in [false, true] {
+        for x in [2.0, 10.0, 0.5, 1.0, 0.0, -2.0] {
+            for expr_str in [
+                "log(x, x)",
+                "log(x, 1)",
+                "log(x * 2, x * 2)",
+                "log(x + 1, 1)",
+            ] {
+                let mut ns = |name: &str, _: Vec<Num>| (name == "x").then_some(x);
+                let mut slab = Slab::new();
+                slab.cs.set_interning(interning);
+                eval_both(&mut slab, &mut ns, expr_str).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn nan_constants_in_min_max() {
+    // 'Slab.nan_policy' is a runtime setting, so NaN constants are not folded
+    // with the other constants.  ('0/0' is used because the 'NaN' literal
+    // needs alpha-keywords.)
+    let compile = |expr_str: &str| {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr = slab.ps.get_expr(expr_i);
+        StableDebug(&expr.compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace)).to_string()
+    };
+    assert_eq!(
+        compile("min(0/0, 4.7)"),
+        "IFuncMin(InstructionI(0), C(NaN))"
+    );
+    assert_eq!(
+        compile("max(1, 0/0, 0.7)"),
+        "IFuncMax(InstructionI(0), C(NaN))"
+    );
+
+    // ...and compiled results match eval() with every policy:
+    for policy in [NanPolicy::Propagate, NanPolicy::Ignore, NanPolicy::Error] {
+        for expr_str in [
+            "min(0/0, 4.7)",
+            "max(0/0, 0.7)",
+            "min(0/0, 0/0)",
+            "min(1, 0/0, 2)",
+            "min(0/0, y7, 4.7)",
+            "max(0/0, y7, 0.7)",
+            "max(x, 0/0, -1/0)",
+            "min(2 * (0/0), x)",
+        ] {
+            let mut ns = |name: &str, _: Vec<Num>| match name {
+                "x" => Some(1.0),
+                "y7" => Some(10.0),
+                _ => None,
+            };
+            let mut slab = Slab::new();
+            slab.nan_policy = policy;
+            let _ = eval_both(&mut slab, &mut ns, expr_str);
+        }
+    }
+}
+
+#[test]
+fn simplify() {
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "x" => Some(3.0),
+        "y" => Some(-2.0),
+        "f" => Some(args.iter().sum()),
+        _ => None,
+    };
+    // Returns the simplified Expression (as JSON, which nests sub-expressions
+    // instead of showing slab indexes) and checks that it evaluates like the original:
+    let mut simplify = |expr_str: &str| {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr = slab.ps.get_expr(expr_i).clone();
+        let simple_i = expr.simplify(&mut slab).unwrap();
+        assert_error_margin(
+            slab.ps.get_expr(simple_i).eval(&slab, &mut ns).unwrap(),
+            expr.eval(&slab, &mut ns).unwrap(),
+        );
+        slab.ps.get_expr(simple_i).to_json(&slab)
+    };
+    let parsed = |expr_str: &str| {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        slab.ps.get_expr(expr_i).to_json(&slab)
+    };
+
+    assert_eq!(simplify("1 + x + 1"), parsed("x + 2"));
+    assert_eq!(simplify("1 - x + 1"), parsed("2 - x"));
+    assert_eq!(simplify("x - 1 - 1"), parsed("x - 2"));
+    assert_eq!(simplify("2 * 3 + x * 1 - (4 - 4)"), parsed("x + 6"));
+    assert_eq!(simplify("1 * x / 1 + 0"), parsed("x"));
+    assert_eq!(simplify("x ^ 1 * y"), parsed("x * y"));
+    assert_eq!(simplify("(x) + sin(0) + -(2)"), parsed("x - 2"));
+    assert_eq!(
+        simplify("x > 1 + 1 && y < 3 - 4"),
+        parsed("x > 2 && y < -1")
+    );
+    assert_eq!(simplify("max(1, 2, 3) * pi() * 0"), parsed("0"));
+    assert_eq!(simplify("!(1 > 2) || 0"), parsed("1"));
+
+    // Identities are kept when a tighter operator follows:
+    assert_eq!(simplify("x * 1 % y"), parsed("x * 1 % y"));
+    assert_eq!(simplify("x / 1 ^ y"), parsed("x / 1 ^ y"));
+
+    // Nested expressions are simplified too:
+    assert_eq!(
+        simplify("f(x + 1 + 1, 2 * 3) + abs(y - 1 - 1)"),
+        parsed("f(x + 2, 6) + abs(y - 2)")
+    );
+    assert_eq!(simplify("2 * (x + 2 * 0.5)"), parsed("2 * (x + 1)"));
+
+    // Variables and custom functions are never evaluated:
+    assert_eq!(simplify("f(1, 2) + x"), parsed("f(1, 2) + x"));
+}
+
+#[test]
+fn const_subtrees() {
+    comp_chk(
+        "sin(pi()/2) + x",
+        IAdd(InstructionI(0), IC::C(1.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        2.0,
+    );
+
+    // Every subtree without variables must end up as a single constant operand,
+    // leaving only the 'x' lookup in the slab:
+    for expr_str in [
+        "x + cos(pi() * 2) * (1 + 2)",
+        "max(x, sin(1), -(-(2)))",
+        "x < log(100) + abs(-2)",
+        "x % ((1 + 1) ^ (2 * 3))",
+        "round(1 + 1, x)",
+        "atan2d(x, 1 + 1)",
+        "blend(x, 1 + 1, 4, abs(-2))",
+        "bucket(x, 0 + 0, 5 * 2, 2 + 2)",
+        "powmod(x, 2 ^ 3, 10 * 10)",
+        "avg(x, 1 + 1, int(2.5))",
+        "sum(x, 1 + 1, int(2.5))",
+    ] {
+        let mut slab = Slab::new();
+        let instr = Parser::new()
+            .parse(expr_str, &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+        assert_eq!(
+            format!("{:?}", slab.cs),
+            "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+            "{expr_str} => {instr:?}"
+        );
+    }
+}
+
+#[test]
+fn bytecode_round_trip() {
+    let mut ns = CachedCallbackNamespace::new(|name, args| match name {
+        "x" => Some(1.0),
+        "y" => Some(2.0),
+        "foo" => Some(args.iter().sum()),
+        _ => None,
+    });
+    let mut expr_strs = vec![
+        "3.5",
+        "x",
+        "-x + 2*y - 1/x + x%3 + x^y^2",
+        "x < 1 || y >= 2 && x != -0 || !(x == y) || x > y || x <= y",
+        "int(x) + ceil(x) + floor(x) + abs(x) + sign(x) + log(2, y) + round(0.1, y) + round_half_up(y) + roundz(y)",
+        "min(x, y, 3) + max(x, y, -3) + blend(x, 1, y, 2) + norm(x, y) + sumsq(x, 3)",
+        "bucket(x, 0, y, 4) + powmod(y, 5, 7) + seq(x, y) + avg(x, y) + sum(x, 2, y) + foo(x, y, 1, 1) + foo(2) + foo",
+        "probround(x + 0.5)",
+        "poly(x, 1, y, 3)",
+        "min_first(x, 0, y)",
+        "max_first(2, x)",
+        "nanmin(x, NaN, y) + nanmax(2, x)",
+        "smoothstep(x, 4, y)",
+        "clamp(x, y, 2)",
+        "select(x, y, 2, x + y)",
+        "sin(x)+cos(x)+tan(x)+asin(x)+acos(x)+atan(x)+asind(x)+acosd(x)+atand(x)+atan2(y,x)+atan2d(y,x)+sinh(x)+cosh(x)+tanh(x)+asinh(x)+acosh(y)+atanh(x/2)+sqrt(y)+cbrt(x)",
+    ];
+    // 'xor' is only a keyword with alpha-keywords:
+    expr_strs.extend(cfg!(feature = "alpha-keywords").then_some("x xor y"));
+    for expr_str in expr_strs {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        let bytes = instr.to_bytecode(&slab);
+        // The high bit of the version byte marks f64 bytecode:
+        assert_eq!(bytes[0] & 0x7f, fasteval3::bytecode::BYTECODE_VERSION);
+        assert_eq!(bytes[0] & 0x80 != 0, cfg!(feature = "f64"));
+
+        let (loaded_slab, loaded) = Instruction::from_bytecode(&bytes).unwrap();
+        assert_eq!(format!("{loaded:?}"), format!("{instr:?}"));
+        assert_eq!(format!("{:?}", loaded_slab.cs), format!("{:?}", slab.cs));
+        assert_eq!(
+            loaded.eval(&loaded_slab, &mut ns),
+            instr.eval(&slab, &mut ns),
+            "{expr_str}"
+        );
+
+        // Truncated input is always rejected:
+        for len in 0..bytes.len() {
+            assert!(Instruction::from_bytecode(&bytes[..len]).is_err());
+        }
+    }
+
+    // Names and constants are stored once:
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("long_name * 1.5 + long_name * 1.5", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    #[cfg(not(feature = "f64"))]
+    assert_eq!(
+        instr.to_bytecode(&slab),
+        vec![
+            1, // version
+            1, 9, b'l', b'o', b'n', b'g', b'_', b'n', b'a', b'm', b'e', // names
+            1, 0, 0, 0xC0, 0x3F, // constants: 1.5
+            4,    // instrs
+            16, 0, // 0: IVar(long_name)
+            16, 0, // 1: IVar(long_name)
+            5, 0, 1, 0, // 2: IMul(0, C(1.5))
+            5, 1, 1, 0, // 3: IMul(1, C(1.5))
+            4, 2, 0, 3, // root: IAdd(2, I(3))
+        ]
+    );
+    #[cfg(feature = "f64")]
+    assert_eq!(
+        instr.to_bytecode(&slab),
+        vec![
+            0x81, // version, with the f64 flag
+            1, 9, b'l', b'o', b'n', b'g', b'_', b'n', b'a', b'm', b'e', // names
+            1, 0, 0, 0, 0, 0, 0, 0xF8, 0x3F, // constants: 1.5
+            4,    // instrs
+            16, 0, // 0: IVar(long_name)
+            16, 0, // 1: IVar(long_name)
+            5, 0, 1, 0, // 2: IMul(0, C(1.5))
+            5, 1, 1, 0, // 3: IMul(1, C(1.5))
+            4, 2, 0, 3, // root: IAdd(2, I(3))
+        ]
+    );
+
+    assert_eq!(
+        Instruction::from_bytecode(&[2, 0, 0, 0, 0, 0]).map(|_| ()),
+        Err(Error::InvalidBytecode(String::from(
+            "unsupported version 2"
+        )))
+    );
+    // Bytecode from a build with the other float width (see the `f64` feature):
+    let mut bytes = instr.to_bytecode(&slab);
+    bytes[0] ^= 0x80;
+    assert_eq!(
+        Instruction::from_bytecode(&bytes).map(|_| ()),
+        Err(Error::InvalidBytecode(String::from(
+            "constants have a different float width (see the `f64` feature)"
+        )))
+    );
+    // The version byte of bytecode from this build:
+    let v = bytes[0] ^ 0x80;
+    // Forward references (which could create cycles) are rejected:
+    assert_eq!(
+        Instruction::from_bytecode(&[v, 0, 0, 1, 1, 0, 1, 0]).map(|_| ()),
+        Err(Error::InvalidBytecode(String::from(
+            "instruction index 0 out of range"
+        )))
+    );
+    assert_eq!(
+        Instruction::from_bytecode(&[v, 0, 0, 0, 200]).map(|_| ()),
+        Err(Error::InvalidBytecode(String::from("unknown opcode 200")))
+    );
+    assert_eq!(
+        Instruction::from_bytecode(&[v, 0, 0, 0, 18, 0, 0]).map(|_| ()),
+        Err(Error::InvalidBytecode(String::from(
+            "instruction index 0 out of range"
+        )))
+    );
+}
+
 #[test]
 fn basics() {
     let mut slab = Slab::new();
@@ -83,7 +821,7 @@ fn comp(expr_str: &str) -> (Slab, Instruction) {
 }
 
 #[allow(clippy::needless_pass_by_value)] // The amount of work it would take to fix this... Is immeasurable.
-fn comp_chk(expr_str: &str, expect_instr: Instruction, expect_fmt: &str, expect_eval: f32) {
+fn comp_chk(expr_str: &str, expect_instr: Instruction, expect_fmt: &str, expect_eval: Num) {
     let mut slab = Slab::new();
 
     let mut ns = CachedCallbackNamespace::new(|name, args| match name {
@@ -104,7 +842,7 @@ fn comp_chk(expr_str: &str, expect_instr: Instruction, expect_fmt: &str, expect_
     let instr = expr.compile(&slab.ps, &mut slab.cs, &mut ns);
 
     assert_eq!(instr, expect_instr);
-    assert_eq!(format!("{:?}", slab.cs), expect_fmt);
+    assert_eq!(StableDebug(&slab.cs).to_string(), expect_fmt);
 
     (|| -> Result<(), Error> {
         assert_error_margin(eval_compiled_ref!(&instr, &slab, &mut ns), expect_eval);
@@ -120,7 +858,7 @@ fn comp_chk(expr_str: &str, expect_instr: Instruction, expect_fmt: &str, expect_
     .unwrap();
 }
 #[cfg(feature = "unsafe-vars")]
-fn unsafe_comp_chk(expr_str: &str, expect_fmt: &str, expect_eval: f32) {
+fn unsafe_comp_chk(expr_str: &str, expect_fmt: &str, expect_eval: Num) {
     fn replace_addrs(mut s: String) -> String {
         let mut start = 0;
         loop {
@@ -186,7 +924,7 @@ fn unsafe_comp_chk(expr_str: &str, expect_fmt: &str, expect_eval: f32) {
     .unwrap();
 }
 
-fn comp_chk_str(expr_str: &str, expect_instr: &str, expect_fmt: &str, expect_eval: f32) {
+fn comp_chk_str(expr_str: &str, expect_instr: &str, expect_fmt: &str, expect_eval: Num) {
     let mut slab = Slab::new();
     let expr = Parser::new()
         .parse(expr_str, &mut slab.ps)
@@ -194,8 +932,8 @@ fn comp_chk_str(expr_str: &str, expect_instr: &str, expect_fmt: &str, expect_eva
         .from(&slab.ps);
     let instr = expr.compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
 
-    assert_eq!(format!("{instr:?}"), expect_instr);
-    assert_eq!(format!("{:?}", slab.cs), expect_fmt);
+    assert_eq!(StableDebug(&instr).to_string(), expect_instr);
+    assert_eq!(StableDebug(&slab.cs).to_string(), expect_fmt);
 
     let mut ns = CachedCallbackNamespace::new(|name, args| match name {
         "w" => Some(0.0),
@@ -311,6 +1049,93 @@ fn double_neg() {
 }
 
 #[test]
+fn neg_mul() {
+    // Negating a product with a constant factor negates the constant instead:
+    comp_chk(
+        "-(x*2)",
+        IMul(InstructionI(0), IC::C(-2.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        -2.0,
+    );
+    comp_chk(
+        "-(x * -0.5)",
+        IMul(InstructionI(0), IC::C(0.5)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        0.5,
+    );
+    comp_chk(
+        "z - x*2",
+        IAdd(InstructionI(1), IC::I(InstructionI(2))),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"z\"), 2:IMul(InstructionI(0), C(-2.0)) } }",
+        1.0,
+    );
+    comp_chk(
+        "-(-(x*2))",
+        IMul(InstructionI(0), IC::C(2.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        2.0,
+    );
+
+    // Without a constant factor, INeg is still needed:
+    comp_chk(
+        "-(x*y)",
+        INeg(InstructionI(2)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\"), 2:IMul(InstructionI(0), I(InstructionI(1))) } }",
+        -2.0,
+    );
+}
+
+#[test]
+fn idempotent_funcs() {
+    // Applying one of these functions twice is the same as applying it once:
+    comp_chk(
+        "int(int(-y7))",
+        IFuncInt(InstructionI(1)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:INeg(InstructionI(0)) } }",
+        -2.0,
+    );
+    comp_chk(
+        "ceil(ceil(y7))",
+        IFuncCeil(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        3.0,
+    );
+    comp_chk(
+        "floor(floor(floor(y7)))",
+        IFuncFloor(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        2.0,
+    );
+    comp_chk(
+        "abs(abs(-y7))",
+        IFuncAbs(InstructionI(1)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:INeg(InstructionI(0)) } }",
+        2.7,
+    );
+    comp_chk(
+        "sign(sign(-y7))",
+        IFuncSign(InstructionI(1)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:INeg(InstructionI(0)) } }",
+        -1.0,
+    );
+
+    // Different functions are not collapsed:
+    comp_chk(
+        "abs(sign(-y7))",
+        IFuncAbs(InstructionI(2)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:INeg(InstructionI(0)), 2:IFuncSign(InstructionI(1)) } }",
+        1.0,
+    );
+    comp_chk(
+        "floor(floor(y7) + 0.5)",
+        IFuncFloor(InstructionI(2)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:IFuncFloor(InstructionI(0)), 2:IAdd(InstructionI(1), C(0.5)) } }",
+        2.0,
+    );
+}
+
+#[test]
+#[cfg(not(feature = "f64"))] // The expected constants are f32 values.
 fn all_instrs() {
     // IConst:
     comp_chk("1", IConst(1.0), "CompileSlab{ instrs:{} }", 1.0);
@@ -425,7 +1250,12 @@ fn all_instrs() {
         "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
         0.017_453_294,
     );
-    comp_chk("1 / -(x/360 * 2*pi())", IInv(InstructionI(2)), "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IMul(InstructionI(0), C(0.017453294)), 2:INeg(InstructionI(1)) } }", -57.295_773);
+    comp_chk(
+        "1 / -(x/360 * 2*pi())",
+        IInv(InstructionI(1)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IMul(InstructionI(0), C(-0.017453294)) } }",
+        -57.295_773,
+    );
     comp_chk(
         "3 * 3 / 3 * 3 / 3 * 3",
         IConst(9.0),
@@ -439,6 +1269,60 @@ fn all_instrs() {
         2.0,
     );
 
+    // Multiplying by zero:
+    // 'x' could be inf or NaN, so this must not be folded:
+    comp_chk(
+        "x * 0",
+        IMul(InstructionI(0), IC::C(0.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        0.0,
+    );
+    comp_chk_str(
+        "inf * 0",
+        "IConst(NaN)",
+        "CompileSlab{ instrs:{} }",
+        Num::NAN,
+    );
+    // Comparisons are always 0 or 1, but a variable might be undefined, so
+    // this is only folded for unsafe variables (see below):
+    comp_chk(
+        "(x > 1) * 0",
+        IMul(InstructionI(1), IC::C(0.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IGT(I(InstructionI(0)), C(1.0)) } }",
+        0.0,
+    );
+    #[cfg(feature = "unsafe-vars")]
+    {
+        unsafe_comp_chk("(x > 1) * 0", "CompileSlab{ instrs:{} }", 0.0);
+        unsafe_comp_chk("0 * (x < y) * 2", "CompileSlab{ instrs:{} }", 0.0);
+        unsafe_comp_chk("!w * 0 * (y == 2)", "CompileSlab{ instrs:{} }", 0.0);
+        unsafe_comp_chk("(x > 1) * -0", "CompileSlab{ instrs:{} }", -0.0);
+    }
+    // ...which are still errors when compiled:
+    let mut slab = Slab::new();
+    let instr = Parser::new()
+        .parse("(u > 1) * 0", &mut slab.ps)
+        .unwrap()
+        .from(&slab.ps)
+        .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+    assert_eq!(
+        instr.eval(&slab, &mut EmptyNamespace),
+        Err(Error::Undefined(String::from("u")))
+    );
+    comp_chk_str(
+        "(x > y) * 0 * inf",
+        "IMul(InstructionI(2), C(NaN))",
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\"), 2:IGT(I(InstructionI(0)), I(InstructionI(1))) } }",
+        Num::NAN,
+    );
+    // Custom functions might have side effects, so they are kept:
+    comp_chk_str(
+        "(foo(x) > 1) * 0",
+        "IMul(InstructionI(2), C(0.0))",
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IFunc { name: \"foo\", args: [I(InstructionI(0))] }, 2:IGT(I(InstructionI(1)), C(1.0)) } }",
+        0.0,
+    );
+
     // IMod:
     comp_chk("8 % 3", IConst(2.0), "CompileSlab{ instrs:{} }", 2.0);
     comp_chk(
@@ -487,15 +1371,15 @@ fn all_instrs() {
     comp_chk("4 ^ 0.5", IConst(2.0), "CompileSlab{ instrs:{} }", 2.0);
     comp_chk(
         "2 ^ 0.5",
-        IConst(std::f32::consts::SQRT_2), // 1.4142135623730951
+        IConst(fasteval3::consts::SQRT_2), // 1.4142135623730951
         "CompileSlab{ instrs:{} }",
-        std::f32::consts::SQRT_2,
+        fasteval3::consts::SQRT_2,
     );
     comp_chk_str(
         "-4 ^ 0.5",
         "IConst(NaN)",
         "CompileSlab{ instrs:{} }",
-        f32::NAN,
+        Num::NAN,
     );
     comp_chk(
         "y ^ 0.5",
@@ -504,16 +1388,32 @@ fn all_instrs() {
             power: IC::C(0.5),
         },
         "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
-        std::f32::consts::SQRT_2,
-    );
-    comp_chk(
-        "2 ^ 3 ^ 2",
-        IConst(512.0),
-        "CompileSlab{ instrs:{} }",
-        512.0,
+        fasteval3::consts::SQRT_2,
     );
-    comp_chk("2 ^ z ^ 2", IExp { base: IC::C(2.0), power: IC::I(InstructionI(1)) }, "CompileSlab{ instrs:{ 0:IVar(\"z\"), 1:IExp { base: I(InstructionI(0)), power: C(2.0) } } }", 512.0);
-    comp_chk("2 ^ z ^ 1 ^ 2 ^ 1", IExp { base: IC::C(2.0), power: IC::I(InstructionI(1)) }, "CompileSlab{ instrs:{ 0:IVar(\"z\"), 1:IExp { base: I(InstructionI(0)), power: C(1.0) } } }", 8.0);
+    #[cfg(not(feature = "left-assoc-exp"))]
+    {
+        comp_chk(
+            "2 ^ 3 ^ 2",
+            IConst(512.0),
+            "CompileSlab{ instrs:{} }",
+            512.0,
+        );
+        comp_chk("2 ^ z ^ 2", IExp { base: IC::C(2.0), power: IC::I(InstructionI(1)) }, "CompileSlab{ instrs:{ 0:IVar(\"z\"), 1:IExp { base: I(InstructionI(0)), power: C(2.0) } } }", 512.0);
+        comp_chk("2 ^ z ^ 1 ^ 2 ^ 1", IExp { base: IC::C(2.0), power: IC::I(InstructionI(1)) }, "CompileSlab{ instrs:{ 0:IVar(\"z\"), 1:IExp { base: I(InstructionI(0)), power: C(1.0) } } }", 8.0);
+    }
+    #[cfg(feature = "left-assoc-exp")]
+    {
+        comp_chk("2 ^ 3 ^ 2", IConst(64.0), "CompileSlab{ instrs:{} }", 64.0);
+        comp_chk(
+            "2 ^ z ^ 2",
+            IExp {
+                base: IC::I(InstructionI(1)),
+                power: IC::C(2.0),
+            },
+            "CompileSlab{ instrs:{ 0:IVar(\"z\"), 1:IExp { base: C(2.0), power: I(InstructionI(0)) } } }",
+            64.0,
+        );
+    }
 
     // ILT:
     comp_chk("2 < 3", IConst(1.0), "CompileSlab{ instrs:{} }", 1.0);
@@ -920,6 +1820,27 @@ fn all_instrs() {
         1.0,
     );
 
+    // IXOR:
+    comp_chk("2 xor 0", IConst(1.0), "CompileSlab{ instrs:{} }", 1.0);
+    comp_chk(
+        "2 xor 3 xor 0",
+        IConst(0.0),
+        "CompileSlab{ instrs:{} }",
+        0.0,
+    );
+    comp_chk(
+        "x xor 2",
+        IXOR(InstructionI(0), IC::C(2.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        0.0,
+    );
+    comp_chk(
+        "w xor x xor 1",
+        IXOR(InstructionI(2), IC::C(1.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"w\"), 1:IVar(\"x\"), 2:IXOR(InstructionI(0), I(InstructionI(1))) } }",
+        0.0,
+    );
+
     // IVar
     comp_chk(
         "x",
@@ -1041,20 +1962,28 @@ fn all_instrs() {
         -1.0,
     );
 
+    // IFuncProbRound (never folded, and needs a random source to eval)
+    let (slab, instr) = comp("probround(2.5)");
+    assert_eq!(instr, IFuncProbRound(InstructionI(0)));
+    assert_eq!(
+        format!("{:?}", slab.cs),
+        "CompileSlab{ instrs:{ 0:IConst(2.5) } }"
+    );
+
     // IFuncLog
     comp_chk("log(1)", IConst(0.0), "CompileSlab{ instrs:{} }", 0.0);
     comp_chk("log(10)", IConst(1.0), "CompileSlab{ instrs:{} }", 1.0);
     comp_chk(
         "log(2, 10)",
-        IConst(std::f32::consts::LOG2_10), // 3.321928094887362
+        IConst(fasteval3::consts::LOG2_10), // 3.321928094887362
         "CompileSlab{ instrs:{} }",
-        std::f32::consts::LOG2_10,
+        fasteval3::consts::LOG2_10,
     );
     comp_chk(
         "log(e(), 10)",
-        IConst(std::f32::consts::LN_10 + 0.0000003), //fix for rounding erros in f32 // 2.302585092994046
+        IConst(fasteval3::consts::LN_10 + 0.0000003), //fix for rounding erros in Num // 2.302585092994046
         "CompileSlab{ instrs:{} }",
-        std::f32::consts::LN_10 + 0.0000003,
+        fasteval3::consts::LN_10 + 0.0000003,
     );
     comp_chk(
         "log(x)",
@@ -1066,31 +1995,94 @@ fn all_instrs() {
         0.0,
     );
     comp_chk(
-        "log(y,x)",
-        IFuncLog {
-            base: IC::I(InstructionI(0)),
-            of: IC::I(InstructionI(1)),
+        "log(y,x)",
+        IFuncLog {
+            base: IC::I(InstructionI(0)),
+            of: IC::I(InstructionI(1)),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y\"), 1:IVar(\"x\") } }",
+        0.0,
+    );
+    comp_chk(
+        "log(y, 1)",
+        IFuncLog {
+            base: IC::I(InstructionI(0)),
+            of: IC::C(1.0),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
+        0.0,
+    );
+    comp_chk("log(2, 1)", IConst(0.0), "CompileSlab{ instrs:{} }", 0.0);
+    // Invalid constant bases give NaN:
+    comp_chk_str(
+        "log(1, 1)",
+        "IConst(NaN)",
+        "CompileSlab{ instrs:{} }",
+        Num::NAN,
+    );
+    comp_chk_str(
+        "log(-2, -2)",
+        "IConst(NaN)",
+        "CompileSlab{ instrs:{} }",
+        Num::NAN,
+    );
+
+    // IFuncRound
+    comp_chk("round(2.7)", IConst(3.0), "CompileSlab{ instrs:{} }", 3.0);
+    comp_chk(
+        "round(-2.7)",
+        IConst(-3.0),
+        "CompileSlab{ instrs:{} }",
+        -3.0,
+    );
+    comp_chk(
+        "round(y7)",
+        IFuncRound {
+            modulus: IC::C(1.0),
+            of: IC::I(InstructionI(0)),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        3.0,
+    );
+
+    // IFuncRoundHalfUp
+    comp_chk(
+        "round_half_up(-2.5)",
+        IConst(-2.0),
+        "CompileSlab{ instrs:{} }",
+        -2.0,
+    );
+    comp_chk(
+        "round_half_up(y7)",
+        IFuncRoundHalfUp {
+            modulus: IC::C(1.0),
+            of: IC::I(InstructionI(0)),
         },
-        "CompileSlab{ instrs:{ 0:IVar(\"y\"), 1:IVar(\"x\") } }",
-        0.0,
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        3.0,
     );
 
-    // IFuncRound
-    comp_chk("round(2.7)", IConst(3.0), "CompileSlab{ instrs:{} }", 3.0);
+    // IFuncRoundZ
     comp_chk(
-        "round(-2.7)",
+        "roundz(-2.5)",
+        IConst(-2.0),
+        "CompileSlab{ instrs:{} }",
+        -2.0,
+    );
+    comp_chk(
+        "rounda(-2.5)",
         IConst(-3.0),
         "CompileSlab{ instrs:{} }",
         -3.0,
     );
     comp_chk(
-        "round(y7)",
-        IFuncRound {
-            modulus: IC::C(1.0),
+        "roundz(0.5, y7)",
+        IFuncRoundZ {
+            modulus: IC::C(0.5),
             of: IC::I(InstructionI(0)),
         },
         "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
-        3.0,
+        2.5,
     );
 
     // IFuncMin
@@ -1127,15 +2119,15 @@ fn all_instrs() {
     );
     comp_chk_str(
         "min(NaN, y7, 4.7)",
-        "IFuncMin(InstructionI(0), C(NaN))",
+        "IFuncMinN([I(InstructionI(0)), C(4.7), C(NaN)])",
         "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
-        f32::NAN,
+        Num::NAN,
     );
     comp_chk_str(
         "min(NaN, 4.7)",
-        "IConst(NaN)",
-        "CompileSlab{ instrs:{} }",
-        f32::NAN,
+        "IFuncMin(InstructionI(0), C(NaN))",
+        "CompileSlab{ instrs:{ 0:IConst(4.7) } }",
+        Num::NAN,
     );
     comp_chk_str(
         "min(inf, y7, 4.7)",
@@ -1153,13 +2145,37 @@ fn all_instrs() {
         "min(-inf, y7, 4.7)",
         "IFuncMin(InstructionI(0), C(-inf))",
         "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
-        f32::NEG_INFINITY,
+        Num::NEG_INFINITY,
     );
     comp_chk_str(
         "min(-inf, 4.7)",
         "IConst(-inf)",
         "CompileSlab{ instrs:{} }",
-        f32::NEG_INFINITY,
+        Num::NEG_INFINITY,
+    );
+
+    // IFuncMinN
+    comp_chk(
+        "min(y7, x, z, w, y)",
+        IFuncMinN(vec![
+            IC::I(InstructionI(0)),
+            IC::I(InstructionI(1)),
+            IC::I(InstructionI(2)),
+            IC::I(InstructionI(3)),
+            IC::I(InstructionI(4)),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:IVar(\"x\"), 2:IVar(\"z\"), 3:IVar(\"w\"), 4:IVar(\"y\") } }",
+        0.0,
+    );
+    comp_chk(
+        "min(y7, 4, x, 3)",
+        IFuncMinN(vec![
+            IC::I(InstructionI(0)),
+            IC::I(InstructionI(1)),
+            IC::C(3.0),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:IVar(\"x\") } }",
+        1.0,
     );
 
     // IFuncMax
@@ -1196,27 +2212,27 @@ fn all_instrs() {
     );
     comp_chk_str(
         "max(NaN, y7, 0.7)",
-        "IFuncMax(InstructionI(0), C(NaN))",
+        "IFuncMaxN([I(InstructionI(0)), C(0.7), C(NaN)])",
         "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
-        f32::NAN,
+        Num::NAN,
     );
     comp_chk_str(
         "max(NaN, 0.7)",
-        "IConst(NaN)",
-        "CompileSlab{ instrs:{} }",
-        f32::NAN,
+        "IFuncMax(InstructionI(0), C(NaN))",
+        "CompileSlab{ instrs:{ 0:IConst(0.7) } }",
+        Num::NAN,
     );
     comp_chk_str(
         "max(inf, y7, 4.7)",
         "IFuncMax(InstructionI(0), C(inf))",
         "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
-        f32::INFINITY,
+        Num::INFINITY,
     );
     comp_chk_str(
         "max(inf, 4.7)",
         "IConst(inf)",
         "CompileSlab{ instrs:{} }",
-        f32::INFINITY,
+        Num::INFINITY,
     );
     comp_chk_str(
         "max(-inf, y7, 4.7)",
@@ -1231,6 +2247,340 @@ fn all_instrs() {
         4.7,
     );
 
+    // IFuncMaxN
+    comp_chk(
+        "max(y7, x, 3, z, w)",
+        IFuncMaxN(vec![
+            IC::I(InstructionI(0)),
+            IC::I(InstructionI(1)),
+            IC::I(InstructionI(2)),
+            IC::I(InstructionI(3)),
+            IC::C(3.0),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\"), 1:IVar(\"x\"), 2:IVar(\"z\"), 3:IVar(\"w\") } }",
+        3.0,
+    );
+
+    // minz/maxz
+    comp_chk_str(
+        "minz()",
+        "IConst(inf)",
+        "CompileSlab{ instrs:{} }",
+        Num::INFINITY,
+    );
+    comp_chk_str(
+        "maxz()",
+        "IConst(-inf)",
+        "CompileSlab{ instrs:{} }",
+        Num::NEG_INFINITY,
+    );
+    comp_chk(
+        "minz(y7, 1.7)",
+        IFuncMin(InstructionI(0), IC::C(1.7)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        1.7,
+    );
+
+    // prod() compiles to IMul
+    comp_chk(
+        "prod(2, 3, 4)",
+        IConst(24.0),
+        "CompileSlab{ instrs:{} }",
+        24.0,
+    );
+    comp_chk(
+        "prod(1, x, 2)",
+        IMul(InstructionI(0), IC::C(2.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        2.0,
+    );
+    comp_chk(
+        "prod(y, 3, x * 2, z)",
+        IMul(InstructionI(4), IC::C(6.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y\"), 1:IVar(\"x\"), 2:IMul(InstructionI(0), I(InstructionI(1))), 3:IVar(\"z\"), 4:IMul(InstructionI(2), I(InstructionI(3))) } }",
+        36.0,
+    );
+
+    // IFuncMinFirst, IFuncMaxFirst
+    comp_chk(
+        "min_first(2.7, 1.7, 0.7)",
+        IConst(0.7),
+        "CompileSlab{ instrs:{} }",
+        0.7,
+    );
+    comp_chk(
+        "max_first(0.7, y7, 1.7)",
+        IFuncMaxFirst(InstructionI(2), IC::C(1.7)),
+        "CompileSlab{ instrs:{ 0:IConst(0.7), 1:IVar(\"y7\"), 2:IFuncMaxFirst(InstructionI(0), I(InstructionI(1))) } }",
+        2.7,
+    );
+    comp_chk(
+        "min_first(x, 2)",
+        IFuncMinFirst(InstructionI(0), IC::C(2.0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        1.0,
+    );
+    comp_chk_str(
+        "max_first(NaN, 0.7)",
+        "IFuncMaxFirst(InstructionI(0), C(0.7))",
+        "CompileSlab{ instrs:{ 0:IConst(NaN) } }",
+        Num::NAN,
+    );
+
+    // IFuncNanMin, IFuncNanMax
+    comp_chk(
+        "nanmin(NaN, 2, 3)",
+        IConst(2.0),
+        "CompileSlab{ instrs:{} }",
+        2.0,
+    );
+    comp_chk_str(
+        "nanmax(NaN, NaN)",
+        "IConst(NaN)",
+        "CompileSlab{ instrs:{} }",
+        Num::NAN,
+    );
+    // NaN constants are dropped:
+    comp_chk(
+        "nanmax(x, NaN, y)",
+        IFuncNanMax(InstructionI(0), IC::I(InstructionI(1))),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\") } }",
+        2.0,
+    );
+    comp_chk(
+        "nanmin(NaN, 3, x)",
+        IFuncNanMin(InstructionI(0), IC::I(InstructionI(1))),
+        "CompileSlab{ instrs:{ 0:IConst(3.0), 1:IVar(\"x\") } }",
+        1.0,
+    );
+
+    // IFuncBlend
+    comp_chk(
+        "blend(1, 1, 4, 2)",
+        IConst(3.0),
+        "CompileSlab{ instrs:{} }",
+        3.0,
+    );
+    comp_chk(
+        "blend(x, 1, y, 1, z, 2)",
+        IFuncBlend(vec![
+            IC::I(InstructionI(0)),
+            IC::C(1.0),
+            IC::I(InstructionI(1)),
+            IC::C(1.0),
+            IC::I(InstructionI(2)),
+            IC::C(2.0),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\"), 2:IVar(\"z\") } }",
+        2.25,
+    );
+
+    // IFuncAvg
+    comp_chk("avg(1, 2, 6)", IConst(3.0), "CompileSlab{ instrs:{} }", 3.0);
+    comp_chk(
+        "avg(x, 2, z)",
+        IFuncAvg(vec![
+            IC::I(InstructionI(0)),
+            IC::C(2.0),
+            IC::I(InstructionI(1)),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"z\") } }",
+        2.0,
+    );
+
+    // IFuncSum
+    comp_chk("sum(1, 2, 6)", IConst(9.0), "CompileSlab{ instrs:{} }", 9.0);
+    comp_chk(
+        "sum(x, 2, z)",
+        IFuncSum(vec![
+            IC::I(InstructionI(0)),
+            IC::C(2.0),
+            IC::I(InstructionI(1)),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"z\") } }",
+        6.0,
+    );
+
+    // IFuncNorm
+    comp_chk("norm(3, 4)", IConst(5.0), "CompileSlab{ instrs:{} }", 5.0);
+    comp_chk(
+        "norm(x, 2, z)",
+        IFuncNorm(vec![
+            IC::I(InstructionI(0)),
+            IC::C(2.0),
+            IC::I(InstructionI(1)),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"z\") } }",
+        3.7416575,
+    );
+
+    // IFuncPoly
+    comp_chk(
+        "poly(2, 1, 2, 3)",
+        IConst(17.0),
+        "CompileSlab{ instrs:{} }",
+        17.0,
+    );
+    comp_chk(
+        "poly(z, 1, 2, 3)",
+        IFuncPoly(vec![
+            IC::I(InstructionI(0)),
+            IC::C(1.0),
+            IC::C(2.0),
+            IC::C(3.0),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"z\") } }",
+        34.0,
+    );
+
+    // IFuncSumSq
+    comp_chk(
+        "sumsq(1, 2, 3)",
+        IConst(14.0),
+        "CompileSlab{ instrs:{} }",
+        14.0,
+    );
+    comp_chk(
+        "sumsq(x, y, 3)",
+        IFuncSumSq(vec![
+            IC::I(InstructionI(0)),
+            IC::I(InstructionI(1)),
+            IC::C(3.0),
+        ]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\") } }",
+        14.0,
+    );
+
+    // IFuncSeq
+    comp_chk("seq(1, 2, 3)", IConst(3.0), "CompileSlab{ instrs:{} }", 3.0);
+    comp_chk(
+        "seq(x, 2, y)",
+        IFuncSeq(vec![IC::I(InstructionI(0)), IC::I(InstructionI(1))]),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"y\") } }",
+        2.0,
+    );
+    comp_chk(
+        "seq(1, x)",
+        IVar(String::from("x")),
+        "CompileSlab{ instrs:{} }",
+        1.0,
+    );
+
+    // IFuncBucket
+    comp_chk(
+        "bucket(2.5, 0, 10, 4)",
+        IConst(1.0),
+        "CompileSlab{ instrs:{} }",
+        1.0,
+    );
+    comp_chk(
+        "bucket(y7, 0, 10, 4)",
+        IFuncBucket {
+            x: IC::I(InstructionI(0)),
+            lo: IC::C(0.0),
+            hi: IC::C(10.0),
+            n: IC::C(4.0),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        1.0,
+    );
+
+    // IFuncPowMod
+    comp_chk(
+        "powmod(2, 10, 1000)",
+        IConst(24.0),
+        "CompileSlab{ instrs:{} }",
+        24.0,
+    );
+    comp_chk(
+        "powmod(y7, 10, 1000)",
+        IFuncPowMod {
+            base: IC::I(InstructionI(0)),
+            exp: IC::C(10.0),
+            modulus: IC::C(1000.0),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y7\") } }",
+        24.0,
+    );
+
+    // IFuncSmoothstep
+    comp_chk(
+        "smoothstep(0, 4, 2)",
+        IConst(0.5),
+        "CompileSlab{ instrs:{} }",
+        0.5,
+    );
+    comp_chk(
+        "smoothstep(0, 4, y)",
+        IFuncSmoothstep {
+            edge0: IC::C(0.0),
+            edge1: IC::C(4.0),
+            x: IC::I(InstructionI(0)),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
+        0.5,
+    );
+
+    // IFuncClamp
+    comp_chk(
+        "clamp(5, 0, 3)",
+        IConst(3.0),
+        "CompileSlab{ instrs:{} }",
+        3.0,
+    );
+    comp_chk(
+        "clamp(y, 0, 1)",
+        IFuncClamp {
+            val: IC::I(InstructionI(0)),
+            lo: IC::C(0.0),
+            hi: IC::C(1.0),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
+        1.0,
+    );
+
+    // IFuncSelect
+    comp_chk(
+        "select(1, x, y, z)",
+        IVar(String::from("y")),
+        "CompileSlab{ instrs:{} }",
+        2.0,
+    );
+    comp_chk(
+        "select(y, 10, 20, z * 10)",
+        IFuncSelect {
+            index: IC::I(InstructionI(0)),
+            options: vec![IC::C(10.0), IC::C(20.0), IC::I(InstructionI(2))],
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"y\"), 1:IVar(\"z\"), 2:IMul(InstructionI(1), C(10.0)) } }",
+        30.0,
+    );
+
+    // IFuncSqrt
+    comp_chk("sqrt(4)", IConst(2.0), "CompileSlab{ instrs:{} }", 2.0);
+    comp_chk("sqrt(0.25)", IConst(0.5), "CompileSlab{ instrs:{} }", 0.5);
+    comp_chk(
+        "sqrt(y)",
+        IFuncSqrt(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
+        Num::sqrt(2.0),
+    );
+
+    // IFuncCbrt
+    comp_chk("cbrt(8)", IConst(2.0), "CompileSlab{ instrs:{} }", 2.0);
+    comp_chk(
+        "cbrt(-0.125)",
+        IConst(-0.5),
+        "CompileSlab{ instrs:{} }",
+        -0.5,
+    );
+    comp_chk(
+        "cbrt(y)",
+        IFuncCbrt(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }",
+        Num::cbrt(2.0),
+    );
+
     // IFuncSin
     comp_chk("sin(0)", IConst(0.0), "CompileSlab{ instrs:{} }", 0.0);
     comp_chk(
@@ -1286,15 +2636,15 @@ fn all_instrs() {
     // IFuncACos
     comp_chk(
         "acos(0)",
-        IConst(std::f32::consts::FRAC_PI_2), // 1.5707963267948966
+        IConst(fasteval3::consts::FRAC_PI_2), // 1.5707963267948966
         "CompileSlab{ instrs:{} }",
-        std::f32::consts::FRAC_PI_2,
+        fasteval3::consts::FRAC_PI_2,
     );
     comp_chk(
         "acos(w)",
         IFuncACos(InstructionI(0)),
         "CompileSlab{ instrs:{ 0:IVar(\"w\") } }",
-        std::f32::consts::FRAC_PI_2,
+        fasteval3::consts::FRAC_PI_2,
     );
 
     // IFuncATan
@@ -1306,6 +2656,67 @@ fn all_instrs() {
         0.0,
     );
 
+    // IFuncASinD
+    comp_chk("asind(1)", IConst(90.0), "CompileSlab{ instrs:{} }", 90.0);
+    comp_chk(
+        "asind(x)",
+        IFuncASinD(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        90.0,
+    );
+
+    // IFuncACosD
+    comp_chk("acosd(0)", IConst(90.0), "CompileSlab{ instrs:{} }", 90.0);
+    comp_chk(
+        "acosd(w)",
+        IFuncACosD(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"w\") } }",
+        90.0,
+    );
+
+    // IFuncATanD
+    comp_chk("atand(1)", IConst(45.0), "CompileSlab{ instrs:{} }", 45.0);
+    comp_chk(
+        "atand(x)",
+        IFuncATanD(InstructionI(0)),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }",
+        45.0,
+    );
+
+    // IFuncATan2
+    comp_chk(
+        "atan2(1, 1)",
+        IConst(fasteval3::consts::FRAC_PI_4),
+        "CompileSlab{ instrs:{} }",
+        fasteval3::consts::FRAC_PI_4,
+    );
+    comp_chk(
+        "atan2(x, w)",
+        IFuncATan2 {
+            y: IC::I(InstructionI(0)),
+            x: IC::I(InstructionI(1)),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"w\") } }",
+        fasteval3::consts::FRAC_PI_2,
+    );
+
+    // IFuncATan2D
+    comp_chk(
+        "atan2d(1, -1)",
+        IConst(135.0),
+        "CompileSlab{ instrs:{} }",
+        135.0,
+    );
+    comp_chk(
+        "atan2d(x, w)",
+        IFuncATan2D {
+            y: IC::I(InstructionI(0)),
+            x: IC::I(InstructionI(1)),
+        },
+        "CompileSlab{ instrs:{ 0:IVar(\"x\"), 1:IVar(\"w\") } }",
+        90.0,
+    );
+
     // IFuncSinH
     comp_chk("sinh(0)", IConst(0.0), "CompileSlab{ instrs:{} }", 0.0);
     comp_chk(
@@ -1361,6 +2772,7 @@ fn all_instrs() {
     );
 
     // IPrintFunc
+    #[cfg(feature = "print-builtin")]
     comp_chk(
         r#"print("test",1.23)"#,
         IPrintFunc(PrintFunc(vec![
@@ -1393,6 +2805,76 @@ fn custom_func() {
     );
 }
 
+#[test]
+fn interning() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    slab.cs.set_interning(true);
+    assert!(slab.cs.is_interning());
+
+    let expr_i = Parser::new().parse("x + 1", &mut slab.ps).unwrap();
+    let instr1 = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    let expr_i = Parser::new().parse("x + 1", &mut slab.ps).unwrap();
+    let instr2 = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr1, IAdd(InstructionI(0), IC::C(1.0)));
+    assert_eq!(instr1, instr2);
+    assert_eq!(
+        format!("{:?}", slab.cs),
+        "CompileSlab{ instrs:{ 0:IVar(\"x\") } }"
+    );
+
+    // Flattening must not disturb shared instructions:
+    let expr_i = Parser::new()
+        .parse("(x + 1) + (x + 1)", &mut slab.ps)
+        .unwrap();
+    let instr3 = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    let mut cb = |name: &str, _args: Vec<Num>| if name == "x" { Some(2.0) } else { None };
+    (|| -> Result<(), Error> {
+        assert_error_margin(eval_compiled_ref!(&instr1, &slab, &mut cb), 3.0);
+        assert_error_margin(eval_compiled_ref!(&instr3, &slab, &mut cb), 6.0);
+        Ok(())
+    })()
+    .unwrap();
+
+    slab.clear();
+    let expr_i = Parser::new().parse("y + 1", &mut slab.ps).unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr, IAdd(InstructionI(0), IC::C(1.0)));
+    assert_eq!(
+        format!("{:?}", slab.cs),
+        "CompileSlab{ instrs:{ 0:IVar(\"y\") } }"
+    );
+
+    // Constants are compared by their bits, so -0 and 0 are kept apart, and
+    // NaN is shared like any other constant:
+    slab.clear();
+    let expr_i = Parser::new()
+        .parse(
+            "sin(y * -0) + sin(y * 0) + sin(y * (0/0)) + sin(y * (0/0))",
+            &mut slab.ps,
+        )
+        .unwrap();
+    slab.ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(
+        StableDebug(&slab.cs).to_string(),
+        "CompileSlab{ instrs:{ 0:IVar(\"y\"), 1:IMul(InstructionI(0), C(-0.0)), 2:IMul(InstructionI(0), C(0.0)), 3:IMul(InstructionI(0), C(NaN)), 4:IFuncSin(InstructionI(1)), 5:IFuncSin(InstructionI(2)), 6:IAdd(InstructionI(4), I(InstructionI(5))), 7:IFuncSin(InstructionI(3)), 8:IAdd(InstructionI(6), I(InstructionI(7))) } }"
+    );
+}
+
 #[test]
 fn eval_macro() {
     fn wrapped() -> Result<(), Error> {
@@ -1404,13 +2886,13 @@ fn eval_macro() {
             .unwrap()
             .from(&slab.ps);
         let instr = expr.compile(&slab.ps, &mut slab.cs, &mut ns);
-        assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - 5.0).abs() < f32::EPSILON);
+        assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - 5.0).abs() < Num::EPSILON);
         (|| -> Result<(), Error> {
-            assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - 5.0).abs() < f32::EPSILON);
+            assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - 5.0).abs() < Num::EPSILON);
             Ok(())
         })()
         .unwrap();
-        assert!((eval_compiled!(instr, &slab, &mut ns) - 5.0).abs() < f32::EPSILON);
+        assert!((eval_compiled!(instr, &slab, &mut ns) - 5.0).abs() < Num::EPSILON);
 
         #[cfg(feature = "unsafe-vars")]
         {
@@ -1435,3 +2917,74 @@ fn eval_macro() {
 
     wrapped().unwrap();
 }
+
+#[test]
+fn eval_macro_many() {
+    fn wrapped() -> Result<(), Error> {
+        let mut slab = Slab::new();
+        let mut map = BTreeMap::<String, Num>::new();
+
+        for expr_str in ["5", "x", "x * 2 + 1", "-x"] {
+            let instr = Parser::new()
+                .parse(expr_str, &mut slab.ps)?
+                .from(&slab.ps)
+                .compile(&slab.ps, &mut slab.cs, &mut map);
+
+            let mut expect = Vec::new();
+            for x in 0..5 {
+                map.insert(String::from("x"), x as Num);
+                expect.push(eval_compiled_ref!(&instr, &slab, &mut map));
+            }
+
+            let mut vals = Vec::new();
+            eval_compiled_many!(instr, &slab, &mut map, for x in 0..5 => {
+                map.insert(String::from("x"), x as Num);
+            } |val| {
+                vals.push(val);
+            });
+            assert_eq!(vals, expect, "{expr_str}");
+
+            // Without a setup block:
+            let mut vals = Vec::new();
+            eval_compiled_many!(&instr, &slab, &mut map, for _ in 0..3 => |val| {
+                vals.push(val);
+            });
+            assert_eq!(vals, vec![eval_compiled!(instr, &slab, &mut map); 3]);
+        }
+
+        // Errors are returned with `?`:
+        let instr = Parser::new()
+            .parse("y", &mut slab.ps)?
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut map);
+        let res = (|| -> Result<(), Error> {
+            eval_compiled_many!(instr, &slab, &mut map, for _ in 0..3 => |_val| {});
+            Ok(())
+        })();
+        assert_eq!(res, Err(Error::Undefined(String::from("y"))));
+
+        #[cfg(feature = "unsafe-vars")]
+        {
+            let mut x: Num = 0.0;
+            unsafe { slab.ps.add_unsafe_var(String::from("x"), &x) }
+            let instr = Parser::new()
+                .parse("x", &mut slab.ps)?
+                .from(&slab.ps)
+                .compile(&slab.ps, &mut slab.cs, &mut map);
+            assert!(matches!(instr, fasteval3::IUnsafeVar { .. }));
+
+            let mut vals = Vec::new();
+            eval_compiled_many!(instr, &slab, &mut map, for i in 0..3 => {
+                x = i as Num * 10.0;
+            } |val| {
+                vals.push(val);
+            });
+            assert_eq!(vals, vec![0.0, 10.0, 20.0]);
+            assert_eq!(x, 20.0);
+        }
+
+        Ok(())
+    }
+
+    wrapped().unwrap();
+}
@@ -1,5 +1,15 @@
+pub(crate) mod common;
+
+use common::{eval_both, eval_both_with};
+
 use fasteval3::bool_to_f32;
-use fasteval3::{Cached, CachedCallbackNamespace, EmptyNamespace, Error, Evaler, Parser, Slab};
+use fasteval3::compiler::Instruction::{IFuncMaxN, IFuncMinN};
+use fasteval3::parser::BinaryOp::{EAdd, EMul, EAND, EOR};
+use fasteval3::{
+    eval_columns, eval_sweep, ez_eval, Cached, CachedCallbackNamespace, Compiler, CustomOp,
+    EmptyNamespace, Error, EvalNamespace, Evaler, IConst, NanPolicy, Num, OpDispatch, Parser,
+    RandomNamespace, Slab, ValueSite,
+};
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::mem;
@@ -7,7 +17,7 @@ use std::mem;
 #[test]
 fn eval() {
     let mut slab = Slab::new();
-    let mut ns = BTreeMap::<String, f32>::new();
+    let mut ns = BTreeMap::<String, Num>::new();
     ns.insert(String::from("x"), 1.0);
     ns.insert(String::from("y"), 2.0);
     ns.insert(String::from("z"), 3.0);
@@ -22,7 +32,7 @@ fn eval() {
             .unwrap()
             - 5.0)
             .abs()
-            < f32::EPSILON
+            < Num::EPSILON
     );
 
     assert!(
@@ -34,7 +44,7 @@ fn eval() {
             .unwrap()
             - 6.0)
             .abs()
-            < f32::EPSILON
+            < Num::EPSILON
     );
 
     assert_eq!(
@@ -49,8 +59,8 @@ fn eval() {
 
 #[test]
 fn aaa_util() {
-    assert!((bool_to_f32!(true) - 1.0).abs() < f32::EPSILON);
-    assert!((bool_to_f32!(false) - 0.0).abs() < f32::EPSILON);
+    assert!((bool_to_f32!(true) - 1.0).abs() < Num::EPSILON);
+    assert!((bool_to_f32!(false) - 0.0).abs() < Num::EPSILON);
 }
 
 #[test]
@@ -70,14 +80,15 @@ fn aaa_aab_single() {
             .from(&slab.ps)
             .eval(&slab, &mut ns)
             .unwrap()
-            - 123.456f32)
+            - 123.456)
             .abs()
-            < f32::EPSILON
+            < Num::EPSILON
     );
 }
 
 #[allow(clippy::cognitive_complexity, clippy::too_many_lines)] // Another revisit
 #[test]
+#[cfg(not(feature = "f64"))] // The expected results are f32 values.
 fn aaa_basics() {
     let mut slab = Slab::new();
 
@@ -319,7 +330,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::NEG_INFINITY)
+        Ok(Num::NEG_INFINITY)
     );
     assert!(Parser::new()
         .parse("1.2 + log(-1)", &mut slab.ps)
@@ -393,6 +404,7 @@ fn aaa_basics() {
         Ok(2.2)
     );
 
+    #[cfg(feature = "print-builtin")]
     assert_eq!(
         Parser::new()
             .parse(r#"12.34 + print ( 43.21, "yay" ) + 11.11"#, &mut slab.ps)
@@ -408,7 +420,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::consts::E) // 2.718281828459045
+        Ok(fasteval3::consts::E) // 2.718281828459045
     );
     assert_eq!(
         Parser::new()
@@ -416,7 +428,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::consts::PI) // 3.141592653589793
+        Ok(fasteval3::consts::PI) // 3.141592653589793
     );
 
     assert_eq!(
@@ -449,7 +461,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::consts::FRAC_PI_2)
+        Ok(fasteval3::consts::FRAC_PI_2)
     );
     assert_eq!(
         Parser::new()
@@ -457,7 +469,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::consts::FRAC_PI_2) // 1.5707963267948966
+        Ok(fasteval3::consts::FRAC_PI_2) // 1.5707963267948966
     );
     assert_eq!(
         Parser::new()
@@ -465,7 +477,7 @@ fn aaa_basics() {
             .unwrap()
             .from(&slab.ps)
             .eval(&slab, &mut ns),
-        Ok(std::f32::consts::FRAC_PI_4) // 0.7853981633974483
+        Ok(fasteval3::consts::FRAC_PI_4) // 0.7853981633974483
     );
     assert_eq!(
         Parser::new()
@@ -532,205 +544,2319 @@ fn corners() {
     );
 }
 
-fn my_evalns_cb_function(_: &str, _: Vec<f32>) -> Option<f32> {
-    None
-}
 #[test]
-fn evalns_cb_ownership() {
-    let _ns = CachedCallbackNamespace::new(my_evalns_cb_function);
-    let _ns = CachedCallbackNamespace::new(my_evalns_cb_function);
-    // Conclusion: You can pass a function pointer into a function that receives ownership.
+fn single_value() {
+    let mut slab = Slab::new();
+    let mut ns = BTreeMap::<String, Num>::new();
+    ns.insert(String::from("x"), 2.0);
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
 
-    let closure = |_: &str, _: Vec<f32>| None;
-    let _ns = CachedCallbackNamespace::new(closure);
-    let _ns = CachedCallbackNamespace::new(closure);
+    assert_eq!(eval("3"), Ok(3.0));
+    assert_eq!(eval("-3"), Ok(-3.0));
+    assert_eq!(eval("x"), Ok(2.0));
+    assert_eq!(eval("(x)"), Ok(2.0));
+    assert_eq!(eval("!x"), Ok(0.0));
+    assert_eq!(eval("sin(0)"), Ok(0.0));
+    assert_eq!(eval("abs(-x)"), Ok(2.0));
+    assert_eq!(eval("y"), Err(Error::Undefined(String::from("y"))));
+}
 
-    let x = 1.0;
-    let closure = |_: &str, _: Vec<f32>| Some(x);
-    let _ns = CachedCallbackNamespace::new(closure);
-    let _ns = CachedCallbackNamespace::new(closure);
+#[test]
+fn required_signatures() {
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("f(1) + f(1, 2) + g(x, f(y)) + x", &mut slab.ps)
+        .unwrap();
+    let sigs = slab.ps.get_expr(expr_i).required_signatures(&slab);
 
-    let mut x = 1.0;
-    let closure = |_: &str, _: Vec<f32>| {
-        x += 1.0;
-        Some(x)
-    };
-    let _ns = CachedCallbackNamespace::new(closure);
-    //let _ns = CachedCallbackNamespace::new(closure);  // Not allowed.
+    let mut expect = BTreeMap::<String, BTreeSet<usize>>::new();
+    expect.insert(String::from("f"), [1, 2].into_iter().collect());
+    expect.insert(String::from("g"), [2].into_iter().collect());
+    expect.insert(String::from("x"), [0].into_iter().collect());
+    expect.insert(String::from("y"), [0].into_iter().collect());
+    assert_eq!(sigs, expect);
 
-    // Conclusion: Functions and Closures that don't mutate state are effectively Copy.
-    //             Closures that mutate state aren't Copy.
-    //             Note that the argument type (FnMut vs Fn) doesn't actually matter,
-    //             just the implementation matters!
+    assert_eq!(
+        slab.ps.get_expr(expr_i).var_names(&slab),
+        ["f", "g", "x", "y"].into_iter().map(String::from).collect()
+    );
+
+    // Evalers that only implement '_var_names()' report names without argument counts:
+    #[derive(Debug)]
+    struct XEvaler;
+    impl Evaler for XEvaler {
+        fn _var_names(&self, _slab: &Slab, dst: &mut BTreeSet<String>) {
+            dst.insert(String::from("x"));
+        }
+        fn eval(&self, _slab: &Slab, ns: &mut impl EvalNamespace) -> Result<Num, Error> {
+            ns.lookup("x", Vec::new(), &mut String::new())
+                .ok_or_else(|| Error::Undefined(String::from("x")))
+        }
+    }
+    let mut expect = BTreeMap::<String, BTreeSet<usize>>::new();
+    expect.insert(String::from("x"), BTreeSet::new());
+    assert_eq!(XEvaler.required_signatures(&slab), expect);
+    assert_eq!(
+        XEvaler.var_names(&slab),
+        ["x"].into_iter().map(String::from).collect()
+    );
 }
 
-#[allow(clippy::too_many_lines)]
 #[test]
-fn custom_func() {
+fn columns() {
+    let rows = 1000;
+    let x: Vec<Num> = (0..rows).map(|i| i as Num * 0.37 - 100.0).collect();
+    let y: Vec<Num> = (0..rows).map(|i| (i % 7) as Num - 3.0).collect();
+    let mut columns = BTreeMap::<String, &[Num]>::new();
+    columns.insert(String::from("x"), &x);
+    columns.insert(String::from("y"), &y);
+
     let mut slab = Slab::new();
-    let mut ns = CachedCallbackNamespace::new(|name, args| {
-        eprintln!("In CB: {name}");
-        match name {
-            "x" => Some(1.0),
-            "y" => Some(2.0),
-            "z" => Some(3.0),
-            "foo" => Some(args.first().unwrap_or(&std::f32::NAN) * 10.0),
-            "bar" => {
-                Some(args.first().unwrap_or(&std::f32::NAN) + args.get(1).unwrap_or(&std::f32::NAN))
-            }
-            _ => None,
-        }
-    });
-    assert_eq!(
-        Parser::new()
-            .parse("x + 1.5", &mut slab.ps)
+    for expr_str in [
+        "x",
+        "3",
+        "x + y * 2",
+        "x - y / 3",
+        "-x ^ 2 % 7",
+        "sin(x) * cos(y) + abs(y)",
+        "x < y || y == 0 && x >= -50",
+        "x xor y",
+        "!y + max(x, y, 10) - min(x, 0)",
+        "round(0.5, x / y)",
+        "log(2, abs(x) + 1) + atan2d(y, x)",
+        "smoothstep(-50, 50, x) + poly(y, 1, 2, 3) + bucket(x, -100, 300, 8)",
+        "avg(x, y, 1) + seq(y, x)",
+        "select(x, y, x, 7)",
+    ] {
+        let instr = Parser::new()
+            .parse(expr_str, &mut slab.ps)
             .unwrap()
             .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(2.5)
-    );
+            .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
 
-    assert_eq!(
-        Parser::new()
-            .parse("x() + 1.5", &mut slab.ps)
-            .unwrap()
+        let mut out = vec![0.0; rows];
+        eval_columns(&instr, &slab, &columns, &mut out).unwrap();
+        for (i, val) in out.iter().enumerate() {
+            let mut ns = BTreeMap::<String, Num>::new();
+            ns.insert(String::from("x"), x[i]);
+            ns.insert(String::from("y"), y[i]);
+            let expect = instr.eval(&slab, &mut ns).unwrap();
+            assert!(
+                val.to_bits() == expect.to_bits() || val.is_nan() && expect.is_nan(),
+                "{expr_str} at row {i}: {val} != {expect}"
+            );
+        }
+    }
+
+    let mut eval = |expr_str: &str, out: &mut [Num]| {
+        let instr = Parser::new()
+            .parse(expr_str, &mut slab.ps)?
             .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(2.5)
+            .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+        eval_columns(&instr, &slab, &columns, out)
+    };
+    assert_eq!(
+        eval("x + z", &mut vec![0.0; rows]),
+        Err(Error::Undefined(String::from("z")))
+    );
+    assert_eq!(
+        eval("x + f(y)", &mut vec![0.0; rows]),
+        Err(Error::UndefinedFunction(String::from("f")))
+    );
+    // An unused column is not looked up, so '||' can skip it:
+    assert_eq!(eval("1 || z", &mut vec![0.0; rows]), Ok(()));
+    assert_eq!(
+        eval("x", &mut [0.0; 10]),
+        Err(Error::WrongArgs(String::from(
+            "eval_columns: column 'x' has 1000 rows, expected 10"
+        )))
     );
 
+    // No rows, no work:
+    let empty = BTreeMap::<String, &[Num]>::new();
+    assert_eq!(eval_columns(&IConst(1.0), &slab, &empty, &mut []), Ok(()));
+}
+
+#[test]
+fn var_names_into() {
+    let mut slab = Slab::new();
+    let parser = Parser::new();
+    let expr1 = parser.parse_noclear("a + f(b, 2)", &mut slab.ps).unwrap();
+    let expr2 = parser
+        .parse_noclear("b * c - sin(d)", &mut slab.ps)
+        .unwrap();
+    let names = |strs: &[&str]| {
+        strs.iter()
+            .copied()
+            .map(String::from)
+            .collect::<BTreeSet<_>>()
+    };
+
+    // Names accumulate across expressions:
+    let mut set = BTreeSet::new();
+    slab.ps.get_expr(expr1).var_names_into(&slab, &mut set);
+    assert_eq!(set, names(&["a", "b", "f"]));
+    slab.ps.get_expr(expr2).var_names_into(&slab, &mut set);
+    assert_eq!(set, names(&["a", "b", "c", "d", "f"]));
+
+    // ...or the set can be cleared and reused:
+    set.clear();
+    slab.ps.get_expr(expr2).var_names_into(&slab, &mut set);
+    assert_eq!(set, names(&["b", "c", "d"]));
+    assert_eq!(set, slab.ps.get_expr(expr2).var_names(&slab));
+
+    // Compiled Instructions work too:
+    set.clear();
+    let instr = slab
+        .ps
+        .get_expr(expr1)
+        .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+    instr.var_names_into(&slab, &mut set);
+    assert_eq!(set, names(&["a", "b", "f"]));
+}
+
+#[test]
+fn constants() {
+    let mut slab = Slab::new();
+    let mut compile = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+        instr.constants(&slab)
+    };
+
+    assert_eq!(compile("(x + 2) * 3"), vec![2.0, 3.0]);
+    // Folded constants are only seen as the result:
+    assert_eq!(compile("2 * 3"), vec![6.0]);
+    assert_eq!(compile("x + y"), Vec::<Num>::new());
+    // Duplicates are kept, and every branch is visited:
     assert_eq!(
-        Parser::new()
-            .parse("x(1,2,3) + 1.5", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(2.5)
+        compile("x < 1 || f(x, 1) > 2 || select(y, 4, x)"),
+        vec![1.0, 1.0, 2.0, 4.0]
+    );
+    assert_eq!(
+        compile("min(x, 5, y) + clamp(x, 0, 1)"),
+        vec![5.0, 0.0, 1.0]
     );
+}
 
-    eprintln!("I should see TWO x lookups, 1 y, and 1 z:");
+#[test]
+fn angle_suffixes() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("sin(90deg)"), Ok(1.0));
+    assert_eq!(eval("cos(0deg)"), Ok(1.0));
+    assert_eq!(eval("180deg == pi()"), Ok(1.0));
+    assert_eq!(eval("1.5rad"), Ok(1.5));
+    assert_eq!(eval("-90deg * 2"), Ok(-fasteval3::consts::PI));
+}
+
+#[test]
+fn angle_functions() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    // Inverse functions return radians, and their 'd' variants return degrees:
+    assert_eq!(eval("atan2d(1, 1)"), Ok(45.0));
+    assert_eq!(eval("atan2(1, 1) == 45deg"), Ok(1.0));
+    assert_eq!(eval("atan(1) == 45deg"), Ok(1.0));
+    assert_eq!(eval("atan2d(1, 1) == atand(1)"), Ok(1.0));
+    assert_eq!(eval("atan2d(-1, -1)"), Ok(-135.0));
+    assert_eq!(eval("asind(1) == acosd(0)"), Ok(1.0));
+
+    // ...and they round-trip with the 'deg' suffix, which converts to radians:
+    assert_eq!(eval("abs(asind(sin(30deg)) - 30) < 1e-4"), Ok(1.0));
+    assert_eq!(eval("abs(acosd(cos(60deg)) - 60) < 1e-4"), Ok(1.0));
+    assert_eq!(eval("abs(atand(tan(45deg)) - 45) < 1e-4"), Ok(1.0));
     assert_eq!(
-        Parser::new()
-            .parse("x(x,y,z) + 1.5", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(2.5)
+        eval("abs(atan2d(sin(120deg), cos(120deg)) - 120) < 1e-4"),
+        Ok(1.0)
     );
+    assert_eq!(eval("asin(sin(30deg)) == 30deg"), Ok(1.0));
+    assert_eq!(eval("tan(atan2(1, 1)) == 1"), Ok(1.0));
+}
 
-    eprintln!("I should see TWO x lookups:");
+#[test]
+fn exp_associativity() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    #[cfg(not(feature = "left-assoc-exp"))]
+    {
+        assert_eq!(eval("2^3^2"), Ok(512.0));
+        assert_eq!(eval("2^3^2 == 2^(3^2)"), Ok(1.0));
+    }
+    #[cfg(feature = "left-assoc-exp")]
+    {
+        assert_eq!(eval("2^3^2"), Ok(64.0));
+        assert_eq!(eval("2^3^2 == (2^3)^2"), Ok(1.0));
+    }
+    assert_eq!(eval("(2^3)^2"), Ok(64.0));
+    assert_eq!(eval("2^(3^2)"), Ok(512.0));
+}
+
+#[test]
+fn single_equals() {
+    let mut slab = Slab::new();
+    let mut ns = BTreeMap::<String, Num>::new();
+    ns.insert(String::from("x"), 2.0);
+    let parser = Parser {
+        single_equals: true,
+        ..Parser::new()
+    };
+    let mut eval =
+        |parser: &Parser, expr_str: &str| eval_both_with(parser, &mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval(&parser, "1 = 1"), Ok(1.0));
+    assert_eq!(eval(&parser, "1 = 2"), Ok(0.0));
+    assert_eq!(eval(&parser, "x=2"), Ok(1.0));
+    assert_eq!(eval(&parser, "1 == 1"), Ok(1.0));
+    assert_eq!(eval(&parser, "1 != 1"), Ok(0.0));
+    assert_eq!(eval(&parser, "1 <= 1"), Ok(1.0));
+    assert_eq!(eval(&parser, "2 >= 3 = 0"), Ok(1.0));
+
+    // Off by default:
     assert_eq!(
-        Parser::new()
-            .parse("x(x,x,x) + 1.5", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(2.5)
+        eval(&Parser::new(), "1 = 1"),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("= 1"),
+            pos: 2
+        })
     );
+}
 
-    eprintln!("I should see TWO x lookups:");
+#[test]
+fn custom_ops() {
+    let mut slab = Slab::new();
+    // 'a @ b' is the multiple of 'b' that is nearest to 'a', like 'round(b, a)':
+    let mut ns = CachedCallbackNamespace::new(|name, args| match (name, args.as_slice()) {
+        ("nearest", [a, b]) => Some((a / b).round() * b),
+        ("pair", [a, b]) => Some(a * 10.0 + b),
+        ("x", []) => Some(7.0),
+        _ => None,
+    });
+    let mut parser = Parser::new();
+    parser.custom_ops.insert(
+        '@',
+        CustomOp {
+            func: String::from("nearest"),
+            precedence: EMul,
+        },
+    );
+    parser.custom_ops.insert(
+        '⊕',
+        CustomOp {
+            func: String::from("pair"),
+            precedence: EOR,
+        },
+    );
+    // Operators are parsed as calls of their functions:
+    let expr_i = parser.parse("x @ y", &mut slab.ps).unwrap();
     assert_eq!(
-        Parser::new()
-            .parse("x(1.0) + x(1.1) + x(1.0) + x(1.1)", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(4.0)
+        slab.ps.get_expr(expr_i).required_signatures(&slab),
+        BTreeMap::from([
+            (String::from("nearest"), BTreeSet::from([2])),
+            (String::from("x"), BTreeSet::from([0])),
+            (String::from("y"), BTreeSet::from([0])),
+        ])
     );
 
-    eprintln!("---------------------------");
+    let mut eval =
+        |parser: &Parser, expr_str: &str| eval_both_with(parser, &mut slab, &mut ns, expr_str);
 
+    assert_eq!(eval(&parser, "7.4 @ 2"), Ok(8.0));
     assert_eq!(
-        Parser::new()
-            .parse("foo(1.23)", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(12.3)
+        eval(&parser, "7.4 @ 2"),
+        ez_eval("round(2, 7.4)", &mut EmptyNamespace)
+    );
+    assert_eq!(eval(&parser, "x@5"), Ok(5.0));
+    // Binds more tightly than '*', but less tightly than '/':
+    assert_eq!(eval(&parser, "1 + 7 @ 2 * 3"), Ok(25.0));
+    assert_eq!(eval(&parser, "1 + 3 * 7 @ 2"), Ok(25.0));
+    assert_eq!(eval(&parser, "7 @ 4 / 2"), Ok(8.0));
+    assert_eq!(eval(&parser, "(1 + 7) @ 3"), Ok(9.0));
+    // Left-to-right:
+    assert_eq!(eval(&parser, "7 @ 2 @ 5"), Ok(10.0));
+    // Lower-precedence custom operators group the higher ones:
+    assert_eq!(eval(&parser, "1 + 2 ⊕ 3 * 4"), Ok(42.0));
+    assert_eq!(eval(&parser, "1 ⊕ 7 @ 2 + 1"), Ok(19.0));
+    assert_eq!(eval(&parser, "0 || 1 ⊕ 2"), Ok(12.0));
+
+    // The function is resolved through the Namespace like any custom function:
+    assert_eq!(
+        eval(&parser, "1 @ y"),
+        Err(Error::Undefined(String::from("y")))
     );
 
+    // Unregistered symbols are still rejected:
     assert_eq!(
-        Parser::new()
-            .parse("bar(1.23, 3.21)", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, {
-                ns.cache_clear();
-                &mut ns
-            }),
-        Ok(4.439_999_999_999_999_5)
+        eval(&Parser::new(), "7 @ 2"),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("@ 2"),
+            pos: 2
+        })
     );
+}
+
+#[test]
+fn minz_maxz() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("minz()"), Ok(Num::INFINITY));
+    assert_eq!(eval("maxz()"), Ok(Num::NEG_INFINITY));
+    assert_eq!(eval("minz(3)"), Ok(3.0));
+    assert_eq!(eval("maxz(3)"), Ok(3.0));
+    assert_eq!(eval("minz(3, -1, 2)"), Ok(-1.0));
+    assert_eq!(eval("maxz(3, -1, 2)"), Ok(3.0));
 
+    // The normal versions still require args:
     assert_eq!(
-        format!(
-            "{:?}",
-            Parser::new()
-                .parse("bar(1.23)", &mut slab.ps)
-                .unwrap()
-                .from(&slab.ps)
-                .eval(&slab, {
-                    ns.cache_clear();
-                    &mut ns
-                })
-        ),
-        "Ok(NaN)"
+        eval("min()"),
+        Err(Error::WrongArgs(String::from(
+            "min: expected one or more args"
+        )))
+    );
+    assert_eq!(
+        eval("max()"),
+        Err(Error::WrongArgs(String::from(
+            "max: expected one or more args"
+        )))
     );
 }
 
 #[test]
-#[cfg(feature = "unsafe-vars")]
-fn unsafe_var() {
+fn min_max_ties() {
     let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "z" => Some(0.0),
+        "nz" => Some(-0.0),
+        _ => None,
+    });
+    // Returns the sign bit of both the interpreted and compiled results:
+    let mut eval_neg = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        let val = expr_ref.eval(&slab, &mut ns).unwrap();
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+        let compiled = instr.eval(&slab, &mut ns).unwrap();
+        assert_eq!(val, 0.0, "{expr_str}");
+        (val.is_sign_negative(), compiled.is_sign_negative())
+    };
 
-    let mut ua = 1.23;
-    let mut ub = 4.56;
-    unsafe {
-        slab.ps.add_unsafe_var("ua".to_string(), &ua);
-        slab.ps.add_unsafe_var("ub".to_string(), &ub);
-    }
+    // 'min' and 'max' return the later argument on ties:
+    assert_eq!(eval_neg("max(0, -0)"), (true, true));
+    assert_eq!(eval_neg("max(-0, 0)"), (false, false));
+    assert_eq!(eval_neg("min(0, -0)"), (true, true));
+    assert_eq!(eval_neg("max(z, nz)"), (true, true));
+    assert_eq!(eval_neg("min(nz, z)"), (false, false));
+    assert_eq!(eval_neg("max(z, -0, 0)"), (false, false));
 
-    let mut ns = EmptyNamespace;
+    // 'min_first' and 'max_first' return the earlier argument:
+    assert_eq!(eval_neg("max_first(0, -0)"), (false, false));
+    assert_eq!(eval_neg("max_first(-0, 0)"), (true, true));
+    assert_eq!(eval_neg("min_first(0, -0)"), (false, false));
+    assert_eq!(eval_neg("max_first(z, nz)"), (false, false));
+    assert_eq!(eval_neg("min_first(nz, z)"), (true, true));
+    assert_eq!(eval_neg("max_first(-0, z, 0)"), (true, true));
+    assert_eq!(eval_neg("max_first(-1, nz, z)"), (true, true));
 
+    // Otherwise they behave like 'min' and 'max':
     assert_eq!(
-        Parser::new()
-            .parse("ua + ub + 5", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, &mut ns),
-        Ok(10.79)
+        ez_eval("min_first(3, -1, 2)", &mut EmptyNamespace),
+        Ok(-1.0)
     );
-
-    ua += 1.0;
-    ub += 2.0;
+    assert_eq!(ez_eval("max_first(3, -1, 2)", &mut EmptyNamespace), Ok(3.0));
     assert_eq!(
-        Parser::new()
-            .parse("ua + ub + 5", &mut slab.ps)
-            .unwrap()
-            .from(&slab.ps)
-            .eval(&slab, &mut ns),
-        Ok(13.79)
+        ez_eval("max_first()", &mut EmptyNamespace),
+        Err(Error::WrongArgs(String::from(
+            "max_first: expected one or more args"
+        )))
+    );
+    slab.nan_policy = NanPolicy::Error;
+    let expr_i = Parser::new()
+        .parse("min_first(1, NaN)", &mut slab.ps)
+        .unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).eval(&slab, &mut EmptyNamespace),
+        Err(Error::NanArgument(String::from("min_first")))
     );
+}
 
-    let _ = (ua, ub); // Silence compiler warnings about variables not being read.
+#[test]
+fn ranges() {
+    // Each value of a range is stored in the Slab:
+    let mut slab = Slab::with_capacity(2048);
+    let mut ns = CachedCallbackNamespace::new(|name, args| match name {
+        "sum" => Some(args.into_iter().sum()),
+        "count" => Some(args.len() as Num),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("sum(1..3)"), Ok(6.0));
+    assert_eq!(eval("sum( 1..3 , 10 )"), Ok(16.0));
+    assert_eq!(eval("sum(10, -2..2)"), Ok(10.0));
+    assert_eq!(eval("count(5..1)"), Ok(5.0));
+    assert_eq!(eval("count(3..3)"), Ok(1.0));
+    assert_eq!(eval("max(1..4) + min(-3..3)"), Ok(1.0));
+    assert_eq!(eval("sum(1..1024)"), Ok(524_800.0));
+    assert_eq!(eval("sum(1..1025)"), Err(Error::TooLong));
+
+    // Only whole arguments are ranges:
+    assert!(eval("sum(1..3 + 1)").is_err());
+    assert!(eval("sum(1.5..3)").is_err());
+}
+
+#[test]
+fn curly_braces() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, args| match name {
+        "x" => Some(2.0),
+        "sum" => Some(args.into_iter().sum()),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("{1+2}*3"), Ok(9.0));
+    assert_eq!(eval("max{1,2,3}"), Ok(3.0));
+    assert_eq!(eval("{ (x + [1]) * {2} }"), Ok(6.0));
+    assert_eq!(eval("sum{1..3} + sum{x; 1}"), Ok(9.0));
+
+    // Delimiters must match:
+    assert_eq!(eval("{x]"), Err(Error::Expected(String::from("}"))));
+    assert_eq!(eval("(x}"), Err(Error::Expected(String::from(")"))));
+    assert_eq!(eval("max{1,2]"), Err(Error::Expected(String::from("}"))));
+    assert_eq!(eval("max(1,2}"), Err(Error::Expected(String::from(")"))));
+}
+
+#[test]
+fn strict_non_finite_rounding() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(2.5),
+        "nan" => Some(Num::NAN),
+        "big" => Some(Num::INFINITY),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str, strict: bool| {
+        slab.strict = strict;
+        eval_both(&mut slab, &mut ns, expr_str)
+    };
+
+    for (expr_str, name) in [
+        ("int(nan)", "int"),
+        ("int(big)", "int"),
+        ("ceil(-big)", "ceil"),
+        ("floor(nan)", "floor"),
+        ("floor(inf)", "floor"),
+        ("round(NaN)", "round"),
+        ("round(0, x)", "round"),
+        ("round_half_up(big)", "round_half_up"),
+        ("roundz(x, -inf)", "roundz"),
+        ("floor(x) + int(1/0)", "int"),
+    ] {
+        assert!(!eval(expr_str, false).unwrap().is_finite(), "{expr_str}");
+        assert_eq!(
+            eval(expr_str, true),
+            Err(Error::NonFinite(String::from(name))),
+            "{expr_str}"
+        );
+    }
+
+    // Finite results are unaffected:
+    assert_eq!(eval("int(x)", true), Ok(2.0));
+    assert_eq!(eval("ceil(x)", true), Ok(3.0));
+    assert_eq!(eval("floor(-x)", true), Ok(-3.0));
+    assert_eq!(eval("round(x)", true), Ok(3.0));
+    assert_eq!(eval("roundz(x)", true), Ok(2.0));
+    assert_eq!(eval("round(0.5, x)", true), Ok(2.5));
+}
+
+#[test]
+fn strict_hyperbolic_overflow() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "big" => Some(1000.0),
+        "small" => Some(2.0),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str, strict: bool| {
+        slab.strict = strict;
+        eval_both(&mut slab, &mut ns, expr_str)
+    };
+
+    for expr_str in [
+        "sinh(big)",
+        "cosh(big)",
+        "cosh(-big)",
+        "sinh(1000)",
+        "cosh(-1000)",
+    ] {
+        assert!(eval(expr_str, false).unwrap().is_infinite());
+        assert_eq!(eval(expr_str, true), Err(Error::Overflow));
+    }
+    assert_eq!(eval("sinh(-big)", false), Ok(Num::NEG_INFINITY));
+    assert_eq!(eval("sinh(-big)", true), Err(Error::Overflow));
+
+    // No error for in-range or non-finite inputs:
+    assert_eq!(eval("sinh(small)", true), Ok(Num::sinh(2.0)));
+    assert_eq!(eval("cosh(small)", true), Ok(Num::cosh(2.0)));
+    assert_eq!(eval("cosh(inf)", true), Ok(Num::INFINITY));
+    assert!(eval("sinh(NaN)", true).unwrap().is_nan());
+}
+
+#[test]
+fn nan_policy() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(2.0),
+        "nan" => Some(Num::NAN),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str, nan_policy: NanPolicy| {
+        slab.nan_policy = nan_policy;
+        eval_both(&mut slab, &mut ns, expr_str)
+    };
+
+    for expr_str in [
+        "min(x, nan, 3)",
+        "min(nan, x, 3)",
+        "min(3, x, NaN)",
+        "min(NaN, 3, 2)",
+    ] {
+        assert!(eval(expr_str, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(eval(expr_str, NanPolicy::Ignore), Ok(2.0));
+        assert_eq!(
+            eval(expr_str, NanPolicy::Error),
+            Err(Error::NanArgument(String::from("min")))
+        );
+    }
+    for expr_str in [
+        "max(x, nan, 1)",
+        "max(nan, x, 1)",
+        "max(1, x, NaN)",
+        "maxz(NaN, 1, 2)",
+    ] {
+        assert!(eval(expr_str, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(eval(expr_str, NanPolicy::Ignore), Ok(2.0));
+        assert_eq!(
+            eval(expr_str, NanPolicy::Error),
+            Err(Error::NanArgument(String::from("max")))
+        );
+    }
+
+    // Only all-NaN arguments give NaN when ignoring:
+    assert!(eval("min(nan, NaN)", NanPolicy::Ignore).unwrap().is_nan());
+    assert!(eval("max(NaN, nan, NaN)", NanPolicy::Ignore)
+        .unwrap()
+        .is_nan());
+
+    // A single argument is never compared, so it is returned as-is:
+    assert!(eval("min(nan)", NanPolicy::Error).unwrap().is_nan());
+    assert!(eval("max(NaN)", NanPolicy::Error).unwrap().is_nan());
+
+    // No NaN, no difference:
+    for nan_policy in [NanPolicy::Propagate, NanPolicy::Ignore, NanPolicy::Error] {
+        assert_eq!(eval("min(x, 3, -1)", nan_policy), Ok(-1.0));
+        assert_eq!(eval("max(x, 3, -1)", nan_policy), Ok(3.0));
+    }
+    assert_eq!(Slab::new().nan_policy, NanPolicy::Propagate);
+}
+
+#[test]
+fn min_max_short_circuit() {
+    let mut slab = Slab::new();
+    let calls = std::cell::Cell::new(0);
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "f" => {
+            calls.set(calls.get() + 1);
+            args.first().copied()
+        }
+        "x" => Some(2.0),
+        "big" => Some(Num::INFINITY),
+        "small" => Some(Num::NEG_INFINITY),
+        _ => None,
+    };
+    // Returns the result and the number of f() calls, which must be the same
+    // for every way of evaluating:
+    let mut eval = |expr_str: &str, nan_policy: NanPolicy| {
+        slab.nan_policy = nan_policy;
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+        calls.set(0);
+        let compiled = instr.eval(&slab, &mut ns);
+        let compiled_calls = calls.get();
+        let others = [
+            expr_ref.eval(&slab, &mut ns),
+            instr.eval_iterative(&slab, &mut ns),
+        ];
+        assert_eq!(calls.get(), 3 * compiled_calls, "{expr_str}");
+        for val in others {
+            assert_eq!(format!("{val:?}"), format!("{compiled:?}"), "{expr_str}");
+        }
+        (compiled, compiled_calls)
+    };
+
+    // Nothing after an infinite argument can change the result when ignoring NaN:
+    for (expr_str, val, n) in [
+        ("max(big, f(x))", Num::INFINITY, 0),
+        ("max(big, f(x), f(x))", Num::INFINITY, 0),
+        ("max(x, big, f(x))", Num::INFINITY, 0),
+        ("max(f(x), big, f(x))", Num::INFINITY, 1),
+        ("min(small, f(x))", Num::NEG_INFINITY, 0),
+        ("min(x, small, f(x), f(x))", Num::NEG_INFINITY, 0),
+        ("max(small, f(x))", 2.0, 1),
+        ("min(big, f(x), f(x))", 2.0, 2),
+    ] {
+        assert_eq!(
+            eval(expr_str, NanPolicy::Ignore),
+            (Ok(val), n),
+            "{expr_str}"
+        );
+    }
+
+    // Otherwise, a later NaN would change the result:
+    for nan_policy in [NanPolicy::Propagate, NanPolicy::Error] {
+        assert_eq!(
+            eval("max(big, f(x), f(x))", nan_policy),
+            (Ok(Num::INFINITY), 2)
+        );
+        assert_eq!(
+            eval("min(small, f(x))", nan_policy),
+            (Ok(Num::NEG_INFINITY), 1)
+        );
+    }
+}
+
+#[test]
+fn nanmin_nanmax() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(2.0),
+        "nan" => Some(Num::NAN),
+        _ => None,
+    });
+    let mut eval = |expr_str: &str, nan_policy: NanPolicy| {
+        slab.nan_policy = nan_policy;
+        eval_both(&mut slab, &mut ns, expr_str)
+    };
+
+    // NaN is skipped whatever the policy is:
+    for nan_policy in [NanPolicy::Propagate, NanPolicy::Ignore, NanPolicy::Error] {
+        assert_eq!(eval("nanmin(NaN, 2, 3)", nan_policy), Ok(2.0));
+        assert_eq!(eval("nanmin(3, nan, x)", nan_policy), Ok(2.0));
+        assert_eq!(eval("nanmin(x, NaN)", nan_policy), Ok(2.0));
+        assert_eq!(eval("nanmax(NaN, 2, 3)", nan_policy), Ok(3.0));
+        assert_eq!(eval("nanmax(nan, x, nan, -1)", nan_policy), Ok(2.0));
+        assert_eq!(eval("nanmax(x)", nan_policy), Ok(2.0));
+
+        // Only all-NaN arguments give NaN:
+        assert!(eval("nanmin(NaN, NaN)", nan_policy).unwrap().is_nan());
+        assert!(eval("nanmin(nan, NaN)", nan_policy).unwrap().is_nan());
+        assert!(eval("nanmax(nan)", nan_policy).unwrap().is_nan());
+    }
+
+    assert_eq!(
+        ez_eval("nanmin()", &mut EmptyNamespace),
+        Err(Error::WrongArgs(String::from(
+            "nanmin: expected one or more args"
+        )))
+    );
+}
+
+#[test]
+fn min_max_n() {
+    for vals in [
+        [3.0, 1.0, 4.0, 1.5, 9.0],
+        [0.0, -0.0, 0.0, -0.0, 0.0],
+        [Num::INFINITY, Num::NEG_INFINITY, 0.0, 1.0, 2.0],
+        [Num::NAN, 2.0, 1.0, 5.0, 3.0],
+        [2.0, -9999.0, 1.0, Num::NAN, 5.0],
+        [-9999.0, -9999.0, 7.0, -9999.0, -9999.0],
+        [-9999.0; 5],
+    ] {
+        let cb = |name: &str, _: Vec<Num>| {
+            ["a", "b", "c", "d", "e"]
+                .iter()
+                .position(|n| *n == name)
+                .map(|i| vals[i])
+        };
+        let mut ns = fasteval3::MissingValueNamespace::new(cb, -9999.0);
+        for nan_policy in [NanPolicy::Propagate, NanPolicy::Ignore, NanPolicy::Error] {
+            for (flat, chained) in [
+                ("min(a, b, c, d, e)", "min(min(min(min(a, b), c), d), e)"),
+                ("max(a, b, c, d, e)", "max(max(max(max(a, b), c), d), e)"),
+                (
+                    "min(a, b, 2, c, d, e)",
+                    "min(min(min(min(min(a, b), c), d), e), 2)",
+                ),
+                (
+                    "max(a, 2, b, c, d, e)",
+                    "max(max(max(max(max(a, b), c), d), e), 2)",
+                ),
+            ] {
+                let mut slab = Slab::new();
+                slab.nan_policy = nan_policy;
+                let mut compile = |expr_str: &str| {
+                    Parser::new()
+                        .parse(expr_str, &mut slab.ps)
+                        .unwrap()
+                        .from(&slab.ps)
+                        .compile(&slab.ps, &mut slab.cs, &mut ns)
+                };
+                let flat_instr = compile(flat);
+                let chained_instr = compile(chained);
+                assert!(matches!(flat_instr, IFuncMinN(_) | IFuncMaxN(_)), "{flat}");
+
+                let expect = format!("{:?}", chained_instr.eval(&slab, &mut ns));
+                assert_eq!(
+                    format!("{:?}", flat_instr.eval(&slab, &mut ns)),
+                    expect,
+                    "{flat} {vals:?} {nan_policy:?}"
+                );
+                assert_eq!(
+                    format!("{:?}", flat_instr.eval_iterative(&slab, &mut ns)),
+                    expect,
+                    "{flat} {vals:?} {nan_policy:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn op_dispatch() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(200.0),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str, slab: &mut Slab| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps)?;
+        slab.ps.get_expr(expr_i).eval(slab, &mut ns)
+    };
+
+    // Saturating add, clamped to the range of an i8:
+    let mut dispatch = OpDispatch::new();
+    dispatch.set(EAdd, |a, b| (a + b).clamp(-128.0, 127.0));
+    slab.op_dispatch = Some(dispatch);
+
+    assert_eq!(eval("100 + 100", &mut slab), Ok(127.0));
+    assert_eq!(eval("-100 + -100", &mut slab), Ok(-128.0));
+    assert_eq!(eval("1 + 2", &mut slab), Ok(3.0));
+    assert_eq!(eval("x + 1", &mut slab), Ok(127.0));
+    assert_eq!(eval("(100 + 100) - 27", &mut slab), Ok(100.0));
+    // Precedence is unchanged, and other operators keep their behavior:
+    assert_eq!(eval("2 + 3 * 50", &mut slab), Ok(127.0));
+    assert_eq!(eval("200 - 1", &mut slab), Ok(199.0));
+    assert_eq!(eval("x * 2", &mut slab), Ok(400.0));
+    assert_eq!(eval("100 + 100 > 126 and 1", &mut slab), Ok(1.0));
+
+    // An overridden 'and' no longer short-circuits:
+    dispatch.set(EAND, |a, b| a.min(b));
+    slab.op_dispatch = Some(dispatch);
+    assert_eq!(eval("0 and 5", &mut slab), Ok(0.0));
+    assert_eq!(eval("3 and 5", &mut slab), Ok(3.0));
+    assert_eq!(
+        eval("0 and y", &mut slab),
+        Err(Error::Undefined(String::from("y")))
+    );
+    assert_eq!(eval("2 + 100 and 5 or 0", &mut slab), Ok(5.0));
+
+    // Removing an override restores the built-in operator:
+    dispatch.unset(EAdd);
+    slab.op_dispatch = Some(dispatch);
+    assert_eq!(eval("100 + 100", &mut slab), Ok(200.0));
+    assert!(dispatch.get(EAdd).is_none());
+    assert!(dispatch.get(EAND).is_some());
+
+    slab.op_dispatch = None;
+    assert_eq!(eval("100 + 100", &mut slab), Ok(200.0));
+    assert_eq!(eval("0 and y", &mut slab), Ok(0.0));
+    assert!(Slab::new().op_dispatch.is_none());
+}
+
+#[test]
+fn eval_or() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(2.0),
+        _ => None,
+    });
+
+    let expr_i = Parser::new().parse("x * 3", &mut slab.ps).unwrap();
+    let expr_ref = slab.ps.get_expr(expr_i);
+    assert_eq!(expr_ref.eval_or(&slab, &mut ns, Num::NAN), 6.0);
+    let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr.eval_or(&slab, &mut ns, Num::NAN), 6.0);
+
+    let expr_i = Parser::new().parse("y * 3", &mut slab.ps).unwrap();
+    let expr_ref = slab.ps.get_expr(expr_i);
+    assert_eq!(expr_ref.eval_or(&slab, &mut ns, 0.0), 0.0);
+    assert!(expr_ref.eval_or(&slab, &mut ns, Num::NAN).is_nan());
+    let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr.eval_or(&slab, &mut ns, -1.0), -1.0);
+}
+
+#[test]
+fn eval_operands() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(4.0),
+        _ => None,
+    });
+
+    let expr_i = Parser::new().parse("1 + 2 * 3", &mut slab.ps).unwrap();
+    let expr_ref = slab.ps.get_expr(expr_i);
+    assert_eq!(
+        expr_ref.eval_operands(&slab, &mut ns),
+        Ok(vec![(None, 1.0), (Some(EAdd), 2.0), (Some(EMul), 3.0)])
+    );
+    assert_eq!(expr_ref.eval(&slab, &mut ns), Ok(7.0));
+
+    // Sub-expressions are evaluated as a whole:
+    let expr_i = Parser::new()
+        .parse("(1 + 2) * -max(x, 2)", &mut slab.ps)
+        .unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).eval_operands(&slab, &mut ns),
+        Ok(vec![(None, 3.0), (Some(EMul), -4.0)])
+    );
+
+    let expr_i = Parser::new().parse("x", &mut slab.ps).unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).eval_operands(&slab, &mut ns),
+        Ok(vec![(None, 4.0)])
+    );
+
+    let expr_i = Parser::new().parse("x < y", &mut slab.ps).unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).eval_operands(&slab, &mut ns),
+        Err(Error::Undefined(String::from("y")))
+    );
+}
+
+#[test]
+fn blend() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    // Two pairs:
+    assert_eq!(eval("blend(1, 1, 4, 2)"), Ok(3.0));
+    assert_eq!(eval("blend(10, 0.25, 20, 0.75)"), Ok(17.5));
+    // Three pairs:
+    assert_eq!(eval("blend(1, 1, 2, 1, 3, 2)"), Ok(2.25));
+    assert_eq!(eval("blend(5, 1, 0, 0, 0, 0)"), Ok(5.0));
+
+    assert!(eval("blend(1, 0, 2, 0)").unwrap().is_nan());
+    assert_eq!(
+        eval("blend(1, 1, 2)"),
+        Err(Error::WrongArgs(String::from(
+            "blend: expected value,weight pairs"
+        )))
+    );
+    assert_eq!(
+        eval("blend()"),
+        Err(Error::WrongArgs(String::from(
+            "blend: expected value,weight pairs"
+        )))
+    );
+}
+
+#[test]
+fn norm_sumsq() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("norm(3, 4)"), Ok(5.0));
+    assert_eq!(eval("norm(-3)"), Ok(3.0));
+    assert_eq!(eval("norm(2, 3, 6)"), Ok(7.0));
+    assert_eq!(eval("sumsq(1, 2, 3)"), Ok(14.0));
+    assert_eq!(eval("sumsq(-2)"), Ok(4.0));
+
+    assert_eq!(
+        eval("norm()"),
+        Err(Error::WrongArgs(String::from(
+            "norm: expected one or more args"
+        )))
+    );
+    assert_eq!(
+        eval("sumsq()"),
+        Err(Error::WrongArgs(String::from(
+            "sumsq: expected one or more args"
+        )))
+    );
+}
+
+#[test]
+fn poly() {
+    let mut slab = Slab::new();
+    for x in [-2.5, -1.0, 0.0, 0.5, 2.0, 3.0] {
+        let mut ns = |name: &str, _: Vec<Num>| match name {
+            "x" => Some(x),
+            _ => None,
+        };
+        for (poly_str, expanded_str) in [
+            ("poly(x, 1, 2, 3)", "1 + 2*x + 3*x^2"),
+            ("poly(x, 7)", "7"),
+            ("poly(x, 0, 1)", "x"),
+            ("poly(x, -1, 0, 0, 0.5)", "-1 + 0.5*x^3"),
+        ] {
+            let expr_i = Parser::new().parse(expanded_str, &mut slab.ps).unwrap();
+            let expect = slab.ps.get_expr(expr_i).eval(&slab, &mut ns).unwrap();
+
+            let expr_i = Parser::new().parse(poly_str, &mut slab.ps).unwrap();
+            let expr_ref = slab.ps.get_expr(expr_i);
+            assert_eq!(expr_ref.eval(&slab, &mut ns), Ok(expect), "{poly_str}");
+            let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+            assert_eq!(instr.eval(&slab, &mut ns), Ok(expect), "{poly_str}");
+        }
+    }
+
+    assert_eq!(ez_eval("poly(2, 1, 2, 3)", &mut EmptyNamespace), Ok(17.0));
+    assert_eq!(
+        ez_eval("poly(2, 1, 2, 3)", &mut EmptyNamespace),
+        ez_eval("1 + 2*2 + 3*2^2", &mut EmptyNamespace)
+    );
+    assert_eq!(
+        Parser::new().parse("poly(x)", &mut slab.ps),
+        Err(Error::WrongArgs(String::from(
+            "poly: expected poly(x, c0, c1, ...)"
+        )))
+    );
+}
+
+#[test]
+fn bucket() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    // Boundaries:
+    assert_eq!(eval("bucket(0, 0, 10, 4)"), Ok(0.0));
+    assert_eq!(eval("bucket(2.5, 0, 10, 4)"), Ok(1.0));
+    assert_eq!(eval("bucket(9.999, 0, 10, 4)"), Ok(3.0));
+    assert_eq!(eval("bucket(10, 0, 10, 4)"), Ok(3.0));
+
+    // Out of range:
+    assert_eq!(eval("bucket(-5, 0, 10, 4)"), Ok(0.0));
+    assert_eq!(eval("bucket(50, 0, 10, 4)"), Ok(3.0));
+    assert_eq!(eval("bucket(-inf, 0, 10, 4)"), Ok(0.0));
+    assert_eq!(eval("bucket(inf, 0, 10, 4)"), Ok(3.0));
+
+    // Degenerate:
+    assert!(eval("bucket(NaN, 0, 10, 4)").unwrap().is_nan());
+    assert!(eval("bucket(5, 0, 10, 0)").unwrap().is_nan());
+    assert!(eval("bucket(5, 10, 10, 4)").unwrap().is_nan());
+
+    assert_eq!(
+        eval("bucket(5, 0, 10)"),
+        Err(Error::WrongArgs(String::from(
+            "bucket: expected bucket(x,lo,hi,n)"
+        )))
+    );
+}
+
+#[test]
+fn short_circuit() {
+    let calls = std::cell::Cell::new(0);
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "f" => {
+            calls.set(calls.get() + 1);
+            Some(5.0)
+        }
+        "zero" => Some(0.0),
+        "one" | "x" => Some(1.0),
+        _ => None,
+    };
+
+    for (expr_str, expect, expect_calls) in [
+        ("0 && f(x)", 0.0, 0),
+        ("1 || f(x)", 1.0, 0),
+        ("zero && f(x)", 0.0, 0),
+        ("one || f(x)", 1.0, 0),
+        ("zero && f(x) && f(x) || one", 1.0, 0),
+        ("one || f(x) && f(x)", 1.0, 0),
+        ("zero * 2 && f(x) + 1", 0.0, 0),
+        ("one && f(x)", 5.0, 1),
+        ("zero || f(x)", 5.0, 1),
+        ("zero && f(x) || f(x) && zero || f(x)", 5.0, 2),
+    ] {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+
+        // Interpreted:
+        calls.set(0);
+        assert_eq!(slab.ps.get_expr(expr_i).eval(&slab, &mut ns), Ok(expect));
+        assert_eq!(calls.get(), expect_calls, "eval {expr_str}");
+
+        // Compiled:
+        calls.set(0);
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(instr.eval(&slab, &mut ns), Ok(expect));
+        assert_eq!(calls.get(), expect_calls, "compiled eval {expr_str}");
+    }
+
+    // Custom functions with constant args are called during compilation, but
+    // not when the constant left side already determines the result:
+    let mut slab = Slab::new();
+    for (expr_str, expect_calls) in [("0 && f(1)", 0), ("1 || f(1)", 0), ("1 && f(1)", 1)] {
+        calls.set(0);
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        slab.ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(calls.get(), expect_calls, "compile {expr_str}");
+    }
+
+    // The skipped side is not evaluated, so its errors are not reported either:
+    let mut slab = Slab::new();
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+    assert_eq!(eval("zero && undefined"), Ok(0.0));
+    assert_eq!(
+        eval("one && undefined"),
+        Err(Error::Undefined(String::from("undefined")))
+    );
+}
+
+#[test]
+fn xor_not() {
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(2.0),
+        "note" => Some(7.0),
+        "nan" => Some(Num::NAN),
+        _ => None,
+    });
+
+    for (expr_str, expect) in [
+        // Truth table:
+        ("0 xor 0", 0.0),
+        ("0 xor 1", 1.0),
+        ("1 xor 0", 1.0),
+        ("1 xor 1", 0.0),
+        // Any non-zero value counts as true, and NaN is non-zero:
+        ("x xor 0", 1.0),
+        ("x xor -3", 0.0),
+        ("nan xor 0", 1.0),
+        ("1 xor 1 xor 1", 1.0),
+        // 'xor' binds tighter than 'or' and looser than 'and':
+        ("1 or 1 xor 1", 1.0),
+        ("1 xor 1 or 1", 1.0),
+        ("1 xor 1 and 0", 1.0),
+        ("0 and 1 xor 1", 1.0),
+        ("x > 1 xor x < 1", 1.0),
+        // 'not' is the same as '!':
+        ("not 0", 1.0),
+        ("not x", 0.0),
+        ("not(0) xor not 1", 1.0),
+        ("not x == 0", 1.0),
+        ("note", 7.0),
+        ("not note", 0.0),
+    ] {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        assert_eq!(expr_ref.eval(&slab, &mut ns), Ok(expect), "eval {expr_str}");
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(
+            instr.eval(&slab, &mut ns),
+            Ok(expect),
+            "compiled eval {expr_str}"
+        );
+    }
+
+    assert_eq!(
+        Parser {
+            reject_repeated_unary: true,
+            ..Parser::new()
+        }
+        .parse("not -x", &mut Slab::new().ps),
+        Err(Error::UnexpectedOperator(String::from("not -")))
+    );
+}
+
+#[test]
+fn eval_iterative() {
+    let calls = std::cell::Cell::new(0);
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "f" => {
+            calls.set(calls.get() + 1);
+            Some(args.iter().sum())
+        }
+        "x" => Some(2.0),
+        "y" => Some(-0.5),
+        "zero" => Some(0.0),
+        _ => None,
+    };
+
+    // Same results, errors and lookups as the recursive eval():
+    for expr_str in [
+        "1 + x * y - x / y",
+        "-x ^ 2 % 3 + !y",
+        "x < y or x >= 2 and y != 0",
+        "zero && f(1) || f(2, x) && zero",
+        "x xor y xor zero",
+        "log(x, 8) + round(0.25, y) + roundz(y) + round_half_up(y)",
+        "int(y) + ceil(y) + floor(y) + abs(y) + sign(y)",
+        "min(x, y, 3) * max(x, y) + min_first(x, 2) - max_first(y, -0.5)",
+        "nanmin(x, y, 3) + nanmax(y, f(1), -0.5)",
+        "avg(x, y, 3) + blend(x, 0.5, y, 1) + norm(x, y) + sumsq(x, y) + poly(x, 1, 2)",
+        "seq(f(1), f(2), x) + bucket(x, 0, 10, 5) + powmod(3, 4, 5) + smoothstep(y, 3, x)",
+        "clamp(x, y, 3) + clamp(f(1), 0, y) + clamp(2, x, zero)",
+        "select(x, f(x), f(y), f(x, y)) + select(zero + 1, f(x), f(y)) + select(y, f(y), x)",
+        "sin(x) + cos(x) + tan(y) + asin(y) + acos(y) + atan(x) + atan2d(y, x) + sqrt(x) + cbrt(y)",
+        "asind(y) + acosd(y) + atand(x) + sinh(x) + cosh(x) + tanh(y) + asinh(x) + acosh(x) + atanh(y)",
+        "f(x, f(y), 3) + undefined",
+        "x + g(1)",
+    ] {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        calls.set(0);
+        let expect = instr.eval(&slab, &mut ns);
+        let expect_calls = calls.get();
+        calls.set(0);
+        assert_eq!(instr.eval_iterative(&slab, &mut ns), expect, "{expr_str}");
+        assert_eq!(calls.get(), expect_calls, "{expr_str}");
+    }
+
+    // A long chain of additions compiles to deeply nested IAdd instructions,
+    // which would overflow the stack with the recursive eval():
+    let expr_str = ["x", "y"].repeat(100_000).join(" + ");
+    let parser = Parser {
+        expr_len_limit: expr_str.len(),
+        ..Parser::new()
+    };
+    let mut slab = Slab::new();
+    let expr_i = parser.parse(&expr_str, &mut slab.ps).unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(instr.eval_iterative(&slab, &mut ns), Ok(150_000.0));
+}
+
+#[test]
+fn num() {
+    assert_eq!(
+        mem::size_of::<Num>(),
+        if cfg!(feature = "f64") { 8 } else { 4 }
+    );
+    // 2^24 + 1 is the first integer that f32 can't represent:
+    assert_eq!(
+        ez_eval("(2^24 + 1) - 2^24", &mut EmptyNamespace),
+        Ok(if cfg!(feature = "f64") { 1.0 } else { 0.0 })
+    );
+    assert_eq!(
+        ez_eval("pi()", &mut EmptyNamespace),
+        Ok(fasteval3::consts::PI)
+    );
+}
+
+#[test]
+fn eval_metered() {
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "f" => Some(args.iter().sum()),
+        "x" => Some(2.0),
+        "y" => Some(-0.5),
+        "zero" => Some(0.0),
+        _ => None,
+    };
+
+    let mut slab = Slab::new();
+    for (expr_str, expect_ops) in [
+        ("3", 0),
+        ("x", 0),
+        ("2 * 3 + 4", 0),
+        ("x * 2 + sin(y)", 3),
+        // Subtraction compiles to an addition of a negation:
+        ("x - y", 2),
+        ("max(x, y, 3) + f(x, y)", 3),
+        ("f(f(x))", 2),
+        // Short-circuiting skips the right operand:
+        ("zero && f(y) + 1", 1),
+        ("x && f(y) + 1", 3),
+        ("x || f(y)", 1),
+        ("zero || y < x", 2),
+    ] {
+        let instr = Parser::new()
+            .parse(expr_str, &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        let expect = instr.eval(&slab, &mut ns).unwrap();
+        assert_eq!(
+            instr.eval_metered(&slab, &mut ns),
+            Ok((expect, expect_ops)),
+            "{expr_str}"
+        );
+    }
+
+    assert_eq!(
+        Parser::new()
+            .parse("x + undefined", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .compile(&slab.ps, &mut slab.cs, &mut ns)
+            .eval_metered(&slab, &mut ns),
+        Err(Error::Undefined(String::from("undefined")))
+    );
+}
+
+#[test]
+fn prod() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(1.5),
+        "inf" => Some(Num::INFINITY),
+        _ => None,
+    };
+
+    for (expr_str, expect) in [
+        ("prod(2, 3, 4)", 24.0),
+        ("prod(x)", 1.5),
+        ("prod(1, x, 2)", 3.0),
+        ("prod(x, -2, x)", -4.5),
+        ("prod(x, 0)", 0.0),
+        ("prod(-x, 0)", -0.0),
+        ("prod(2, inf)", Num::INFINITY),
+        ("prod(0, inf)", Num::NAN),
+        ("prod(x, NaN, 0)", Num::NAN),
+    ] {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+        for val in [
+            expr_ref.eval(&slab, &mut ns).unwrap(),
+            instr.eval(&slab, &mut ns).unwrap(),
+        ] {
+            if expect.is_nan() {
+                assert!(val.is_nan(), "{expr_str} => {val}");
+            } else {
+                // Compare bits, so that the sign of zero is checked too:
+                assert_eq!(val.to_bits(), expect.to_bits(), "{expr_str} => {val}");
+            }
+        }
+    }
+
+    assert_eq!(
+        Parser::new().parse("prod()", &mut slab.ps),
+        Err(Error::WrongArgs(String::from(
+            "prod: expected one or more args"
+        )))
+    );
+    assert_eq!(
+        Parser::new().parse("sum()", &mut slab.ps),
+        Err(Error::WrongArgs(String::from(
+            "sum: expected one or more args"
+        )))
+    );
+}
+
+#[cfg(feature = "print-builtin")]
+#[test]
+fn print_return() {
+    use fasteval3::PrintReturn;
+
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(2.0),
+        _ => None,
+    };
+    let mut slab = Slab::new();
+    assert_eq!(slab.print_return, PrintReturn::LastValue);
+    for (print_return, expect) in [
+        (PrintReturn::LastValue, [2.0, 4.0, 0.0]),
+        (PrintReturn::NaN, [Num::NAN, Num::NAN, Num::NAN]),
+        (PrintReturn::Zero, [0.0, 0.0, 0.0]),
+    ] {
+        slab.print_return = print_return;
+        for (expr_str, expect) in [
+            (r#"print("x =", x)"#, expect[0]),
+            (r#"print(x, "is x") + print(x)"#, expect[1]),
+            (r#"print("no values")"#, expect[2]),
+        ] {
+            let expr = Parser::new()
+                .parse(expr_str, &mut slab.ps)
+                .unwrap()
+                .from(&slab.ps);
+            let instr = expr.compile(&slab.ps, &mut slab.cs, &mut ns);
+            for val in [
+                expr.eval(&slab, &mut ns).unwrap(),
+                instr.eval(&slab, &mut ns).unwrap(),
+            ] {
+                assert!(
+                    val == expect || val.is_nan() && expect.is_nan(),
+                    "{expr_str} {print_return:?}: {val}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "print-builtin")]
+#[test]
+fn printf() {
+    use fasteval3::compiler::Instruction::IPrintFunc;
+
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(3.0),
+        "y" => Some(4.567),
+        _ => None,
+    };
+    let mut slab = Slab::new();
+    let mut format = |expr_str: &str| -> Result<(String, Num), Error> {
+        let expr = Parser::new()
+            .parse(expr_str, &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps);
+        match expr.compile(&slab.ps, &mut slab.cs, &mut ns) {
+            IPrintFunc(pf) => pf.format(&slab, &mut ns),
+            instr => panic!("{expr_str}: {instr:?}"),
+        }
+    };
+
+    for (expr_str, out, val) in [
+        (r#"print("x=%d y=%.2f", 3, 4.567)"#, "x=3 y=4.57", 4.567),
+        (r#"print("x=%d y=%.2f", x, y)"#, "x=3 y=4.57", 4.567),
+        (r#"print("100%% of %d", x)"#, "100% of 3", 3.0),
+        (r#"print("%%")"#, "%", 0.0),
+        (
+            r#"print("%f %.3f %.0f", 1.5, -2, 2.5)"#,
+            "1.500000 -2.000 2",
+            2.5,
+        ),
+        (
+            r#"print("%e %.2E", 1500, -0.000123)"#,
+            "1.500000e+03 -1.23E-04",
+            -0.000123,
+        ),
+        (
+            r#"print("%g %g %g %.3g", 1500, 0.0001, 1234567, 9.87654)"#,
+            "1500 0.0001 1.23457e+06 9.88",
+            9.87654,
+        ),
+        (
+            r#"print("%x %X %d %d", 255, 255, -3.7, 2.9)"#,
+            "ff FF -3 2",
+            2.9,
+        ),
+        (
+            r#"print("[%5d] [%-5d] [%05d] [%+d] [% d]", 42, 42, -42, 42, 42)"#,
+            "[   42] [42   ] [-0042] [+42] [ 42]",
+            42.0,
+        ),
+        (r#"print("[%8.3f] [%.3d]", x, 7)"#, "[   3.000] [007]", 7.0),
+        (
+            r#"print("%f %d %05f", 0/0, 1/0, -1/0)"#,
+            "NaN inf  -inf",
+            Num::NEG_INFINITY,
+        ),
+        (r#"print("x=%d\n", x, y)"#, "x=3\n", 4.567),
+        (r#"print("x", x, "y", y)"#, "x 3 y 4.567", 4.567),
+        (r#"print("%256.256f", 1)"#, &format!("{:.256}", 1.0), 1.0),
+    ] {
+        let (got, got_val) = format(expr_str).unwrap();
+        assert_eq!(got, out, "{expr_str}");
+        assert!(
+            got_val == val || got_val.is_nan() && val.is_nan(),
+            "{expr_str}: {got_val}"
+        );
+    }
+
+    for (expr_str, msg) in [
+        (
+            r#"print("x=%d y=%d", 3)"#,
+            "print: not enough values for format string",
+        ),
+        (r#"print("%s", 3)"#, "print: unsupported conversion '%s'"),
+        (
+            r#"print("x=%5", 3)"#,
+            "print: incomplete conversion at end of format",
+        ),
+        (
+            r#"print("%d", 3, "and", 4)"#,
+            "print: only values can follow a format string",
+        ),
+        // Width and precision are limited, so they can't exhaust memory:
+        (r#"print("%257d", 1)"#, "print: width is larger than 256"),
+        (
+            r#"print("%99999999999999999999d", 1)"#,
+            "print: width is larger than 256",
+        ),
+        (
+            r#"print("%.70000f", 1)"#,
+            "print: precision is larger than 256",
+        ),
+        (
+            r#"print("%.99999999999999999999d", 1)"#,
+            "print: precision is larger than 256",
+        ),
+        (
+            r#"print("%-1000.3e", 1)"#,
+            "print: width is larger than 256",
+        ),
+    ] {
+        assert_eq!(
+            format(expr_str),
+            Err(Error::WrongArgs(String::from(msg))),
+            "{expr_str}"
+        );
+    }
+
+    let expr = Parser::new()
+        .parse(r#"print("y=%.1f", y) + 1"#, &mut slab.ps)
+        .unwrap()
+        .from(&slab.ps);
+    assert_eq!(expr.eval(&slab, &mut ns), Ok(5.567));
+}
+
+#[test]
+fn seq() {
+    let notes = std::cell::RefCell::new(Vec::new());
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "note" => {
+            let val = args.first().copied()?;
+            notes.borrow_mut().push(val);
+            Some(val)
+        }
+        "x" => Some(1.0),
+        _ => None,
+    };
+
+    for (expr_str, expect, expect_notes) in [
+        ("seq(note(x), note(x * 2), x * 3)", 3.0, vec![1.0, 2.0]),
+        ("seq(note(x) + 10)", 11.0, vec![1.0]),
+        ("seq(note(x), 5, note(x * 4))", 4.0, vec![1.0, 4.0]),
+        ("note(seq(note(x * 2), x))", 1.0, vec![2.0, 1.0]),
+        #[cfg(feature = "print-builtin")]
+        (r#"seq(print("seq:", note(x)), x + 1)"#, 2.0, vec![1.0]),
+        #[cfg(feature = "print-builtin")]
+        (r#"print("seq:", seq(note(x), 7))"#, 7.0, vec![1.0]),
+    ] {
+        let mut slab = Slab::new();
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+
+        // Interpreted:
+        notes.borrow_mut().clear();
+        assert_eq!(slab.ps.get_expr(expr_i).eval(&slab, &mut ns), Ok(expect));
+        assert_eq!(*notes.borrow(), expect_notes, "eval {expr_str}");
+
+        // Compiled:
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        notes.borrow_mut().clear();
+        assert_eq!(instr.eval(&slab, &mut ns), Ok(expect));
+        assert_eq!(*notes.borrow(), expect_notes, "compiled eval {expr_str}");
+    }
+
+    let mut slab = Slab::new();
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+    assert_eq!(eval("seq(1, 2, 3)"), Ok(3.0));
+    assert_eq!(
+        eval("seq(undefined, 1)"),
+        Err(Error::Undefined(String::from("undefined")))
+    );
+    assert_eq!(
+        eval("seq()"),
+        Err(Error::WrongArgs(String::from(
+            "seq: expected one or more args"
+        )))
+    );
+}
+
+#[test]
+fn sweep() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|_, _| None);
+    let expr_i = Parser::new()
+        .parse("sin(deg * pi() / 180)", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+
+    let vals = eval_sweep(&instr, &slab, &mut ns, "deg", 0.0, 360.0, 90.0).unwrap();
+    assert_eq!(vals.len(), 5);
+    for (val, expect) in vals.into_iter().zip([0.0, 1.0, 0.0, -1.0, 0.0]) {
+        assert!((val - expect).abs() < 1e-6, "{val} != {expect}");
+    }
+
+    // Works with un-compiled Expressions too, and sweeps downward:
+    let vals = eval_sweep(
+        slab.ps.get_expr(expr_i),
+        &slab,
+        &mut ns,
+        "deg",
+        90.0,
+        -90.0,
+        -90.0,
+    );
+    assert_eq!(vals, Ok(vec![1.0, 0.0, -1.0]));
+
+    // Many small steps still reach the end:
+    let vals = eval_sweep(&instr, &slab, &mut ns, "deg", 0.0, 1.0, 0.1).unwrap();
+    assert_eq!(vals.len(), 11);
+
+    assert_eq!(
+        eval_sweep(&instr, &slab, &mut ns, "deg", 1.0, 0.0, 0.5),
+        Ok(vec![])
+    );
+    assert_eq!(
+        eval_sweep(&instr, &slab, &mut ns, "deg", 0.0, 1.0, 0.0),
+        Err(Error::WrongArgs(String::from(
+            "eval_sweep: step must be finite and non-zero"
+        )))
+    );
+    // Huge sweeps are rejected before anything is allocated:
+    assert_eq!(
+        eval_sweep(&instr, &slab, &mut ns, "deg", 0.0, 1e30, 1e-10),
+        Err(Error::WrongArgs(String::from(
+            "eval_sweep: more than 16777216 steps"
+        )))
+    );
+    assert_eq!(
+        eval_sweep(&instr, &slab, &mut ns, "deg", 0.0, Num::INFINITY, 1.0),
+        Err(Error::WrongArgs(String::from(
+            "eval_sweep: start and end must be finite"
+        )))
+    );
+    assert_eq!(
+        eval_sweep(&instr, &slab, &mut ns, "deg", Num::NAN, 1.0, 1.0),
+        Err(Error::WrongArgs(String::from(
+            "eval_sweep: start and end must be finite"
+        )))
+    );
+}
+
+#[test]
+fn undefined_function() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, _args| match name {
+        "x" => Some(1.0),
+        _ => None,
+    });
+
+    for (expr_str, expect) in [
+        ("y + 1", Error::Undefined(String::from("y"))),
+        ("f(x) + 1", Error::UndefinedFunction(String::from("f"))),
+        ("f(1) + 1", Error::UndefinedFunction(String::from("f"))),
+        ("f() + 1", Error::UndefinedFunction(String::from("f"))),
+        ("f[x]", Error::UndefinedFunction(String::from("f"))),
+        ("sin(f(x, y))", Error::Undefined(String::from("y"))),
+        ("sin(g(x)) + y", Error::UndefinedFunction(String::from("g"))),
+    ] {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        assert_eq!(
+            expr_ref.eval(&slab, &mut ns),
+            Err(expect.clone()),
+            "{expr_str}"
+        );
+
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(
+            instr.eval(&slab, &mut ns),
+            Err(expect),
+            "compiled {expr_str}"
+        );
+    }
+}
+
+#[test]
+fn parse_owned() {
+    let mut ns = BTreeMap::new();
+    ns.insert(String::from("x"), 2.0);
+
+    let (slab, expr) = Parser::new().parse_owned("x * (1 + abs(-x))").unwrap();
+    assert_eq!(expr.eval(&slab, &mut ns), Ok(6.0));
+    assert_eq!(
+        format!("{:?}", slab.ps),
+        "ParseSlab{ exprs:{ 0:Expression { first: EUnaryOp(ENeg(ValueI(0))), pairs: [] }, 1:Expression { first: EConstant(1.0), pairs: [ExprPair(EAdd, EStdFunc(EFuncAbs(ExpressionI(0))))] } }, vals:{ 0:EStdFunc(EVar(\"x\")) } }"
+    );
+
+    let (mut slab, expr) = Parser::new().parse_owned("3").unwrap();
+    assert_eq!(expr.eval(&slab, &mut ns), Ok(3.0));
+
+    // The root's integer literals go with it, so a later parse into the
+    // returned slab doesn't inherit them:
+    let expr_i = Parser::new().parse_noclear("3.0", &mut slab.ps).unwrap();
+    assert!(!slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0)));
+
+    assert_eq!(
+        Parser::new().parse_owned("1 +").err(),
+        Some(Error::EofWhileParsing(String::from("value")))
+    );
+}
+
+#[test]
+fn round_half_up() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    // 'round' rounds ties away from zero; 'round_half_up' rounds them toward +inf:
+    assert_eq!(eval("round(2.5)"), Ok(3.0));
+    assert_eq!(eval("round_half_up(2.5)"), Ok(3.0));
+    assert_eq!(eval("round(-2.5)"), Ok(-3.0));
+    assert_eq!(eval("round_half_up(-2.5)"), Ok(-2.0));
+    assert_eq!(eval("round(3.5)"), Ok(4.0));
+    assert_eq!(eval("round_half_up(3.5)"), Ok(4.0));
+    assert_eq!(eval("round_half_up(-3.5)"), Ok(-3.0));
+    assert_eq!(eval("round_half_up(-3.6)"), Ok(-4.0));
+    assert_eq!(eval("round_half_up(0.5, -1.25)"), Ok(-1.0));
+
+    assert_eq!(
+        eval("round_half_up()"),
+        Err(Error::WrongArgs(String::from(
+            "round_half_up: expected round_half_up(x) or round_half_up(modulus,x)"
+        )))
+    );
+
+    // Compiled and folded results match:
+    for (val, expect) in [(2.5, 3.0), (-2.5, -2.0), (3.5, 4.0)] {
+        let mut ns = |_: &str, _: Vec<Num>| Some(val);
+        let expr_i = Parser::new()
+            .parse("round_half_up(x)", &mut slab.ps)
+            .unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(instr.eval(&slab, &mut ns), Ok(expect));
+
+        let expr_i = Parser::new()
+            .parse(&format!("round_half_up({val})"), &mut slab.ps)
+            .unwrap();
+        let instr = slab
+            .ps
+            .get_expr(expr_i)
+            .compile(&slab.ps, &mut slab.cs, &mut ns);
+        assert_eq!(format!("{instr:?}"), format!("IConst({expect:?})"));
+    }
+}
+
+#[test]
+fn rounding_ties() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "pos" => Some(2.5),
+        "neg" => Some(-2.5),
+        _ => None,
+    };
+
+    for (func, expect_pos, expect_neg) in [
+        ("round", 3.0, -3.0),
+        ("rounda", 3.0, -3.0),
+        ("roundz", 2.0, -2.0),
+        ("round_half_up", 3.0, -2.0),
+    ] {
+        for (arg, expect) in [("pos", expect_pos), ("neg", expect_neg)] {
+            for expr_str in [format!("{func}({arg})"), format!("{func}({arg} * 1)")] {
+                let expr_i = Parser::new().parse(&expr_str, &mut slab.ps).unwrap();
+                let expr_ref = slab.ps.get_expr(expr_i);
+                assert_eq!(expr_ref.eval(&slab, &mut ns), Ok(expect), "{expr_str}");
+                let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+                assert_eq!(instr.eval(&slab, &mut ns), Ok(expect), "{expr_str}");
+            }
+        }
+    }
+
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+    // Only exact ties are affected:
+    assert_eq!(eval("roundz(2.6)"), Ok(3.0));
+    assert_eq!(eval("roundz(-2.4)"), Ok(-2.0));
+    assert_eq!(eval("roundz(-3.5)"), Ok(-3.0));
+    assert_eq!(eval("roundz(0.5, 1.25)"), Ok(1.0));
+    assert_eq!(eval("rounda(0.5, 1.25)"), Ok(1.5));
+    assert_eq!(eval("roundz(0.5, -1.25)"), Ok(-1.0));
+    assert_eq!(eval("rounda(0.5, -1.25)"), Ok(-1.5));
+    assert_eq!(
+        eval("roundz(1, 2, 3)"),
+        Err(Error::WrongArgs(String::from(
+            "roundz: expected roundz(x) or roundz(modulus,x)"
+        )))
+    );
+}
+
+#[test]
+fn degree_inverse_trig() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("asind(1)"), Ok(90.0));
+    assert_eq!(eval("asind(0)"), Ok(0.0));
+    assert!((eval("asind(-0.5)").unwrap() + 30.0).abs() < 1e-4);
+    assert_eq!(eval("acosd(-1)"), Ok(180.0));
+    assert_eq!(eval("acosd(1)"), Ok(0.0));
+    assert_eq!(eval("atand(1)"), Ok(45.0));
+    assert_eq!(eval("atand(-inf)"), Ok(-90.0));
+    assert_eq!(eval("atan2d(1, 0)"), Ok(90.0));
+    assert_eq!(eval("atan2d(0, -1)"), Ok(180.0));
+    assert_eq!(eval("atan2d(-1, -1)"), Ok(-135.0));
+    assert!(eval("asind(2)").unwrap().is_nan());
+
+    assert_eq!(
+        eval("atan2d(1)"),
+        Err(Error::WrongArgs(String::from(
+            "atan2d: expected atan2d(y,x)"
+        )))
+    );
+}
+
+#[test]
+fn atan2() {
+    use fasteval3::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    // Every quadrant, the axes, and signed zeros, like Num::atan2():
+    for (y, x) in [
+        (1.0, 1.0),
+        (1.0, -1.0),
+        (-1.0, -1.0),
+        (-1.0, 1.0),
+        (2.0, 0.5),
+        (-3.0, -0.25),
+        (0.0, 1.0),
+        (0.0, -1.0),
+        (-0.0, -1.0),
+        (1.0, 0.0),
+        (-1.0, 0.0),
+        (0.0, 0.0),
+        (-0.0, -0.0),
+        (1.0, Num::NEG_INFINITY),
+        (Num::NAN, 1.0),
+    ] {
+        let expect = y.atan2(x);
+        let mut ns = |name: &str, _: Vec<Num>| match name {
+            "y" => Some(y),
+            "x" => Some(x),
+            _ => None,
+        };
+        let mut slab = Slab::new();
+        for expr_str in ["atan2(y, x)", &format!("atan2({y:?}, {x:?})")] {
+            let expr = Parser::new()
+                .parse(expr_str, &mut slab.ps)
+                .unwrap()
+                .from(&slab.ps);
+            let instr = expr.compile(&slab.ps, &mut slab.cs, &mut ns);
+            for val in [
+                expr.eval(&slab, &mut ns).unwrap(),
+                instr.eval(&slab, &mut ns).unwrap(),
+            ] {
+                assert!(
+                    val.to_bits() == expect.to_bits() || val.is_nan() && expect.is_nan(),
+                    "{expr_str} with y={y}, x={x}: {val} != {expect}"
+                );
+            }
+        }
+    }
+
+    let eval = |expr_str: &str| fasteval3::ez_eval(expr_str, &mut EmptyNamespace);
+    assert_eq!(eval("atan2(1, 1)"), Ok(FRAC_PI_4));
+    assert_eq!(eval("atan2(1, -1)"), Ok(3.0 * FRAC_PI_4));
+    assert_eq!(eval("atan2(-1, -1)"), Ok(-3.0 * FRAC_PI_4));
+    assert_eq!(eval("atan2(-1, 1)"), Ok(-FRAC_PI_4));
+    assert_eq!(eval("atan2(1, 0)"), Ok(FRAC_PI_2));
+    assert_eq!(eval("atan2(0, -1)"), Ok(PI));
+    assert_eq!(
+        eval("atan2d(1, -1)"),
+        Ok(eval("atan2(1, -1)").unwrap().to_degrees())
+    );
+
+    assert_eq!(
+        eval("atan2(1)"),
+        Err(Error::WrongArgs(String::from("atan2: expected atan2(y,x)")))
+    );
+}
+
+#[test]
+fn sqrt() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(2.25),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("sqrt(4)"), Ok(2.0));
+    assert_eq!(eval("sqrt(0)"), Ok(0.0));
+    assert_eq!(eval("sqrt(x)"), Ok(1.5));
+    assert_eq!(eval("sqrt(x) == x^0.5"), Ok(1.0));
+    assert_eq!(eval("sqrt(inf)"), Ok(Num::INFINITY));
+    assert!(eval("sqrt(-1)").unwrap().is_nan());
+    assert!(eval("sqrt(-x)").unwrap().is_nan());
+
+    assert_eq!(
+        eval("sqrt(1, 2)"),
+        Err(Error::WrongArgs(String::from("sqrt: expected one arg")))
+    );
+}
+
+#[test]
+fn cbrt() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(3.375),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("cbrt(8)"), Ok(2.0));
+    assert_eq!(eval("cbrt(0)"), Ok(0.0));
+    assert_eq!(eval("cbrt(x)"), Ok(1.5));
+    assert_eq!(eval("cbrt(8)^3 == 8"), Ok(1.0));
+    assert_eq!(eval("cbrt(inf)"), Ok(Num::INFINITY));
+    // Fractional inputs:
+    assert_eq!(eval("cbrt(0.125)"), Ok(0.5));
+    assert_eq!(eval("cbrt(0.001) == 0.1"), Ok(1.0));
+    // Negative inputs, unlike '^(1/3)':
+    assert_eq!(eval("cbrt(-8)"), Ok(-2.0));
+    assert_eq!(eval("cbrt(-x)"), Ok(-1.5));
+    assert_eq!(eval("cbrt(-0.125)"), Ok(-0.5));
+    assert_eq!(eval("cbrt(-inf)"), Ok(Num::NEG_INFINITY));
+    assert!(eval("(-8)^(1/3)").unwrap().is_nan());
+    assert!(eval("cbrt(NaN)").unwrap().is_nan());
+
+    assert_eq!(
+        eval("cbrt(1, 2)"),
+        Err(Error::WrongArgs(String::from("cbrt: expected one arg")))
+    );
+}
+
+#[test]
+fn powmod() {
+    let mut slab = Slab::new();
+    let mut ns = EmptyNamespace;
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("powmod(2, 10, 1000)"), Ok(24.0));
+    assert_eq!(eval("powmod(3, 4, 5)"), Ok(1.0));
+    assert_eq!(eval("powmod(7, 0, 13)"), Ok(1.0));
+    assert_eq!(eval("powmod(7, 0, 1)"), Ok(0.0));
+    assert_eq!(eval("powmod(0, 5, 13)"), Ok(0.0));
+    assert_eq!(eval("powmod(2.9, 10.9, 1000.9)"), Ok(24.0));
+
+    // No overflow, even though 123456^654321 is huge:
+    assert_eq!(eval("powmod(4, 13, 497)"), Ok(445.0));
+    assert_eq!(eval("powmod(123456, 654321, 1000003)"), Ok(690_057.0));
+
+    // Degenerate:
+    assert!(eval("powmod(-2, 3, 5)").unwrap().is_nan());
+    assert!(eval("powmod(2, -3, 5)").unwrap().is_nan());
+    assert!(eval("powmod(2, 3, 0)").unwrap().is_nan());
+    assert!(eval("powmod(2, inf, 5)").unwrap().is_nan());
+    assert!(eval("powmod(NaN, 3, 5)").unwrap().is_nan());
+
+    assert_eq!(
+        eval("powmod(2, 10)"),
+        Err(Error::WrongArgs(String::from(
+            "powmod: expected powmod(base,exp,mod)"
+        )))
+    );
+}
+
+#[test]
+fn smoothstep() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(7.5),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    // At or below edge0:
+    assert_eq!(eval("smoothstep(0, 10, 0)"), Ok(0.0));
+    assert_eq!(eval("smoothstep(0, 10, -5)"), Ok(0.0));
+    // At or above edge1:
+    assert_eq!(eval("smoothstep(0, 10, 10)"), Ok(1.0));
+    assert_eq!(eval("smoothstep(0, 10, 1e9)"), Ok(1.0));
+    // Midpoint:
+    assert_eq!(eval("smoothstep(0, 10, 5)"), Ok(0.5));
+    assert_eq!(eval("smoothstep(-4, 6, 1)"), Ok(0.5));
+    // In between, the curve is 't*t*(3-2*t)':
+    assert_eq!(eval("smoothstep(0, 10, 2.5)"), Ok(0.156_25));
+    assert_eq!(eval("smoothstep(5, 10, x)"), Ok(0.5));
+    assert_eq!(eval("smoothstep(x, 10, 10)"), Ok(1.0));
+
+    assert_eq!(
+        eval("smoothstep(0, 10)"),
+        Err(Error::WrongArgs(String::from(
+            "smoothstep: expected smoothstep(edge0,edge1,x)"
+        )))
+    );
+}
+
+#[test]
+fn clamp() {
+    let mut slab = Slab::new();
+    let mut ns = |name: &str, _: Vec<Num>| match name {
+        "x" => Some(7.5),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str| eval_both(&mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("clamp(5, 0, 3)"), Ok(3.0));
+    assert_eq!(eval("clamp(-5, 0, 3)"), Ok(0.0));
+    assert_eq!(eval("clamp(2, 0, 3)"), Ok(2.0));
+    assert_eq!(eval("clamp(x, 0, 5)"), Ok(5.0));
+    assert_eq!(eval("clamp(x, 8, 10)"), Ok(8.0));
+    assert_eq!(eval("clamp(1, x, 10)"), Ok(7.5));
+    // 'lo > hi' does not panic like Num::clamp(); 'hi' wins:
+    assert_eq!(eval("clamp(5, 3, 0)"), Ok(0.0));
+    assert_eq!(eval("clamp(-5, 3, 0)"), Ok(0.0));
+    assert_eq!(eval("clamp(x, 10, 8)"), Ok(8.0));
+    // A NaN 'val' gives 'lo', and NaN bounds are ignored:
+    assert_eq!(eval("clamp(NaN, 0, 3)"), Ok(0.0));
+    assert_eq!(eval("clamp(x, NaN, 3)"), Ok(3.0));
+    assert_eq!(eval("clamp(x, 0, NaN)"), Ok(7.5));
+
+    assert_eq!(
+        eval("clamp(1, 2)"),
+        Err(Error::WrongArgs(String::from(
+            "clamp: expected clamp(val,lo,hi)"
+        )))
+    );
+}
+
+#[test]
+fn select() {
+    let mut slab = Slab::new();
+    let calls = std::cell::Cell::new(0);
+    let mut ns = |name: &str, args: Vec<Num>| match name {
+        "f" => {
+            calls.set(calls.get() + 1);
+            Some(args.iter().sum())
+        }
+        "i" => Some(1.7),
+        "n" => Some(-1.0),
+        _ => None,
+    };
+    let mut eval = |expr_str: &str, strict: bool| {
+        slab.strict = strict;
+        eval_both(&mut slab, &mut ns, expr_str)
+    };
+
+    // The index is truncated:
+    assert_eq!(eval("select(0, 10, 20, 30)", false), Ok(10.0));
+    assert_eq!(eval("select(2, 10, 20, 30)", false), Ok(30.0));
+    assert_eq!(eval("select(2.9, 10, 20, 30)", false), Ok(30.0));
+    assert_eq!(eval("select(-0.5, 10, 20, 30)", false), Ok(10.0));
+    assert_eq!(eval("select(i, 10, 20, 30)", false), Ok(20.0));
+    assert_eq!(eval("select(i, 10, 20, 30)", true), Ok(20.0));
+
+    // Out-of-range indices are NaN, or an error in strict mode:
+    for (expr_str, index) in [
+        ("select(3, 10, 20, 30)", "3"),
+        ("select(-1, 10)", "-1"),
+        ("select(n, 10, 20)", "-1"),
+        ("select(i + 5, 10, 20)", "6.7"),
+        ("select(NaN, 10)", "NaN"),
+    ] {
+        assert!(eval(expr_str, false).unwrap().is_nan(), "{expr_str}");
+        assert_eq!(
+            eval(expr_str, true),
+            Err(Error::WrongArgs(format!(
+                "select: index {index} is out of range"
+            ))),
+            "{expr_str}"
+        );
+    }
+
+    // Only the chosen option is evaluated, once by each of the 3 evals:
+    calls.set(0);
+    assert_eq!(eval("select(i, f(i), f(i, 1), f(i, 2))", false), Ok(2.7));
+    assert_eq!(calls.get(), 3);
+    calls.set(0);
+    assert!(eval("select(n, f(i))", false).unwrap().is_nan());
+    assert_eq!(calls.get(), 0);
+
+    assert_eq!(
+        eval("select(1)", false),
+        Err(Error::WrongArgs(String::from(
+            "select: expected select(index,option0,...)"
+        )))
+    );
+}
+
+fn my_evalns_cb_function(_: &str, _: Vec<Num>) -> Option<Num> {
+    None
+}
+#[test]
+fn evalns_cb_ownership() {
+    let _ns = CachedCallbackNamespace::new(my_evalns_cb_function);
+    let _ns = CachedCallbackNamespace::new(my_evalns_cb_function);
+    // Conclusion: You can pass a function pointer into a function that receives ownership.
+
+    let closure = |_: &str, _: Vec<Num>| None;
+    let _ns = CachedCallbackNamespace::new(closure);
+    let _ns = CachedCallbackNamespace::new(closure);
+
+    let x = 1.0;
+    let closure = |_: &str, _: Vec<Num>| Some(x);
+    let _ns = CachedCallbackNamespace::new(closure);
+    let _ns = CachedCallbackNamespace::new(closure);
+
+    let mut x = 1.0;
+    let closure = |_: &str, _: Vec<Num>| {
+        x += 1.0;
+        Some(x)
+    };
+    let _ns = CachedCallbackNamespace::new(closure);
+    //let _ns = CachedCallbackNamespace::new(closure);  // Not allowed.
+
+    // Conclusion: Functions and Closures that don't mutate state are effectively Copy.
+    //             Closures that mutate state aren't Copy.
+    //             Note that the argument type (FnMut vs Fn) doesn't actually matter,
+    //             just the implementation matters!
+}
+
+#[allow(clippy::too_many_lines)]
+#[test]
+fn custom_func() {
+    let mut slab = Slab::new();
+    let mut ns = CachedCallbackNamespace::new(|name, args| {
+        eprintln!("In CB: {name}");
+        match name {
+            "x" => Some(1.0),
+            "y" => Some(2.0),
+            "z" => Some(3.0),
+            "foo" => Some(args.first().unwrap_or(&Num::NAN) * 10.0),
+            "bar" => Some(args.first().unwrap_or(&Num::NAN) + args.get(1).unwrap_or(&Num::NAN)),
+            _ => None,
+        }
+    });
+    assert_eq!(
+        Parser::new()
+            .parse("x + 1.5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(2.5)
+    );
+
+    assert_eq!(
+        Parser::new()
+            .parse("x() + 1.5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(2.5)
+    );
+
+    assert_eq!(
+        Parser::new()
+            .parse("x(1,2,3) + 1.5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(2.5)
+    );
+
+    eprintln!("I should see TWO x lookups, 1 y, and 1 z:");
+    assert_eq!(
+        Parser::new()
+            .parse("x(x,y,z) + 1.5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(2.5)
+    );
+
+    eprintln!("I should see TWO x lookups:");
+    assert_eq!(
+        Parser::new()
+            .parse("x(x,x,x) + 1.5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(2.5)
+    );
+
+    eprintln!("I should see TWO x lookups:");
+    assert_eq!(
+        Parser::new()
+            .parse("x(1.0) + x(1.1) + x(1.0) + x(1.1)", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(4.0)
+    );
+
+    eprintln!("---------------------------");
+
+    assert_eq!(
+        Parser::new()
+            .parse("foo(1.23)", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(12.3)
+    );
+
+    assert_eq!(
+        Parser::new()
+            .parse("bar(1.23, 3.21)", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, {
+                ns.cache_clear();
+                &mut ns
+            }),
+        Ok(4.439_999_999_999_999_5)
+    );
+
+    assert_eq!(
+        format!(
+            "{:?}",
+            Parser::new()
+                .parse("bar(1.23)", &mut slab.ps)
+                .unwrap()
+                .from(&slab.ps)
+                .eval(&slab, {
+                    ns.cache_clear();
+                    &mut ns
+                })
+        ),
+        "Ok(NaN)"
+    );
+}
+
+#[test]
+#[cfg(feature = "unsafe-vars")]
+fn unsafe_var() {
+    let mut slab = Slab::new();
+
+    let mut ua = 1.23;
+    let mut ub = 4.56;
+    unsafe {
+        slab.ps.add_unsafe_var("ua".to_string(), &ua);
+        slab.ps.add_unsafe_var("ub".to_string(), &ub);
+    }
+
+    let mut ns = EmptyNamespace;
+
+    assert_eq!(
+        Parser::new()
+            .parse("ua + ub + 5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, &mut ns),
+        Ok(10.79)
+    );
+
+    ua += 1.0;
+    ub += 2.0;
+    assert_eq!(
+        Parser::new()
+            .parse("ua + ub + 5", &mut slab.ps)
+            .unwrap()
+            .from(&slab.ps)
+            .eval(&slab, &mut ns),
+        Ok(13.79)
+    );
+
+    let _ = (ua, ub); // Silence compiler warnings about variables not being read.
+}
+
+#[test]
+fn probround() {
+    // A small LCG, so that the test is deterministic:
+    let mut state = 12345_u32;
+    let rng = move || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 8) as Num / (1 << 24) as Num
+    };
+    let mut ns = RandomNamespace::new(
+        |name: &str, _args: Vec<Num>| match name {
+            "x" => Some(-1.75),
+            _ => None,
+        },
+        rng,
+    );
+
+    let mut slab = Slab::new();
+    for (expr_str, lo, mean) in [
+        ("probround(2.25)", 2.0, 2.25),
+        ("probround(x)", -2.0, -1.75),
+        ("probround(0.5)", 0.0, 0.5),
+        ("probround(3)", 3.0, 3.0),
+    ] {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let expr_ref = slab.ps.get_expr(expr_i);
+        let instr = expr_ref.compile(&slab.ps, &mut slab.cs, &mut ns);
+
+        let n = 10_000;
+        let mut sum = 0.0;
+        for i in 0..n {
+            let val = if i % 2 == 0 {
+                expr_ref.eval(&slab, &mut ns).unwrap()
+            } else {
+                instr.eval(&slab, &mut ns).unwrap()
+            };
+            assert!(val == lo || val == lo + 1.0, "{expr_str} => {val}");
+            sum += val;
+        }
+        assert!((sum / n as Num - mean).abs() < 0.02, "{expr_str}");
+    }
+
+    // Never folded:
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("probround(2.25)", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(
+        format!("{instr:?} {:?}", slab.cs),
+        "IFuncProbRound(InstructionI(0)) CompileSlab{ instrs:{ 0:IConst(2.25) } }"
+    );
+
+    // A random source is required:
+    assert_eq!(
+        instr.eval(&slab, &mut EmptyNamespace),
+        Err(Error::NoRandomSource)
+    );
 }
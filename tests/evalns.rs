@@ -1,8 +1,10 @@
 pub(crate) mod common;
 
-use common::assert_error_margin;
+use common::{assert_error_margin, eval_both, eval_both_with};
 
-use fasteval3::ez_eval;
+use fasteval3::{ez_eval, ez_eval_with_vars, Cached, Compiler, Error, Evaler, Num, Parser, Slab};
+
+use std::collections::HashMap;
 
 #[test]
 fn empty() {
@@ -33,6 +35,36 @@ fn str_to_f32() {
     }
 }
 
+#[test]
+fn hashmap_str_to_f32() {
+    {
+        let mut ns = HashMap::<String, Num>::new();
+        ns.insert(String::from("a"), 1.11);
+        ns.insert(String::from("b"), 2.22);
+
+        let val = ez_eval("a + b + 1", &mut ns).unwrap();
+        assert_error_margin(val, 4.33);
+    }
+
+    {
+        let mut ns = HashMap::<&'static str, Num>::new();
+        ns.insert("a", 1.11);
+        ns.insert("b", 2.22);
+
+        let val = ez_eval("a + b + 1", &mut ns).unwrap();
+        assert_error_margin(val, 4.33);
+    }
+
+    // Function-style lookups use the name and args as the key:
+    let mut ns = HashMap::<String, Num>::new();
+    ns.insert(String::from("f , 1 , 2"), 3.0);
+    assert_eq!(ez_eval("f(1, 2) * 2", &mut ns), Ok(6.0));
+    assert_eq!(
+        ez_eval("f(2, 1)", &mut ns),
+        Err(Error::UndefinedFunction(String::from("f")))
+    );
+}
+
 #[test]
 fn str_to_cb() {
     {
@@ -54,6 +86,69 @@ fn str_to_cb() {
     }
 }
 
+#[test]
+fn unit_conversions() {
+    let mut ns = fasteval3::evalns::unit_conversions(&[
+        ("ft_to_m", 0.3048, 0.0),
+        ("c_to_f", 1.8, 32.0),
+        ("f_to_c", 1.0 / 1.8, -32.0 / 1.8),
+    ]);
+    ns.insert("x", Box::new(|_| 10.0));
+
+    assert_error_margin(ez_eval("ft_to_m(10)", &mut ns).unwrap(), 3.048);
+    assert_error_margin(ez_eval("c_to_f(x)", &mut ns).unwrap(), 50.0);
+    assert_error_margin(ez_eval("c_to_f(-40)", &mut ns).unwrap(), -40.0);
+    assert_error_margin(
+        ez_eval("f_to_c(c_to_f(37))", &mut ns).unwrap(),
+        (37.0 * 1.8 + 32.0) * (1.0 / 1.8) + -32.0 / 1.8,
+    );
+    assert!(ez_eval("ft_to_m(1, 2)", &mut ns).unwrap().is_nan());
+}
+
+#[test]
+fn budgeted() {
+    let mut lookups = 0;
+    let cb = |name: &str, args: Vec<Num>| {
+        lookups += 1;
+        match name {
+            "x" => Some(2.0),
+            "f" => Some(args[0] + 1.0),
+            _ => None,
+        }
+    };
+    let mut ns = fasteval3::BudgetedNamespace::new(cb, 4);
+
+    assert_error_margin(ez_eval("x + f(x) + 1", &mut ns).unwrap(), 6.0);
+    assert_eq!(ns.used(), 3);
+    assert!(!ns.exhausted());
+
+    // The count carries over until reset, so only 1 lookup is left:
+    assert_eq!(
+        ez_eval("x * x", &mut ns),
+        Err(Error::Undefined(String::from("x")))
+    );
+    assert_eq!(ns.used(), 4);
+    assert!(ns.exhausted());
+
+    ns.reset();
+    assert_error_margin(ez_eval("x * x * x", &mut ns).unwrap(), 8.0);
+    assert_eq!(
+        ez_eval("x + x", &mut ns),
+        Err(Error::Undefined(String::from("x")))
+    );
+
+    // Undefined variables also count against the budget:
+    ns.reset();
+    assert_eq!(
+        ez_eval("y", &mut ns),
+        Err(Error::Undefined(String::from("y")))
+    );
+    assert_eq!(ns.used(), 1);
+    assert!(!ns.exhausted());
+
+    assert_eq!(lookups, 9);
+}
+
 #[test]
 fn layered_str_to_f32() {
     let mut ns = fasteval3::LayeredStringTof32Namespace::new();
@@ -78,12 +173,54 @@ fn layered_str_to_f32() {
     assert_error_margin(val, 4.33);
 }
 
+#[test]
+fn nested() {
+    use fasteval3::{NestedNamespace, NestedValue};
+
+    let mut sensor = NestedNamespace::new();
+    sensor.insert(String::from("temp"), NestedValue::Value(21.5));
+    sensor.insert(String::from("id"), NestedValue::Value(7.0));
+    let mut ns = NestedNamespace::new();
+    ns.insert(String::from("sensor"), NestedValue::Map(sensor));
+    ns.insert(String::from("offset"), NestedValue::Value(0.5));
+
+    let parser = Parser {
+        dotted_names: true,
+        ..Parser::new()
+    };
+    let mut slab = Slab::new();
+    let mut eval = |expr_str: &str| eval_both_with(&parser, &mut slab, &mut ns, expr_str);
+
+    assert_eq!(eval("sensor.temp"), Ok(21.5));
+    assert_eq!(eval("sensor.temp + offset"), Ok(22.0));
+    assert_eq!(eval("sensor.id*1.5"), Ok(10.5));
+    assert_eq!(
+        eval("sensor"),
+        Err(Error::Undefined(String::from("sensor")))
+    );
+    assert_eq!(
+        eval("sensor.humidity"),
+        Err(Error::Undefined(String::from("sensor.humidity")))
+    );
+    assert_eq!(
+        eval("offset.x"),
+        Err(Error::Undefined(String::from("offset.x")))
+    );
+    assert!(eval("sensor.").is_err());
+    assert!(eval("sensor..temp").is_err());
+
+    // Without the option, '.' is not part of a name:
+    assert!(Parser::new()
+        .parse("sensor.temp", &mut Slab::new().ps)
+        .is_err());
+}
+
 #[test]
 fn cb() {
-    let mut ns = |name: &str, args: Vec<f32>| match name {
+    let mut ns = |name: &str, args: Vec<Num>| match name {
         "a" => Some(1.11),
         "b" => Some(2.22),
-        "len" => Some(args.len() as f32),
+        "len" => Some(args.len() as Num),
         _ => None,
     };
 
@@ -93,13 +230,13 @@ fn cb() {
 
 #[test]
 fn cached_cb() {
-    let mut ns = fasteval3::CachedCallbackNamespace::new(|name: &str, args: Vec<f32>| match name {
+    let mut ns = fasteval3::CachedCallbackNamespace::new(|name: &str, args: Vec<Num>| match name {
         "a" => {
             eprintln!("cached_cb: a: This should only be printed once.");
             Some(1.11)
         }
         "b" => Some(2.22),
-        "len" => Some(args.len() as f32),
+        "len" => Some(args.len() as Num),
         _ => None,
     });
 
@@ -111,9 +248,54 @@ fn cached_cb() {
     ez_eval("a + b + 1", &mut ns).unwrap();
 }
 
+#[test]
+fn cache_contents() {
+    let mut ns = fasteval3::CachedCallbackNamespace::new(|name: &str, args: Vec<Num>| match name {
+        "a" => Some(1.11),
+        "len" => Some(args.len() as Num),
+        _ => None,
+    });
+    assert_eq!(ns.cache_get("a"), None);
+    assert_eq!(ns.cache_iter().count(), 0);
+
+    ez_eval("a + len(1, 2)", &mut ns).unwrap();
+    assert_eq!(ns.cache_get("a"), Some(1.11));
+    assert_eq!(ns.cache_get("b"), None);
+    assert_eq!(
+        ns.cache_iter().collect::<Vec<_>>(),
+        vec![("a", 1.11), ("len , 1 , 2", 2.0)]
+    );
+
+    ns.cache_set(String::from("a"), 5.0);
+    assert_eq!(ns.cache_get("a"), Some(5.0));
+
+    ns.cache_clear();
+    assert_eq!(ns.cache_get("a"), None);
+    assert_eq!(ns.cache_iter().count(), 0);
+
+    // Existing implementations that don't provide cache_iter() still work:
+    struct OldCache(Option<Num>);
+    impl Cached for OldCache {
+        fn cache_create(&mut self, _name: String, val: Num) -> Result<(), Error> {
+            self.0 = Some(val);
+            Ok(())
+        }
+        fn cache_set(&mut self, _name: String, val: Num) {
+            self.0 = Some(val);
+        }
+        fn cache_clear(&mut self) {
+            self.0 = None;
+        }
+    }
+    let mut old = OldCache(None);
+    old.cache_set(String::from("a"), 1.0);
+    assert_eq!(old.cache_iter().count(), 0);
+    assert_eq!(old.cache_get("a"), None);
+}
+
 #[test]
 fn custom_vector_funcs() {
-    let vecs_cell = std::cell::RefCell::new(Vec::<Vec<f32>>::new());
+    let vecs_cell = std::cell::RefCell::new(Vec::<Vec<Num>>::new());
 
     let mut ns = fasteval3::StrToCallbackNamespace::new();
 
@@ -125,7 +307,7 @@ fn custom_vector_funcs() {
             let mut vecs = vecs_cell.borrow_mut();
             let index = vecs.len();
             vecs.push(args);
-            index as f32
+            index as Num
         }),
     );
 
@@ -137,10 +319,212 @@ fn custom_vector_funcs() {
                     return v.iter().sum();
                 }
             }
-            f32::NAN
+            Num::NAN
         }),
     );
 
     let val = ez_eval("vec_sum(vec_store(1.1, x, 3.3)) + vec_sum(0)", &mut ns).unwrap();
-    assert_error_margin(val, 12.799999);
+    assert_error_margin(val, 2.0 * (1.1 + 2.0 + 3.3));
+}
+
+#[test]
+fn with_vars() {
+    let mut ns = fasteval3::StrTof32Namespace::new();
+    ns.insert("x", 2.0);
+    ns.insert("y", 3.0);
+
+    let (val, vars) = ez_eval_with_vars("x * y + 1", &mut ns).unwrap();
+    assert_error_margin(val, 7.0);
+    assert_eq!(vars.into_iter().collect::<Vec<_>>(), vec!["x", "y"]);
+
+    // Names in skipped branches are still reported:
+    let (val, vars) = ez_eval_with_vars("0 && z", &mut ns).unwrap();
+    assert_error_margin(val, 0.0);
+    assert_eq!(vars.into_iter().collect::<Vec<_>>(), vec!["z"]);
+
+    assert_eq!(
+        ez_eval_with_vars("x + z", &mut ns),
+        Err(Error::Undefined(String::from("z")))
+    );
+}
+
+#[test]
+fn missing_value() {
+    let cb = |name: &str, _args: Vec<Num>| match name {
+        "a" => Some(3.0),
+        "b" => Some(-9999.0),
+        "c" => Some(5.0),
+        "n" => Some(Num::NAN),
+        _ => None,
+    };
+
+    let mut ns = fasteval3::MissingValueNamespace::new(cb, -9999.0);
+    for (expr_str, expect) in [
+        ("min(a, b, c)", 3.0),
+        ("min(b, c)", 5.0),
+        ("max(b, -10000)", -10000.0),
+        ("avg(a, b, c)", 4.0),
+        ("avg(b, c, -9999)", 5.0),
+        ("min(-9999, 4, c)", 4.0),
+        ("max(-9999, -10000, b)", -10000.0),
+        ("avg(-9999, 4, 6)", 5.0),
+        ("sum(a, b, c)", 8.0),
+        ("sum(b, -9999, 1)", 1.0),
+        // Every argument is missing:
+        ("min(b, -9999)", -9999.0),
+        ("max(b, b)", -9999.0),
+        ("avg(b, -9999)", -9999.0),
+        ("sum(b, -9999)", -9999.0),
+        ("min(-9999, -9999)", -9999.0),
+        // Other operations are not affected:
+        ("a + b", -9996.0),
+    ] {
+        assert_eq!(
+            eval_both(&mut Slab::new(), &mut ns, expr_str),
+            Ok(expect),
+            "{expr_str}"
+        );
+    }
+
+    // A NaN sentinel matches any NaN:
+    let mut ns = fasteval3::MissingValueNamespace::new(cb, Num::NAN);
+    assert_eq!(ez_eval("min(n, a, NaN)", &mut ns), Ok(3.0));
+    assert_eq!(ez_eval("avg(n, a, c)", &mut ns), Ok(4.0));
+    assert_eq!(ez_eval("sum(n, a, c)", &mut ns), Ok(8.0));
+    assert!(ez_eval("max(n, NaN)", &mut ns).unwrap().is_nan());
+
+    // No sentinel by default:
+    let mut ns = cb;
+    assert_eq!(ez_eval("min(a, b, c)", &mut ns), Ok(-9999.0));
+    assert_eq!(ez_eval("avg(a, b, c)", &mut ns), Ok(-9991.0 / 3.0));
+    assert_eq!(ez_eval("avg(-9999, 1)", &mut ns), Ok(-4999.0));
+    assert_eq!(ez_eval("sum(a, b, c)", &mut ns), Ok(-9991.0));
+}
+
+#[test]
+fn context() {
+    struct Frame {
+        t: Num,
+        offsets: [Num; 2],
+    }
+
+    let frames = [
+        Frame {
+            t: 1.0,
+            offsets: [10.0, 20.0],
+        },
+        Frame {
+            t: 3.0,
+            offsets: [-1.0, -2.0],
+        },
+    ];
+    let cb = |frame: &Frame, name: &str, args: &[Num]| match name {
+        "t" => Some(frame.t),
+        "offset" => args
+            .first()
+            .and_then(|&i| frame.offsets.get(i as usize))
+            .copied(),
+        _ => None,
+    };
+    let mut ns = fasteval3::ContextNamespace::new(cb, &frames[0]);
+
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("t * 2 + offset(t > 2)", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(12.0));
+    assert!(std::ptr::eq(ns.context(), &frames[0]));
+
+    ns.set_context(&frames[1]);
+    assert_eq!(instr.eval(&slab, &mut ns), Ok(4.0));
+    assert_eq!(ez_eval("offset(0) - t", &mut ns), Ok(-4.0));
+    assert_eq!(
+        ez_eval("offset(5)", &mut ns),
+        Err(Error::UndefinedFunction(String::from("offset")))
+    );
+}
+
+#[test]
+fn reduce() {
+    let cb = |name: &str, args: Vec<Num>| match name {
+        "x" => Some(4.0),
+        "y" => Some(-2.0),
+        "first" => args.first().copied(),
+        // Shadowed by the reducer below:
+        "mymax" => Some(-1.0),
+        _ => None,
+    };
+    let mut ns = fasteval3::ReduceNamespace::new(cb);
+    ns.register(String::from("mymax"), Num::max);
+    ns.register(String::from("diff"), |a, b| a - b);
+
+    for (expr_str, expect) in [
+        ("mymax(1, 5, 3)", 5.0),
+        ("mymax(x, y)", 4.0),
+        ("mymax(y)", -2.0),
+        ("mymax(y, mymax(1, 2)) * 10", 20.0),
+        ("diff(10, 1, 2, 3)", 4.0),
+        ("diff(x, y, x)", 2.0),
+        // Other functions and variables are looked up as usual:
+        ("first(7, 8) + x", 11.0),
+    ] {
+        assert_eq!(
+            eval_both(&mut Slab::new(), &mut ns, expr_str),
+            Ok(expect),
+            "{expr_str}"
+        );
+    }
+
+    assert!(ez_eval("mymax()", &mut ns).unwrap().is_nan());
+    // A bare name is a variable, not a call:
+    assert_eq!(ez_eval("mymax", &mut ns), Ok(-1.0));
+    assert_eq!(
+        ez_eval("nope(1, 2)", &mut ns),
+        Err(Error::UndefinedFunction(String::from("nope")))
+    );
+
+    // Constant args are folded during compilation, like other custom functions:
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("mymax(1, 2) + x", &mut slab.ps)
+        .unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut ns);
+    assert_eq!(format!("{instr:?}"), "IAdd(InstructionI(0), C(2.0))");
+}
+
+#[test]
+fn budgeted_reduce() {
+    let cb = |name: &str, _args: Vec<Num>| match name {
+        "x" => Some(2.0),
+        _ => None,
+    };
+    let mut reduce_ns = fasteval3::ReduceNamespace::new(cb);
+    reduce_ns.register(String::from("mymax"), Num::max);
+    let mut ns = fasteval3::BudgetedNamespace::new(reduce_ns, 3);
+
+    // Reducer calls count against the budget, like lookups:
+    assert_eq!(ez_eval("mymax(x, 1) + x", &mut ns), Ok(4.0));
+    assert_eq!(ns.used(), 3);
+    assert!(!ns.exhausted());
+    assert_eq!(
+        ez_eval("mymax(1, 2)", &mut ns),
+        Err(Error::UndefinedFunction(String::from("mymax")))
+    );
+    assert!(ns.exhausted());
+
+    // Functions without a reducer are only counted once, by their lookup:
+    ns.reset();
+    assert_eq!(
+        ez_eval("f(1)", &mut ns),
+        Err(Error::UndefinedFunction(String::from("f")))
+    );
+    assert_eq!(ns.used(), 1);
 }
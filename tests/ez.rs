@@ -1,20 +1,23 @@
-use fasteval3::{ez_eval, Error};
+use fasteval3::{eval_all, ez_eval, Error, Num};
 
 use std::collections::BTreeMap;
 
 #[test]
 fn ez() {
     assert_eq!(
-        ez_eval("3+3-3/3", &mut BTreeMap::<String, f32>::new()),
+        ez_eval("3+3-3/3", &mut BTreeMap::<String, Num>::new()),
         Ok(5.0)
     );
     assert_eq!(
-        ez_eval("3abc+3-3/3", &mut BTreeMap::<String, f32>::new()),
-        Err(Error::UnparsedTokensRemaining(String::from("abc+3-3/3")))
+        ez_eval("3abc+3-3/3", &mut BTreeMap::<String, Num>::new()),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("abc+3-3/3"),
+            pos: 1
+        })
     );
     assert_eq!(
         ez_eval("z+z-z/z", &mut {
-            let mut m = BTreeMap::<String, f32>::new();
+            let mut m = BTreeMap::<String, Num>::new();
             m.insert(String::from("x"), 1.0);
             m.insert(String::from("y"), 2.0);
             m.insert(String::from("z"), 3.0);
@@ -23,3 +26,43 @@ fn ez() {
         Ok(5.0)
     );
 }
+
+#[test]
+fn eval_all_formulas() {
+    let mut ns = BTreeMap::<String, Num>::new();
+    ns.insert(String::from("x"), 2.0);
+
+    let results = eval_all(
+        &[
+            ("double", "x * 2"),
+            ("trailing_op", "x +"),
+            ("undefined", "y + 1"),
+            ("unclosed", "max(1, 2"),
+            ("square", "x^2"),
+            ("garbage", "1 2"),
+        ],
+        &mut ns,
+    );
+    assert_eq!(
+        results,
+        vec![
+            ("double", Ok(4.0)),
+            (
+                "trailing_op",
+                Err(Error::EofWhileParsing(String::from("value")))
+            ),
+            ("undefined", Err(Error::Undefined(String::from("y")))),
+            ("unclosed", Err(Error::EofWhileParsing(String::from("max")))),
+            ("square", Ok(4.0)),
+            (
+                "garbage",
+                Err(Error::UnparsedTokensRemaining {
+                    rest: String::from("2"),
+                    pos: 2
+                })
+            ),
+        ]
+    );
+
+    assert_eq!(eval_all(&[], &mut ns), vec![]);
+}
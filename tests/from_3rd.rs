@@ -1,12 +1,12 @@
 use fasteval3::{
-    eval_compiled_ref, CachedCallbackNamespace, Compiler, EmptyNamespace, Error, Evaler, Parser,
-    Slab,
+    eval_compiled_ref, CachedCallbackNamespace, Compiler, EmptyNamespace, Error, Evaler, Num,
+    Parser, Slab,
 };
 
 use std::str::from_utf8;
 
 #[allow(clippy::needless_pass_by_value)] // This type is explicitly required by our namespace.
-fn evalns_cb(name: &str, args: Vec<f32>) -> Option<f32> {
+fn evalns_cb(name: &str, args: Vec<Num>) -> Option<Num> {
     match name {
         "w" => Some(0.0),
         "x" => Some(1.0),
@@ -19,7 +19,7 @@ fn evalns_cb(name: &str, args: Vec<f32>) -> Option<f32> {
     }
 }
 
-fn chk_ok(expr_str: &str, expect_compile_str: &str, expect_slab_str: &str, expect_eval: f32) {
+fn chk_ok(expr_str: &str, expect_compile_str: &str, expect_slab_str: &str, expect_eval: Num) {
     let mut slab = Slab::new();
     let expr = Parser::new()
         .parse(expr_str, &mut slab.ps)
@@ -32,12 +32,12 @@ fn chk_ok(expr_str: &str, expect_compile_str: &str, expect_slab_str: &str, expec
 
     (|| -> Result<(), Error> {
         let mut ns = CachedCallbackNamespace::new(evalns_cb);
-        assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - expect_eval).abs() < f32::EPSILON);
+        assert!((eval_compiled_ref!(&instr, &slab, &mut ns) - expect_eval).abs() < Num::EPSILON);
 
         // Make sure Instruction eval matches normal eval:
         assert!(
             (eval_compiled_ref!(&instr, &slab, &mut ns) - expr.eval(&slab, &mut ns).unwrap()).abs()
-                < f32::EPSILON
+                < Num::EPSILON
         );
 
         Ok(())
@@ -66,19 +66,38 @@ fn chk_eerr(expr_str: &str, expect_err: Error) {
 fn meval() {
     chk_perr("", Error::EofWhileParsing(String::from("value")));
     chk_perr("(", Error::EofWhileParsing(String::from("value")));
-    chk_perr("0(", Error::UnparsedTokensRemaining(String::from("(")));
+    chk_perr(
+        "0(",
+        Error::UnparsedTokensRemaining {
+            rest: String::from("("),
+            pos: 1,
+        },
+    );
     chk_eerr("e", Error::Undefined(String::from("e")));
-    chk_perr("1E", Error::ParseF32(String::from("1E")));
-    chk_perr("1e+", Error::ParseF32(String::from("1e+")));
-    chk_perr("()", Error::InvalidValue);
-    chk_perr("2)", Error::UnparsedTokensRemaining(String::from(")")));
+    chk_perr(
+        "1E",
+        Error::MalformedNumber(String::from("1E: missing exponent digits")),
+    );
+    chk_perr(
+        "1e+",
+        Error::MalformedNumber(String::from("1e+: missing exponent digits")),
+    );
+    chk_perr("()", Error::InvalidValue { pos: 1 });
+    chk_perr(
+        "2)",
+        Error::UnparsedTokensRemaining {
+            rest: String::from(")"),
+            pos: 1,
+        },
+    );
     chk_perr("2^", Error::EofWhileParsing(String::from("value")));
     chk_perr("(((2)", Error::EofWhileParsing(String::from("parentheses")));
-    chk_perr("f(2,)", Error::InvalidValue);
-    chk_perr("f(,2)", Error::InvalidValue);
+    chk_perr("f(2,)", Error::InvalidValue { pos: 4 });
+    chk_perr("f(,2)", Error::InvalidValue { pos: 2 });
 
+    // sin(pi()) is slightly below zero in f32, but slightly above it in f64:
     chk_ok("round(sin (pi()) * cos(0))",
-"IConst(-0.0)",
+if cfg!(feature = "f64") { "IConst(0.0)" } else { "IConst(-0.0)" },
 "Slab{ exprs:{ 0:Expression { first: EStdFunc(EFuncPi), pairs: [] }, 1:Expression { first: EConstant(0.0), pairs: [] }, 2:Expression { first: EStdFunc(EFuncSin(ExpressionI(0))), pairs: [ExprPair(EMul, EStdFunc(EFuncCos(ExpressionI(1))))] }, 3:Expression { first: EStdFunc(EFuncRound { modulus: None, expr: ExpressionI(2) }), pairs: [] } }, vals:{}, instrs:{} }",
 0.0);
 
@@ -93,9 +112,9 @@ fn meval() {
 2.0);
 
     chk_ok("sin(1.) + cos(2.)",
-"IConst(0.4253241)",
+&format!("IConst({:?})", Num::sin(1.0) + Num::cos(2.0)),
 "Slab{ exprs:{ 0:Expression { first: EConstant(1.0), pairs: [] }, 1:Expression { first: EConstant(2.0), pairs: [] }, 2:Expression { first: EStdFunc(EFuncSin(ExpressionI(0))), pairs: [ExprPair(EAdd, EStdFunc(EFuncCos(ExpressionI(1))))] } }, vals:{}, instrs:{} }",
-(1f32).sin() + (2f32).cos());
+Num::sin(1.0) + Num::cos(2.0));
 }
 
 #[test]
@@ -138,7 +157,8 @@ fn overflow_stack() {
     assert_eq!(
         Parser {
             expr_len_limit: fasteval3::parser::DEFAULT_EXPR_LEN_LIMIT,
-            expr_depth_limit: 31
+            expr_depth_limit: 31,
+            ..Parser::new()
         }
         .parse(from_utf8(&[b'('; 32]).unwrap(), &mut Slab::new().ps),
         Err(Error::TooDeep)
@@ -147,7 +167,8 @@ fn overflow_stack() {
     assert_eq!(
         Parser {
             expr_len_limit: 8,
-            expr_depth_limit: fasteval3::parser::DEFAULT_EXPR_DEPTH_LIMIT
+            expr_depth_limit: fasteval3::parser::DEFAULT_EXPR_DEPTH_LIMIT,
+            ..Parser::new()
         }
         .parse(from_utf8(&[b'('; 32]).unwrap(), &mut Slab::new().ps),
         Err(Error::TooLong)
@@ -6,7 +6,7 @@ pub(crate) mod common;
 use common::assert_error_margin;
 
 use fasteval3::{
-    CachedCallbackNamespace, EmptyNamespace, Error, Evaler, ExpressionI, Parser, Slab,
+    CachedCallbackNamespace, EmptyNamespace, Error, Evaler, ExpressionI, Num, Parser, Slab,
 };
 
 use std::collections::BTreeMap;
@@ -19,7 +19,7 @@ fn ok_parse(s: &str, slab: &mut Slab) -> ExpressionI {
     parse_raw(s, slab).unwrap()
 }
 
-fn do_eval(s: &str) -> f32 {
+fn do_eval(s: &str) -> Num {
     //println!("do_eval({s})");
     let mut slab = Slab::new();
     let mut ns = EmptyNamespace;
@@ -71,8 +71,12 @@ fn aaa_test_b0() {
     let mut slab = Slab::new();
 
     ok_parse("3.14 + 4.99999999999999", &mut slab);
+    #[cfg(not(feature = "f64"))]
     assert_eq!(format!("{:?}",&slab),
 "Slab{ exprs:{ 0:Expression { first: EConstant(3.14), pairs: [ExprPair(EAdd, EConstant(5.0))] } }, vals:{}, instrs:{} }");
+    #[cfg(feature = "f64")]
+    assert_eq!(format!("{:?}",&slab),
+"Slab{ exprs:{ 0:Expression { first: EConstant(3.14), pairs: [ExprPair(EAdd, EConstant(4.99999999999999))] } }, vals:{}, instrs:{} }");
     ok_parse(
         "3.14 + 4.99999999999999999999999999999999999999999999999999999",
         &mut slab,
@@ -96,10 +100,12 @@ fn aaa_test_b1() {
     let mut slab = Slab::new();
 
     assert_eq!(parse_raw("3.14 + 4.99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999.9999", &mut slab),
-Err(Error::ParseF32(String::from("4.99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999.9999"))));
+Err(Error::MalformedNumber(String::from("4.99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999.9999: more than one decimal point"))));
     assert_eq!(
         parse_raw("3.14 + 4.9999.9999", &mut slab),
-        Err(Error::ParseF32(String::from("4.9999.9999")))
+        Err(Error::MalformedNumber(String::from(
+            "4.9999.9999: more than one decimal point"
+        )))
     );
 }
 
@@ -109,7 +115,7 @@ fn aaa_test_b2() {
 
     assert_eq!(
         parse_raw("3.14 + .", &mut slab),
-        Err(Error::ParseF32(String::from(".")))
+        Err(Error::MalformedNumber(String::from(".: no digits")))
     );
 }
 
@@ -137,7 +143,10 @@ fn aaa_test_c1() {
 
     assert_eq!(
         parse_raw("3+5-XYZ_ab~c_def123", &mut slab),
-        Err(Error::UnparsedTokensRemaining(String::from("~c_def123")))
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("~c_def123"),
+            pos: 10
+        })
     );
 }
 
@@ -175,8 +184,11 @@ fn aaa_test_e() {
     assert_error_margin(do_eval("3+4+5+6"), 18.0);
     assert_error_margin(do_eval("3-4-5-6"), -12.0);
     assert_error_margin(do_eval("3*4*5*6"), 360.0);
-    assert_error_margin(do_eval("3/4/5/6"), 0.025_000_002); // Fragile!
+    assert_error_margin(do_eval("3/4/5/6"), 3.0 / 4.0 / 5.0 / 6.0);
+    #[cfg(not(feature = "left-assoc-exp"))]
     assert_error_margin(do_eval("2^3^4"), 2_417_851_639_229_258_349_412_352.0);
+    #[cfg(feature = "left-assoc-exp")]
+    assert_error_margin(do_eval("2^3^4"), 4096.0);
     assert_error_margin(do_eval("3*3-3/3"), 8.0);
     assert_error_margin(do_eval("(1+1)^3"), 8.0);
     assert_error_margin(do_eval("(1+(-1)^4)^3"), 8.0);
@@ -186,19 +198,28 @@ fn aaa_test_e() {
 
     assert_error_margin(do_eval("5%2"), 1.0);
     assert_error_margin(do_eval("5%3"), 2.0);
-    assert_error_margin(do_eval("5.1%3.2"), 1.899_999_9);
-    assert_error_margin(do_eval("5.1%2.5"), 0.099_999_9);
+    assert_error_margin(do_eval("5.1%3.2"), 5.1 % 3.2);
+    assert_error_margin(do_eval("5.1%2.5"), 5.1 % 2.5);
+    #[cfg(not(feature = "f64"))]
     assert_error_margin(do_eval("5.1%2.499999999"), 0.100_000);
+    #[cfg(feature = "f64")]
+    assert_error_margin(do_eval("5.1%2.499999999"), 0.100_000_002);
     assert_error_margin(do_eval("-5%2"), -1.0);
     assert_error_margin(do_eval("-5%3"), -2.0);
-    assert_error_margin(do_eval("-5.1%3.2"), -1.899_999_9);
-    assert_error_margin(do_eval("-5.1%2.5"), -0.099_999_9);
+    assert_error_margin(do_eval("-5.1%3.2"), -5.1 % 3.2);
+    assert_error_margin(do_eval("-5.1%2.5"), -5.1 % 2.5);
+    #[cfg(not(feature = "f64"))]
     assert_error_margin(do_eval("-5.1%2.499999999"), -0.100_000_0);
+    #[cfg(feature = "f64")]
+    assert_error_margin(do_eval("-5.1%2.499999999"), -0.100_000_002);
     assert_error_margin(do_eval("5%-2"), 1.0);
     assert_error_margin(do_eval("5%-3"), 2.0);
-    assert_error_margin(do_eval("5.1%-3.2"), 1.899_999_9);
-    assert_error_margin(do_eval("5.1%-2.5"), 0.099_999_9);
+    assert_error_margin(do_eval("5.1%-3.2"), 5.1 % -3.2);
+    assert_error_margin(do_eval("5.1%-2.5"), 5.1 % -2.5);
+    #[cfg(not(feature = "f64"))]
     assert_error_margin(do_eval("5.1%-2.499999999"), 0.100_000_0);
+    #[cfg(feature = "f64")]
+    assert_error_margin(do_eval("5.1%-2.499999999"), 0.100_000_002);
     assert_error_margin(do_eval("int(5)%int(2)"), 1.0);
     assert_error_margin(do_eval("int(5)%int(3)"), 2.0);
     assert_error_margin(do_eval("int(5.1)%round(3.2)"), 2.0);
@@ -228,7 +249,7 @@ fn aaa_test_f() {
                     [("x", 2.0)]
                         .iter()
                         .copied()
-                        .collect::<BTreeMap<&str, f32>>()
+                        .collect::<BTreeMap<&str, Num>>()
                         .get(n)
                         .copied()
                 }),
@@ -245,7 +266,7 @@ fn aaa_test_f() {
                     [("x", 2.0), ("y", 3.0)]
                         .iter()
                         .copied()
-                        .collect::<BTreeMap<&str, f32>>()
+                        .collect::<BTreeMap<&str, Num>>()
                         .get(n)
                         .copied()
                 }),
@@ -367,11 +388,12 @@ fn aaa_test_i() {
 fn aaa_test_j() {
     assert_error_margin(do_eval("2/3*3/2"), 1.0);
     assert_error_margin(do_eval("2%3*3/2"), 3.0);
-    assert_error_margin(do_eval("3^2%2^2*2^2/3^2"), 0.444_444_45);
+    assert_error_margin(do_eval("3^2%2^2*2^2/3^2"), 4.0 / 9.0);
     assert_error_margin(do_eval("1+2-3+4"), 4.0);
 }
 
 #[test]
+#[cfg(feature = "print-builtin")]
 fn aaa_test_k() {
     do_eval(r#"print("a",print("b",print("c",5,"C"),"B"),"A")"#);
 
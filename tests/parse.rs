@@ -1,4 +1,11 @@
-use fasteval3::{Error, Parser, Slab};
+pub(crate) mod common;
+
+use common::eval_both_with;
+
+use fasteval3::parser::BinaryOp::EMul;
+use fasteval3::{
+    CustomOp, EmptyNamespace, Error, Evaler, Num, Parser, Slab, ValueI, ValueSite, Warning,
+};
 
 #[test]
 fn basics() {
@@ -21,17 +28,20 @@ fn basics() {
     assert_eq!(format!("{:?}",&slab),
 "Slab{ exprs:{ 0:Expression { first: EConstant(-43.0), pairs: [ExprPair(ESub, EConstant(0.21))] }, 1:Expression { first: EConstant(12.34), pairs: [ExprPair(EAdd, EStdFunc(EFuncAbs(ExpressionI(0)))), ExprPair(EAdd, EConstant(11.11))] } }, vals:{}, instrs:{} }");
 
-    Parser::new()
-        .parse("12.34 + print ( 43.21 ) + 11.11", &mut slab.ps)
-        .unwrap();
-    assert_eq!(format!("{:?}",&slab),
+    #[cfg(feature = "print-builtin")]
+    {
+        Parser::new()
+            .parse("12.34 + print ( 43.21 ) + 11.11", &mut slab.ps)
+            .unwrap();
+        assert_eq!(format!("{:?}",&slab),
 "Slab{ exprs:{ 0:Expression { first: EConstant(43.21), pairs: [] }, 1:Expression { first: EConstant(12.34), pairs: [ExprPair(EAdd, EPrintFunc(PrintFunc([EExpr(ExpressionI(0))]))), ExprPair(EAdd, EConstant(11.11))] } }, vals:{}, instrs:{} }");
 
-    Parser::new()
-        .parse("12.34 + print [ 43.21 ] + 11.11", &mut slab.ps)
-        .unwrap();
-    assert_eq!(format!("{:?}",&slab),
+        Parser::new()
+            .parse("12.34 + print [ 43.21 ] + 11.11", &mut slab.ps)
+            .unwrap();
+        assert_eq!(format!("{:?}",&slab),
 "Slab{ exprs:{ 0:Expression { first: EConstant(43.21), pairs: [] }, 1:Expression { first: EConstant(12.34), pairs: [ExprPair(EAdd, EPrintFunc(PrintFunc([EExpr(ExpressionI(0))]))), ExprPair(EAdd, EConstant(11.11))] } }, vals:{}, instrs:{} }");
+    }
 
     Parser::new().parse("(-1) ^ 0.5", &mut slab.ps).unwrap();
     assert_eq!(format!("{:?}",&slab),
@@ -69,12 +79,14 @@ fn consts() {
 
     assert_eq!(
         Parser::new().parse(".", &mut slab.ps),
-        Err(Error::ParseF32(String::from(".")))
+        Err(Error::MalformedNumber(String::from(".: no digits")))
     );
 
     assert_eq!(
         Parser::new().parse("12..34", &mut slab.ps),
-        Err(Error::ParseF32(String::from("12..34")))
+        Err(Error::MalformedNumber(String::from(
+            "12..34: more than one decimal point"
+        )))
     );
 
     Parser::new().parse("12.34k", &mut slab.ps).unwrap();
@@ -191,16 +203,937 @@ fn consts() {
 
     assert_eq!(
         Parser::new().parse("-infK", &mut slab.ps),
-        Err(Error::UnparsedTokensRemaining(String::from("K")))
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("K"),
+            pos: 4
+        })
     );
     assert_eq!(
         Parser::new().parse("NaNK", &mut slab.ps),
-        Err(Error::UnparsedTokensRemaining(String::from("K")))
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("K"),
+            pos: 3
+        })
     );
     assert_eq!(
         Parser::new().parse("12.34e56K", &mut slab.ps),
-        Err(Error::UnparsedTokensRemaining(String::from("K")))
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("K"),
+            pos: 8
+        })
+    );
+
+    Parser::new().parse("180deg", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", &slab),
+        format!(
+            "Slab{{ exprs:{{ 0:Expression {{ first: EConstant({:?}), pairs: [] }} }}, vals:{{}}, instrs:{{}} }}",
+            Num::to_radians(180.0)
+        )
+    );
+
+    Parser::new().parse("1.5rad", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", &slab),
+        "Slab{ exprs:{ 0:Expression { first: EConstant(1.5), pairs: [] } }, vals:{}, instrs:{} }"
+    );
+
+    Parser::new().parse("1.5mrad", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", &slab),
+        "Slab{ exprs:{ 0:Expression { first: EConstant(0.0015), pairs: [] } }, vals:{}, instrs:{} }"
+    );
+
+    Parser::new().parse("90deg+x", &mut slab.ps).unwrap();
+    assert_eq!(format!("{:?}",&slab),
+format!("Slab{{ exprs:{{ 0:Expression {{ first: EConstant({:?}), pairs: [ExprPair(EAdd, EStdFunc(EVar(\"x\")))] }} }}, vals:{{}}, instrs:{{}} }}", Num::to_radians(90.0)));
+
+    assert_eq!(
+        Parser::new().parse("90degrees", &mut slab.ps),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("degrees"),
+            pos: 2
+        })
+    );
+    assert_eq!(
+        Parser::new().parse("2radius", &mut slab.ps),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("radius"),
+            pos: 1
+        })
+    );
+    assert_eq!(
+        Parser::new().parse("12.34e2deg", &mut slab.ps),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("deg"),
+            pos: 7
+        })
+    );
+}
+
+#[test]
+fn func_arities() {
+    let mut slab = Slab::new();
+    let mut parser = Parser::new();
+    parser.func_arities.insert(String::from("foo"), 1);
+    parser.func_arities.insert(String::from("bar"), 2);
+    parser.func_arities.insert(String::from("baz"), 0);
+
+    assert!(parser
+        .parse("foo(1) + bar(1, 2) + baz() + baz + x(1,2,3)", &mut slab.ps)
+        .is_ok());
+    assert_eq!(
+        parser.parse("foo(1, 2)", &mut slab.ps),
+        Err(Error::WrongArgs(String::from("foo: expected 1 arg")))
+    );
+    assert_eq!(
+        parser.parse("1 + foo", &mut slab.ps),
+        Err(Error::WrongArgs(String::from("foo: expected 1 arg")))
+    );
+    assert_eq!(
+        parser.parse("bar(foo(1))", &mut slab.ps),
+        Err(Error::WrongArgs(String::from("bar: expected 2 args")))
+    );
+    assert_eq!(
+        parser.parse("baz(1)", &mut slab.ps),
+        Err(Error::WrongArgs(String::from("baz: expected 0 args")))
+    );
+
+    // Without declarations, anything goes:
+    assert!(Parser::new().parse("foo(1, 2) + foo", &mut slab.ps).is_ok());
+}
+
+#[test]
+fn reject_variables() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        reject_variables: true,
+        ..Parser::new()
+    };
+
+    assert!(parser.parse("1+2", &mut slab.ps).is_ok());
+    assert!(parser
+        .parse("sin(pi()/2) * max(1, 2)", &mut slab.ps)
+        .is_ok());
+    assert_eq!(
+        parser.parse("x+1", &mut slab.ps),
+        Err(Error::VariablesNotAllowed)
+    );
+    assert_eq!(
+        parser.parse("1 + foo(2)", &mut slab.ps),
+        Err(Error::VariablesNotAllowed)
+    );
+    assert_eq!(
+        parser.parse("abs(x)", &mut slab.ps),
+        Err(Error::VariablesNotAllowed)
+    );
+}
+
+#[test]
+fn reject_square_brackets() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        reject_square_brackets: true,
+        ..Parser::new()
+    };
+
+    assert!(parser.parse("(1+2) * max(1, {2})", &mut slab.ps).is_ok());
+    assert_eq!(
+        parser.parse("[1+2]", &mut slab.ps),
+        Err(Error::InvalidValue { pos: 0 })
+    );
+    assert_eq!(
+        parser.parse("3 * [1+2]", &mut slab.ps),
+        Err(Error::InvalidValue { pos: 4 })
+    );
+    assert_eq!(
+        parser.parse("max[1, 2]", &mut slab.ps),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("[1, 2]"),
+            pos: 3
+        })
+    );
+    assert_eq!(
+        parser.parse("(1+2]", &mut slab.ps),
+        Err(Error::Expected(String::from(")")))
+    );
+
+    // Allowed by default:
+    assert!(Parser::new().parse("[1+2]", &mut slab.ps).is_ok());
+    assert!(Parser::new().parse("max[1, 2]", &mut slab.ps).is_ok());
+}
+
+#[test]
+fn reject_repeated_unary() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        reject_repeated_unary: true,
+        ..Parser::new()
+    };
+
+    assert_eq!(
+        parser.parse("++4", &mut slab.ps),
+        Err(Error::UnexpectedOperator(String::from("++")))
+    );
+    assert_eq!(
+        parser.parse("1 + ++4", &mut slab.ps),
+        Err(Error::UnexpectedOperator(String::from("++")))
+    );
+    assert_eq!(
+        parser.parse("--x", &mut slab.ps),
+        Err(Error::UnexpectedOperator(String::from("--")))
+    );
+    assert_eq!(
+        parser.parse("2 * - !x", &mut slab.ps),
+        Err(Error::UnexpectedOperator(String::from("-!")))
+    );
+
+    // A single unary operator is fine, even after a binary one:
+    assert!(parser.parse("-x", &mut slab.ps).is_ok());
+    assert!(parser.parse("1 - -4", &mut slab.ps).is_ok());
+    assert!(parser.parse("1 + -x", &mut slab.ps).is_ok());
+    assert!(parser.parse("!x != 1", &mut slab.ps).is_ok());
+    // Parentheses make the intent explicit:
+    assert!(parser.parse("-(-x)", &mut slab.ps).is_ok());
+
+    // Allowed by default:
+    assert!(Parser::new().parse("1 + ++4", &mut slab.ps).is_ok());
+    assert!(Parser::new().parse("--x", &mut slab.ps).is_ok());
+}
+
+#[test]
+fn literal_limit() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        literal_limit: Some(1000.0),
+        ..Parser::new()
+    };
+
+    assert_eq!(
+        parser.parse("1e38", &mut slab.ps),
+        Err(Error::LiteralOutOfRange(String::from("1e38")))
+    );
+    assert_eq!(
+        parser.parse("x + 1001", &mut slab.ps),
+        Err(Error::LiteralOutOfRange(String::from("1001")))
+    );
+    // The sign is part of the literal, and the magnitude is checked:
+    assert_eq!(
+        parser.parse("-1e4", &mut slab.ps),
+        Err(Error::LiteralOutOfRange(String::from("-1e4")))
+    );
+    // Suffixes are applied before the check:
+    assert_eq!(
+        parser.parse("2k", &mut slab.ps),
+        Err(Error::LiteralOutOfRange(String::from("2k")))
+    );
+    assert_eq!(
+        parser.parse("inf", &mut slab.ps),
+        Err(Error::LiteralOutOfRange(String::from("inf")))
+    );
+
+    // The limit itself is allowed:
+    assert!(parser.parse("1000", &mut slab.ps).is_ok());
+    assert!(parser.parse("-1000 + 1k", &mut slab.ps).is_ok());
+    assert!(parser.parse("999.5 * 2", &mut slab.ps).is_ok());
+    assert!(parser.parse("NaN", &mut slab.ps).is_ok());
+
+    // No limit by default:
+    assert!(Parser::new().parse("1e38", &mut slab.ps).is_ok());
+}
+
+#[test]
+fn reject_si_suffixes() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        reject_si_suffixes: true,
+        ..Parser::new()
+    };
+
+    for (expr_str, rest) in [
+        ("1M", "M"),
+        ("2k", "k"),
+        ("3.5G", "G"),
+        ("1 + 5m", "m"),
+        ("4u * 2", "u * 2"),
+        ("2kk", "kk"),
+    ] {
+        assert_eq!(
+            parser.parse(expr_str, &mut slab.ps),
+            Err(Error::UnparsedTokensRemaining {
+                rest: String::from(rest),
+                pos: expr_str.len() - rest.len(),
+            }),
+            "{expr_str}"
+        );
+    }
+
+    // Suffixes can be written as an explicit multiplication instead:
+    let expr_i = parser.parse("1*M", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", slab.ps.get_expr(expr_i)),
+        "Expression { first: EConstant(1.0), pairs: [ExprPair(EMul, EStdFunc(EVar(\"M\")))] }"
+    );
+    // Angle suffixes and exponents still work:
+    assert!(parser.parse("sin(90deg) + 1e6", &mut slab.ps).is_ok());
+    assert!(parser.parse("M + k", &mut slab.ps).is_ok());
+
+    // Allowed by default:
+    let expr_i = Parser::new().parse("1M", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", slab.ps.get_expr(expr_i)),
+        "Expression { first: EConstant(1000000.0), pairs: [] }"
+    );
+}
+
+#[test]
+fn parse_recover() {
+    let parsed = |parser: &Parser, expr_str: &str| {
+        let mut slab = Slab::new();
+        parser
+            .parse(expr_str, &mut slab.ps)
+            .map(|_| format!("{:?}", slab.ps))
+    };
+    let recovered = |parser: &Parser, expr_str: &str| {
+        let mut slab = Slab::new();
+        let (expr_i, err) = parser.parse_recover(expr_str, &mut slab.ps);
+        (expr_i.map(|_| format!("{:?}", slab.ps)), err)
+    };
+
+    // Complete expressions parse normally:
+    assert_eq!(
+        recovered(&Parser::new(), "1 + 2 * x"),
+        (parsed(&Parser::new(), "1 + 2 * x").ok(), None)
+    );
+
+    // Truncated expressions give the longest prefix that parses, with open
+    // groups closed:
+    for (truncated, partial, err) in [
+        (
+            "1 + 2 *",
+            "1 + 2",
+            Error::EofWhileParsing(String::from("value")),
+        ),
+        (
+            "sin(x) * (3 +",
+            "sin(x) * (3)",
+            Error::EofWhileParsing(String::from("value")),
+        ),
+        (
+            "max(1, y",
+            "max(1, y)",
+            Error::EofWhileParsing(String::from("max")),
+        ),
+        (
+            "max(1,",
+            "max(1)",
+            Error::EofWhileParsing(String::from("value")),
+        ),
+        (
+            "x + 12 )",
+            "x + 12",
+            Error::UnparsedTokensRemaining {
+                rest: String::from(")"),
+                pos: 7,
+            },
+        ),
+    ] {
+        assert_eq!(
+            recovered(&Parser::new(), truncated),
+            (parsed(&Parser::new(), partial).ok(), Some(err)),
+            "{truncated}"
+        );
+    }
+
+    // Nothing parses:
+    assert_eq!(
+        recovered(&Parser::new(), "* 2"),
+        (None, Some(Error::InvalidValue { pos: 0 }))
+    );
+    let mut slab = Slab::new();
+    Parser::new().parse("1 + 2", &mut slab.ps).unwrap();
+    assert_eq!(
+        Parser::new().parse_recover("", &mut slab.ps),
+        (None, Some(Error::EofWhileParsing(String::from("value"))))
+    );
+    assert_eq!(format!("{:?}", slab.ps), format!("{:?}", Slab::new().ps));
+
+    // The Parser's other options still apply:
+    let parser = Parser {
+        reject_variables: true,
+        ..Parser::new()
+    };
+    assert_eq!(
+        recovered(&parser, "2 * (3 + x"),
+        (
+            parsed(&parser, "2 * (3)").ok(),
+            Some(Error::VariablesNotAllowed)
+        )
+    );
+    let parser = Parser {
+        expr_len_limit: 3,
+        ..Parser::new()
+    };
+    assert_eq!(recovered(&parser, "1 + 2"), (None, Some(Error::TooLong)));
+}
+
+#[test]
+fn auto_close() {
+    let parser = Parser {
+        auto_close: true,
+        ..Parser::new()
+    };
+    let parsed = |parser: &Parser, expr_str: &str| {
+        let mut slab = Slab::new();
+        parser
+            .parse(expr_str, &mut slab.ps)
+            .map(|_| format!("{:?}", slab.ps))
+    };
+
+    for (lenient, strict) in [
+        ("((1+2", "((1+2))"),
+        ("((1+2) * 3", "((1+2) * 3)"),
+        ("sin(x", "sin(x)"),
+        ("max(1, [2 + {3  ", "max(1, [2 + {3}])"),
+        ("abs(-x) + log(2, x", "abs(-x) + log(2, x)"),
+        ("1 + 2", "1 + 2"),
+    ] {
+        assert_eq!(
+            parsed(&parser, lenient),
+            parsed(&Parser::new(), strict),
+            "{lenient}"
+        );
+    }
+    #[cfg(feature = "print-builtin")]
+    assert_eq!(
+        parsed(&parser, r#"print("x", (1"#),
+        parsed(&Parser::new(), r#"print("x", (1))"#)
+    );
+
+    // Only the end of input closes groups:
+    assert_eq!(
+        parsed(&parser, "(1 + 2]"),
+        Err(Error::Expected(String::from(")")))
+    );
+    assert_eq!(
+        parsed(&parser, "max(1,"),
+        Err(Error::EofWhileParsing(String::from("value")))
+    );
+
+    // Off by default:
+    assert_eq!(
+        parsed(&Parser::new(), "((1+2"),
+        Err(Error::EofWhileParsing(String::from("parentheses")))
+    );
+    assert_eq!(
+        parsed(&Parser::new(), "sin(x"),
+        Err(Error::EofWhileParsing(String::from("sin")))
+    );
+}
+
+#[test]
+fn int_literals() {
+    let mut slab = Slab::new();
+    let mut is_int = |expr_str: &str| {
+        let expr_i = Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0))
+    };
+
+    assert!(is_int("3"));
+    assert!(is_int("-42"));
+    assert!(is_int("+7 * 2"));
+    assert!(!is_int("3.0"));
+    assert!(!is_int("3."));
+    assert!(!is_int(".5"));
+    assert!(!is_int("3e0"));
+    assert!(!is_int("3k"));
+    assert!(!is_int("90deg"));
+    assert!(!is_int("x"));
+    assert!(!is_int("(3)"));
+    assert!(!is_int("inf"));
+
+    // Every operand is tracked, and evaluation is unaffected:
+    let expr_i = Parser::new().parse("3 + 4.5 * -2", &mut slab.ps).unwrap();
+    let flags: Vec<bool> = (0..3)
+        .map(|n| slab.ps.is_int_literal(ValueSite::Operand(expr_i, n)))
+        .collect();
+    assert_eq!(flags, vec![true, false, true]);
+    assert_eq!(
+        slab.ps.get_expr(expr_i).eval(&slab, &mut EmptyNamespace),
+        Ok(-6.0)
+    );
+
+    // Operands of unary operators are stored as Values:
+    let expr_i = Parser::new().parse("- 3", &mut slab.ps).unwrap();
+    assert!(!slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0)));
+    assert!(slab.ps.is_int_literal(ValueSite::Val(ValueI(0))));
+    Parser::new().parse("!3.0", &mut slab.ps).unwrap();
+    assert!(!slab.ps.is_int_literal(ValueSite::Val(ValueI(0))));
+
+    // Function arguments are Expressions of their own:
+    let expr_i = Parser::new().parse("max(1, 2.5)", &mut slab.ps).unwrap();
+    let args = &slab.ps.get_expr(expr_i).calls(&slab)[0].args;
+    assert!(slab.ps.is_int_literal(ValueSite::Operand(args[0], 0)));
+    assert!(!slab.ps.is_int_literal(ValueSite::Operand(args[1], 0)));
+
+    // ...including the operands of custom operators:
+    let mut parser = Parser::new();
+    parser.custom_ops.insert(
+        '@',
+        CustomOp {
+            func: String::from("nearest"),
+            precedence: EMul,
+        },
+    );
+    let expr_i = parser.parse("1 + 7 @ 2.5", &mut slab.ps).unwrap();
+    assert!(slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0)));
+    let args = &slab.ps.get_expr(expr_i).calls(&slab)[0].args;
+    assert!(slab.ps.is_int_literal(ValueSite::Operand(args[0], 0)));
+    assert!(!slab.ps.is_int_literal(ValueSite::Operand(args[1], 0)));
+
+    // Clearing the Slab forgets the integer literals:
+    let expr_i = Parser::new().parse("3", &mut slab.ps).unwrap();
+    assert!(slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0)));
+    slab.clear();
+    let expr_i = Parser::new().parse_noclear("3.5", &mut slab.ps).unwrap();
+    assert!(!slab.ps.is_int_literal(ValueSite::Operand(expr_i, 0)));
+}
+
+#[test]
+fn parse_with_doc() {
+    let mut slab = Slab::new();
+    let parsed = Parser::new()
+        .parse_with_doc(
+            "  # Kinetic energy.\n#\n#m: mass, v: velocity\r\n  0.5 * m * v^2",
+            &mut slab.ps,
+        )
+        .unwrap();
+    assert_eq!(
+        parsed.doc.as_deref(),
+        Some("Kinetic energy.\n\nm: mass, v: velocity")
+    );
+    assert_eq!(
+        format!("{:?}", slab.ps.get_expr(parsed.expr)),
+        "Expression { first: EConstant(0.5), pairs: [ExprPair(EMul, EStdFunc(EVar(\"m\"))), ExprPair(EMul, EStdFunc(EVar(\"v\"))), ExprPair(EExp, EConstant(2.0))] }"
+    );
+
+    let parsed = Parser::new().parse_with_doc("1 + 2", &mut slab.ps).unwrap();
+    assert_eq!(parsed.doc, None);
+
+    // Comments are only allowed before the expression:
+    assert!(Parser::new()
+        .parse_with_doc("1 + 2 # three", &mut slab.ps)
+        .is_err());
+    assert_eq!(
+        Parser::new().parse_with_doc("# nothing", &mut slab.ps),
+        Err(Error::EofWhileParsing(String::from("value")))
+    );
+}
+
+#[test]
+fn malformed_numbers() {
+    let mut slab = Slab::new();
+    for (expr_str, msg) in [
+        ("1e", "1e: missing exponent digits"),
+        ("1eK", "1e: missing exponent digits"),
+        ("2 * 1e-", "1e-: missing exponent digits"),
+        ("1.2.3", "1.2.3: more than one decimal point"),
+        ("1.2.3k + 1", "1.2.3: more than one decimal point"),
+        ("1e5e3", "1e5e3: more than one exponent"),
+        ("1e2.5", "1e2.5: exponent must be an integer"),
+        ("-.", "-.: no digits"),
+        ("1µµ", "1µµ: more than one SI suffix"),
+        ("1kk", "1kk: more than one SI suffix"),
+        ("1mµ + 2", "1mµ: more than one SI suffix"),
+    ] {
+        assert_eq!(
+            Parser::new().parse(expr_str, &mut slab.ps),
+            Err(Error::MalformedNumber(String::from(msg))),
+            "{expr_str}"
+        );
+    }
+
+    // Suffixes that start a name are left for the rest of the parser:
+    assert_eq!(
+        Parser::new().parse("1kkx", &mut slab.ps),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("kx"),
+            pos: 2
+        })
+    );
+    assert!(Parser::new().parse("1mdeg", &mut slab.ps).is_ok());
+}
+
+#[test]
+fn parse_bytes() {
+    let mut slab = Slab::new();
+    let parser = Parser {
+        expr_len_limit: 8,
+        ..Parser::new()
+    };
+
+    let expr_i = parser.parse_bytes(b"1 + x", &mut slab.ps).unwrap();
+    assert_eq!(
+        format!("{:?}", slab.ps.get_expr(expr_i)),
+        "Expression { first: EConstant(1.0), pairs: [ExprPair(EAdd, EStdFunc(EVar(\"x\")))] }"
+    );
+
+    // The length is checked before UTF-8 validation:
+    assert_eq!(
+        parser.parse_bytes(&[0xFF; 9], &mut slab.ps),
+        Err(Error::TooLong)
+    );
+    assert_eq!(
+        parser.parse_bytes(&[b'1', b'+', 0xFF], &mut slab.ps),
+        Err(Error::Utf8ErrorWhileParsing(String::from("expression")))
+    );
+    assert_eq!(
+        parser.parse_bytes(b"1 + 2 + 3 + 4", &mut slab.ps),
+        Err(Error::TooLong)
+    );
+}
+
+#[test]
+fn calls() {
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("x + log(2, abs(f(y, sin(1)))) * -min(3, x)", &mut slab.ps)
+        .unwrap();
+    let calls = slab.ps.get_expr(expr_i).calls(&slab);
+    let names: Vec<&str> = calls.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["log", "abs", "f", "sin", "min"]);
+
+    let arg_strs: Vec<Vec<String>> = calls
+        .iter()
+        .map(|c| {
+            c.args
+                .iter()
+                .map(|&xi| format!("{:?}", slab.ps.get_expr(xi)))
+                .collect()
+        })
+        .collect();
+    assert_eq!(arg_strs[2].len(), 2);
+    assert_eq!(
+        arg_strs[2][0],
+        "Expression { first: EStdFunc(EVar(\"y\")), pairs: [] }"
+    );
+    assert_eq!(
+        arg_strs[3],
+        ["Expression { first: EConstant(1.0), pairs: [] }"]
+    );
+    assert_eq!(calls[4].args.len(), 2);
+
+    let expr_i = Parser::new().parse("x + y", &mut slab.ps).unwrap();
+    assert_eq!(slab.ps.get_expr(expr_i).calls(&slab), []);
+}
+
+#[test]
+fn to_json() {
+    let mut slab = Slab::new();
+    let expr_i = Parser::new()
+        .parse("2.5 * (x + 1) >= log(!y) || f()", &mut slab.ps)
+        .unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).to_json(&slab),
+        concat!(
+            r#"{"first":{"kind":"constant","value":2.5},"pairs":["#,
+            r#"{"op":"*","value":{"kind":"parentheses","expr":{"first":{"kind":"var","name":"x"},"pairs":[{"op":"+","value":{"kind":"constant","value":1}}]}}},"#,
+            r#"{"op":">=","value":{"kind":"call","name":"log","args":[{"first":{"kind":"unary","op":"!","operand":{"kind":"var","name":"y"}},"pairs":[]}]}},"#,
+            r#"{"op":"||","value":{"kind":"call","name":"f","args":[]}}]}"#,
+        )
+    );
+
+    let expr_i = Parser::new().parse("-inf", &mut slab.ps).unwrap();
+    assert_eq!(
+        slab.ps.get_expr(expr_i).to_json(&slab),
+        r#"{"first":{"kind":"constant","value":"-inf"},"pairs":[]}"#
+    );
+
+    #[cfg(feature = "print-builtin")]
+    {
+        let expr_i = Parser::new()
+            .parse(r#"print("a\b", 1)"#, &mut slab.ps)
+            .unwrap();
+        assert_eq!(
+            slab.ps.get_expr(expr_i).to_json(&slab),
+            r#"{"first":{"kind":"call","name":"print","args":[{"kind":"string","value":"a\\b"},{"first":{"kind":"constant","value":1},"pairs":[]}]},"pairs":[]}"#
+        );
+    }
+}
+
+#[test]
+fn warnings() {
+    let mut slab = Slab::new();
+    let mut warnings = |expr_str: &str| {
+        Parser::new()
+            .parse_with_warnings(expr_str, &mut slab.ps)
+            .map(|(_, warnings)| warnings)
+    };
+
+    assert_eq!(warnings("2 * pi() * r + sin(x)"), Ok(vec![]));
+    assert_eq!(warnings("16777215 + 0.5e7"), Ok(vec![]));
+
+    // Bare names of built-in functions are variables:
+    assert_eq!(
+        warnings("2 * pi * r"),
+        Ok(vec![Warning::ShadowsBuiltin(String::from("pi"))])
+    );
+    assert_eq!(
+        warnings("max(-abs, 1) + (log)"),
+        Ok(vec![
+            Warning::ShadowsBuiltin(String::from("abs")),
+            Warning::ShadowsBuiltin(String::from("log")),
+        ])
+    );
+
+    // Literals that f32 can't represent exactly:
+    #[cfg(not(feature = "f64"))]
+    {
+        assert_eq!(
+            warnings("x + 16777217"),
+            Ok(vec![Warning::LargeLiteral(16_777_216.0)])
+        );
+        assert_eq!(
+            warnings("-3e9 * 2 + min(1, 5G)"),
+            Ok(vec![
+                Warning::LargeLiteral(-3e9),
+                Warning::LargeLiteral(5e9)
+            ])
+        );
+    }
+    // ...and those that f64 can't:
+    #[cfg(feature = "f64")]
+    {
+        assert_eq!(warnings("x + 16777217"), Ok(vec![]));
+        assert_eq!(
+            warnings("x + 9007199254740993"),
+            Ok(vec![Warning::LargeLiteral(9_007_199_254_740_992.0)])
+        );
+        assert_eq!(
+            warnings("-3e16 * 2 + min(1, 5e18)"),
+            Ok(vec![
+                Warning::LargeLiteral(-3e16),
+                Warning::LargeLiteral(5e18)
+            ])
+        );
+    }
+    assert_eq!(warnings("inf - 1"), Ok(vec![]));
+
+    // Warnings don't change the parse, and errors are still errors:
+    let (expr_i, _) = Parser::new()
+        .parse_with_warnings("e * 2", &mut slab.ps)
+        .unwrap();
+    assert_eq!(
+        slab.ps
+            .get_expr(expr_i)
+            .eval(&slab, &mut |_: &str, _| Some(3.0)),
+        Ok(6.0)
+    );
+    assert_eq!(
+        Parser::new().parse_with_warnings("pi +", &mut slab.ps),
+        Err(Error::EofWhileParsing(String::from("value")))
+    );
+}
+
+#[test]
+fn named_constants() {
+    use fasteval3::{Compiler, Instruction::IConst, Num};
+
+    let c: Num = 299_792_458.0;
+    let mut parser = Parser::new();
+    parser.constants.insert(String::from("c"), c);
+    parser.constants.insert(String::from("g"), 9.81);
+
+    let mut slab = Slab::new();
+    let expr_i = parser.parse("c * 2", &mut slab.ps).unwrap();
+    let instr = slab
+        .ps
+        .get_expr(expr_i)
+        .compile(&slab.ps, &mut slab.cs, &mut EmptyNamespace);
+    assert_eq!(instr, IConst(c * 2.0));
+    assert_eq!(format!("{:?}", slab.cs), "CompileSlab{ instrs:{} }");
+
+    let eval = |parser: &Parser, expr_str: &str| {
+        let mut ns = |name: &str, _: Vec<Num>| match name {
+            "t" => Some(2.0),
+            "g" => Some(-1.0),
+            _ => None,
+        };
+        eval_both_with(parser, &mut Slab::new(), &mut ns, expr_str)
+    };
+    // The Namespace is not asked for constants:
+    assert_eq!(eval(&parser, "g * t^2 / 2"), Ok(9.81 * 2.0));
+    assert_eq!(eval(&Parser::new(), "g * t^2 / 2"), Ok(-2.0));
+    // Called names are still looked up as functions:
+    assert_eq!(eval(&parser, "g() + g"), Ok(-1.0 + 9.81));
+    // Constants are allowed when variables are rejected:
+    parser.reject_variables = true;
+    assert_eq!(eval(&parser, "c / c"), Ok(1.0));
+    assert_eq!(eval(&parser, "c * t"), Err(Error::VariablesNotAllowed));
+}
+
+#[test]
+fn error_offsets() {
+    let mut slab = Slab::new();
+    let parse = |expr_str: &str, slab: &mut Slab| Parser::new().parse(expr_str, &mut slab.ps);
+
+    let expr_str = "1 + @";
+    let err = parse(expr_str, &mut slab);
+    assert_eq!(err, Err(Error::InvalidValue { pos: 4 }));
+    assert_eq!(&expr_str[4..], "@");
+
+    assert_eq!(
+        parse("max(1, (2 * ,))", &mut slab),
+        Err(Error::InvalidValue { pos: 12 })
+    );
+    assert_eq!(
+        parse("1 + 2  3", &mut slab),
+        Err(Error::UnparsedTokensRemaining {
+            rest: String::from("3"),
+            pos: 7,
+        })
+    );
+
+    // Leading comments are included in the offset, which counts bytes:
+    assert_eq!(
+        Parser::new().parse_with_doc("# Doc.\n1 + @", &mut slab.ps),
+        Err(Error::InvalidValue { pos: 11 })
+    );
+    assert_eq!(
+        Parser::new().parse_with_doc("# Gr\u{f6}\u{df}e\n1 + @", &mut slab.ps),
+        Err(Error::InvalidValue { pos: 14 })
+    );
+}
+
+#[test]
+fn limit_builders() {
+    let nested = format!("{}1{}", "(".repeat(40), ")".repeat(40));
+    let parse = |parser: Parser| parser.parse(&nested, &mut Slab::new().ps).map(|_| ());
+
+    assert_eq!(parse(Parser::new()), Err(Error::TooDeep));
+    assert_eq!(
+        parse(Parser::new().with_depth_limit(32)),
+        Err(Error::TooDeep)
+    );
+    assert_eq!(parse(Parser::new().with_depth_limit(64)), Ok(()));
+    assert_eq!(
+        parse(Parser::new().with_depth_limit(64).with_len_limit(80)),
+        Err(Error::TooLong)
+    );
+    assert_eq!(
+        parse(Parser::new().with_len_limit(81).with_depth_limit(64)),
+        Ok(())
+    );
+
+    const PARSER: Parser = Parser::new().with_depth_limit(64);
+    assert_eq!(PARSER.expr_depth_limit, 64);
+}
+
+#[test]
+fn var_limit() {
+    let mut slab = Slab::new();
+    let mut parser = Parser::new().with_var_limit(2);
+    let mut parse =
+        |parser: &Parser, expr_str: &str| parser.parse(expr_str, &mut slab.ps).map(|_| ());
+
+    assert_eq!(Parser::new().var_limit, None);
+    assert_eq!(parse(&parser, "x + y * x - y"), Ok(()));
+    assert_eq!(parse(&parser, "x + y + z"), Err(Error::TooManyVariables));
+    // Custom functions count too, but built-ins don't:
+    assert_eq!(parse(&parser, "f(x) + y"), Err(Error::TooManyVariables));
+    assert_eq!(parse(&parser, "sin(x) + max(y, x)"), Ok(()));
+    // The count starts over for each expression:
+    assert_eq!(parse(&parser, "a + b"), Ok(()));
+    assert_eq!(parse(&parser, "c + d"), Ok(()));
+
+    parser.constants.insert(String::from("g"), 9.81);
+    assert_eq!(parse(&parser, "g * t * t + v"), Ok(()));
+    parser.var_limit = Some(1);
+    assert_eq!(
+        parse(&parser, "g * t * t + v"),
+        Err(Error::TooManyVariables)
+    );
+}
+
+#[test]
+fn classify_name() {
+    use fasteval3::classify_name;
+    use fasteval3::NameKind::{BuiltinConstant, BuiltinFunction, Custom};
+
+    for (name, called, kind) in [
+        ("pi", false, Custom),
+        ("pi", true, BuiltinConstant),
+        ("e", false, Custom),
+        ("e", true, BuiltinConstant),
+        ("x", false, Custom),
+        ("x", true, Custom),
+        ("sin", false, Custom),
+        ("sin", true, BuiltinFunction),
+        ("round_half_up", true, BuiltinFunction),
+        ("print", false, Custom),
+        ("print", true, BuiltinFunction),
+        ("pie", true, Custom),
+        ("Sin", true, Custom),
+    ] {
+        assert_eq!(classify_name(name, called), kind, "{name} {called}");
+    }
+
+    #[cfg(feature = "alpha-keywords")]
+    for name in ["inf", "NaN"] {
+        assert_eq!(classify_name(name, false), BuiltinConstant);
+        assert_eq!(classify_name(name, true), BuiltinConstant);
+    }
+    #[cfg(not(feature = "alpha-keywords"))]
+    assert_eq!(classify_name("inf", false), Custom);
+
+    // Matches how the Parser reads the names:
+    for expr_str in ["pi", "pi()", "x", "x()", "e()", "abs(-1)"] {
+        let mut slab = Slab::new();
+        Parser::new().parse(expr_str, &mut slab.ps).unwrap();
+        let parsed = format!("{:?}", slab.ps);
+        let kind = if parsed.contains("EStdFunc(EVar(") || parsed.contains("EStdFunc(EFunc {") {
+            Custom
+        } else if parsed.contains("EFuncPi") || parsed.contains("EFuncE") {
+            BuiltinConstant
+        } else {
+            BuiltinFunction
+        };
+        let (name, called) = expr_str
+            .split_once('(')
+            .map_or((expr_str, false), |(name, _)| (name, true));
+        assert_eq!(classify_name(name, called), kind, "{expr_str}");
+    }
+}
+
+#[test]
+#[cfg(feature = "unicode-ops")]
+fn unicode_ops() {
+    let parse_eval = |s: &str| fasteval3::ez_eval(s, &mut fasteval3::EmptyNamespace);
+
+    assert_eq!(parse_eval("3 × 4 ≥ 10"), Ok(1.0));
+    assert_eq!(parse_eval("3×4≥13"), Ok(0.0));
+    assert_eq!(parse_eval("3 × 4 ≥ 10"), parse_eval("3 * 4 >= 10"));
+    assert_eq!(parse_eval("12 ÷ 4 − 1"), Ok(2.0));
+    assert_eq!(parse_eval("2 ≤ 2"), Ok(1.0));
+    assert_eq!(parse_eval("2 ≠ 2"), Ok(0.0));
+    assert_eq!(parse_eval("2 ≠ 3"), Ok(1.0));
+    // ASCII operators are unaffected, and can be mixed with the Unicode ones:
+    assert_eq!(parse_eval("1 - 2 × 3 <= -5"), Ok(1.0));
+    // Other non-ASCII characters are still rejected:
+    assert!(parse_eval("3 ⋅ 4").is_err());
+    assert!(parse_eval("3 ×").is_err());
+}
+
+#[test]
+#[cfg(not(feature = "print-builtin"))]
+fn print_disabled() {
+    let mut slab = Slab::new();
+    assert_eq!(
+        Parser::new().parse(r#"1 + print("x", 2)"#, &mut slab.ps),
+        Err(Error::DisabledFunction(String::from("print")))
     );
+    // Without parentheses, 'print' is still just a variable name:
+    assert!(Parser::new().parse("1 + print", &mut slab.ps).is_ok());
 }
 
 #[test]